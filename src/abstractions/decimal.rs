@@ -0,0 +1,265 @@
+/*!
+
+An exact decimal number: an integer mantissa together with a scale, the number of digits after the decimal point, so
+that `Decimal::new(12345, 2)` is `123.45`. Decimals are meant for values like money for which binary floating point
+is unacceptable, e.g. `0.1 + 0.2` is exactly `0.3`.
+
+Addition and subtraction align the operands to the larger scale, and the scale of a product is the sum of the scales
+of its factors, so these three operations are exact. Division generally is not, so `Decimal::checked_div(…)` takes
+the scale of the quotient and *truncates* toward zero, e.g. `2/3` at scale 2 is `0.66` and `-2/3` is `-0.66`.
+
+The mantissa is an `i128`, which holds 38 significant digits. The `checked_` methods return `None` on overflow, and
+the operators panic on overflow like the primitive integer operators do in debug builds.
+
+ToDo: Use a big integer for the mantissa if a built-in decimal sort needs unbounded precision.
+
+*/
+
+use std::{
+  cmp::Ordering,
+  fmt::{Display, Formatter},
+  ops::{Add, Mul, Neg, Sub},
+  str::FromStr
+};
+
+#[derive(Copy, Clone, Debug)]
+pub struct Decimal {
+  mantissa: i128,
+  scale   : u32,
+}
+
+impl Decimal {
+  /// The decimal `mantissa × 10^-scale`.
+  pub fn new(mantissa: i128, scale: u32) -> Decimal {
+    Decimal { mantissa, scale }
+  }
+
+  #[inline(always)]
+  pub fn mantissa(&self) -> i128 {
+    self.mantissa
+  }
+
+  /// The number of digits after the decimal point.
+  #[inline(always)]
+  pub fn scale(&self) -> u32 {
+    self.scale
+  }
+
+  /// The same value with `scale` digits after the decimal point, or `None` if `scale` is smaller than the current
+  /// scale, which could lose digits, or the mantissa overflows.
+  pub fn rescale(&self, scale: u32) -> Option<Decimal> {
+    let factor = 10i128.checked_pow(scale.checked_sub(self.scale)?)?;
+    Some(Decimal::new(self.mantissa.checked_mul(factor)?, scale))
+  }
+
+  /// Both operands at the larger of their scales.
+  fn aligned(&self, other: &Decimal) -> Option<(Decimal, Decimal)> {
+    let scale = self.scale.max(other.scale);
+    Some((self.rescale(scale)?, other.rescale(scale)?))
+  }
+
+  pub fn checked_add(&self, other: &Decimal) -> Option<Decimal> {
+    let (a, b) = self.aligned(other)?;
+    Some(Decimal::new(a.mantissa.checked_add(b.mantissa)?, a.scale))
+  }
+
+  pub fn checked_sub(&self, other: &Decimal) -> Option<Decimal> {
+    let (a, b) = self.aligned(other)?;
+    Some(Decimal::new(a.mantissa.checked_sub(b.mantissa)?, a.scale))
+  }
+
+  pub fn checked_mul(&self, other: &Decimal) -> Option<Decimal> {
+    Some(Decimal::new(self.mantissa.checked_mul(other.mantissa)?, self.scale.checked_add(other.scale)?))
+  }
+
+  /// The quotient with `scale` digits after the decimal point, truncated toward zero. Returns `None` if `other` is
+  /// zero or the computation overflows.
+  pub fn checked_div(&self, other: &Decimal, scale: u32) -> Option<Decimal> {
+    if other.mantissa == 0 {
+      return None;
+    }
+    // self / other = (m₁ × 10^(scale + s₂ - s₁) / m₂) × 10^-scale
+    let shift    = scale.checked_add(other.scale)? as i64 - self.scale as i64;
+    let mantissa = match shift >= 0 {
+      true  => self.mantissa.checked_mul(10i128.checked_pow(shift as u32)?)?.checked_div(other.mantissa)?,
+      false => self.mantissa.checked_div(other.mantissa.checked_mul(10i128.checked_pow((-shift) as u32)?)?)?,
+    };
+    Some(Decimal::new(mantissa, scale))
+  }
+}
+
+impl Add for Decimal {
+  type Output = Decimal;
+
+  fn add(self, other: Decimal) -> Decimal {
+    self.checked_add(&other).expect("decimal addition overflowed")
+  }
+}
+
+impl Sub for Decimal {
+  type Output = Decimal;
+
+  fn sub(self, other: Decimal) -> Decimal {
+    self.checked_sub(&other).expect("decimal subtraction overflowed")
+  }
+}
+
+impl Mul for Decimal {
+  type Output = Decimal;
+
+  fn mul(self, other: Decimal) -> Decimal {
+    self.checked_mul(&other).expect("decimal multiplication overflowed")
+  }
+}
+
+impl Neg for Decimal {
+  type Output = Decimal;
+
+  fn neg(self) -> Decimal {
+    Decimal::new(-self.mantissa, self.scale)
+  }
+}
+
+// Decimals are compared by value, so `0.1` and `0.10` are equal.
+impl PartialEq for Decimal {
+  fn eq(&self, other: &Decimal) -> bool {
+    self.cmp(other) == Ordering::Equal
+  }
+}
+
+impl Eq for Decimal {}
+
+impl PartialOrd for Decimal {
+  fn partial_cmp(&self, other: &Decimal) -> Option<Ordering> {
+    Some(self.cmp(other))
+  }
+}
+
+// Decimals are compared without aligning them, which could overflow: by sign first, and then by magnitude.
+impl Ord for Decimal {
+  fn cmp(&self, other: &Decimal) -> Ordering {
+    let sign = self.mantissa.signum();
+    if sign != other.mantissa.signum() || sign == 0 {
+      return sign.cmp(&other.mantissa.signum());
+    }
+    let (magnitude, other_magnitude) = (self.mantissa.unsigned_abs(), other.mantissa.unsigned_abs());
+    let magnitude = compare_magnitudes(magnitude, self.scale, other_magnitude, other.scale);
+    match sign > 0 {
+      true  => magnitude,
+      false => magnitude.reverse()
+    }
+  }
+}
+
+/// Compares `m₁ × 10^-s₁` with `m₂ × 10^-s₂` for nonzero `m₁` and `m₂`. The number with more digits before the
+/// decimal point is larger. Otherwise the digits of the mantissas line up, and the longer mantissa, cut to the length
+/// of the shorter, is compared with it, with the cut digits breaking a tie.
+fn compare_magnitudes(m1: u128, s1: u32, m2: u128, s2: u32) -> Ordering {
+  let (digits1, digits2) = (m1.ilog10() as i64 + 1, m2.ilog10() as i64 + 1);
+  let order = (digits1 - s1 as i64).cmp(&(digits2 - s2 as i64));
+  if order != Ordering::Equal {
+    return order;
+  }
+  let (long, short, flip) = match digits1 >= digits2 {
+    true  => (m1, m2, false),
+    false => (m2, m1, true)
+  };
+  // The difference in length is less than the 39 digits of a `u128`, so the power does not overflow.
+  let factor = 10u128.pow((digits1 - digits2).unsigned_abs() as u32);
+  let order  = (long / factor).cmp(&short).then((long % factor).cmp(&0));
+  match flip {
+    true  => order.reverse(),
+    false => order
+  }
+}
+
+impl Display for Decimal {
+  /// Prints all `scale` digits after the decimal point, e.g. `Decimal::new(1200, 3)` is `1.200`.
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    let digits = self.mantissa.unsigned_abs().to_string();
+    let sign   = if self.mantissa < 0 { "-" } else { "" };
+    let scale  = self.scale as usize;
+    if scale == 0 {
+      return write!(f, "{}{}", sign, digits);
+    }
+
+    let digits = format!("{:0>width$}", digits, width = scale + 1);
+    let (whole, fraction) = digits.split_at(digits.len() - scale);
+    write!(f, "{}{}.{}", sign, whole, fraction)
+  }
+}
+
+impl FromStr for Decimal {
+  type Err = std::num::ParseIntError;
+
+  /// Parses a decimal like `-123.45`, keeping the number of digits after the point as the scale.
+  fn from_str(text: &str) -> Result<Decimal, Self::Err> {
+    match text.split_once('.') {
+      Some((whole, fraction)) => {
+        let mantissa = format!("{}{}", whole, fraction).parse::<i128>()?;
+        Ok(Decimal::new(mantissa, fraction.len() as u32))
+      }
+      None => Ok(Decimal::new(text.parse::<i128>()?, 0))
+    }
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn decimal(text: &str) -> Decimal {
+    text.parse().unwrap()
+  }
+
+  #[test]
+  fn addition_is_exact() {
+    let sum = decimal("0.1") + decimal("0.2");
+    assert_eq!(sum, decimal("0.3"));
+    assert_eq!(sum.to_string(), "0.3");
+    assert_eq!((decimal("1.05") - decimal("2")).to_string(), "-0.95");
+    assert_eq!(decimal("0.10"), decimal("0.1"));
+  }
+
+  #[test]
+  fn scales_propagate_through_multiplication_and_division() {
+    let product = decimal("1.5") * decimal("0.25");
+    assert_eq!(product.scale(), 3);
+    assert_eq!(product.to_string(), "0.375");
+    assert_eq!((decimal("19.99") * decimal("3")).to_string(), "59.97");
+
+    assert_eq!(decimal("2").checked_div(&decimal("3"), 2).unwrap().to_string(), "0.66");
+    assert_eq!(decimal("-2").checked_div(&decimal("3"), 2).unwrap().to_string(), "-0.66");
+    assert_eq!(decimal("1.000").checked_div(&decimal("0.5"), 0).unwrap().to_string(), "2");
+    assert_eq!(decimal("1").checked_div(&decimal("0.0"), 2), None);
+    assert_eq!(decimal("1").checked_div(&decimal("0.1"), u32::MAX), None);
+  }
+
+  #[test]
+  fn overflow_is_reported() {
+    let big = Decimal::new(i128::MAX, 0);
+    assert_eq!(big.checked_add(&decimal("1")), None);
+    assert_eq!(big.checked_add(&decimal("0.1")), None);
+    assert!(decimal("0.001") < big);
+  }
+
+  #[test]
+  fn comparison_does_not_depend_on_alignment() {
+    let tiny = Decimal::new(1, 39);
+    assert_ne!(Decimal::new(0, 0), tiny);
+    assert!(Decimal::new(0, 0) < tiny);
+    assert!(-tiny < Decimal::new(0, 7));
+    assert_eq!(Decimal::new(0, 0), Decimal::new(0, 50));
+
+    let big = Decimal::new(i128::MAX, 0);
+    assert!(Decimal::new(1, 1000) < big);
+    assert!(-big < Decimal::new(-1, 1000));
+    assert_eq!(Decimal::new(i128::MAX / 10, 0), Decimal::new(i128::MAX / 10 * 10, 1));
+    assert!(Decimal::new(i128::MAX, 38) > Decimal::new(1, 0));
+    assert!(Decimal::new(i128::MAX, 38) < Decimal::new(2, 0));
+    assert!(Decimal::new(-12, 1) < Decimal::new(-119, 2));
+    assert!(decimal("1.25") > decimal("1.2"));
+    assert!(decimal("1.2") < decimal("1.25"));
+  }
+}
@@ -0,0 +1,262 @@
+/*!
+
+A directed graph with data on its nodes and labels on its edges, for the graphs the engine builds as it explores, like
+the state graph of a term, whose nodes are states and whose edges are labeled by rules. Nodes are identified by their
+index in the order they were added. There can be several edges between two nodes as long as their labels differ.
+
+The components of a graph are computed by `Graph::strongly_connected_components(…)`, with Tarjan's algorithm, and by
+`Graph::connected_components(…)`, which ignores the direction of the edges. The kinds of a module, for example, are the
+connected components of the graph of the subsort relation.
+
+*/
+
+/// An edge from the node with index `from` to the node with index `to`.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub struct Edge<E> {
+  pub from : usize,
+  pub to   : usize,
+  pub label: E,
+}
+
+#[derive(Clone, Debug)]
+pub struct Graph<N, E> {
+  nodes: Vec<N>,
+  /// The edges in the order they were added.
+  edges: Vec<Edge<E>>,
+}
+
+impl<N, E> Default for Graph<N, E> {
+  fn default() -> Self {
+    Graph {
+      nodes: vec![],
+      edges: vec![],
+    }
+  }
+}
+
+impl<N, E: PartialEq> Graph<N, E> {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Adds a node carrying `data` and returns its index.
+  pub fn add_node(&mut self, data: N) -> usize {
+    self.nodes.push(data);
+    self.nodes.len() - 1
+  }
+
+  /// Adds an edge labeled `label` from `from` to `to` unless there already is one. Returns whether the edge is new.
+  pub fn add_edge(&mut self, from: usize, to: usize, label: E) -> bool {
+    assert!(from < self.nodes.len() && to < self.nodes.len(), "edge between nonexistent nodes");
+    let edge = Edge { from, to, label };
+    if self.edges.contains(&edge) {
+      return false;
+    }
+    self.edges.push(edge);
+    true
+  }
+
+  #[inline(always)]
+  pub fn node(&self, index: usize) -> &N {
+    &self.nodes[index]
+  }
+
+  /// The data of the nodes, in order of their indices.
+  #[inline(always)]
+  pub fn nodes(&self) -> &[N] {
+    &self.nodes
+  }
+
+  #[inline(always)]
+  pub fn edges(&self) -> &[Edge<E>] {
+    &self.edges
+  }
+
+  #[inline(always)]
+  pub fn node_count(&self) -> usize {
+    self.nodes.len()
+  }
+
+  #[inline(always)]
+  pub fn edge_count(&self) -> usize {
+    self.edges.len()
+  }
+
+  /// The edges leaving the node `from`.
+  pub fn edges_from(&self, from: usize) -> impl Iterator<Item = &Edge<E>> + '_ {
+    self.edges.iter().filter(move |edge| edge.from == from)
+  }
+
+  /// The labels of the edges from `from` to `to`.
+  pub fn labels(&self, from: usize, to: usize) -> impl Iterator<Item = &E> + '_ {
+    self.edges.iter().filter(move |edge| edge.from == from && edge.to == to).map(|edge| &edge.label)
+  }
+
+  /**
+  The strongly connected components of the graph, the maximal sets of nodes that can each be reached from the others
+  along the edges, by Tarjan's algorithm. Each component lists its nodes in index order. The components are in
+  reverse topological order: an edge between two components leads from the later one to the earlier one.
+  */
+  pub fn strongly_connected_components(&self) -> Vec<Vec<usize>> {
+    let successors = self.successors();
+    let unvisited  = usize::MAX;
+
+    // The order in which the nodes are reached, and the earliest node on the stack reachable from each.
+    let mut index    = vec![unvisited; self.nodes.len()];
+    let mut low_link = vec![0; self.nodes.len()];
+    let mut on_stack = vec![false; self.nodes.len()];
+    let mut stack    = Vec::new();
+    let mut visited  = 0;
+
+    let mut components = Vec::new();
+    for root in 0..self.nodes.len() {
+      if index[root] != unvisited {
+        continue;
+      }
+      // The depth first search is iterative, so large graphs do not overflow the call stack. Each frame is a node
+      // and the number of its successors visited so far.
+      let mut frames = vec![(root, 0)];
+      index[root]    = visited;
+      low_link[root] = visited;
+      visited       += 1;
+      stack.push(root);
+      on_stack[root] = true;
+
+      while let Some(&mut (node, ref mut next)) = frames.last_mut() {
+        if let Some(&successor) = successors[node].get(*next) {
+          *next += 1;
+          if index[successor] == unvisited {
+            index[successor]    = visited;
+            low_link[successor] = visited;
+            visited            += 1;
+            stack.push(successor);
+            on_stack[successor] = true;
+            frames.push((successor, 0));
+          } else if on_stack[successor] {
+            low_link[node] = low_link[node].min(index[successor]);
+          }
+          continue;
+        }
+
+        frames.pop();
+        if let Some(&(parent, _)) = frames.last() {
+          low_link[parent] = low_link[parent].min(low_link[node]);
+        }
+        if low_link[node] == index[node] {
+          let mut component = Vec::new();
+          while let Some(member) = stack.pop() {
+            on_stack[member] = false;
+            component.push(member);
+            if member == node {
+              break;
+            }
+          }
+          component.sort_unstable();
+          components.push(component);
+        }
+      }
+    }
+    components
+  }
+
+  /// The connected components of the graph with the direction of its edges ignored. Each component lists its nodes
+  /// in index order, and the components are in the order of their first nodes.
+  pub fn connected_components(&self) -> Vec<Vec<usize>> {
+    let mut neighbors = vec![Vec::new(); self.nodes.len()];
+    for edge in self.edges.iter() {
+      neighbors[edge.from].push(edge.to);
+      neighbors[edge.to].push(edge.from);
+    }
+
+    let mut reached    = vec![false; self.nodes.len()];
+    let mut components = Vec::new();
+    for root in 0..self.nodes.len() {
+      if reached[root] {
+        continue;
+      }
+      reached[root]     = true;
+      let mut component = vec![root];
+      let mut stack     = vec![root];
+      while let Some(node) = stack.pop() {
+        for &neighbor in neighbors[node].iter() {
+          if !reached[neighbor] {
+            reached[neighbor] = true;
+            component.push(neighbor);
+            stack.push(neighbor);
+          }
+        }
+      }
+      component.sort_unstable();
+      components.push(component);
+    }
+    components
+  }
+
+  /// The successors of each node, in the order the edges were added.
+  fn successors(&self) -> Vec<Vec<usize>> {
+    let mut successors = vec![Vec::new(); self.nodes.len()];
+    for edge in self.edges.iter() {
+      successors[edge.from].push(edge.to);
+    }
+    successors
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn edges_carry_labels_between_nodes() {
+    let mut graph: Graph<&str, char> = Graph::new();
+    let a = graph.add_node("a");
+    let b = graph.add_node("b");
+    assert_eq!(graph.node(b), &"b");
+
+    assert!(graph.add_edge(a, b, 'x'));
+    assert!(graph.add_edge(a, b, 'y'));
+    assert!(graph.add_edge(b, a, 'x'));
+    // An edge with the same endpoints and label is only recorded once.
+    assert!(!graph.add_edge(a, b, 'x'));
+
+    assert_eq!(graph.edge_count(), 3);
+    assert_eq!(graph.labels(a, b).copied().collect::<Vec<_>>(), vec!['x', 'y']);
+    assert_eq!(graph.labels(b, b).count(), 0);
+    assert_eq!(graph.edges_from(b).map(|edge| edge.to).collect::<Vec<_>>(), vec![a]);
+  }
+
+  /// A graph with `node_count` nodes and an unlabeled edge for each pair in `edges`.
+  fn graph_with_edges(node_count: usize, edges: &[(usize, usize)]) -> Graph<usize, ()> {
+    let mut graph = Graph::new();
+    for node in 0..node_count {
+      graph.add_node(node);
+    }
+    for &(from, to) in edges {
+      graph.add_edge(from, to, ());
+    }
+    graph
+  }
+
+  #[test]
+  fn a_cycle_is_one_strongly_connected_component() {
+    let graph = graph_with_edges(4, &[(0, 1), (1, 2), (2, 0), (2, 3)]);
+    assert_eq!(graph.strongly_connected_components(), vec![vec![3], vec![0, 1, 2]]);
+    assert_eq!(graph.connected_components(), vec![vec![0, 1, 2, 3]]);
+  }
+
+  #[test]
+  fn every_node_of_a_dag_is_its_own_strongly_connected_component() {
+    let graph = graph_with_edges(4, &[(0, 1), (0, 2), (1, 3), (2, 3)]);
+    // Reverse topological order: every edge leads to an earlier component.
+    assert_eq!(graph.strongly_connected_components(), vec![vec![3], vec![1], vec![2], vec![0]]);
+    assert_eq!(graph.connected_components(), vec![vec![0, 1, 2, 3]]);
+  }
+
+  #[test]
+  fn disjoint_subgraphs_are_separate_components() {
+    let graph = graph_with_edges(5, &[(3, 0), (1, 4), (4, 1)]);
+    assert_eq!(graph.connected_components(), vec![vec![0, 3], vec![1, 4], vec![2]]);
+    assert_eq!(graph.strongly_connected_components(), vec![vec![0], vec![1, 4], vec![2], vec![3]]);
+  }
+}
@@ -0,0 +1,69 @@
+/*!
+
+Introspection of the string interner behind `IString`.
+
+`IString` is backed by `ustr`, which keeps one global cache for the lifetime of the process. Interned strings are never
+freed, so a long-running process that interns many distinct identifiers (a server checking one module after another,
+say) grows without bound. `interner_stats()` reports how large the cache has become, so such a process can decide when
+to restart.
+
+The cache is global rather than thread-local, so it cannot be reset between jobs of one thread without affecting every
+other thread. `clear_interner()` exists for processes that can guarantee no `IString` survives the reset; see its
+safety requirements.
+
+*/
+
+/// A snapshot of the size of the string interner.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub struct InternerStats {
+  /// The number of distinct interned strings.
+  pub entries        : usize,
+  /// The bytes in use by interned strings.
+  pub allocated_bytes: usize,
+  /// The bytes reserved by the interner.
+  pub capacity_bytes : usize,
+}
+
+/// Reports the current size of the string interner. The counts may be underestimates while other threads intern
+/// strings.
+pub fn interner_stats() -> InternerStats {
+  InternerStats {
+    entries        : ustr::num_entries(),
+    allocated_bytes: ustr::total_allocated(),
+    capacity_bytes : ustr::total_capacity(),
+  }
+}
+
+/**
+Removes every string from the interner.
+
+# Safety
+
+Every `IString` created before the call dangles afterward, in every thread. This includes the names held by any
+`Module`, `Symbol`, or `Sort` still alive. The caller must ensure that none of them is used again.
+*/
+pub unsafe fn clear_interner() {
+  ustr::_clear_cache();
+}
+
+
+#[cfg(test)]
+mod tests {
+  use crate::abstractions::IString;
+  use super::*;
+
+  #[test]
+  fn interner_stats_count_new_strings() {
+    let before = interner_stats();
+    let strings: Vec<IString> = (0..1000).map(|i| IString::from(format!("interner_stats_{}", i).as_str())).collect();
+    let after  = interner_stats();
+
+    // Other tests intern strings concurrently, so the counts can only be bounded from below.
+    assert!(after.entries >= before.entries + strings.len());
+    assert!(after.allocated_bytes > before.allocated_bytes);
+    assert!(after.capacity_bytes >= after.allocated_bytes);
+
+    // Interning an existing string does not grow the interner.
+    assert_eq!(IString::from("interner_stats_0"), strings[0]);
+  }
+}
@@ -0,0 +1,73 @@
+/*!
+
+Reinterpreting plain values as bytes and back, for serializing small values like indices or hashes.
+
+Both directions are restricted to `Pod` ("plain old data") types, for which every bit pattern of the right size is a
+valid value and which contain no padding or pointers. Reading a value from bytes has two further preconditions, which
+`from_bytes` checks instead of assuming:
+
+ 1. the slice must be exactly `size_of::<T>()` bytes long, and
+ 2. the slice must start at an address aligned for `T`.
+
+Byte order is that of the host, so bytes are only meaningful to the machine that produced them.
+
+*/
+
+use std::mem::size_of;
+
+/// Marks types that can be soundly reinterpreted from any bytes of the right size and alignment.
+///
+/// # Safety
+///
+/// Implementors must have no padding, no invalid bit patterns, and no pointers or references.
+pub unsafe trait Pod: Copy {}
+
+macro_rules! impl_pod {
+  ($($t:ty),*) => { $(unsafe impl Pod for $t {})* };
+}
+impl_pod!(u8, u16, u32, u64, u128, usize, i8, i16, i32, i64, i128, isize, f32, f64);
+
+/// The bytes of `value`.
+pub fn as_bytes<T: Pod>(value: &T) -> &[u8] {
+  // Safety: `T: Pod` has no padding, so all `size_of::<T>()` bytes of `value` are initialized.
+  unsafe { std::slice::from_raw_parts((value as *const T).cast::<u8>(), size_of::<T>()) }
+}
+
+/// The value whose bytes are `bytes`, or `None` if `bytes` has the wrong length or is misaligned for `T`.
+pub fn from_bytes<T: Pod>(bytes: &[u8]) -> Option<&T> {
+  let pointer = bytes.as_ptr();
+  if bytes.len() != size_of::<T>() || !pointer.cast::<T>().is_aligned() {
+    return None;
+  }
+  // Safety: The size and alignment were just checked, and every bit pattern is a valid `T: Pod`.
+  Some(unsafe { &*pointer.cast::<T>() })
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn pod_values_round_trip() {
+    let index: u32 = 0xDEAD_BEEF;
+    assert_eq!(from_bytes::<u32>(as_bytes(&index)), Some(&index));
+
+    let offset: i64 = -42;
+    assert_eq!(from_bytes::<i64>(as_bytes(&offset)), Some(&offset));
+
+    let weight: f64 = 0.625;
+    assert_eq!(from_bytes::<f64>(as_bytes(&weight)), Some(&weight));
+  }
+
+  #[test]
+  fn wrong_length_or_alignment_is_rejected() {
+    let values: [u64; 2] = [1, 2];
+    let bytes: &[u8] = unsafe { std::slice::from_raw_parts(values.as_ptr().cast::<u8>(), 16) };
+
+    assert_eq!(from_bytes::<u64>(&bytes[8..16]), Some(&2));
+    assert_eq!(from_bytes::<u64>(&bytes[0..4]), None);
+    // Shifting by one byte misaligns the slice for a `u64`.
+    assert_eq!(from_bytes::<u64>(&bytes[1..9]), None);
+  }
+}
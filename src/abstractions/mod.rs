@@ -12,9 +12,13 @@ Most of this module consists of either pub imports, type aliases, or little snip
 */
 
 #![allow(unused)]
+mod decimal;
+mod graph;
 mod nat_set;
 mod rccell;
 mod heap;
+mod interner;
+pub mod memory;
 
 
 // A fast hash set and hash map
@@ -32,7 +36,12 @@ pub use nat_set::NatSet;
 
 
 // Reference counted pointers with mutable stable, and complementary weak pointers.
-pub use rccell::{rc_cell, RcCell, WeakCell};
+pub use rccell::{rc_cell, RcCell, RcRefMut, WeakCell};
+
+
+
+// Directed graphs with node data and edge labels
+pub use graph::{Edge, Graph};
 
 
 
@@ -43,6 +52,19 @@ pub use heap::{heap_construct, heap_destroy};
 use ustr::Ustr;
 /// Interned strings. Create an interned string with `IString::from(..)`
 pub type IString = Ustr;
+pub use interner::{interner_stats, clear_interner, InternerStats};
+
+
+
+// Hashing
+/// The type of structural hash values.
+pub type HashType = u32;
+
+/// Combines two hash values into one. Order matters: `hash2(a, b)` and `hash2(b, a)` are generally different.
+#[inline(always)]
+pub fn hash2(a: HashType, b: HashType) -> HashType {
+  (a.rotate_left(5) ^ b).wrapping_mul(0x9E37_79B9)
+}
 
 
 
@@ -53,6 +75,8 @@ pub type NaturalNumber = u64;
 pub type Integer       = i16;
 /// Floating Point Numbers
 pub type Float         = f64;
+/// Exact decimal numbers
+pub use decimal::Decimal;
 
 use std::iter::once;
 /**
@@ -79,3 +103,87 @@ pub fn join_iter<T>(mut iter: impl Iterator<Item = T>, sep: impl Fn(&T) -> T)
       .into_iter()
       .chain(iter.flat_map(move |s| once(sep(&s)).chain(once(s))))
 }
+
+
+// Unicode sub- and superscripts
+
+/// The subscript digits `₀` through `₉`.
+const SUBSCRIPT_DIGITS  : [char; 10] = ['₀', '₁', '₂', '₃', '₄', '₅', '₆', '₇', '₈', '₉'];
+/// The superscript digits `⁰` through `⁹`. Note that `¹`, `²`, and `³` are not in the same Unicode block as the rest.
+const SUPERSCRIPT_DIGITS: [char; 10] = ['⁰', '¹', '²', '³', '⁴', '⁵', '⁶', '⁷', '⁸', '⁹'];
+
+fn int_to_script(value: i64, digits: &[char; 10], minus: char) -> String {
+  let mut result: String = value.unsigned_abs()
+                                .to_string()
+                                .bytes()
+                                .map(|digit| digits[(digit - b'0') as usize])
+                                .collect();
+  if value < 0 {
+    result.insert(0, minus);
+  }
+  result
+}
+
+/// Renders `value` in Unicode subscript digits, e.g. `12` as `₁₂`, for indexed names like `x₁₂`.
+pub fn int_to_subscript(value: i64) -> String {
+  int_to_script(value, &SUBSCRIPT_DIGITS, '₋')
+}
+
+/**
+Splits a name like `x₁₂` into its base `x` and the value of its trailing subscript digits, `12`, undoing the naming
+of indexed variables with `int_to_subscript(…)`. Only the trailing run of subscript digits is read, so `x1₂` is `x1`
+with index `2`, and the base can be empty, as in `₅`. Returns `None` if the name does not end in a subscript digit or
+the index does not fit in a `NaturalNumber`.
+*/
+pub fn subscript_to_int(name: &str) -> Option<(&str, NaturalNumber)> {
+  let base = name.trim_end_matches(|c| SUBSCRIPT_DIGITS.contains(&c));
+  if base.len() == name.len() {
+    return None;
+  }
+  let mut index: NaturalNumber = 0;
+  for c in name[base.len()..].chars() {
+    let digit = SUBSCRIPT_DIGITS.iter().position(|d| *d == c)? as NaturalNumber;
+    index     = index.checked_mul(10)?.checked_add(digit)?;
+  }
+  Some((base, index))
+}
+
+/// Renders `value` in Unicode superscript digits, e.g. `3` as `³`, for iterated symbols like `s³`.
+pub fn int_to_superscript(value: i64) -> String {
+  int_to_script(value, &SUPERSCRIPT_DIGITS, '⁻')
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn integers_convert_to_sub_and_superscripts() {
+    assert_eq!(int_to_subscript(0), "₀");
+    assert_eq!(int_to_subscript(7), "₇");
+    assert_eq!(int_to_subscript(1234), "₁₂₃₄");
+    assert_eq!(int_to_subscript(-5), "₋₅");
+
+    assert_eq!(int_to_superscript(0), "⁰");
+    assert_eq!(int_to_superscript(7), "⁷");
+    assert_eq!(int_to_superscript(1234), "¹²³⁴");
+    assert_eq!(int_to_superscript(-5), "⁻⁵");
+
+    // The most negative value has no positive counterpart, which must not overflow.
+    assert_eq!(int_to_superscript(i64::MIN), "⁻⁹²²³³⁷²⁰³⁶⁸⁵⁴⁷⁷⁵⁸⁰⁸");
+  }
+
+  #[test]
+  fn subscripts_are_split_from_names() {
+    assert_eq!(subscript_to_int("x₁₂"), Some(("x", 12)));
+    assert_eq!(subscript_to_int(&format!("X{}", int_to_subscript(307))), Some(("X", 307)));
+    assert_eq!(subscript_to_int("foo"), None);
+    assert_eq!(subscript_to_int(""), None);
+    assert_eq!(subscript_to_int("₅"), Some(("", 5)));
+    // Only the trailing run of subscript digits is the index.
+    assert_eq!(subscript_to_int("x1₂"), Some(("x1", 2)));
+    assert_eq!(subscript_to_int("x₁y"), None);
+    assert_eq!(subscript_to_int("x₉₉₉₉₉₉₉₉₉₉₉₉₉₉₉₉₉₉₉₉₉"), None);
+  }
+}
@@ -2,17 +2,37 @@
 
 A thin wrapper around BitSet (the bit-set crate). We could just use a type alias if we didn't also need a `min` method.
 
+## Byte Encoding
+
+`NatSet::to_bytes(…)` encodes a set as its runs of consecutive values in increasing order. Each run is a pair of
+numbers: the count of absent values before it, counted from the end of the previous run (or from 0 for the first
+run), followed by the length of the run. Each number is an unsigned LEB128 varint, seven bits per byte with the least
+significant group first and the high bit set on every byte but the last. So `{0, 1, 2, 10}` is the bytes
+`[0, 3, 7, 1]`, a sparse set like `{3, 1000000}` takes six bytes, and the empty set is no bytes at all.
+
+The encoding is canonical: every run but the first is preceded by at least one absent value, and no run is empty.
+`NatSet::from_bytes(…)` rejects anything else, so equal sets always have equal encodings. The format is stable and
+will not change.
+
+A set is stored as a bit for every value up to its largest, so a few bytes can encode a set that takes far more memory
+than the machine has, like `{2⁵⁶}`, or a run so long that inserting its values never finishes. So
+`NatSet::from_bytes(…)` also rejects encodings of sets with a value above `NatSet::MAX_DECODED_VALUE`, which take at
+most 2 MiB. Sets with larger values can be encoded but not decoded.
+
 */
 
 use bit_set::BitSet;
 pub use bit_set::Iter as BitSetIterator;
 
-#[derive(Default, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+#[derive(Default, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
 pub struct NatSet(BitSet<u32>);
 
 pub type NatSetIterator<'a> = BitSetIterator<'a, u32>;
 
 impl NatSet {
+  /// The largest value of a set decoded by `NatSet::from_bytes(…)`. See the module documentation.
+  pub const MAX_DECODED_VALUE: usize = (1 << 24) - 1;
+
   /*
     capacity
       clear
@@ -143,18 +163,33 @@ impl NatSet {
     self.0.shrink_to_fit()
   }
 
-  /// Returns the symmetric difference with the other specified bit vector.
+  /// Keeps only the values for which `predicate` returns true.
+  pub fn retain<F: FnMut(usize) -> bool>(&mut self, mut predicate: F) {
+    // Values are removed while scanning the capacity, as the set cannot be modified while it is iterated.
+    for value in 0..self.0.capacity() {
+      if self.0.contains(value) && !predicate(value) {
+        self.0.remove(value);
+      }
+    }
+  }
+
+  /// Returns the symmetric difference with the other specified bit vector, the values in exactly one of the two.
   #[inline(always)]
   pub fn symmetric_difference(&self, other: &NatSet) -> NatSet {
+    if self.is_empty() {
+      return other.clone();
+    }
     let mut new_set = self.clone();
-    new_set.0.symmetric_difference(&other.0);
+    new_set.symmetric_difference_in_place(other);
     new_set
   }
 
-  /// Returns the symmetric difference with the other specified bit vector.
+  /// Makes this bit vector the symmetric difference with the specified other bit vector in-place.
   #[inline(always)]
   pub fn symmetric_difference_in_place(&mut self, other: &NatSet) {
-    self.0.symmetric_difference(&other.0);
+    if !other.is_empty() {
+      self.0.symmetric_difference_with(&other.0);
+    }
   }
 
   /// Returns the union with the other specified bit vector.
@@ -175,4 +210,156 @@ impl NatSet {
   pub fn with_capacity(nbits: usize) -> NatSet {
     NatSet(BitSet::with_capacity(nbits))
   }
+
+  /// Encodes the set as its runs of consecutive values. See the module documentation for the format.
+  pub fn to_bytes(&self) -> Vec<u8> {
+    let mut bytes  = Vec::new();
+    let mut values = self.iter().peekable();
+    // The value after the end of the previous run.
+    let mut end = 0;
+    while let Some(start) = values.next() {
+      let mut length = 1;
+      while values.next_if_eq(&(start + length)).is_some() {
+        length += 1;
+      }
+      write_varint(&mut bytes, start - end);
+      write_varint(&mut bytes, length);
+      end = start + length;
+    }
+    bytes
+  }
+
+  /// Decodes a set encoded by `NatSet::to_bytes(…)`. Returns `None` if `bytes` is not a canonical encoding of a set, or
+  /// if the set has a value above `NatSet::MAX_DECODED_VALUE`.
+  pub fn from_bytes(bytes: &[u8]) -> Option<NatSet> {
+    let mut set        = NatSet::new();
+    let mut bytes      = bytes.iter();
+    let mut end: usize = 0;
+    while !bytes.as_slice().is_empty() {
+      let gap    = read_varint(&mut bytes)?;
+      let length = read_varint(&mut bytes)?;
+      if length == 0 || (gap == 0 && end > 0) {
+        return None;
+      }
+      let start = end.checked_add(gap)?;
+      end       = start.checked_add(length)?;
+      if end > NatSet::MAX_DECODED_VALUE + 1 {
+        return None;
+      }
+      set.reserve_len(end);
+      for value in start..end {
+        set.insert(value);
+      }
+    }
+    Some(set)
+  }
+}
+
+/// Appends `value` to `bytes` as an unsigned LEB128 varint.
+fn write_varint(bytes: &mut Vec<u8>, mut value: usize) {
+  while value >= 0x80 {
+    bytes.push((value as u8 & 0x7f) | 0x80);
+    value >>= 7;
+  }
+  bytes.push(value as u8);
+}
+
+/// Reads an unsigned LEB128 varint from `bytes`. Returns `None` if the varint is truncated, does not fit in a `usize`,
+/// or has a redundant final zero byte.
+fn read_varint(bytes: &mut std::slice::Iter<u8>) -> Option<usize> {
+  let mut value = 0usize;
+  let mut shift = 0;
+  loop {
+    let byte  = *bytes.next()?;
+    let group = (byte & 0x7f) as usize;
+    if shift >= usize::BITS || (group << shift) >> shift != group {
+      return None;
+    }
+    value |= group << shift;
+    if byte & 0x80 == 0 {
+      return (byte != 0 || shift == 0).then_some(value);
+    }
+    shift += 7;
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn nat_set(values: &[usize]) -> NatSet {
+    let mut set = NatSet::new();
+    for value in values {
+      set.insert(*value);
+    }
+    set
+  }
+
+  #[test]
+  fn symmetric_difference_keeps_values_in_exactly_one_set() {
+    let a = nat_set(&[1, 2, 3]);
+    let b = nat_set(&[3, 4, 70]);
+    assert_eq!(a.symmetric_difference(&b), nat_set(&[1, 2, 4, 70]));
+
+    // Disjoint sets
+    let c = nat_set(&[5, 6]);
+    assert_eq!(a.symmetric_difference(&c), a.union(&c));
+    // Identical sets
+    assert!(a.symmetric_difference(&a).is_empty());
+    // Empty sets
+    assert_eq!(NatSet::new().symmetric_difference(&b), b);
+    assert_eq!(b.symmetric_difference(&NatSet::new()), b);
+
+    let mut d = a.clone();
+    d.symmetric_difference_in_place(&b);
+    assert_eq!(d, nat_set(&[1, 2, 4, 70]));
+  }
+
+  #[test]
+  fn byte_encoding_round_trips() {
+    let dense: Vec<usize> = (0..64).collect();
+    for set in [NatSet::new(), nat_set(&dense), nat_set(&[3, 1000000]), nat_set(&[0, 1, 2, 10])] {
+      assert_eq!(NatSet::from_bytes(&set.to_bytes()), Some(set));
+    }
+
+    assert!(NatSet::new().to_bytes().is_empty());
+    assert_eq!(nat_set(&dense).to_bytes(), vec![0, 64]);
+    assert_eq!(nat_set(&[0, 1, 2, 10]).to_bytes(), vec![0, 3, 7, 1]);
+    assert_eq!(nat_set(&[3, 1000000]).to_bytes(), vec![3, 1, 0xbc, 0x84, 0x3d, 1]);
+  }
+
+  #[test]
+  fn byte_decoding_rejects_noncanonical_encodings() {
+    // A truncated varint, a missing run length, an empty run, adjacent runs, and a redundant zero byte.
+    for bytes in [&[0x80][..], &[3], &[3, 0], &[0, 2, 0, 1], &[0x83, 0x00, 1]] {
+      assert_eq!(NatSet::from_bytes(bytes), None);
+    }
+    // A gap beyond the largest `usize`.
+    assert_eq!(NatSet::from_bytes(&[0xff; 11]), None);
+  }
+
+  #[test]
+  fn byte_decoding_rejects_values_above_the_maximum() {
+    // `{2⁵⁶}` and a run of 2³⁵ values.
+    assert_eq!(NatSet::from_bytes(&[0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x01, 0x01]), None);
+    assert_eq!(NatSet::from_bytes(&[0, 0x80, 0x80, 0x80, 0x80, 0x80, 0x01]), None);
+
+    let largest = nat_set(&[0, NatSet::MAX_DECODED_VALUE]);
+    assert_eq!(NatSet::from_bytes(&largest.to_bytes()), Some(largest));
+    let too_large = nat_set(&[NatSet::MAX_DECODED_VALUE + 1]);
+    assert_eq!(NatSet::from_bytes(&too_large.to_bytes()), None);
+    // The last value of a run counts, not its start.
+    let mut run = vec![];
+    write_varint(&mut run, NatSet::MAX_DECODED_VALUE);
+    write_varint(&mut run, 2);
+    assert_eq!(NatSet::from_bytes(&run), None);
+  }
+
+  #[test]
+  fn retain_keeps_matching_values() {
+    let mut set = nat_set(&[0, 1, 2, 3, 4, 65, 100]);
+    set.retain(|value| value % 2 == 0);
+    assert_eq!(set.iter().collect::<Vec<_>>(), vec![0, 2, 4, 100]);
+  }
 }
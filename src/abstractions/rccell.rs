@@ -32,6 +32,16 @@ assert!(d.upgrade().is_some());  // Use the upgrade method to get a RcCell point
 
 `RcCell<T>` structs implement the `Hash` trait by using the value of their inner `Rc` pointer value.
 
+## Borrow Locations
+
+A borrow that conflicts with a mutable borrow panics, as with `RefCell`. In debug builds, `RcCell::borrow_mut(…)` and
+`RcCell::try_borrow_mut(…)` also record the source location they were called from, and the panic message says where
+the conflicting mutable borrow was taken, which is usually the hard part of finding an aliasing bug. The location of
+the active mutable borrow is available from `RcCell::mutable_borrow_location(…)`. Locations are kept per allocation in
+a thread local table, and borrows made on the inner `RefCell` directly are not recorded. A mutable borrow is an
+`RcRefMut`, which removes the location from the table when it is dropped, so the table only holds the active mutable
+borrows, and a location is never reported for a later allocation at the same address.
+
 */
 
 use std::{
@@ -48,7 +58,11 @@ use std::{
     Hash,
     Hasher
   },
-  ops::Deref,
+  ops::{
+    Deref,
+    DerefMut
+  },
+  panic::Location,
   pin::Pin,
   rc::{
     Rc,
@@ -56,6 +70,44 @@ use std::{
   },
 };
 
+#[cfg(debug_assertions)]
+thread_local! {
+  /// The source location of the latest mutable borrow of each allocation. See the module documentation.
+  static MUTABLE_BORROW_LOCATIONS: RefCell<std::collections::HashMap<usize, &'static Location<'static>>> =
+      RefCell::new(std::collections::HashMap::new());
+}
+
+/// A mutable borrow of the value of an `RcCell`, which is a `RefMut` that also forgets the location of the borrow when
+/// it is dropped in debug builds. See the module documentation.
+pub struct RcRefMut<'b, T: ?Sized> {
+  value  : RefMut<'b, T>,
+  /// The address of the allocation, the key of the borrow's location in `MUTABLE_BORROW_LOCATIONS`.
+  #[cfg(debug_assertions)]
+  address: usize,
+}
+
+impl<T: ?Sized> Deref for RcRefMut<'_, T> {
+  type Target = T;
+
+  fn deref(&self) -> &T {
+    &self.value
+  }
+}
+
+impl<T: ?Sized> DerefMut for RcRefMut<'_, T> {
+  fn deref_mut(&mut self) -> &mut T {
+    &mut self.value
+  }
+}
+
+#[cfg(debug_assertions)]
+impl<T: ?Sized> Drop for RcRefMut<'_, T> {
+  fn drop(&mut self) {
+    // The table is gone if the thread is exiting.
+    let _ = MUTABLE_BORROW_LOCATIONS.try_with(|locations| locations.borrow_mut().remove(&self.address));
+  }
+}
+
 /// Wrapper for `Rc<RefCell<T>>`.
 #[derive(Debug, Default, Eq)]
 pub struct RcCell<T: ?Sized>(pub Rc<RefCell<T>>);
@@ -166,7 +218,7 @@ impl<T: ?Sized> RcCell<T> {
   /// Returns a [Ref] to the inner value if there is no [RefMut] pointing to the same allocation.
   /// Otherwise, it returns a `BorrowError`.
   /// # Examples
-  /// ```rust
+  /// ```ignore
   /// use mod2::abstractions::RcCell;
   ///
   /// let x = RcCell::new(1);
@@ -179,10 +231,10 @@ impl<T: ?Sized> RcCell<T> {
   }
 
   /// Similar to [RefCell::try_borrow_mut].
-  /// Returns a [RefMut] to the inner value if there is no [RefMut] nor [Ref] pointing to the same allocation.
+  /// Returns an [RcRefMut] to the inner value if there is no [RcRefMut] nor [Ref] pointing to the same allocation.
   /// Otherwise, it returns a `BorrowMutError`.
   /// # Examples
-  /// ```rust
+  /// ```ignore
   /// use mod2::abstractions::RcCell;
   ///
   /// let x = RcCell::new(1);
@@ -190,13 +242,24 @@ impl<T: ?Sized> RcCell<T> {
   /// let mut x_ref = x.try_borrow_mut();
   /// assert!(x_ref.is_ok());
   /// ```
-  pub fn try_borrow_mut(&self) -> Result<RefMut<T>, BorrowMutError> {
-    self.0.try_borrow_mut()
+  #[track_caller]
+  pub fn try_borrow_mut(&self) -> Result<RcRefMut<'_, T>, BorrowMutError> {
+    let value = self.0.try_borrow_mut()?;
+    #[cfg(debug_assertions)]
+    {
+      let location = Location::caller();
+      MUTABLE_BORROW_LOCATIONS.with(|locations| locations.borrow_mut().insert(self.address(), location));
+    }
+    Ok(RcRefMut {
+      value,
+      #[cfg(debug_assertions)]
+      address: self.address(),
+    })
   }
 
   /// Similar to [RefCell::borrow].
   /// Returns a [Ref] to the inner value if there is no [RefMut] pointing to the same allocation.
-  /// Otherwise, it panics.
+  /// Otherwise, it panics, saying where the mutable borrow was taken in debug builds.
   /// # Examples
   /// ```rust
   /// use mod2::abstractions::RcCell;
@@ -204,13 +267,17 @@ impl<T: ?Sized> RcCell<T> {
   /// let x = RcCell::new(1);
   /// let x_ref = x.borrow();
   /// ```
+  #[track_caller]
   pub fn borrow(&self) -> Ref<T> {
-    self.0.borrow()
+    match self.0.try_borrow() {
+      Ok(value)  => value,
+      Err(error) => panic!("{}{}", error, self.conflict_note())
+    }
   }
 
   /// Similar to [RefCell::borrow_mut].
-  /// Returns a [RefMut] to the inner value if there is no [RefMut] nor [Ref] pointing to the same allocation.
-  /// Otherwise, it panics.
+  /// Returns an [RcRefMut] to the inner value if there is no [RcRefMut] nor [Ref] pointing to the same allocation.
+  /// Otherwise, it panics, saying where the conflicting mutable borrow was taken in debug builds.
   /// # Examples
   /// ```rust
   /// use mod2::abstractions::RcCell;
@@ -218,8 +285,37 @@ impl<T: ?Sized> RcCell<T> {
   /// let x = RcCell::new(1);
   /// let x_ref = x.borrow_mut();
   /// ```
-  pub fn borrow_mut(&self) -> RefMut<T> {
-    self.0.borrow_mut()
+  #[track_caller]
+  pub fn borrow_mut(&self) -> RcRefMut<'_, T> {
+    match self.try_borrow_mut() {
+      Ok(value)  => value,
+      Err(error) => panic!("{}{}", error, self.conflict_note())
+    }
+  }
+
+  /// The source location of the active mutable borrow, if there is one. Only available in debug builds. See the module
+  /// documentation.
+  #[cfg(debug_assertions)]
+  pub fn mutable_borrow_location(&self) -> Option<&'static Location<'static>> {
+    match self.0.try_borrow() {
+      Ok(_)  => None,
+      Err(_) => MUTABLE_BORROW_LOCATIONS.with(|locations| locations.borrow().get(&self.address()).copied())
+    }
+  }
+
+  /// The address of the allocation, which identifies it in `MUTABLE_BORROW_LOCATIONS`.
+  #[cfg(debug_assertions)]
+  fn address(&self) -> usize {
+    Rc::as_ptr(&self.0) as *const () as usize
+  }
+
+  /// The part of a borrow conflict's panic message that says where the active mutable borrow was taken.
+  fn conflict_note(&self) -> String {
+    #[cfg(debug_assertions)]
+    if let Some(location) = self.mutable_borrow_location() {
+      return format!("; the mutable borrow was taken at {}", location);
+    }
+    String::new()
   }
 }
 
@@ -347,3 +443,59 @@ impl<T: ?Sized> PartialEq for WeakCell<T> {
 }
 
 impl<T: ?Sized> Eq for WeakCell<T> {}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn try_borrow_mut_fails_while_a_borrow_is_held() {
+    let x = RcCell::new(1);
+    *x.try_borrow_mut().unwrap() = 2;
+    assert_eq!(*x.borrow(), 2);
+
+    let shared = x.borrow();
+    assert!(x.try_borrow_mut().is_err());
+    drop(shared);
+
+    let _mutable = x.borrow_mut();
+    assert!(x.try_borrow().is_err() && x.try_borrow_mut().is_err());
+  }
+
+  #[test]
+  #[cfg(debug_assertions)]
+  fn conflicts_report_where_the_mutable_borrow_was_taken() {
+    let x = RcCell::new(1);
+    assert!(x.mutable_borrow_location().is_none());
+
+    let line     = line!() + 1;
+    let mutable  = x.borrow_mut();
+    let location = x.mutable_borrow_location().unwrap();
+    assert_eq!((location.file(), location.line()), (file!(), line));
+
+    let message = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| { x.borrow(); })).unwrap_err();
+    let message = message.downcast_ref::<String>().unwrap();
+    assert!(message.ends_with(&format!("the mutable borrow was taken at {}:{}:{}", file!(), line, location.column())));
+
+    drop(mutable);
+    assert!(x.mutable_borrow_location().is_none());
+  }
+
+  #[test]
+  #[cfg(debug_assertions)]
+  fn dropped_mutable_borrows_are_forgotten() {
+    let count = || MUTABLE_BORROW_LOCATIONS.with(|locations| locations.borrow().len());
+    let before = count();
+    let x      = RcCell::new(1);
+    let y      = RcCell::new(2);
+    {
+      let _x = x.borrow_mut();
+      let _y = y.borrow_mut();
+      assert_eq!(count(), before + 2);
+    }
+    assert_eq!(count(), before);
+    *x.try_borrow_mut().unwrap() += 1;
+    assert_eq!(count(), before);
+  }
+}
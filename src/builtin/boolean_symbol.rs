@@ -10,7 +10,8 @@ use crate::{
     symbol::{
       Symbol,
       TheorySymbol,
-      UNSPECIFIED
+      UNSPECIFIED,
+      UNINDEXED
     },
     symbol_type::{CoreSymbolType, SymbolType},
   }
@@ -33,11 +34,19 @@ impl BooleanSymbol {
     };
 
     Symbol {
-      // ToDo: What should the name be?
-      name         : IString::from(""),
+      name         : IString::from(if bool_literal { "true" } else { "false" }),
       arity        : UNSPECIFIED,
       symbol_type,
       sort_spec    : None,
+      overloads    : vec![],
+      index_within_module: UNINDEXED,
+      hash_seed          : 0,
+      identity           : None,
+      doc          : None,
+      latex        : None,
+      frozen       : Default::default(),
+      precedence   : None,
+      gather       : vec![],
       theory_symbol: Some(Box::new(
         BooleanSymbol{
           value: bool_literal
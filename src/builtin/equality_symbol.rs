@@ -0,0 +1,186 @@
+/*!
+
+The built-in equality predicates `_==_` and `_=/=_`, which are available in every module that does not declare
+symbols of those names. An application `t == u` is reduced by reducing `t` and `u` to normal form and comparing the
+normal forms with `DagNode::equals`, giving the built-in `true` or `false`. Terms of different kinds have different
+normal forms, so they are never equal. See `RewritingContext::reduce(…)`.
+
+*/
+
+use crate::{
+  abstractions::{
+    IString,
+    NaturalNumber
+  },
+  theory::{
+    dag_node::DagNode,
+    symbol::{
+      Symbol,
+      TheorySymbol,
+      UNINDEXED
+    },
+    symbol_type::{
+      CoreSymbolType,
+      SymbolType
+    },
+  }
+};
+
+/// The name of the built-in equality predicate.
+pub const EQUAL_NAME: &str = "_==_";
+/// The name of the built-in inequality predicate.
+pub const NOT_EQUAL_NAME: &str = "_=/=_";
+/// The precedence of the equality predicates as infix operators, as in Maude.
+pub const EQUALITY_PRECEDENCE: NaturalNumber = 51;
+
+/// Whether `name` is the name of one of the built-in equality predicates.
+pub fn is_equality_name(name: &str) -> bool {
+  name == EQUAL_NAME || name == NOT_EQUAL_NAME
+}
+
+pub struct EqualitySymbol {
+  /// Whether the symbol is `_=/=_` rather than `_==_`.
+  negated: bool,
+}
+
+impl EqualitySymbol {
+  /// The symbol `_=/=_` if `negated`, and `_==_` otherwise.
+  #[allow(clippy::new_ret_no_self)]
+  pub fn new(negated: bool) -> Symbol {
+    let symbol_type = SymbolType{
+      core_type : CoreSymbolType::Equality,
+      attributes: Default::default(),
+    };
+
+    Symbol {
+      name         : IString::from(if negated { NOT_EQUAL_NAME } else { EQUAL_NAME }),
+      arity        : 2,
+      symbol_type,
+      sort_spec    : None,
+      overloads    : vec![],
+      index_within_module: UNINDEXED,
+      hash_seed          : 0,
+      identity           : None,
+      doc          : None,
+      latex        : None,
+      frozen       : Default::default(),
+      precedence   : Some(EQUALITY_PRECEDENCE),
+      gather       : vec![],
+      theory_symbol: Some(Box::new(
+        EqualitySymbol{
+          negated
+        }
+      )),
+    }
+  }
+}
+
+impl TheorySymbol for EqualitySymbol {
+
+}
+
+/// The value of the equality predicate `symbol` applied to the normal forms `lhs` and `rhs`.
+pub(crate) fn evaluate_equality(symbol: &Symbol, lhs: &DagNode, rhs: &DagNode) -> bool {
+  let equal = lhs.equals(rhs);
+  match symbol.name.as_str() == NOT_EQUAL_NAME {
+    true  => !equal,
+    false => equal
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use lalrpop_util::ParseError;
+
+  use crate::{
+    core::pre_equation::PreEquationKind,
+    parser::parse_to_module
+  };
+  use super::*;
+
+  #[test]
+  fn equality_compares_normal_forms() {
+    let module = parse_to_module(
+      "
+      sort Bool;
+      sort Nat;
+      symbol t :: Bool;
+      symbol z :: Nat;
+      symbol _+_ [prec 33];
+
+      equation 1 + 1 = 2;
+      "
+    ).unwrap();
+    let eval = | source: &str | module.eval(source).unwrap().borrow().to_string();
+
+    assert_eq!(eval("_==_(_+_(1, 1), 2)"), "true");
+    assert_eq!(eval("_==_(\"a\", \"b\")"), "false");
+    assert_eq!(eval("1 + 1 == 2"), "true");
+    assert_eq!(eval("1 + 1 =/= 2"), "false");
+    assert_eq!(eval("_=/=_(\"a\", \"b\")"), "true");
+    // Terms of different kinds are never equal.
+    assert_eq!(eval("t == z"), "false");
+  }
+
+  #[test]
+  fn declared_symbols_shadow_the_built_in_predicates() {
+    let module = parse_to_module(
+      "
+      symbol _==_;
+
+      equation a == b = c;
+      "
+    ).unwrap();
+    assert_eq!(module.eval("a == b").unwrap().borrow().to_string(), "c");
+    assert_eq!(module.eval("a == a").unwrap().borrow().to_string(), "_==_(a, a)");
+  }
+
+  #[test]
+  fn a_module_has_one_symbol_for_each_predicate() {
+    let module = parse_to_module(
+      "
+      variable X;
+
+      equation f(X) = X == a;
+      equation g(X) = X == b;
+      equation h(X) = a if X =/= b;
+      "
+    ).unwrap();
+    let equal = module.symbols[&IString::from(EQUAL_NAME)];
+    for equation in &module.equations[..2] {
+      let rhs_symbol = match &equation.kind {
+        PreEquationKind::Equation { rhs_term } => rhs_term.top_symbol().unwrap(),
+        _ => unreachable!()
+      };
+      assert_eq!(rhs_symbol, equal);
+    }
+    assert!(unsafe{ (*module.symbols[&IString::from(NOT_EQUAL_NAME)]).is_equality() });
+  }
+
+  #[test]
+  fn evaluated_terms_share_the_predicates_without_adding_symbols() {
+    let module = parse_to_module("symbol a;").unwrap();
+    let symbol_count = module.symbols.len();
+    for source in ["a == a", "a =/= a", "a == a"] {
+      module.eval(source).unwrap();
+    }
+    assert_eq!(module.symbols.len(), symbol_count);
+    assert_eq!(module.builtin_symbols.borrow().len(), 2);
+  }
+
+  #[test]
+  fn an_equals_sign_is_not_joined_to_a_following_minus() {
+    let unexpected = | source: &str | match parse_to_module(source) {
+      Err(ParseError::UnrecognizedToken { token: (_, token, _), .. }) => token.to_string(),
+      _ => panic!("`{}` should not parse", source)
+    };
+    // There are no negative literals, so the `-` is unexpected however the equation is spaced.
+    assert_eq!(unexpected("equation f(X) = -1;"), "-");
+    assert_eq!(unexpected("equation f(X)=-1;"), "-");
+    // An operator that begins with `=-` can only be applied by name. Written infix, it reads as `=` and `-`.
+    let module = parse_to_module("symbol _=-_; equation f(X)=_=-_(X, 1);").unwrap();
+    assert!(module.symbols.contains_key(&IString::from("_=-_")));
+    assert_eq!(unexpected("symbol _=-_; reduce a =- b;"), "=");
+  }
+}
@@ -13,7 +13,8 @@ use crate::{
     symbol::{
       Symbol,
       TheorySymbol,
-      UNSPECIFIED
+      UNSPECIFIED,
+      UNINDEXED
     },
     symbol_type::{
       CoreSymbolType,
@@ -34,12 +35,20 @@ impl IntegerSymbol {
     };
 
     Symbol {
-      // ToDo: What should the name be? So far we have assumed the symbol name uniquely identifies the symbol. However,
-      //       literals have no name.
-      name         : IString::from(""),
+      // The name of a literal is its printed value, which cannot collide with an identifier.
+      name         : IString::from(integer_literal.to_string().as_str()),
       arity        : UNSPECIFIED,
       symbol_type,
       sort_spec    : None,
+      overloads    : vec![],
+      index_within_module: UNINDEXED,
+      hash_seed          : 0,
+      identity           : None,
+      doc          : None,
+      latex        : None,
+      frozen       : Default::default(),
+      precedence   : None,
+      gather       : vec![],
       theory_symbol: Some(Box::new(
         IntegerSymbol{
           value: integer_literal
@@ -1,3 +1,5 @@
 pub mod string_symbol;
 pub mod integer_symbol;
+pub mod rational_symbol;
 pub mod boolean_symbol;
+pub mod equality_symbol;
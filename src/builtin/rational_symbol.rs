@@ -0,0 +1,82 @@
+/*!
+
+A rational literal like `1/3` is represented by a special rational literal symbol. The fraction is reduced to lowest
+terms when the symbol is created, so `2/6` and `1/3` are the same literal, and its name is the reduced fraction. A
+rational literal is always written as a fraction, even when its denominator is 1, so `3/1` is a rational and is
+distinct from the natural number `3`.
+
+ToDo: Rationals are limited to the range of `NaturalNumber`, and there are no negative rationals.
+
+*/
+
+use crate::{
+  abstractions::{
+    IString,
+    NaturalNumber
+  },
+  theory::{
+    symbol::{
+      Symbol,
+      TheorySymbol,
+      UNSPECIFIED,
+      UNINDEXED
+    },
+    symbol_type::{
+      CoreSymbolType,
+      SymbolType
+    },
+  }
+};
+
+pub struct RationalSymbol {
+  numerator  : NaturalNumber,
+  denominator: NaturalNumber,
+}
+
+impl RationalSymbol {
+  /// The literal for the fraction `numerator/denominator`, which must have a nonzero denominator.
+  pub fn new(numerator: NaturalNumber, denominator: NaturalNumber) -> Symbol {
+    assert_ne!(denominator, 0, "a rational literal must have a nonzero denominator");
+    let divisor     = gcd(numerator, denominator);
+    let numerator   = numerator / divisor;
+    let denominator = denominator / divisor;
+    let symbol_type = SymbolType{
+      core_type : CoreSymbolType::Rational,
+      attributes: Default::default(),
+    };
+
+    Symbol {
+      // The name of a literal is its printed value, which cannot collide with an identifier.
+      name         : IString::from(format!("{}/{}", numerator, denominator).as_str()),
+      arity        : UNSPECIFIED,
+      symbol_type,
+      sort_spec    : None,
+      overloads    : vec![],
+      index_within_module: UNINDEXED,
+      hash_seed          : 0,
+      identity           : None,
+      doc          : None,
+      latex        : None,
+      frozen       : Default::default(),
+      precedence   : None,
+      gather       : vec![],
+      theory_symbol: Some(Box::new(
+        RationalSymbol{
+          numerator,
+          denominator
+        }
+      )),
+    }
+  }
+}
+
+impl TheorySymbol for RationalSymbol {
+
+}
+
+fn gcd(mut a: NaturalNumber, mut b: NaturalNumber) -> NaturalNumber {
+  while b != 0 {
+    (a, b) = (b, a % b);
+  }
+  a
+}
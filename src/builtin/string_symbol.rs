@@ -10,7 +10,8 @@ use crate::{
     symbol::{
       Symbol,
       TheorySymbol,
-      UNSPECIFIED
+      UNSPECIFIED,
+      UNINDEXED
     },
     symbol_type::{
       CoreSymbolType,
@@ -32,12 +33,20 @@ impl StringSymbol {
     };
 
     Symbol {
-      // ToDo: What should the name be? So far we have assumed the symbol name uniquely identifies the symbol. However,
-      //       strings have no name.
-      name         : IString::from(""),
+      // The name of a literal is its value. Formatting adds the surrounding quotes.
+      name         : IString::from(string_literal.as_str()),
       arity        : UNSPECIFIED,
       symbol_type,
       sort_spec    : Some(Box::new(SortSpec::Any)),
+      overloads    : vec![],
+      index_within_module: UNINDEXED,
+      hash_seed          : 0,
+      identity           : None,
+      doc          : None,
+      latex        : None,
+      frozen       : Default::default(),
+      precedence   : None,
+      gather       : vec![],
       theory_symbol: Some(Box::new(StringSymbol{value: string_literal})),
     }
   }
@@ -0,0 +1,55 @@
+/*!
+
+Applying a particular rule at a particular position with `Module::apply_rule(…)` fails if there is no rule with the
+given label, if the position does not exist in the subject, or if no rule with the label applies there.
+
+*/
+
+use std::error::Error;
+use std::fmt::{Debug, Display, Formatter};
+
+use crate::abstractions::IString;
+
+#[derive(Clone, Eq, PartialEq)]
+pub enum ApplyError {
+  /// The module has no rule with this label.
+  UnknownLabel(IString),
+  /// The position, a path of argument indices from the top of the subject, does not exist in the subject.
+  InvalidPosition(Vec<usize>),
+  /// No rule with the label matches the subterm at the position, or its condition fails.
+  NoMatch {
+    label   : IString,
+    position: Vec<usize>
+  },
+}
+
+impl Display for ApplyError {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    match self {
+
+      ApplyError::UnknownLabel(label) => write!(f, "there is no rule with label {}.", label),
+
+      ApplyError::InvalidPosition(position) => {
+        write!(f, "the subject has no subterm at position {}.", position_string(position))
+      }
+
+      ApplyError::NoMatch { label, position } => {
+        write!(f, "rule {} does not apply at position {}.", label, position_string(position))
+      }
+
+    }
+  }
+}
+
+impl Debug for ApplyError {
+  fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+    Display::fmt(self, f)
+  }
+}
+
+impl Error for ApplyError{}
+
+/// Renders a position like `[0, 1]`.
+pub(crate) fn position_string(position: &[usize]) -> String {
+  format!("[{}]", position.iter().map(|index| index.to_string()).collect::<Vec<_>>().join(", "))
+}
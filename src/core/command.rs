@@ -0,0 +1,76 @@
+/*!
+
+Commands exercise a module, like Maude's `reduce` and `match` commands. Commands can appear among the items of a
+module file and are returned alongside the constructed module by `parse_to_module_with_commands(…)`.
+
+```ebnf
+Command := ("reduce" | "red") Term ";"
+         | "match" Term "with" Term ";"
+         ;
+```
+
+*/
+
+use std::fmt::{Display, Formatter};
+
+use crate::{
+  core::{
+    format::{
+      FormatStyle,
+      Formattable
+    },
+    module::Module,
+    substitution::Substitution
+  },
+  theory::{
+    dag_node::RcDagNode,
+    term::BxTerm
+  }
+};
+
+pub enum Command {
+  /// Reduces the term to normal form.
+  Reduce(BxTerm),
+  /// Matches the pattern against the subject, which is reduced first.
+  Match {
+    pattern: BxTerm,
+    subject: BxTerm
+  },
+}
+
+impl Command {
+  /// Executes the command in `module`.
+  pub fn run(&self, module: &Module) -> CommandResult {
+    match self {
+
+      Command::Reduce(term) => CommandResult::Reduced(module.reduce_dag(&term.dagify())),
+
+      Command::Match { pattern, subject } => {
+        let subject          = module.reduce_dag(&subject.dagify());
+        let mut substitution = Substitution::with_capacity(module.minimum_substitution_size());
+        match pattern.match_dag(&subject, &mut substitution) {
+          true  => CommandResult::Matched(Some(substitution)),
+          false => CommandResult::Matched(None)
+        }
+      }
+
+    }
+  }
+}
+
+pub enum CommandResult {
+  /// The normal form of a reduced term.
+  Reduced(RcDagNode),
+  /// The matching substitution, or `None` if there is no match.
+  Matched(Option<Substitution>),
+}
+
+impl Display for CommandResult {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    match self {
+      CommandResult::Reduced(result)             => write!(f, "result: {}", result.borrow().repr(FormatStyle::Default)),
+      CommandResult::Matched(Some(substitution)) => write!(f, "match: {}", substitution.repr(FormatStyle::Default)),
+      CommandResult::Matched(None)               => write!(f, "no match"),
+    }
+  }
+}
@@ -0,0 +1,39 @@
+/*!
+
+A `Debugger` is consulted by a `RewritingContext` before every equation or rule application, which lets an embedder
+build an interactive stepper, a breakpoint facility, or a limit on the amount of work done. A context has no debugger
+unless one is attached with `RewritingContext::set_debugger(…)`.
+
+The debugger is shown the redex, the subterm about to be rewritten, together with the statement that matched it, and
+answers with a `StepAction`:
+
+ * `StepAction::Step` performs the rewrite and consults the debugger again before the next one.
+ * `StepAction::Continue` performs the rewrite and detaches the debugger, so the rest of the work runs freely.
+ * `StepAction::Abort` discards the rewrite and stops all further rewriting in the context. Reduction then returns
+   the term as it stands, which is not in normal form, and `RewritingContext::is_aborted()` is true.
+
+*/
+
+use crate::{
+  core::pre_equation::PreEquation,
+  theory::dag_node::RcDagNode
+};
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum StepAction {
+  Continue,
+  Step,
+  Abort,
+}
+
+pub trait Debugger {
+  /// Called when `statement` is about to rewrite `redex`.
+  fn on_step(&mut self, redex: &RcDagNode, statement: &PreEquation) -> StepAction;
+}
+
+/// A closure can serve as a debugger.
+impl<F: FnMut(&RcDagNode, &PreEquation) -> StepAction> Debugger for F {
+  fn on_step(&mut self, redex: &RcDagNode, statement: &PreEquation) -> StepAction {
+    self(redex, statement)
+  }
+}
@@ -0,0 +1,84 @@
+/*!
+
+A `Diagnostic` reports a problem with a statement of a module that does not prevent the module from being constructed,
+like a kind clash found by `Module::sort_check(…)`. Frontends can display diagnostics next to the statement they refer
+to, which is identified by its index in the order of `Module::statements(…)`: equations, then rules, then membership
+axioms. A statement parsed from source text also records its `Span`, the range of bytes of the text it was parsed
+from, which a diagnostic carries so that frontends can underline the statement in the source.
+
+*/
+
+use std::fmt::{Display, Formatter};
+
+/// A range of bytes of the source text, from `start` up to but not including `end`, which begins at the 1-based
+/// `line` and `column`.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default)]
+pub struct Span {
+  pub start : usize,
+  pub end   : usize,
+  pub line  : usize,
+  pub column: usize,
+}
+
+impl Span {
+  /// The span of the bytes of `source` from `start` up to but not including `end`.
+  pub fn new(source: &str, start: usize, end: usize) -> Span {
+    let (line, column) = line_and_column(source, start);
+    Span { start, end, line, column }
+  }
+}
+
+/// Spans are shown as `line:column`, the position of their start in the source text.
+impl Display for Span {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    write!(f, "{}:{}", self.line, self.column)
+  }
+}
+
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct Diagnostic {
+  /// The index of the offending statement among the statements of the module.
+  pub statement_index: usize,
+  /// The span of the offending statement, if it was parsed from source text.
+  pub span           : Option<Span>,
+  pub message        : String,
+}
+
+impl Display for Diagnostic {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    write!(f, "statement {}: {}", self.statement_index, self.message)
+  }
+}
+
+/// The 1-based line and column of the character at byte `offset` of `input`. Columns count characters, not bytes. An
+/// offset past the end of `input` is taken to be the end, and an offset inside a character is taken to be its start.
+pub(crate) fn line_and_column(input: &str, offset: usize) -> (usize, usize) {
+  let mut offset = offset.min(input.len());
+  while !input.is_char_boundary(offset) {
+    offset -= 1;
+  }
+  let before     = &input[..offset];
+  let line       = before.matches('\n').count() + 1;
+  let line_start = before.rfind('\n').map_or(0, |newline| newline + 1);
+  (line, before[line_start..].chars().count() + 1)
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn offsets_are_located_without_panicking() {
+    let source = "a\nbé c";
+    assert_eq!(line_and_column(source, 0), (1, 1));
+    assert_eq!(line_and_column(source, 3), (2, 2));
+    // The second byte of `é` is inside the character, so the offset is taken to be the start of `é`.
+    assert_eq!(line_and_column(source, 4), (2, 2));
+    assert_eq!(line_and_column(source, 5), (2, 3));
+    assert_eq!(line_and_column(source, 100), (2, 5));
+
+    let span = Span::new(source, 5, 7);
+    assert_eq!(span.to_string(), "2:3");
+  }
+}
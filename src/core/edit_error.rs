@@ -0,0 +1,79 @@
+/*!
+
+Adding a statement to a module with `Module::add_statement(…)` fails if the source text is not exactly one item of a
+module, if the item is a submodule or a command, if it is an inconsistent declaration, or if it is a subsort
+declaration that would merge kinds of the module, which an incremental edit does not do.
+
+*/
+
+use std::error::Error;
+use std::fmt::{Debug, Display, Formatter};
+
+use crate::{
+  abstractions::IString,
+  parser::{
+    ParseError,
+    ParseErrorKind
+  }
+};
+
+#[derive(Clone, Eq, PartialEq)]
+pub enum EditError {
+  /// The source text is not an item of a module. The message is that of the parse error, which borrows the source
+  /// text.
+  Parse(String),
+  /// The source text has this many items rather than one.
+  ItemCount(usize),
+  /// An item that cannot be added to an existing module, either "submodule" or "command".
+  Unsupported(&'static str),
+  /// A declaration that is inconsistent with the module, like a symbol declared again without overloading it.
+  Declaration(ParseErrorKind),
+  /// A subsort declaration relating sorts of these two kinds, given by a sort of each. Merging kinds changes the
+  /// kinds of existing terms, so the module must be constructed again from its complete source instead.
+  KindMerge(IString, IString),
+}
+
+impl From<ParseError<'_>> for EditError {
+  fn from(error: ParseError<'_>) -> Self {
+    EditError::Parse(error.to_string())
+  }
+}
+
+impl From<ParseErrorKind> for EditError {
+  fn from(error: ParseErrorKind) -> Self {
+    EditError::Declaration(error)
+  }
+}
+
+impl Display for EditError {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    match self {
+
+      EditError::Parse(message) => write!(f, "the source is not an item of a module: {}", message),
+
+      EditError::ItemCount(count) => write!(f, "the source has {} items rather than one.", count),
+
+      EditError::Unsupported(item) => write!(f, "a {} cannot be added to an existing module.", item),
+
+      EditError::Declaration(error) => Display::fmt(error, f),
+
+      EditError::KindMerge(first, second) => {
+        write!(
+          f,
+          "the subsort declaration would merge the kinds of {} and {}, which requires constructing the module again.",
+          first,
+          second
+        )
+      }
+
+    }
+  }
+}
+
+impl Debug for EditError {
+  fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+    Display::fmt(self, f)
+  }
+}
+
+impl Error for EditError{}
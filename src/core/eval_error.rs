@@ -0,0 +1,59 @@
+/*!
+
+Evaluating source text with `Module::eval(…)` fails if the text is not a term, if it names a symbol the module does
+not have, or if the term is ill-sorted.
+
+*/
+
+use std::error::Error;
+use std::fmt::{Debug, Display, Formatter};
+
+use crate::{
+  abstractions::IString,
+  parser::{
+    ParseError,
+    ParseErrorKind
+  }
+};
+
+#[derive(Clone, Eq, PartialEq)]
+pub enum EvalError {
+  /// The source text is not a term. The message is that of the parse error, which borrows the source text.
+  Parse(String),
+  /// The term names a symbol that is not a symbol of the module.
+  UnknownSymbol(IString),
+  /// No declaration of the top symbol of this subterm, the innermost one that is ill-sorted, fits the sorts of its
+  /// arguments.
+  IllSorted(String),
+}
+
+impl From<ParseError<'_>> for EvalError {
+  fn from(error: ParseError<'_>) -> Self {
+    match error {
+      ParseError::User { error: ParseErrorKind::UnknownSymbol(name) } => EvalError::UnknownSymbol(name),
+      error => EvalError::Parse(error.to_string())
+    }
+  }
+}
+
+impl Display for EvalError {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    match self {
+
+      EvalError::Parse(message) => write!(f, "the source is not a term: {}", message),
+
+      EvalError::UnknownSymbol(name) => write!(f, "the symbol {} is not declared in the module.", name),
+
+      EvalError::IllSorted(term) => write!(f, "the term {} is ill-sorted.", term),
+
+    }
+  }
+}
+
+impl Debug for EvalError {
+  fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+    Display::fmt(self, f)
+  }
+}
+
+impl Error for EvalError{}
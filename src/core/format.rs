@@ -0,0 +1,166 @@
+/*!
+
+Formatting of terms, symbols, and DAG nodes for display. A `Formattable` item can be rendered in several
+`FormatStyle`s. The `Default` and `Simple` styles are intended for users, while the `Debug` style exposes internal
+details like the wrappers around built-in literals. The `Latex` style produces math-mode LaTeX, using the macro given
+in a symbol's `latex(…)` attribute when there is one.
+
+*/
+
+/// Selects how a `Formattable` item is rendered.
+#[derive(Copy, Clone, Eq, PartialEq, Default, Debug)]
+pub enum FormatStyle {
+  #[default]
+  Default,
+  /// A minimal rendering. Currently identical to `Default`.
+  Simple,
+  /// Shows internal structure, e.g. `NaturalNumber(42)` instead of `42`.
+  Debug,
+  /// Math-mode LaTeX. Symbols without a LaTeX macro are rendered as their escaped names.
+  Latex,
+  /// An S-expression, e.g. `(f (g a) (var X) 1)`, for tools that read terms. Variables are written `(var X)`, strings
+  /// are double quoted, and names that would not read back as themselves are quoted with `|…|`. The text can be read
+  /// back with `parse_sexpr(…)`.
+  SExpr,
+}
+
+/// Items that can be rendered to a string in a given `FormatStyle`.
+pub trait Formattable {
+  fn repr(&self, style: FormatStyle) -> String;
+}
+
+/// Escapes the characters that are special in LaTeX.
+pub fn latex_escape(text: &str) -> String {
+  let mut escaped = String::with_capacity(text.len());
+  for c in text.chars() {
+    match c {
+      '\\'                                    => escaped.push_str("\\backslash{}"),
+      '~'                                     => escaped.push_str("\\sim{}"),
+      '^'                                     => escaped.push_str("\\hat{}"),
+      '{' | '}' | '_' | '#' | '$' | '%' | '&' => {
+        escaped.push('\\');
+        escaped.push(c);
+      }
+      _                                       => escaped.push(c),
+    }
+  }
+  escaped
+}
+
+/// Quotes `name` with `|…|` if it would not read back as a single S-expression atom naming a symbol. Names that read
+/// as numbers and the name `var`, which introduces variables, are quoted too.
+pub fn sexpr_atom(name: &str) -> String {
+  let needs_quotes = name.is_empty()
+      || reads_as_number(name)
+      || name == "var"
+      || name.chars().any(|c| c.is_whitespace() || matches!(c, '(' | ')' | '"' | '|' | ';' | '\\' | '\''));
+  if needs_quotes {
+    format!("|{}|", name.replace('\\', "\\\\").replace('|', "\\|"))
+  } else {
+    name.to_string()
+  }
+}
+
+/// Whether `text` is written like a natural number literal, as in `3`, or a rational literal, as in `1/3`.
+pub(crate) fn reads_as_number(text: &str) -> bool {
+  let digits = |text: &str| !text.is_empty() && text.chars().all(|c| c.is_ascii_digit());
+  match text.split_once('/') {
+    Some((numerator, denominator)) => digits(numerator) && digits(denominator),
+    None                           => digits(text)
+  }
+}
+
+/// A double quoted string literal with `"` and `\` escaped, which reads back as `text` both as an S-expression string
+/// and as a string literal in a term.
+pub fn sexpr_string(text: &str) -> String {
+  format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+
+#[cfg(test)]
+mod tests {
+  use crate::{
+    builtin::{
+      boolean_symbol::BooleanSymbol,
+      integer_symbol::IntegerSymbol,
+      string_symbol::StringSymbol
+    },
+    heap_construct,
+    parser::{
+      parse_to_module,
+      parse_to_term
+    },
+    theory::{
+      symbol::Symbol,
+      term::{Term, TermAttributes, TermNode}
+    },
+    abstractions::IString,
+  };
+  use super::*;
+
+  fn symbol_term(symbol: Symbol) -> Term {
+    Term {
+      term_node : TermNode::Symbol(heap_construct!(symbol)),
+      attributes: TermAttributes::default()
+    }
+  }
+
+  #[test]
+  fn built_in_literals_print_as_values() {
+    let integer = symbol_term(IntegerSymbol::new(42));
+    let string  = symbol_term(StringSymbol::new("hello world".to_string()));
+    let boolean = symbol_term(BooleanSymbol::new(true));
+
+    assert_eq!(integer.repr(FormatStyle::Default), "42");
+    assert_eq!(integer.repr(FormatStyle::Simple), "42");
+    assert_eq!(string.repr(FormatStyle::Default), "\"hello world\"");
+    assert_eq!(boolean.repr(FormatStyle::Default), "true");
+
+    assert_eq!(integer.repr(FormatStyle::Debug), "NaturalNumber(42)");
+    assert_eq!(string.repr(FormatStyle::Debug), "String(\"hello world\")");
+
+    // Quotes and backslashes are escaped, so the literal can be parsed back.
+    let quoted = symbol_term(StringSymbol::new("say \"hi\" \\o/".to_string()));
+    let printed = quoted.repr(FormatStyle::Default);
+    assert_eq!(printed, "\"say \\\"hi\\\" \\\\o/\"");
+    let module = parse_to_module("symbol f;").unwrap();
+    assert_eq!(parse_to_term(&printed, &module).unwrap().repr(FormatStyle::Default), printed);
+  }
+
+  #[test]
+  fn literals_print_within_applications() {
+    let term = Term {
+      term_node: TermNode::Application {
+        head: Box::new(symbol_term(Symbol::new(IString::from("f")))),
+        tail: vec![
+          Box::new(symbol_term(IntegerSymbol::new(1))),
+          Box::new(symbol_term(StringSymbol::new("a".to_string()))),
+        ],
+      },
+      attributes: TermAttributes::default()
+    };
+
+    assert_eq!(term.repr(FormatStyle::Default), "f(1, \"a\")");
+    assert_eq!(term.to_string(), "f(1, \"a\")");
+  }
+
+  #[test]
+  fn latex_escapes_special_characters() {
+    assert_eq!(latex_escape("a_b"), "a\\_b");
+    assert_eq!(latex_escape("50% & {x}"), "50\\% \\& \\{x\\}");
+  }
+
+  #[test]
+  fn sexpr_quotes_special_names() {
+    assert_eq!(sexpr_atom("plus"), "plus");
+    assert_eq!(sexpr_atom("_+_"), "_+_");
+    assert_eq!(sexpr_atom("a b"), "|a b|");
+    assert_eq!(sexpr_atom("f(x)"), "|f(x)|");
+    assert_eq!(sexpr_atom("var"), "|var|");
+    assert_eq!(sexpr_atom("12"), "|12|");
+    assert_eq!(sexpr_atom("1/3"), "|1/3|");
+    assert_eq!(sexpr_atom("_/_"), "_/_");
+    assert_eq!(sexpr_atom(""), "||");
+    assert_eq!(sexpr_string("say \"hi\""), "\"say \\\"hi\\\"\"");
+  }
+}
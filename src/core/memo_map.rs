@@ -0,0 +1,54 @@
+/*!
+
+A `MemoMap` remembers the normal forms of terms whose top symbol carries the `memo` attribute, as in
+`symbol fib [memo];`, so that a `RewritingContext` reduces each such term only once. Reducing `fib(s(s(N)))` to
+`plus(fib(s(N)), fib(N))` then applies the `fib` equations a number of times linear in `N` instead of exponential in
+it, because the many copies of each `fib(M)` subterm are looked up rather than recomputed.
+
+Entries are keyed by the structural hash of the term after its arguments are reduced, and confirmed with
+`DagNode::equals`, in the same way a `DagNodeCache` hash conses nodes. All structurally equal terms therefore share
+one entry, even when they are distinct nodes. A memo map belongs to a single context, so memoized results do not
+outlive the context they were computed in.
+
+*/
+
+use crate::{
+  abstractions::{
+    HashMap,
+    HashType
+  },
+  theory::dag_node::RcDagNode
+};
+
+#[derive(Clone, Default)]
+pub struct MemoMap {
+  /// Pairs of a term and its normal form, bucketed by the structural hash of the term.
+  entries: HashMap<HashType, Vec<(RcDagNode, RcDagNode)>>,
+}
+
+impl MemoMap {
+  /// The normal form of the term structurally equal to `subject`, if one was recorded.
+  pub fn get(&self, subject: &RcDagNode) -> Option<RcDagNode> {
+    let hash = subject.borrow_mut().structural_hash();
+    self.entries
+        .get(&hash)?
+        .iter()
+        .find(|(term, _)| term.borrow().equals(&subject.borrow()))
+        .map(|(_, normal_form)| normal_form.clone())
+  }
+
+  /// Records `normal_form` as the normal form of `subject`.
+  pub fn insert(&mut self, subject: &RcDagNode, normal_form: &RcDagNode) {
+    let hash = subject.borrow_mut().structural_hash();
+    self.entries.entry(hash).or_default().push((subject.clone(), normal_form.clone()));
+  }
+
+  /// The number of recorded normal forms.
+  pub fn len(&self) -> usize {
+    self.entries.values().map(|bucket| bucket.len()).sum()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.entries.is_empty()
+  }
+}
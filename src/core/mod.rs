@@ -1,3 +1,26 @@
 pub mod sort;
 pub mod module;
 pub mod pre_equation;
+pub mod format;
+pub mod substitution;
+pub mod command;
+pub mod rewriting_context;
+pub mod debugger;
+pub mod memo_map;
+pub mod apply_error;
+pub mod position_error;
+pub mod reduce_error;
+pub mod eval_error;
+pub mod edit_error;
+pub mod reduce_cache;
+pub mod signature;
+pub mod state_graph;
+pub mod strategy;
+pub mod unification;
+pub mod module_builder;
+pub mod module_error;
+pub mod diagnostic;
+pub mod narrowing;
+pub mod subsumption;
+#[cfg(feature = "profiling")]
+pub mod profile;
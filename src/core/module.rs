@@ -13,21 +13,69 @@ same statuses as Maude, but it's not clear to me if I'll need them.
 The connected components of the lattice of sorts (the "kinds") is computed by computing the transitive closure of the
 subsort relation. This is done by calling the method `Module::compute_kind_closures(…)`.
 
+### Closure of the Theory
+
+`Module::close_theory(…)` performs the remaining steps once every item of the module has been added: it computes the
+kind closures, assigns each symbol its `index_within_module` and the module's hash seed, and computes the minimum
+substitution size. The hash seed makes structural hashes module-scoped, so a `DagNodeCache` shared between modules
+does not group the nodes of different modules together by accident.
+
+### Editing a Closed Module
+
+`Module::add_statement(…)` adds a single declaration or statement, given as source text, to a closed module. Rather
+than closing the theory again, it recomputes only the kinds the item affects and numbers only the new symbols, so the
+sorts and symbols of the rest of the module keep their numbering. A subsort declaration that would merge two kinds is
+rejected, because merging kinds changes the kinds of existing sorts.
+
+## Reduction
+
+`Module::reduce(…)` rewrites a term to normal form with the module's equations, innermost first, within a
+`RewritingContext`, and `Module::reduce_dag(…)` does the same for a DAG. Rules are not applied by reduction. If the
+module's reduce cache is enabled with `Module::set_reduce_cache_limit(…)`, reducing a term that has been reduced
+before returns the cached normal form. See the `reduce_cache` module. `Module::rewrite(…)` applies rules one at a
+time, reducing to equational normal form before and after every rule application, as Maude's `rewrite` command does.
+A particular rule can be applied at a particular position by its label, as in `rule [swap] g(X, Y) => g(Y, X);`,
+with `Module::apply_rule(…)`. Commands parsed along with a module, like `reduce f(a);`, are executed with
+`Module::run_commands(…)`. `Module::eval(…)` parses, sort-checks, and reduces a term given as source text in one
+step. `Module::reduce_with_canonicity(…)` also reports whether the normal form consists only of constructors, as a
+normal form should if the equations define every function completely.
+
 ## See Also...
 
  * The module system section of the [Design Notes](crate).
 
 */
 
+use std::{
+  cell::RefCell,
+  sync::atomic::{
+    AtomicU32,
+    Ordering as AtomicOrdering
+  }
+};
 
 use crate::{
   abstractions::{
     HashMap,
+    HashSet,
+    HashType,
     IString,
     Channel,
     log
   },
   core::{
+    apply_error::ApplyError,
+    diagnostic::Diagnostic,
+    eval_error::EvalError,
+    edit_error::EditError,
+    command::{
+      Command,
+      CommandResult
+    },
+    format::{
+      FormatStyle,
+      Formattable
+    },
     sort::{
       collection::SortCollection,
       kind::{
@@ -36,16 +84,51 @@ use crate::{
         KindPtr
       },
       kind_error::KindError,
+      sort_cycle_error::SortCycleError,
+      SortPtr
+    },
+    pre_equation::{
+      PreEquation,
+      PreEquationAttribute,
+      PreEquationKind
+    },
+    reduce_cache::ReduceCache,
+    reduce_error::ReduceError,
+    rewriting_context::{
+      RewriteStats,
+      RewritingContext
     },
-    pre_equation::PreEquation,
+    substitution::Substitution,
   },
   heap_destroy,
-  theory::symbol::{
-    Symbol,
-    SymbolPtr
+  parser::{
+    parse_item,
+    parse_to_term,
+    ParseErrorKind
+  },
+  theory::{
+    dag_node::RcDagNode,
+    symbol::{
+      Symbol,
+      SymbolPtr,
+      UNINDEXED
+    },
+    symbol_type::SymbolAttribute,
+    term::{
+      BxTerm,
+      Term
+    }
   },
 };
+#[cfg(feature = "profiling")]
+use crate::core::profile::{
+  ModuleProfile,
+  StatementProfile
+};
+
 
+/// The source of module hash seeds. Zero is reserved for unseeded symbols.
+static NEXT_HASH_SEED: AtomicU32 = AtomicU32::new(1);
 
 #[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Default, Debug)]
 pub enum ModuleStatus {
@@ -75,71 +158,1508 @@ pub struct Module {
   pub membership: Vec<PreEquation>,
   // pub strategies: Vec<PreEquation>, // Unimplemented
 
-  // Members for performance profiling
+  /// The number of substitution slots needed to apply any statement of the module. See
+  /// `Module::notify_substitution_size(…)`.
+  pub(crate) minimum_substitution_size: usize,
+  /// Mixed into the hash of every symbol of the module, so that equal terms of different modules have different
+  /// structural hashes. Zero until `Module::close_theory(…)` assigns it.
+  pub(crate) hash_seed: HashType,
+  /// Normal forms of previously reduced terms. Disabled unless given a limit. See the `reduce_cache` module.
+  pub(crate) reduce_cache: RefCell<ReduceCache>,
+  /// The fresh variables made for narrowing, each with the variable it copies. See the `narrowing` module.
+  pub(crate) fresh_variables: RefCell<Vec<(SymbolPtr, SymbolPtr)>>,
+  /// The built-in symbols made for terms parsed by `parse_to_term(…)` that the module has no symbols for, like the
+  /// equality predicates. See `TermAST::construct_existing(…)`.
+  pub(crate) builtin_symbols: RefCell<HashMap<IString, SymbolPtr>>,
+  /// Counts of the rewrites and condition fragments of each statement. See the `profile` module.
+  #[cfg(feature = "profiling")]
+  pub(crate) profile: RefCell<ModuleProfile>,
+
+  // Members for performance profiling not yet implemented
   // symbol_info: Vec<SymbolProfile>,
-  // mb_info    : Vec<StatementProfile>, // Membership
-  // eq_info    : Vec<StatementProfile>, // Equation
-  // rl_info    : Vec<StatementProfile>, // Rule
   // sd_info    : Vec<StatementProfile>, // Strategy Definition
 }
 
 impl Module {
   /**
-  Computes the transitive closure of the subsort relation, constructing the lattice of sorts. This only needs to be
-  done once when the module is constructed. It is not idempotent.
+  Computes the transitive closure of the subsort relation, constructing the lattice of sorts. This assigns each sort its
+  `Kind`, its `index_within_kind`, and its `leq_sorts`.
+
+  The `Module::close_theory(…)` method calls this method automatically, so any module constructed by the parser,
+  for example, will not need to have this method called on it. The method can be called again after subsorts have
+  been added to recompute the `Kind`s from scratch. Any previously computed `Kind`s are discarded, and every sort is
+  renumbered. If the subsort declarations form a cycle, a warning is logged and no kinds are computed. See
+  `SortCollection::compute_closure(…)`. The kinds are the connected components of the closure, as found by
+  `Graph::connected_components(…)`.
+
+  Before the first call to this method, a module will have `status == ModuleStatus::Open`. The method sets the status
+  to `ModuleStatus::SortSetClosed` if it is not already later than that, so at any point after this method call, a
+  module will have `status >= ModuleStatus::SortSetClosed`.
+  */
+  pub fn compute_kind_closures(&mut self) {
+    // Dropping the old kinds detaches their error sorts from the lattice.
+    self.kinds.clear();
+
+    // Visit the sorts in a deterministic order so that the numbering does not depend on hash map iteration order.
+    let mut sorts: Vec<_> = self.sorts.iter().collect();
+    sorts.sort_by_key(|(name, _)| *name);
+
+    for (_, sort) in sorts.iter() {
+      unsafe { (**sort).clear_kind_info(); }
+    }
+
+    // Kinds are only computed for a partial order.
+    let closure = match self.sorts.compute_closure() {
+      Ok(closure) => closure,
+      Err(error)  => {
+        log(Channel::Warning, 1, error.to_string().as_str());
+        return;
+      }
+    };
+
+    // The kinds are the connected components of the subsort relation. The sorts of the closure are in name order, so
+    // each component starts with the least name among its sorts.
+    for component in closure.connected_components() {
+      let component: Vec<SortPtr> = component.iter().map(|index| *closure.node(*index)).collect();
+      self.add_kind(&component);
+    }
+
+    if self.status < ModuleStatus::SortSetClosed {
+      self.status = ModuleStatus::SortSetClosed
+    }
+  }
+
+  /// Constructs the kind of the sorts of `component`, a connected component of the subsort relation whose sorts have
+  /// no kind, and adds it to the module. A malformed kind is logged and added anyway.
+  fn add_kind(&mut self, component: &[SortPtr]) {
+    let kind = unsafe { Kind::new(component) };
+    let kind = kind.unwrap_or_else(
+      | kind_error | {
+        // Maude sets the "is_bad" flag of a module in the case of a cycle in the Sort graph.
+        let msg = kind_error.to_string();
+        match kind_error {
 
-  The `ModuleAST::construct(…)` method calls this method automatically, so any module constructed by the parser,
-  for example, will not need to have this method called on it.
+          KindError::NoMaximalSort { kind, .. }
+          | KindError::CycleDetected { kind, .. } => {
+            log(Channel::Warning, 1, msg.as_str());
+            // Box::into_raw(kind)
+            kind
+          }
 
-  Before this method call, a module will have `status == ModuleStatus::Open`. The method sets the status to
-  `ModuleStatus::SortSetClosed`, so at any point after this method call, a module will have
-  `status >= ModuleStatus::SortSetClosed`.
+        }
+      }
+    );
 
-  ToDo: It would be nice if this method were idempotent. Low priority.
+    // Maude sets the index_in_parent of the kind here.
+    self.kinds.push(kind);
+  }
+
+  /**
+  Completes the construction of the module. Computes the kind closures, numbers the symbols, marks malformed statements
+  as bad, and computes the minimum substitution size, after which the module has
+  `status == ModuleStatus::TheoryClosed`.
+
+  Symbols are numbered in name order so that the numbering, and therefore `Symbol::compare(…)`, does not depend on
+  hash map iteration order. Every symbol of the module, whether declared or implicitly defined, gets a distinct index.
   */
-  pub unsafe fn compute_kind_closures(&mut self) {
-    assert_eq!(self.status, ModuleStatus::Open, "tried to compute kind closure when module status is not open");
-
-    for (_, sort) in
-        self.sorts
-            .iter()
-            .filter(|(_, sort_ptr)| (**sort_ptr).kind.is_null())
-    {
-      let kind = unsafe { Kind::new(sort) };
-      let mut kind = kind.unwrap_or_else(
-        | kind_error | {
-          // Maude sets the "is_bad" flag of a module in the case of a cycle in the Sort graph.
-          let msg = kind_error.to_string();
-          match kind_error {
-
-            KindError::NoMaximalSort { kind, .. }
-            | KindError::CycleDetected { kind, .. } => {
-              log(Channel::Warning, 1, msg.as_str());
-              // Box::into_raw(kind)
-              kind
-            }
+  pub fn close_theory(&mut self) {
+    self.compute_kind_closures();
 
+    let mut symbols: Vec<_> = self.symbols.iter().collect();
+    symbols.sort_by_key(|(name, _)| *name);
+    if self.hash_seed == 0 {
+      self.hash_seed = NEXT_HASH_SEED.fetch_add(1, AtomicOrdering::Relaxed);
+    }
+    for (index, (_, symbol)) in symbols.iter().enumerate() {
+      unsafe {
+        (***symbol).index_within_module = index as u32;
+        (***symbol).hash_seed           = self.hash_seed;
+      }
+    }
+
+    self.check_statements();
+    self.compute_minimum_substitution_size();
+    self.status = ModuleStatus::TheoryClosed;
+  }
+
+  /**
+  Reduces `term` to normal form with the equations of the module: the term is normalized in place and dagified, and
+  the DAG is reduced in a fresh `RewritingContext`, consulting the reduce cache. Returns the normal form together
+  with the statistics of the reduction. The top symbol of `term` must be a symbol of the module, a built-in literal,
+  or a built-in equality predicate.
+  */
+  pub fn reduce(&self, term: &mut Term) -> Result<(RcDagNode, RewriteStats), ReduceError> {
+    let top_symbol = term.top_symbol().ok_or(ReduceError::NonSymbolHead)?;
+    let symbol     = unsafe{ &*top_symbol };
+    if !symbol.is_literal() && !symbol.is_equality() && self.symbols.get(&symbol.name) != Some(&top_symbol) {
+      return Err(ReduceError::UndeclaredSymbol(symbol.name));
+    }
+
+    term.normalize();
+    let mut context = RewritingContext::new(self);
+    let result      = self.reduce_dag_in(&term.dagify(), &mut context);
+    Ok((result, context.stats()))
+  }
+
+  /// Reduces `term` like `Module::reduce(…)` and also returns whether the normal form is canonical, that is, whether
+  /// the equations eliminated every defined function from it. See `Module::is_canonical(…)`.
+  pub fn reduce_with_canonicity(&self, term: &mut Term) -> Result<(RcDagNode, bool), ReduceError> {
+    let (result, _) = self.reduce(term)?;
+    let canonical   = self.is_canonical(&result);
+    Ok((result, canonical))
+  }
+
+  /// Reduces `term` like `Module::reduce(…)` and returns the normal form as a term, which, unlike the DAG, is owned
+  /// by the caller outright and stays valid however the module's caches change.
+  pub fn reduce_to_term(&self, term: &mut Term) -> Result<BxTerm, ReduceError> {
+    let (result, _) = self.reduce(term)?;
+    let result      = Term::from_dag(&result.borrow());
+    Ok(result)
+  }
+
+  /// Reduces `subject` to normal form with the equations of the module in a fresh `RewritingContext`. See
+  /// `RewritingContext::reduce(…)`.
+  pub fn reduce_dag(&self, subject: &RcDagNode) -> RcDagNode {
+    self.reduce_dag_in(subject, &mut RewritingContext::new(self))
+  }
+
+  /// Reduces `subject` to normal form within `context`, consulting the reduce cache first. A cache hit makes no
+  /// rewrites in `context`. A context with a debugger, a rewrite limit, or nonexec statements allowed bypasses the
+  /// cache, since its reductions can differ from those of a fresh context, and the result of a reduction aborted by a
+  /// debugger is not cached.
+  pub fn reduce_dag_in(&self, subject: &RcDagNode, context: &mut RewritingContext) -> RcDagNode {
+    if !context.reduces_like_fresh() {
+      return context.reduce(subject);
+    }
+    if let Some(result) = self.reduce_cache.borrow().get(subject) {
+      return result;
+    }
+    let result = context.reduce(subject);
+    if !context.is_aborted() {
+      self.reduce_cache.borrow_mut().insert(subject, &result);
+    }
+    result
+  }
+
+  /**
+  Parses `source` as a term over the symbols of the module, checks its sorts, and reduces it to normal form, like a
+  REPL evaluating what is typed at it. The term cannot introduce symbols, so a misspelled name is an
+  `EvalError::UnknownSymbol` error rather than a new free constant, and an ill-sorted term is an error rather than
+  being reduced in the error sort of its kind.
+  */
+  pub fn eval(&self, source: &str) -> Result<RcDagNode, EvalError> {
+    let mut term = parse_to_term(source, self)?;
+    term.normalize();
+    let subject = term.dagify();
+    if let Some(subterm) = ill_sorted_subterm(&subject) {
+      return Err(EvalError::IllSorted(subterm.borrow().to_string()));
+    }
+    Ok(self.reduce_dag(&subject))
+  }
+
+  /**
+  Adds the item of a module in `source` to the closed module without constructing the module again, like a REPL
+  adding what is typed at it to the current module. The item is a sort, symbol, or variable declaration, or an
+  equation, rule, or membership axiom, whose terms can introduce symbols just as the terms of a module can.
+
+  Only the kinds the item affects are recomputed: a new sort gets a kind of its own, and a subsort declaration
+  recomputes the kind its sorts belong to. A subsort declaration relating sorts of different kinds would change the
+  kinds of existing sorts, and so the sorts of existing terms, and is rejected with `EditError::KindMerge` instead.
+  The sorts named by a rejected symbol declaration remain declared.
+  */
+  pub fn add_statement(&mut self, source: &str) -> Result<(), EditError> {
+    let item   = parse_item(source)?;
+    let result = item.add_to(self);
+    self.close_edit();
+    result
+  }
+
+  /**
+  Declares each of the sorts named by `subsorts` to be a subsort of each of the sorts named by `supersorts`, as
+  `sort A, B < C;` does, and recomputes the kind of the sorts. A declaration without supersorts only declares the
+  sorts. See `Module::add_statement(…)`.
+  */
+  pub(crate) fn declare_subsorts(&mut self, subsorts: &[IString], supersorts: &[IString]) -> Result<(), EditError> {
+    if supersorts.is_empty() {
+      for name in subsorts.iter() {
+        self.sorts.declare_sort(*name);
+      }
+      return Ok(());
+    }
+
+    for subsort in subsorts.iter() {
+      for supersort in supersorts.iter() {
+        if subsort == supersort {
+          return Err(ParseErrorKind::SelfSubsort(*subsort).into());
+        }
+        if let (Some(sub), Some(sup)) = (self.sorts.get(*subsort), self.sorts.get(*supersort)) {
+          if unsafe { (*sup).leq_sort(&*sub) } {
+            let cycle = vec![*subsort, *supersort, *subsort];
+            return Err(ParseErrorKind::SubsortCycle(SortCycleError { cycle }).into());
           }
         }
+      }
+    }
+
+    // The existing kinds of the sorts, each with the name of one of its sorts.
+    let mut kinds: Vec<(KindPtr, IString)> = Vec::new();
+    for name in subsorts.iter().chain(supersorts.iter()) {
+      if let Some(sort) = self.sorts.get(*name) {
+        let kind = unsafe { (*sort).kind };
+        if !kind.is_null() && kinds.iter().all(|(other, _)| *other != kind) {
+          kinds.push((kind, *name));
+        }
+      }
+    }
+    if let [(_, first), (_, second), ..] = kinds.as_slice() {
+      return Err(EditError::KindMerge(*first, *second));
+    }
+
+    let mut component: Vec<SortPtr> = Vec::new();
+    if let Some(&(kind, _)) = kinds.first() {
+      component.extend_from_slice(unsafe { &(&*kind).sorts[1..] });
+      // Dropping the old kind detaches its error sort from the lattice.
+      self.kinds.retain(|other| !std::ptr::eq(other.as_ref(), kind));
+    }
+    for subsort in subsorts.iter() {
+      for supersort in supersorts.iter() {
+        self.sorts.add_subsort(*subsort, *supersort);
+      }
+    }
+    for name in subsorts.iter().chain(supersorts.iter()) {
+      let sort = self.sorts.declare_sort(*name);
+      if !component.contains(&sort) {
+        component.push(sort);
+      }
+    }
+
+    // The sorts are numbered in name order, as `Module::compute_kind_closures(…)` numbers them.
+    component.sort_by_key(|sort| unsafe { (**sort).name });
+    for sort in component.iter() {
+      unsafe { (**sort).clear_kind_info(); }
+    }
+    self.add_kind(&component);
+    Ok(())
+  }
+
+  /// Adds `statement` to the closed module, marking it as bad if its variables are out of scope. See
+  /// `Module::add_statement(…)`.
+  pub(crate) fn insert_statement(&mut self, mut statement: PreEquation) {
+    check_statement(&mut statement);
+    self.notify_substitution_size(statement.variable_count());
+    // The statements are profiled by their index, which shifts for the statements after the new one.
+    #[cfg(feature = "profiling")]
+    self.clear_profile();
+    match statement.kind {
+      PreEquationKind::Equation { .. }   => self.equations.push(statement),
+      PreEquationKind::Rule { .. }       => self.rules.push(statement),
+      PreEquationKind::Membership { .. } => self.membership.push(statement),
+    }
+  }
+
+  /// Completes an edit of the closed module: gives every sort without a kind a kind of its own, numbers the new
+  /// symbols after the existing ones in name order, and clears the reduce cache, which may hold normal forms that the
+  /// edit has changed.
+  fn close_edit(&mut self) {
+    let mut sorts: Vec<_> = self.sorts.iter().filter(|(_, sort)| unsafe { (**sort).kind.is_null() }).collect();
+    sorts.sort_by_key(|(name, _)| *name);
+    for (_, sort) in sorts {
+      self.add_kind(&[sort]);
+    }
+
+    let mut symbols: Vec<_> = self.symbols.iter().collect();
+    symbols.sort_by_key(|(name, _)| *name);
+    let (mut new_symbols, indexed): (Vec<_>, Vec<_>) =
+        symbols.into_iter().partition(|(_, symbol)| unsafe { (***symbol).index_within_module == UNINDEXED });
+    for (index, (_, symbol)) in new_symbols.iter_mut().enumerate() {
+      unsafe {
+        (***symbol).index_within_module = (indexed.len() + index) as u32;
+        (***symbol).hash_seed           = self.hash_seed;
+      }
+    }
+
+    self.clear_reduce_cache();
+  }
+
+  /**
+  Whether `node` consists only of constructors, symbols declared with the `ctor` attribute, and built-in literals. A
+  normal form that is not canonical has a defined function left in it, which means the equations for that function
+  do not cover every case.
+  */
+  pub fn is_canonical(&self, node: &RcDagNode) -> bool {
+    let node   = node.borrow();
+    let symbol = unsafe{ &*node.top_symbol };
+    (symbol.is_literal() || symbol.symbol_type.attributes.contains(SymbolAttribute::Constructor))
+        && node.args.iter().all(|arg| self.is_canonical(arg))
+  }
+
+  /**
+  All substitutions under which `pattern` matches `subject`, without reducing either. The result is empty if there
+  is no match, and a pattern with an AC symbol can match in several ways. The bindings of a substitution can be read
+  by variable name with `Substitution::to_name_map()`. See `Term::match_all(…)`.
+  */
+  pub fn match_all(&self, pattern: &Term, subject: &RcDagNode) -> Vec<Substitution> {
+    pattern.match_all(subject, Substitution::with_capacity(self.minimum_substitution_size))
+  }
+
+  /// Enables the reduce cache with room for `limit` entries, or disables it if `limit` is zero. The cache is
+  /// disabled by default.
+  pub fn set_reduce_cache_limit(&self, limit: usize) {
+    self.reduce_cache.borrow_mut().set_limit(limit);
+  }
+
+  /// Discards every entry of the reduce cache.
+  pub fn clear_reduce_cache(&self) {
+    self.reduce_cache.borrow_mut().clear();
+  }
+
+  /// The number of entries in the reduce cache.
+  pub fn reduce_cache_len(&self) -> usize {
+    self.reduce_cache.borrow().len()
+  }
+
+  /**
+  The profile of the module's statements as a table with a line for every statement that has been profiled, the one
+  with the most rewrites first. The columns are the rewrites, the condition fragments that held and failed, the time
+  spent, and the statement. See the `profile` module.
+  */
+  #[cfg(feature = "profiling")]
+  pub fn profile_report(&self) -> String {
+    let statements: Vec<&PreEquation> = self.statements().collect();
+    let mut report = format!("{:>10} {:>10} {:>10} {:>12}  statement\n", "rewrites", "successes", "failures", "time");
+    for (index, profile) in self.profile.borrow().by_rewrites() {
+      report.push_str(
+        &format!(
+          "{:>10} {:>10} {:>10} {:>12}  {}\n",
+          profile.rewrites,
+          profile.fragment_successes,
+          profile.fragment_failures,
+          format!("{:?}", profile.time),
+          statements[index].repr(FormatStyle::Default)
+        )
       );
+    }
+    report
+  }
+
+  /// Discards the profile of the module's statements.
+  #[cfg(feature = "profiling")]
+  pub fn clear_profile(&self) {
+    self.profile.borrow_mut().clear();
+  }
+
+  /// Applies `update` to the profile of `statement` if it is a statement of the module.
+  #[cfg(feature = "profiling")]
+  pub(crate) fn profile_statement(&self, statement: &PreEquation, update: impl FnOnce(&mut StatementProfile)) {
+    if let Some(index) = self.statement_index(statement) {
+      update(self.profile.borrow_mut().statement_mut(index));
+    }
+  }
+
+  /// Reduces `term` to equational normal form, then alternately applies a rule and reduces again, up to `steps` times
+  /// or until no rule applies. See `RewritingContext::rewrite_step(…)` for which rule application is chosen.
+  pub fn rewrite(&self, term: &Term, steps: usize) -> RcDagNode {
+    let mut context = RewritingContext::new(self);
+    let mut subject = context.reduce(&term.dagify());
+    for _ in 0..steps {
+      match context.rewrite_step(&subject) {
+        Some(result) => subject = context.reduce(&result),
+        None         => break
+      }
+    }
+    subject
+  }
+
+  /**
+  Applies the rule labeled `label` to the subterm of `subject` at `position`, a path of argument indices from the top
+  of `subject`, so that `[]` is `subject` itself and `[1, 0]` is the first argument of its second argument. If several
+  rules have the label, they are tried in declaration order. The other subterms are left as they are, and neither
+  `subject` nor the result is reduced, so positions refer to `subject` as written.
+  */
+  pub fn apply_rule(&self, label: &str, subject: &Term, position: &[usize]) -> Result<RcDagNode, ApplyError> {
+    let label = IString::from(label);
+    let rules: Vec<&PreEquation> = self.rules.iter().filter(|rule| rule.name == Some(label)).collect();
+    if rules.is_empty() {
+      return Err(ApplyError::UnknownLabel(label));
+    }
+
+    match RewritingContext::new(self).apply_rule_at(&rules, &subject.dagify(), position) {
+      Ok(Some(result)) => Ok(result),
+      Ok(None)         => Err(ApplyError::NoMatch { label, position: position.to_vec() }),
+      Err(_)           => Err(ApplyError::InvalidPosition(position.to_vec()))
+    }
+  }
+
+  /// Executes `commands` in order, returning one result per command.
+  pub fn run_commands(&self, commands: &[Command]) -> Vec<CommandResult> {
+    commands.iter().map(|command| command.run(self)).collect()
+  }
+
+  /// Renders the equations, rules, and membership axioms of the module, in that order, as a LaTeX `align*`
+  /// environment. Symbols are rendered with the macros given by their `latex(…)` attributes.
+  pub fn to_latex(&self) -> String {
+    let statements: Vec<String> = self.equations
+                                      .iter()
+                                      .chain(self.rules.iter())
+                                      .chain(self.membership.iter())
+                                      .map(|statement| statement.repr(FormatStyle::Latex))
+                                      .collect();
+    format!("\\begin{{align*}}\n  {}\n\\end{{align*}}", statements.join(" \\\\\n  "))
+  }
+
+  /// Marks every statement that fails `PreEquation::check_variable_scope(…)` as bad, logging a warning for each. The
+  /// warning gives the line and column of the statement if it was parsed from source text.
+  fn check_statements(&mut self) {
+    for statement in self.equations.iter_mut().chain(self.rules.iter_mut()).chain(self.membership.iter_mut()) {
+      check_statement(statement);
+    }
+  }
+
+  /// Ensures that substitutions created for this module have at least `size` slots. This allows substitutions to be
+  /// sized once for the largest statement of the module instead of growing during reduction.
+  #[inline(always)]
+  pub fn notify_substitution_size(&mut self, size: usize) {
+    if size > self.minimum_substitution_size {
+      self.minimum_substitution_size = size;
+    }
+  }
+
+  /// The number of substitution slots needed to apply any statement of the module, including variables that only
+  /// occur in conditions.
+  #[inline(always)]
+  pub fn minimum_substitution_size(&self) -> usize {
+    self.minimum_substitution_size
+  }
+
+  /// Notifies the module of the substitution size needed by each of its statements.
+  pub fn compute_minimum_substitution_size(&mut self) {
+    let size = self.equations
+                   .iter()
+                   .chain(self.rules.iter())
+                   .chain(self.membership.iter())
+                   .map(|pre_equation| pre_equation.variable_count())
+                   .max()
+                   .unwrap_or(0);
+    self.notify_substitution_size(size);
+  }
+
+  /**
+  The equations whose left-hand side is not linear, that is, in which some variable occurs more than once. Matching
+  such an equation requires an equality subproblem, and non-left-linear equations are a common source of
+  non-confluence. This is a cheap diagnostic, not a confluence check.
+  */
+  pub fn nonleft_linear_equations(&self) -> Vec<&PreEquation> {
+    self.equations.iter().filter(|equation| !equation.lhs_term.is_linear()).collect()
+  }
+
+  /// The statements of the module: equations, then rules, then membership axioms.
+  pub fn statements(&self) -> impl Iterator<Item = &PreEquation> {
+    self.equations.iter().chain(self.rules.iter()).chain(self.membership.iter())
+  }
+
+  /// The index of `statement` in the order of `Module::statements(…)`, or `None` if it is not a statement of the
+  /// module.
+  pub fn statement_index(&self, statement: &PreEquation) -> Option<usize> {
+    let mut offset = 0;
+    for statements in [&self.equations, &self.rules, &self.membership] {
+      if statements.as_ptr_range().contains(&(statement as *const PreEquation)) {
+        return Some(offset + unsafe{ (statement as *const PreEquation).offset_from(statements.as_ptr()) } as usize);
+      }
+      offset += statements.len();
+    }
+    None
+  }
+
+  /// The symbols and variables of the module, in name order.
+  pub fn symbols(&self) -> impl Iterator<Item = SymbolPtr> + '_ {
+    let mut symbols: Vec<(&IString, &SymbolPtr)> = self.symbols.iter().collect();
+    symbols.sort_by_key(|(name, _)| **name);
+    symbols.into_iter().map(|(_, symbol)| *symbol)
+  }
+
+  /// The sorts of the module, in name order.
+  pub fn sorts(&self) -> impl Iterator<Item = SortPtr> {
+    let mut sorts: Vec<(IString, SortPtr)> = self.sorts.iter().collect();
+    sorts.sort_by_key(|(name, _)| *name);
+    sorts.into_iter().map(|(_, sort)| sort)
+  }
+
+  /// The symbol or variable of the module named `name`, if there is one.
+  pub fn symbol_named(&self, name: &str) -> Option<SymbolPtr> {
+    self.symbols.get(&IString::from(name)).copied()
+  }
+
+  /**
+  The symbol or variable of the module named `name`, ignoring case if no name matches exactly, for resolving a name
+  typed with the wrong case. If several names differ from `name` only in case, the first in name order is chosen.
+  */
+  pub fn symbol_named_ignoring_case(&self, name: &str) -> Option<SymbolPtr> {
+    self.symbol_named(name).or_else(
+      || {
+        let name = name.to_lowercase();
+        self.symbols().find(|symbol| unsafe{ (**symbol).name.to_lowercase() == name })
+      }
+    )
+  }
+
+  /// The symbols and variables of the module whose names begin with `prefix`, in name order, for completing a name.
+  pub fn symbols_with_prefix(&self, prefix: &str) -> Vec<SymbolPtr> {
+    self.symbols()
+        .filter(|symbol| unsafe{ (**symbol).name.starts_with(prefix) })
+        .collect()
+  }
+
+  /**
+  The symbol or variable of the module whose name is closest to `name` in Levenshtein distance, together with the
+  distance, for suggesting a correction to a misspelled name. A difference in case is an edit like any other. Ties
+  go to the first name in name order, and the result is `None` only if the module has no symbols. This is a linear
+  scan over the symbols of the module.
+  */
+  pub fn closest_symbol(&self, name: &str) -> Option<(SymbolPtr, usize)> {
+    self.symbols()
+        .map(|symbol| (symbol, levenshtein_distance(name, unsafe{ &(*symbol).name })))
+        .min_by_key(|(_, distance)| *distance)
+  }
+
+  /// The sort of the module named `name`, if there is one.
+  pub fn sort_named(&self, name: &str) -> Option<SortPtr> {
+    self.sorts.get(IString::from(name))
+  }
+
+  /// The display names of the variables of every statement, in the order of `Module::statements(…)`, equations first.
+  /// See `PreEquation::canonical_variable_names(…)`.
+  pub fn canonical_variable_names(&self) -> Vec<Vec<(SymbolPtr, String)>> {
+    self.statements().map(|statement| statement.canonical_variable_names()).collect()
+  }
+
+  /**
+  The sorts of the module that are not named by any symbol declaration or statement, in name order. A supersort of a
+  used sort is connected to it and counts as used, as do the special sorts `Any` and `None`. This is a code hygiene
+  diagnostic.
+  */
+  pub fn unused_sorts(&self) -> Vec<SortPtr> {
+    let mut used = HashSet::new();
+    for symbol in self.symbols.values() {
+      for sort_spec in unsafe { (**symbol).declarations() } {
+        sort_spec.add_sorts(&mut used);
+      }
+    }
+    for statement in self.statements() {
+      statement.add_sorts(&mut used);
+    }
+
+    let mut unvisited: Vec<SortPtr> = used.iter().copied().collect();
+    while let Some(sort) = unvisited.pop() {
+      for supersort in unsafe { (*sort).supersorts.iter() } {
+        if used.insert(*supersort) {
+          unvisited.push(*supersort);
+        }
+      }
+    }
+
+    let mut unused: Vec<(IString, SortPtr)> = self.sorts.iter().filter(
+      | (_, sort) | !used.contains(sort) && unsafe { (**sort).arity() } == 0
+    ).collect();
+    unused.sort_by_key(|(name, _)| *name);
+    unused.into_iter().map(|(_, sort)| sort).collect()
+  }
+
+  /**
+  Checks that the terms of every statement that must have sorts of the same kind do, like the two sides of an
+  equation, returning a diagnostic for each clash. A statement with a kind clash can never apply, so a clash is almost
+  always a mistake in the declarations. See `PreEquation::kind_clashes(…)`.
+  */
+  pub fn sort_check(&self) -> Vec<Diagnostic> {
+    self.statements()
+        .enumerate()
+        .flat_map(
+          | (statement_index, statement) | statement.kind_clashes().into_iter().map(
+            move | clash | Diagnostic {
+              statement_index,
+              span   : statement.span,
+              message: format!("{}: {}", statement.repr(FormatStyle::Default), clash)
+            }
+          )
+        )
+        .collect()
+  }
 
-      // Maude sets the index_in_parent of the kind here.
-      self.kinds.push(kind);
+  /// The symbols and variables of the module that do not occur in any statement, in name order. This is a code
+  /// hygiene diagnostic.
+  pub fn unused_symbols(&self) -> Vec<SymbolPtr> {
+    let mut used = HashSet::new();
+    for statement in self.statements() {
+      statement.add_symbols(&mut used);
     }
-    self.status = ModuleStatus::SortSetClosed
+
+    let mut unused: Vec<(&IString, &SymbolPtr)> =
+        self.symbols.iter().filter(|(_, symbol)| !used.contains(*symbol)).collect();
+    unused.sort_by_key(|(name, _)| **name);
+    unused.into_iter().map(|(_, symbol)| *symbol).collect()
+  }
+
+}
+
+
+/// Marks `statement` as bad, logging a warning, if it fails `PreEquation::check_variable_scope(…)`.
+fn check_statement(statement: &mut PreEquation) {
+  if let Err(error) = statement.check_variable_scope() {
+    let location = statement.span.map_or(String::new(), |span| format!(" (at {})", span));
+    log(Channel::Warning, 1, format!("{}{}: {}", statement.repr(FormatStyle::Default), location, error).as_str());
+    statement.attributes.insert(PreEquationAttribute::Bad);
   }
+}
 
+/// The number of single-character insertions, deletions, and substitutions that turn `a` into `b`.
+fn levenshtein_distance(a: &str, b: &str) -> usize {
+  let b: Vec<char> = b.chars().collect();
+  // The distances from the prefix of `a` read so far to each prefix of `b`.
+  let mut row: Vec<usize> = (0..=b.len()).collect();
+  for (i, a_char) in a.chars().enumerate() {
+    let mut diagonal = row[0];
+    row[0] = i + 1;
+    for (j, b_char) in b.iter().enumerate() {
+      let substitution = diagonal + (a_char != *b_char) as usize;
+      diagonal = row[j + 1];
+      row[j + 1] = substitution.min(row[j] + 1).min(diagonal + 1);
+    }
+  }
+  row[b.len()]
 }
 
+/// The innermost subterm of `node` that is error-sorted, if any, which is where the sort error originates.
+fn ill_sorted_subterm(node: &RcDagNode) -> Option<RcDagNode> {
+  node.borrow()
+      .iter_args()
+      .find_map(|arg| ill_sorted_subterm(&arg))
+      .or_else(|| node.borrow().is_error_sorted().then(|| node.clone()))
+}
 
 impl Drop for Module {
   /// A module owns its symbols, which are raw pointers to allocated memory. The module must reclaim this owned memory
-  /// when it is dropped.
+  /// when it is dropped. This includes the fresh variables made for narrowing and the built-in symbols made for
+  /// parsed terms.
   fn drop(&mut self) {
     for (_, symbol_ptr) in self.symbols.iter() {
       unsafe {
         heap_destroy!(*symbol_ptr);
       }
     }
+    for (_, fresh) in self.fresh_variables.get_mut().drain(..) {
+      unsafe {
+        heap_destroy!(fresh);
+      }
+    }
+    for (_, builtin) in self.builtin_symbols.get_mut().drain() {
+      unsafe {
+        heap_destroy!(builtin);
+      }
+    }
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use crate::{
+    abstractions::{
+      IString,
+      RcCell
+    },
+    theory::dag_node_attributes::DagNodeAttribute,
+    builtin::{
+      integer_symbol::IntegerSymbol,
+      string_symbol::StringSymbol
+    },
+    core::debugger::StepAction,
+    parser::{
+      parse_to_module,
+      parse_to_module_with_commands
+    }
+  };
+  use std::{
+    cell::RefCell,
+    rc::Rc
+  };
+  use super::*;
+
+  #[test]
+  fn eval_reduces_source_text_with_the_symbols_of_the_module() {
+    let module = parse_to_module(
+      "
+      sort Bool;
+      sort Integer;
+      symbol t :: Bool;
+      symbol g :: Integer -> Integer;
+      variable x;
+
+      eq double(x) = plus(x, x);
+      "
+    ).unwrap();
+
+    assert_eq!(module.eval("double(3)").unwrap().borrow().to_string(), "plus(3, 3)");
+    assert_eq!(module.eval("double(double(t))").unwrap().borrow().to_string(), "plus(plus(t, t), plus(t, t))");
+
+    assert_eq!(module.eval("tripple(3)").err(), Some(EvalError::UnknownSymbol(IString::from("tripple"))));
+    assert_eq!(module.eval("double(g(t))").err(), Some(EvalError::IllSorted("g(t)".to_string())));
+    assert!(matches!(module.eval("double(3"), Err(EvalError::Parse(_))));
+  }
+
+  #[test]
+  fn added_statements_take_effect_in_the_closed_module() {
+    let mut module = parse_to_module(
+      "
+      sort Bool;
+      symbol t :: Bool;
+      symbol f :: Bool;
+      symbol not :: Bool -> Bool;
+
+      eq not(t) = f;
+      "
+    ).unwrap();
+    module.set_reduce_cache_limit(16);
+    assert_eq!(module.eval("not(f)").unwrap().borrow().to_string(), "not(f)");
+
+    module.add_statement("eq not(f) = t;").unwrap();
+    assert_eq!(module.equations.len(), 2);
+    assert_eq!(module.eval("not(f)").unwrap().borrow().to_string(), "t");
+    assert_eq!(module.eval("not(not(t))").unwrap().borrow().to_string(), "t");
+
+    // A new symbol is numbered after the symbols of the module.
+    module.add_statement("symbol u :: Bool;").unwrap();
+    unsafe {
+      assert_eq!((*module.symbol_named("u").unwrap()).index_within_module, 3);
+      assert_eq!((*module.symbol_named("u").unwrap()).hash_seed, module.hash_seed);
+    }
+
+    assert_eq!(module.add_statement("eq u = t; eq t = u;").err(), Some(EditError::ItemCount(2)));
+    assert_eq!(module.add_statement("reduce not(t);").err(), Some(EditError::Unsupported("command")));
+    assert_eq!(
+      module.add_statement("sort Bool < Bool;").err(),
+      Some(EditError::Declaration(ParseErrorKind::SelfSubsort(IString::from("Bool"))))
+    );
+    assert!(matches!(module.add_statement("eq not(u = t;"), Err(EditError::Parse(_))));
+  }
+
+  #[test]
+  fn added_subsorts_recompute_only_their_kind() {
+    let mut module = parse_to_module(
+      "
+      sort Zero < Nat;
+      sort Bool;
+      "
+    ).unwrap();
+    let nat       = module.sort_named("Nat").unwrap();
+    let bool_kind = unsafe { (*module.sort_named("Bool").unwrap()).kind };
+    assert_eq!(module.kinds.len(), 2);
+
+    module.add_statement("sort NzNat < Nat;").unwrap();
+    let nz_nat = module.sort_named("NzNat").unwrap();
+    assert_eq!(module.kinds.len(), 2);
+    unsafe {
+      let kind = (*nat).kind;
+      assert_eq!((*nz_nat).kind, kind);
+      assert_eq!((*(*kind).error_sort()).name, IString::from("[Nat]"));
+      assert_eq!((*kind).sorts.len(), 4);
+      assert!((*kind).error_free);
+      assert!((*nz_nat).leq_sort(&*nat));
+      assert!(!(*nat).leq_sort(&*nz_nat));
+      // The error sort of the discarded kind was detached, leaving only the new error sort.
+      assert_eq!((*nat).supersorts, vec![(*kind).error_sort()]);
+      // The kind of `Bool` is untouched.
+      assert_eq!((*module.sort_named("Bool").unwrap()).kind, bool_kind);
+    }
+
+    // A sort declared on its own gets a kind of its own.
+    module.add_statement("sort Char;").unwrap();
+    assert_eq!(module.kinds.len(), 3);
+
+    assert_eq!(
+      module.add_statement("sort Nat < Bool;").err(),
+      Some(EditError::KindMerge(IString::from("Nat"), IString::from("Bool")))
+    );
+    assert!(
+      matches!(
+        module.add_statement("sort Nat < Zero;"),
+        Err(EditError::Declaration(ParseErrorKind::SubsortCycle(_)))
+      )
+    );
+    assert_eq!(module.kinds.len(), 3);
+  }
+
+  #[test]
+  fn recompute_kind_closures_after_adding_subsort() {
+    let mut module = Module::default();
+    let a = module.sorts.get_or_create_sort(IString::from("A"));
+    let b = module.sorts.get_or_create_sort(IString::from("B"));
+    let c = module.sorts.get_or_create_sort(IString::from("C"));
+    // A < B
+    unsafe { (*b).insert_subsort(a); }
+    module.compute_kind_closures();
+
+    assert_eq!(module.status, ModuleStatus::SortSetClosed);
+    assert_eq!(module.kinds.len(), 2);
+    unsafe {
+      assert_eq!((*a).kind, (*b).kind);
+      assert_ne!((*a).kind, (*c).kind);
+      assert!((*b).leq_sorts.contains((*a).index_within_kind));
+    }
+
+    // A < B < C
+    unsafe { (*c).insert_subsort(b); }
+    module.compute_kind_closures();
+
+    assert_eq!(module.kinds.len(), 1);
+    unsafe {
+      let kind = (*a).kind;
+      assert_eq!((*b).kind, kind);
+      assert_eq!((*c).kind, kind);
+      // The error sort comes first, and supersorts always have smaller indices than their subsorts.
+      assert_eq!((*kind).sorts.len(), 4);
+      assert_eq!((*c).index_within_kind, 1);
+      assert_eq!((*b).index_within_kind, 2);
+      assert_eq!((*a).index_within_kind, 3);
+      assert!((*c).leq_sorts.contains((*a).index_within_kind));
+      assert!(!(*a).leq_sorts.contains((*c).index_within_kind));
+      // The error sort of the discarded kind `{C}` was detached, leaving only the new error sort.
+      assert_eq!((*c).supersorts, vec![(*kind).error_sort()]);
+    }
+  }
+
+  #[test]
+  fn kinds_are_the_connected_components_of_the_subsort_relation() {
+    let module = parse_to_module(
+      "
+      sort Zero, NzNat < Nat;
+      sort Nat < Int;
+      sort Bool;
+      sort Char, String < Text;
+      "
+    ).unwrap();
+
+    let kinds: Vec<(&str, Vec<&str>)> = module.kinds
+                                              .iter()
+                                              .map(|kind| unsafe {
+                                                let mut names: Vec<&str> =
+                                                    kind.sorts[1..].iter().map(|sort| (**sort).name.as_str()).collect();
+                                                names.sort();
+                                                ((*kind.error_sort()).name.as_str(), names)
+                                              })
+                                              .collect();
+    assert_eq!(
+      kinds,
+      vec![
+        ("[Bool]", vec!["Bool"]),
+        ("[Char]", vec!["Char", "String", "Text"]),
+        ("[Int]", vec!["Int", "Nat", "NzNat", "Zero"]),
+      ]
+    );
+    assert!(module.kinds.iter().all(|kind| kind.error_free));
+  }
+
+  #[test]
+  fn minimum_substitution_size_covers_largest_statement() {
+    let module = parse_to_module(
+      "
+      variable X;
+      variable Y;
+      variable Z;
+      symbol f;
+      symbol g;
+
+      equation f(X) = X;
+      rule g(X, Y) => g(Y, X) if Z := f(X);
+      "
+    ).unwrap();
+
+    // The rule's condition introduces a third variable.
+    assert_eq!(module.minimum_substitution_size(), 3);
+  }
+
+  #[test]
+  fn closed_theory_orders_symbols_totally() {
+    let module = parse_to_module(
+      "
+      variable X;
+      symbol f;
+      symbol g;
+
+      equation f(X, 3) = g(X, \"c\");
+      "
+    ).unwrap();
+    assert_eq!(module.status, ModuleStatus::TheoryClosed);
+
+    let mut indices: Vec<u32> = module.symbols
+                                      .values()
+                                      .map(|symbol| unsafe { (**symbol).index_within_module })
+                                      .collect();
+    indices.sort();
+    assert_eq!(indices, (0..module.symbols.len() as u32).collect::<Vec<_>>());
+
+    let symbols: Vec<&Symbol> = module.symbols.values().map(|symbol| unsafe { &**symbol }).collect();
+    for &a in symbols.iter() {
+      for &b in symbols.iter() {
+        assert_eq!(a.compare(b), b.compare(a).reverse());
+        assert_eq!(a == b, std::ptr::eq(a, b));
+      }
+    }
+
+    // Literals are unindexed and compare by value, so they come after every symbol of the module.
+    let three = IntegerSymbol::new(3);
+    let other_three = IntegerSymbol::new(3);
+    let quoted_three = StringSymbol::new("3".to_string());
+    assert!(three == other_three);
+    assert!(three != quoted_three);
+    assert!(symbols.iter().all(|&symbol| *symbol < three));
+  }
+
+  #[test]
+  fn rewrite_reduces_between_rule_applications() {
+    let (module, commands) = parse_to_module_with_commands(
+      "
+      symbol f;
+
+      equation f(b) = c;
+      rule a => b;
+      rule c => a;
+
+      reduce f(a);
+      "
+    ).unwrap();
+    let term = match &commands[0] {
+      Command::Reduce(term) => term,
+      _ => unreachable!()
+    };
+
+    // `reduce` never applies rules.
+    assert_eq!(module.reduce_dag(&term.dagify()).borrow().to_string(), "f(a)");
+
+    let results: Vec<String> = (0..5).map(|steps| module.rewrite(term, steps).borrow().to_string()).collect();
+    // After `a => b`, the equation rewrites `f(b)` to `c` before the next rule is tried.
+    assert_eq!(results, vec!["f(a)", "c", "a", "b", "b"]);
+  }
+
+  #[test]
+  fn module_renders_to_latex() {
+    let module = parse_to_module(
+      r##"
+      variable X;
+      symbol plus [latex("#1 \\oplus #2")];
+      symbol zero [latex("\\mathbf{0}")];
+      symbol double_it;
+
+      equation plus(X, zero) = X;
+      rule double_it(X) => plus(X, X) if X := "a_b";
+      "##
+    ).unwrap();
+
+    assert_eq!(
+      module.to_latex(),
+      concat!(
+        "\\begin{align*}\n",
+        "  X \\oplus \\mathbf{0} &= X \\\\\n",
+        "  \\mathrm{double\\_it}(X) &\\Rightarrow X \\oplus X \\quad \\text{if } X := \\texttt{\"a\\_b\"}\n",
+        "\\end{align*}"
+      )
+    );
+  }
+
+  #[test]
+  fn reports_only_nonleft_linear_equations() {
+    let module = parse_to_module(
+      "
+      variable X;
+      variable Y;
+
+      equation f(X, Y) = X;
+      equation f(X, X) = X;
+      equation g(h(X), Y) = Y;
+      equation g(X, h(X)) = X;
+      "
+    ).unwrap();
+
+    let reported: Vec<String> = module.nonleft_linear_equations()
+                                      .iter()
+                                      .map(|equation| equation.lhs_term.to_string())
+                                      .collect();
+    assert_eq!(reported, vec!["f(X, X)", "g(X, h(X))"]);
+  }
+
+  #[test]
+  fn equal_terms_of_different_modules_hash_differently() {
+    let source = "
+      symbol f;
+      symbol a;
+      reduce f(a);
+      ";
+    let hash_of_command = |source| {
+      let (module, commands) = parse_to_module_with_commands(source).unwrap();
+      match &commands[0] {
+        Command::Reduce(term) => {
+          // Within a module, a term and its DAG still agree.
+          let hash = term.structural_hash();
+          assert_eq!(term.dagify().borrow_mut().structural_hash(), hash);
+          (module, hash)
+        }
+        _ => unreachable!()
+      }
+    };
+
+    let (_first, first_hash)   = hash_of_command(source);
+    let (_second, second_hash) = hash_of_command(source);
+    assert_ne!(first_hash, second_hash);
+  }
+
+  #[test]
+  fn sort_check_reports_kind_clashes() {
+    let source = "
+      sort A < B;
+      sort C;
+      symbol a :: A;
+      symbol b :: B;
+      symbol c :: C;
+      symbol f :: A -> B;
+      variable X :: A;
+
+      equation f(a) = b;
+      equation f(X) = b if X = a;
+    ";
+    let module = parse_to_module(source).unwrap();
+    assert!(module.sort_check().is_empty());
+
+    let module = parse_to_module(
+      &format!("{}\n equation f(a) = c; equation g(X) = c if X = c; membership b :: C;", source)
+    ).unwrap();
+    let diagnostics: Vec<(usize, String)> = module.sort_check()
+                                                  .into_iter()
+                                                  .map(|diagnostic| (diagnostic.statement_index, diagnostic.message))
+                                                  .collect();
+    let clash = | terms: &str, left: &str, right: &str | {
+      format!("{} have sorts {} and {}, which are in different kinds.", terms, left, right)
+    };
+    assert_eq!(diagnostics.len(), 3);
+    assert_eq!(diagnostics[0].0, 2);
+    assert!(diagnostics[0].1.ends_with(&clash("the left- and right-hand sides", "B", "C")));
+    // The undeclared `g` has no sort, so only the condition is checked.
+    assert_eq!(diagnostics[1].0, 3);
+    assert!(diagnostics[1].1.ends_with(&clash("the two sides of condition 1", "A", "C")));
+    assert_eq!(diagnostics[2].0, 4);
+    assert!(diagnostics[2].1.ends_with(&clash("the left-hand side and the membership sort", "B", "C")));
+  }
+
+  #[test]
+  fn reports_unused_sorts_and_symbols() {
+    let module = parse_to_module(
+      "
+      sort Nat < Int;
+      sort Zero < Nat;
+      sort Unused;
+      variable X :: Nat;
+      variable Y;
+      symbol s :: Nat -> Nat;
+      symbol f;
+      symbol unused :: Zero;
+
+      equation f(s(X)) = X;
+      membership f(X) :: Any;
+      "
+    ).unwrap();
+
+    let sorts: Vec<&str> = module.unused_sorts().iter().map(|sort| unsafe { (**sort).name.as_str() }).collect();
+    // `Int` is a supersort of the used sort `Nat`. `Zero` is used by the declaration of `unused`.
+    assert_eq!(sorts, vec!["Unused"]);
+
+    let symbols: Vec<&str> = module.unused_symbols().iter().map(|symbol| unsafe { (**symbol).name.as_str() }).collect();
+    assert_eq!(symbols, vec!["Y", "unused"]);
+  }
+
+  #[test]
+  fn symbols_and_sorts_are_listed_and_found_by_name() {
+    let module = parse_to_module(
+      "
+      sort Nat < Int;
+      sort Bool;
+      variable X :: Nat;
+      symbol s :: Nat -> Nat;
+      symbol zero :: Nat;
+
+      equation s(X) = X;
+      "
+    ).unwrap();
+
+    let names: Vec<&str> = module.symbols().map(|symbol| unsafe { (*symbol).name.as_str() }).collect();
+    assert_eq!(names, vec!["X", "s", "zero"]);
+    let names: Vec<&str> = module.sorts().map(|sort| unsafe { (*sort).name.as_str() }).collect();
+    assert_eq!(names, vec!["Bool", "Int", "Nat"]);
+
+    let s = module.symbol_named("s").unwrap();
+    assert_eq!(s, module.symbols[&IString::from("s")]);
+    assert!(unsafe { (*module.symbol_named("X").unwrap()).is_variable() });
+    assert!(module.symbol_named("t").is_none());
+    let nat = module.sort_named("Nat").unwrap();
+    assert!(unsafe { (*nat).leq_sort(&*module.sort_named("Int").unwrap()) });
+    assert!(module.sort_named("Real").is_none());
+  }
+
+  #[test]
+  fn symbol_names_are_completed_and_corrected() {
+    let module = parse_to_module(
+      "
+      symbol f;
+      symbol first;
+      symbol fst;
+      symbol length;
+      symbol g;
+      "
+    ).unwrap();
+
+    let names: Vec<&str> = module.symbols_with_prefix("f")
+                                 .into_iter()
+                                 .map(|symbol| unsafe { (*symbol).name.as_str() })
+                                 .collect();
+    assert_eq!(names, vec!["f", "first", "fst"]);
+    assert!(module.symbols_with_prefix("h").is_empty());
+
+    assert_eq!(module.closest_symbol("lenght"), Some((module.symbol_named("length").unwrap(), 2)));
+    assert_eq!(module.closest_symbol("lengh"), Some((module.symbol_named("length").unwrap(), 1)));
+    assert_eq!(module.closest_symbol("Length"), Some((module.symbol_named("length").unwrap(), 1)));
+    assert_eq!(module.closest_symbol("g"), Some((module.symbol_named("g").unwrap(), 0)));
+    assert_eq!(Module::default().closest_symbol("g"), None);
+  }
+
+  #[test]
+  fn symbols_are_found_ignoring_case() {
+    let module = parse_to_module(
+      "
+      variable X;
+      symbol length;
+      symbol Cons;
+      symbol cons;
+      "
+    ).unwrap();
+    let named = | name: &str | module.symbol_named(name).unwrap();
+
+    assert_eq!(module.symbol_named_ignoring_case("LENGTH"), Some(named("length")));
+    assert_eq!(module.symbol_named_ignoring_case("x"), Some(named("X")));
+    // An exact match is preferred, and otherwise the first name in name order.
+    assert_eq!(module.symbol_named_ignoring_case("cons"), Some(named("cons")));
+    assert_eq!(module.symbol_named_ignoring_case("CONS"), Some(named("Cons")));
+    assert!(module.symbol_named("LENGTH").is_none());
+    assert!(module.symbol_named_ignoring_case("lengths").is_none());
+  }
+
+  #[test]
+  fn reduce_cache_returns_earlier_results_without_rewriting() {
+    let (module, commands) = parse_to_module_with_commands(
+      "
+      variable X;
+      symbol f;
+      symbol g;
+
+      equation f(X) = g(X);
+      equation g(a) = b;
+
+      reduce f(a);
+      "
+    ).unwrap();
+    let term = match &commands[0] {
+      Command::Reduce(term) => term,
+      _ => unreachable!()
+    };
+
+    // The cache is disabled by default.
+    let mut context = RewritingContext::new(&module);
+    module.reduce_dag_in(&term.dagify(), &mut context);
+    assert_eq!(context.rewrite_count(), 2);
+    assert_eq!(module.reduce_cache_len(), 0);
+
+    module.set_reduce_cache_limit(16);
+    let mut first  = RewritingContext::new(&module);
+    let mut second = RewritingContext::new(&module);
+    let result     = module.reduce_dag_in(&term.dagify(), &mut first);
+    let cached     = module.reduce_dag_in(&term.dagify(), &mut second);
+    assert_eq!(first.rewrite_count(), 2);
+    assert_eq!(second.rewrite_count(), 0);
+    assert!(RcCell::ptr_eq(&result, &cached));
+    assert_eq!(cached.borrow().to_string(), "b");
+    assert_eq!(module.reduce_cache_len(), 1);
+
+    module.clear_reduce_cache();
+    let mut third = RewritingContext::new(&module);
+    module.reduce_dag_in(&term.dagify(), &mut third);
+    assert_eq!(third.rewrite_count(), 2);
+  }
+
+  #[test]
+  fn reduce_cache_is_bypassed_by_contexts_that_reduce_differently() {
+    let (module, commands) = parse_to_module_with_commands(
+      "
+      variable X;
+      symbol f;
+      symbol g;
+
+      equation f(X) = c [nonexec];
+      equation f(X) = g(X);
+      equation g(a) = b;
+
+      reduce f(a);
+      "
+    ).unwrap();
+    let term = match &commands[0] {
+      Command::Reduce(term) => term,
+      _ => unreachable!()
+    };
+    module.set_reduce_cache_limit(16);
+    let normal_form = module.reduce_dag_in(&term.dagify(), &mut RewritingContext::new(&module));
+    assert_eq!(normal_form.borrow().to_string(), "b");
+    assert_eq!(module.reduce_cache_len(), 1);
+
+    // The nonexec equation applies, and its result is not cached.
+    let mut nonexec = RewritingContext::new(&module);
+    nonexec.set_allow_nonexec(true);
+    assert_eq!(module.reduce_dag_in(&term.dagify(), &mut nonexec).borrow().to_string(), "c");
+    assert_eq!(nonexec.rewrite_count(), 1);
+    assert_eq!(module.reduce_cache_len(), 1);
+
+    // The debugger is shown every rewrite.
+    let steps        = Rc::new(RefCell::new(0));
+    let seen         = steps.clone();
+    let mut debugged = RewritingContext::new(&module);
+    debugged.set_debugger(Box::new(move |_: &RcDagNode, _: &PreEquation| {
+      *seen.borrow_mut() += 1;
+      StepAction::Step
+    }));
+    assert_eq!(module.reduce_dag_in(&term.dagify(), &mut debugged).borrow().to_string(), "b");
+    assert_eq!(*steps.borrow(), 2);
+
+    let mut fresh = RewritingContext::new(&module);
+    assert!(RcCell::ptr_eq(&module.reduce_dag_in(&term.dagify(), &mut fresh), &normal_form));
+    assert_eq!(fresh.rewrite_count(), 0);
+  }
+
+  #[test]
+  fn labeled_rule_applies_at_a_position() {
+    let (module, commands) = parse_to_module_with_commands(
+      "
+      variable X;
+      variable Y;
+      symbol f;
+      symbol g;
+
+      rule [swap] g(X, Y) => g(Y, X);
+      rule f(X) => X;
+
+      reduce f(g(a, b), g(c, d));
+      "
+    ).unwrap();
+    let term = match &commands[0] {
+      Command::Reduce(term) => term,
+      _ => unreachable!()
+    };
+    assert_eq!(module.rules[0].repr(FormatStyle::Default), "rule [swap] g(X, Y) => g(Y, X);");
+
+    let result = module.apply_rule("swap", term, &[1]).unwrap();
+    assert_eq!(result.borrow().to_string(), "f(g(a, b), g(d, c))");
+
+    assert_eq!(
+      module.apply_rule("swap", term, &[]).err(),
+      Some(ApplyError::NoMatch { label: IString::from("swap"), position: vec![] })
+    );
+    assert_eq!(module.apply_rule("swap", term, &[0, 2]).err(), Some(ApplyError::InvalidPosition(vec![0, 2])));
+    assert_eq!(module.apply_rule("flip", term, &[0]).err(), Some(ApplyError::UnknownLabel(IString::from("flip"))));
+  }
+
+  #[test]
+  fn normal_forms_are_canonical_when_only_constructors_remain() {
+    let (module, commands) = parse_to_module_with_commands(
+      "
+      variable X;
+      variable Y;
+      symbol z [ctor];
+      symbol s [ctor];
+      symbol plus;
+      symbol minus;
+
+      equation plus(X, z) = X;
+      equation plus(X, s(Y)) = s(plus(X, Y));
+
+      reduce plus(s(z), s(z));
+      reduce s(minus(z, s(z)));
+      "
+    ).unwrap();
+    let results: Vec<RcDagNode> = commands.iter().map(
+      | command | match command {
+        Command::Reduce(term) => module.reduce_dag(&term.dagify()),
+        _ => unreachable!()
+      }
+    ).collect();
+
+    assert_eq!(results[0].borrow().to_string(), "s(s(z))");
+    assert!(module.is_canonical(&results[0]));
+    // `minus` has no equations, so it is stuck.
+    assert_eq!(results[1].borrow().to_string(), "s(minus(z, s(z)))");
+    assert!(!module.is_canonical(&results[1]));
+
+    // The canonicity is reported along with the normal form.
+    let mut terms: Vec<BxTerm> = commands.iter().map(
+      | command | match command {
+        Command::Reduce(term) => term.deep_copy(),
+        _ => unreachable!()
+      }
+    ).collect();
+    let (result, canonical) = module.reduce_with_canonicity(&mut terms[0]).unwrap();
+    assert_eq!((result.borrow().to_string(), canonical), ("s(s(z))".to_string(), true));
+    let (result, canonical) = module.reduce_with_canonicity(&mut terms[1]).unwrap();
+    assert_eq!((result.borrow().to_string(), canonical), ("s(minus(z, s(z)))".to_string(), false));
+  }
+
+  #[test]
+  fn reduce_normalizes_a_parsed_term() {
+    let (module, mut commands) = parse_to_module_with_commands(
+      "
+      symbol f;
+
+      equation f(a) = b;
+
+      reduce f(a);
+      "
+    ).unwrap();
+    let mut term = match commands.remove(0) {
+      Command::Reduce(term) => term,
+      _ => unreachable!()
+    };
+
+    let (result, stats) = module.reduce(&mut term).unwrap();
+    assert_eq!(result.borrow().to_string(), "b");
+    assert_eq!(stats.rewrites, 1);
+
+    // A term of another module is rejected.
+    let (_, mut commands) = parse_to_module_with_commands("symbol f; reduce f(a);").unwrap();
+    let mut foreign = match commands.remove(0) {
+      Command::Reduce(term) => term,
+      _ => unreachable!()
+    };
+    assert_eq!(module.reduce(&mut foreign).err(), Some(ReduceError::UndeclaredSymbol(IString::from("f"))));
+  }
+
+  #[test]
+  fn conditions_that_reduce_their_own_subject_terminate() {
+    let (module, mut commands) = parse_to_module_with_commands(
+      "
+      variable X;
+      symbol f;
+
+      equation f(X) = a if f(X) = b;
+
+      reduce f(c);
+      "
+    ).unwrap();
+    let mut term = match commands.remove(0) {
+      Command::Reduce(term) => term,
+      _ => unreachable!()
+    };
+
+    // Inside the condition, `f(c)` is already being reduced, so it is left as it is and the condition fails.
+    let (result, stats) = module.reduce(&mut term).unwrap();
+    assert_eq!(result.borrow().to_string(), "f(c)");
+    assert_eq!(stats.rewrites, 0);
+    assert_eq!(stats.condition_stats.failures, 1);
+    assert!(!result.borrow().attributes.contains(DagNodeAttribute::Reducing));
+  }
+
+  #[test]
+  fn repeated_variables_match_equal_subterms() {
+    let (module, commands) = parse_to_module_with_commands(
+      "
+      variable X;
+      symbol f;
+
+      reduce f(X, X);
+      reduce f(a, a);
+      reduce f(a, b);
+      "
+    ).unwrap();
+    let terms: Vec<&Term> = commands.iter().map(
+      | command | match command {
+        Command::Reduce(term) => term.as_ref(),
+        _ => unreachable!()
+      }
+    ).collect();
+
+    let matches = module.match_all(terms[0], &terms[1].dagify());
+    assert_eq!(matches.len(), 1);
+    let bindings = matches[0].to_name_map();
+    assert_eq!(bindings.len(), 1);
+    assert_eq!(bindings[&IString::from("X")].borrow().to_string(), "a");
+
+    assert!(module.match_all(terms[0], &terms[2].dagify()).is_empty());
+  }
+
+  #[test]
+  fn variables_match_subjects_of_their_sort() {
+    let (module, commands) = parse_to_module_with_commands(
+      "
+      sort Nat < Int;
+      sort Bool;
+      variable X;
+      variable N :: Nat;
+      symbol s :: Int -> Int;
+      symbol z :: Nat;
+      symbol m :: Int;
+      symbol t :: Bool;
+
+      reduce s(X);
+      reduce s(N);
+      reduce s(z);
+      reduce s(m);
+      reduce s(t);
+      "
+    ).unwrap();
+    let terms: Vec<&Term> = commands.iter().map(
+      | command | match command {
+        Command::Reduce(term) => term.as_ref(),
+        _ => unreachable!()
+      }
+    ).collect();
+    let matches = | pattern: usize, subject: usize | {
+      !module.match_all(terms[pattern], &terms[subject].dagify()).is_empty()
+    };
+
+    // A sortless variable matches anything in the kind of its position.
+    assert!(matches(0, 2));
+    assert!(matches(0, 3));
+    assert!(!matches(0, 4));
+    // A sorted variable matches only subsorts of its sort.
+    assert!(matches(1, 2));
+    assert!(!matches(1, 3));
+  }
+
+  #[test]
+  fn reduced_terms_outlive_their_dags() {
+    let (module, mut commands) = parse_to_module_with_commands(
+      "
+      variable X;
+      symbol f;
+      symbol g;
+
+      equation f(X) = g(X, X);
+
+      reduce f(h(a));
+      "
+    ).unwrap();
+    let mut term = match commands.remove(0) {
+      Command::Reduce(term) => term,
+      _ => unreachable!()
+    };
+
+    module.set_reduce_cache_limit(16);
+    let result = module.reduce_to_term(&mut term).unwrap();
+    // Release every node of the reduction.
+    module.clear_reduce_cache();
+    drop(term);
+
+    assert_eq!(result.to_string(), "g(h(a), h(a))");
+    assert_eq!(result.top_symbol(), module.symbols.get(&IString::from("g")).copied());
+    assert!(result.arguments().iter().all(|arg| arg.to_string() == "h(a)"));
   }
 }
@@ -0,0 +1,362 @@
+/*!
+
+A `ModuleBuilder` constructs a `Module` directly, for embedders that build sorts, symbols, and statements in code
+rather than from source text. The builder does the same work as the construction of a module from its AST: after the
+declarations are made, `ModuleBuilder::build()` computes the subsort closure, rejecting cyclic declarations, and
+closes the theory of the module, which computes its kinds, indexes its symbols, and checks its statements. The built
+module can be used for reduction right away.
+
+```ignore
+let mut builder = ModuleBuilder::new();
+builder.add_subsort("NzNat", "Nat")
+       .declare_op("zero", &[], "Nat")
+       .declare_op("s", &["Nat"], "NzNat")
+       .declare_variable("N", Some("Nat"));
+let n   = builder.term("N", vec![]);
+let lhs = builder.term("s", vec![n]);
+```
+
+Terms are built with `ModuleBuilder::term(…)` from the names of declared symbols and variables. Unlike in source text,
+symbols are not declared implicitly by being used, and using a name that is not declared is an error. Errors in the
+declarations and terms are reported by `ModuleBuilder::build()`, the first error made being the one reported.
+
+*/
+
+use std::collections::hash_map::Entry;
+use std::cmp::max;
+
+use crate::{
+  abstractions::{
+    HashMap,
+    IString
+  },
+  core::{
+    module::{
+      BxModule,
+      Module
+    },
+    module_error::ModuleError,
+    pre_equation::{
+      condition::Conditions,
+      PreEquation,
+      PreEquationKind
+    },
+    sort::{
+      collection::SortCollection,
+      sort_spec::{
+        BxSortSpec,
+        SortSpec
+      }
+    }
+  },
+  heap_construct,
+  parser::ParseErrorKind,
+  theory::{
+    symbol::{
+      symbol_for_symbol_type,
+      Symbol,
+      SymbolPtr,
+      UNSPECIFIED,
+      VARIADIC
+    },
+    symbol_type::{
+      CoreSymbolType,
+      SymbolAttributes,
+      SymbolType
+    },
+    term::{
+      BxTerm,
+      Term,
+      TermAttributes,
+      TermNode
+    }
+  }
+};
+
+#[derive(Default)]
+pub struct ModuleBuilder {
+  sorts     : SortCollection,
+  symbols   : HashMap<IString, SymbolPtr>,
+  equations : Vec<PreEquation>,
+  rules     : Vec<PreEquation>,
+  /// The first error in the declarations, which is reported by `ModuleBuilder::build()`.
+  error     : Option<ModuleError>,
+}
+
+impl ModuleBuilder {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Declares the sort `name`. Sorts are also declared implicitly by being used.
+  pub fn add_sort(&mut self, name: &str) -> &mut Self {
+    self.sorts.get_or_create_sort(IString::from(name));
+    self
+  }
+
+  /// Declares `subsort` to be a subsort of `supersort`.
+  pub fn add_subsort(&mut self, subsort: &str, supersort: &str) -> &mut Self {
+    if subsort == supersort {
+      self.fail(ParseErrorKind::SelfSubsort(IString::from(subsort)).into());
+      return self;
+    }
+    self.sorts.add_subsort(IString::from(subsort), IString::from(supersort));
+    self
+  }
+
+  /// Declares the symbol `name` taking arguments of the sorts `domain_sorts` to a result of sort `range_sort`. A
+  /// symbol with an empty domain is a constant. Declaring a symbol again with the same arity overloads it.
+  pub fn declare_op(&mut self, name: &str, domain_sorts: &[&str], range_sort: &str) -> &mut Self {
+    self.declare_op_with_attributes(name, domain_sorts, range_sort, SymbolAttributes::empty())
+  }
+
+  /// Declares a symbol like `ModuleBuilder::declare_op(…)` with the given attributes, like `assoc` and `comm`.
+  pub fn declare_op_with_attributes(
+    &mut self,
+    name: &str,
+    domain_sorts: &[&str],
+    range_sort: &str,
+    attributes: SymbolAttributes
+  ) -> &mut Self
+  {
+    let range_sort = self.sort_spec(range_sort);
+    let sort_spec  = match domain_sorts.is_empty() {
+      true  => range_sort,
+      false => Box::new(
+        SortSpec::Functor {
+          arg_sorts: domain_sorts.iter().map(|sort| self.sort_spec(sort)).collect(),
+          sort_spec: range_sort
+        }
+      )
+    };
+    self.declare_symbol(IString::from(name), Some(sort_spec), attributes, CoreSymbolType::Standard)
+  }
+
+  /// Declares the variable `name`, of sort `sort` if one is given and of any sort otherwise.
+  pub fn declare_variable(&mut self, name: &str, sort: Option<&str>) -> &mut Self {
+    let sort_spec = sort.map(|sort| self.sort_spec(sort));
+    self.declare_symbol(IString::from(name), sort_spec, SymbolAttributes::empty(), CoreSymbolType::Variable)
+  }
+
+  /// Adds the equation `lhs = rhs if conditions`, with an optional label.
+  pub fn add_equation(&mut self, label: Option<&str>, lhs: BxTerm, rhs: BxTerm, conditions: Conditions) -> &mut Self {
+    let equation = statement(label, lhs, PreEquationKind::Equation { rhs_term: rhs }, conditions);
+    self.equations.push(equation);
+    self
+  }
+
+  /// Adds the rule `lhs => rhs if conditions`, with an optional label.
+  pub fn add_rule(&mut self, label: Option<&str>, lhs: BxTerm, rhs: BxTerm, conditions: Conditions) -> &mut Self {
+    let rule = statement(label, lhs, PreEquationKind::Rule { rhs_term: rhs }, conditions);
+    self.rules.push(rule);
+    self
+  }
+
+  /// The term with top symbol `name` applied to `args`, or the constant or variable `name` if there are no arguments.
+  /// If no symbol or variable `name` has been declared, the error is recorded, and `name` is declared as a symbol
+  /// without a sort so that a term can still be returned.
+  pub fn term(&mut self, name: &str, args: Vec<BxTerm>) -> BxTerm {
+    let name   = IString::from(name);
+    let symbol = match self.symbols.get(&name) {
+      Some(symbol) => *symbol,
+      None         => {
+        self.fail(ModuleError::UndeclaredSymbol(name));
+        self.declare_symbol(name, None, SymbolAttributes::empty(), CoreSymbolType::Standard);
+        self.symbols[&name]
+      }
+    };
+    let symbol_term = Term {
+      term_node : TermNode::Symbol(symbol),
+      attributes: TermAttributes::default()
+    };
+    if args.is_empty() {
+      return Box::new(symbol_term);
+    }
+    Box::new(
+      Term {
+        term_node : TermNode::Application {
+          head: Box::new(symbol_term),
+          tail: args
+        },
+        attributes: TermAttributes::default()
+      }
+    )
+  }
+
+  /**
+  Constructs the module, computing the subsort closure and closing its theory. Fails with the first error in the
+  declarations, or if the subsort declarations form a cycle.
+
+  ToDo: Nothing constructed before an error is reclaimed.
+  */
+  pub fn build(self) -> Result<BxModule, ModuleError> {
+    if let Some(error) = self.error {
+      return Err(error);
+    }
+    self.sorts.compute_closure().map_err(ParseErrorKind::SubsortCycle)?;
+
+    // `Module` implements `Drop`, so the remaining fields cannot be moved out of a default module with `..`.
+    let mut module = Box::new(Module::default());
+    module.sorts     = self.sorts;
+    module.symbols   = self.symbols;
+    module.equations = self.equations;
+    module.rules     = self.rules;
+    module.close_theory();
+    Ok(module)
+  }
+
+  fn declare_symbol(
+    &mut self,
+    name            : IString,
+    sort_spec       : Option<BxSortSpec>,
+    attributes      : SymbolAttributes,
+    core_symbol_type: CoreSymbolType
+  ) -> &mut Self
+  {
+    let entry = match self.symbols.entry(name) {
+      Entry::Occupied(occupied) => {
+        let symbol = unsafe{ &mut **occupied.get() };
+        match sort_spec {
+          Some(sort_spec) if symbol.accepts_overload(&sort_spec, core_symbol_type, attributes) => {
+            symbol.overloads.push(sort_spec);
+          }
+          _ => self.fail(ParseErrorKind::DuplicateSymbol(name).into())
+        }
+        return self;
+      }
+      Entry::Vacant(vacant) => vacant
+    };
+
+    let default_arity = match core_symbol_type {
+      CoreSymbolType::Variable => UNSPECIFIED,
+      _                        => VARIADIC
+    };
+    let symbol_type = SymbolType {
+      core_type: core_symbol_type,
+      attributes,
+    };
+    let mut symbol       = Symbol::new(name);
+    symbol.arity         = sort_spec.as_ref().map_or(default_arity, |sort_spec| max(default_arity, sort_spec.arity()));
+    symbol.theory_symbol = symbol_for_symbol_type(&symbol_type);
+    symbol.symbol_type   = symbol_type;
+    symbol.sort_spec     = sort_spec;
+    entry.insert(heap_construct!(symbol));
+    self
+  }
+
+  /// The sort spec of the sort `name`, which is created if it does not exist.
+  fn sort_spec(&mut self, name: &str) -> BxSortSpec {
+    Box::new(SortSpec::Sort(self.sorts.get_or_create_sort(IString::from(name))))
+  }
+
+  /// Records `error` unless an earlier error was recorded.
+  fn fail(&mut self, error: ModuleError) {
+    self.error.get_or_insert(error);
+  }
+}
+
+fn statement(label: Option<&str>, lhs: BxTerm, kind: PreEquationKind, conditions: Conditions) -> PreEquation {
+  PreEquation {
+    name      : label.map(IString::from),
+    attributes: Default::default(),
+    conditions,
+    lhs_term  : lhs,
+    kind,
+    doc       : None,
+    span      : None,
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use crate::core::format::{
+    FormatStyle,
+    Formattable
+  };
+  use super::*;
+
+  #[test]
+  fn built_modules_reduce_like_parsed_ones() {
+    // The sort diagram of the numbers: `Zero` and `NzNat` are subsorts of `Nat`, which is a subsort of `Int`, and
+    // `NzNat` is also a subsort of `NzInt`, the other subsort of `Int`.
+    let mut builder = ModuleBuilder::new();
+    builder.add_subsort("Zero", "Nat")
+           .add_subsort("NzNat", "Nat")
+           .add_subsort("NzNat", "NzInt")
+           .add_subsort("Nat", "Int")
+           .add_subsort("NzInt", "Int")
+           .add_sort("Bool")
+           .declare_op("zero", &[], "Zero")
+           .declare_op("s", &["Nat"], "NzNat")
+           .declare_op("plus", &["Nat", "Nat"], "Nat")
+           .declare_op("plus", &["NzNat", "Nat"], "NzNat")
+           .declare_op("true", &[], "Bool")
+           .declare_variable("N", Some("Nat"))
+           .declare_variable("M", Some("Nat"));
+
+    // Building a term borrows the builder mutably, so subterms are built before the terms containing them.
+    let mut term = |name: &str, args: Vec<BxTerm>| builder.term(name, args);
+    // plus(zero, N) = N
+    let args      = vec![term("zero", vec![]), term("N", vec![])];
+    let plus_zero = (term("plus", args), term("N", vec![]));
+    // plus(s(N), M) = s(plus(N, M))
+    let args      = vec![term("N", vec![])];
+    let args      = vec![term("s", args), term("M", vec![])];
+    let lhs       = term("plus", args);
+    let args      = vec![term("N", vec![]), term("M", vec![])];
+    let args      = vec![term("plus", args)];
+    let plus_s    = (lhs, term("s", args));
+    let equations = [plus_zero, plus_s];
+    // plus(s(zero), s(zero))
+    let zeros     = vec![term("zero", vec![]), term("zero", vec![])];
+    let ones      = zeros.into_iter().map(|zero| term("s", vec![zero])).collect();
+    let mut two   = term("plus", ones);
+
+    for (lhs, rhs) in equations {
+      builder.add_equation(None, lhs, rhs, vec![]);
+    }
+    let module = builder.build().unwrap();
+
+    // The numbers and the booleans are two connected components.
+    assert_eq!(module.kinds.len(), 2);
+    let sort = |name: &str| module.sorts.iter().find(|(sort_name, _)| *sort_name == IString::from(name)).unwrap().1;
+    unsafe {
+      assert!((*sort("NzNat")).leq_sort(&*sort("Int")));
+      assert!(!(*sort("Nat")).leq_sort(&*sort("NzInt")));
+    }
+    let (result, _) = module.reduce(&mut two).unwrap();
+    assert_eq!(result.borrow().repr(FormatStyle::Default), "s(s(zero))");
+    assert_eq!(result.borrow().sort(), Some(sort("NzNat")));
+  }
+
+  #[test]
+  fn inconsistent_declarations_are_reported() {
+    let mut builder = ModuleBuilder::new();
+    builder.add_subsort("A", "A");
+    assert_eq!(builder.build().err(), Some(ParseErrorKind::SelfSubsort(IString::from("A")).into()));
+
+    let mut builder = ModuleBuilder::new();
+    builder.add_subsort("A", "B").add_subsort("B", "A");
+    assert!(matches!(builder.build().err(), Some(ModuleError::Declaration(ParseErrorKind::SubsortCycle(_)))));
+
+    let mut builder = ModuleBuilder::new();
+    builder.declare_op("c", &[], "A").declare_op("c", &[], "B").add_subsort("C", "C");
+    // The first error is the one reported.
+    assert_eq!(builder.build().err(), Some(ParseErrorKind::DuplicateSymbol(IString::from("c")).into()));
+  }
+
+  #[test]
+  fn undeclared_symbols_in_terms_are_reported() {
+    let mut builder = ModuleBuilder::new();
+    builder.declare_op("f", &["A"], "A").declare_variable("X", Some("A"));
+    let x   = builder.term("X", vec![]);
+    let g   = builder.term("g", vec![x]);
+    let lhs = builder.term("f", vec![g]);
+    // A second use of the name refers to the same placeholder symbol rather than recording another error.
+    let rhs = builder.term("g", vec![]);
+    assert_eq!(lhs.repr(FormatStyle::Default), "f(g(X))");
+    builder.add_equation(None, lhs, rhs, vec![]).declare_op("f", &[], "B");
+    assert_eq!(builder.build().err(), Some(ModuleError::UndeclaredSymbol(IString::from("g"))));
+  }
+}
@@ -0,0 +1,51 @@
+/*!
+
+Building a module with a `ModuleBuilder` fails if its declarations are inconsistent, as constructing a module from
+source text does: a sort declared as a subsort of itself, subsort declarations forming a cycle, or a symbol declared
+twice with declarations that do not overload each other. These are reported with the `ParseErrorKind` the parser
+reports them with. Building also fails if a term names a symbol or variable that was never declared, since the
+builder does not declare symbols implicitly.
+
+*/
+
+use std::error::Error;
+use std::fmt::{Debug, Display, Formatter};
+
+use crate::{
+  abstractions::IString,
+  parser::ParseErrorKind
+};
+
+#[derive(Clone, Eq, PartialEq)]
+pub enum ModuleError {
+  /// An inconsistent declaration, like a sort declared as a subsort of itself.
+  Declaration(ParseErrorKind),
+  /// A name used in a term built with `ModuleBuilder::term(…)` that is not a declared symbol or variable.
+  UndeclaredSymbol(IString),
+}
+
+impl From<ParseErrorKind> for ModuleError {
+  fn from(error: ParseErrorKind) -> Self {
+    ModuleError::Declaration(error)
+  }
+}
+
+impl Display for ModuleError {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    match self {
+
+      ModuleError::Declaration(error) => Display::fmt(error, f),
+
+      ModuleError::UndeclaredSymbol(name) => write!(f, "symbol {} is used in a term but not declared.", name),
+
+    }
+  }
+}
+
+impl Debug for ModuleError {
+  fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+    Display::fmt(self, f)
+  }
+}
+
+impl Error for ModuleError{}
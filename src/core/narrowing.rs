@@ -0,0 +1,292 @@
+/*!
+
+Narrowing generalizes rewriting to terms with variables. Where a rule rewrites a subterm that its left-hand side
+matches, narrowing rewrites a subterm that its left-hand side *unifies* with, instantiating the variables of the
+term in the process. Narrowing `f(Y)` with the rule `f(g(X)) => h(X)` binds `Y` to `g(X₁)` and gives `h(X₁)`, where
+`X₁` is a fresh copy of `X`. Rules meant for narrowing are marked with the `narrowing` attribute, as in
+`rule f(g(X)) => h(X) [narrowing];`.
+
+`narrow_one_step(…)` computes every narrowing step of a term with one rule, at every position of the term that is not
+a variable, outermost first and then left to right. Rules without the `narrowing` attribute are not used.
+
+## Fresh Variables
+
+Variables are symbols of the module, so the rule and the term can share variables. The variables of the rule are
+therefore renamed to fresh variables before unification, which keeps `f(X)` from being captured by `X` in
+`f(g(X)) => h(X)`. A fresh variable has the sort of the variable it copies, and its name is the original name with a
+subscript, like `X₁`.
+
+Fresh variables belong to the module, which frees them when it is dropped, so narrowed terms are valid as long as the
+module is, like any other term of the module. A fresh variable that does not occur in the subject is reused for the
+same variable of a later step, so a narrowing loop makes only as many fresh variables as its terms have at once.
+
+ToDo: Conditional rules are not used for narrowing.
+
+*/
+
+use std::sync::atomic::{
+  AtomicU64,
+  Ordering
+};
+
+use crate::{
+  abstractions::{
+    int_to_subscript,
+    HashSet,
+    IString,
+    RcCell
+  },
+  core::{
+    module::Module,
+    pre_equation::{
+      PreEquation,
+      PreEquationKind
+    },
+    sort::sort_spec::SortSpec,
+    substitution::Substitution,
+    unification::{
+      resolve,
+      unify
+    }
+  },
+  heap_construct,
+  theory::{
+    dag_node::{
+      DagNode,
+      RcDagNode
+    },
+    symbol::{
+      symbol_for_symbol_type,
+      Symbol,
+      SymbolPtr
+    }
+  }
+};
+
+/// The source of the subscripts of fresh variables.
+static NEXT_FRESH_VARIABLE: AtomicU64 = AtomicU64::new(1);
+
+/**
+Every narrowing step of `subject` with `rule`, as pairs of the narrowed term and the unifier that was applied,
+restricted to the variables of `subject`. Returns nothing if `rule` is not a rule marked `narrowing`, or if it is
+conditional, bad, or `nonexec`. The fresh variables of the results belong to `module`. See the module documentation.
+*/
+pub fn narrow_one_step(subject: &RcDagNode, rule: &PreEquation, module: &Module) -> Vec<(RcDagNode, Substitution)> {
+  let rhs_term = match &rule.kind {
+    PreEquationKind::Rule { rhs_term }
+      if rule.is_narrowing() && rule.conditions.is_empty() && !rule.is_bad() && !rule.is_nonexec() => rhs_term,
+    _ => return vec![]
+  };
+
+  let mut subject_variables = HashSet::new();
+  add_dag_variables(subject, &mut subject_variables);
+
+  let mut variables = Vec::new();
+  rule.lhs_term.add_variables_in_order(&mut variables);
+  rhs_term.add_variables_in_order(&mut variables);
+  let mut renaming = Substitution::with_capacity(variables.len());
+  let mut used     = subject_variables.clone();
+  for variable in variables {
+    let fresh = fresh_variable(variable, module, &used);
+    used.insert(fresh);
+    renaming.bind(variable, RcCell::new(DagNode::new(fresh, vec![])));
+  }
+  let lhs = rule.lhs_term.instantiate(&renaming);
+  let rhs = rhs_term.instantiate(&renaming);
+
+  narrowings_at(subject, &lhs, &rhs, module)
+      .into_iter()
+      .map(
+        | (narrowed, unifier) | {
+          let mut restricted = Substitution::with_capacity(subject_variables.len());
+          for (variable, value) in unifier.iter() {
+            if subject_variables.contains(&variable) {
+              restricted.bind(variable, value);
+            }
+          }
+          (resolve(&narrowed, &unifier), restricted)
+        }
+      )
+      .collect()
+}
+
+/// The narrowing steps of `node` with the renamed rule `lhs => rhs`, before the unifiers are applied to the results.
+fn narrowings_at(
+  node  : &RcDagNode,
+  lhs   : &RcDagNode,
+  rhs   : &RcDagNode,
+  module: &Module
+) -> Vec<(RcDagNode, Substitution)>
+{
+  let dag_node = node.borrow();
+  if unsafe{ (*dag_node.top_symbol).is_variable() } {
+    return vec![];
+  }
+
+  let mut narrowings = Vec::new();
+  if let Some(unifier) = unify(node, lhs, Substitution::with_capacity(module.minimum_substitution_size())) {
+    narrowings.push((rhs.clone(), unifier));
+  }
+
+  let args: Vec<RcDagNode> = dag_node.iter_args().collect();
+  for (index, arg) in args.iter().enumerate() {
+    for (narrowed_arg, unifier) in narrowings_at(arg, lhs, rhs, module) {
+      // The argument of a tower is the tower one lower, so only the application on top is rebuilt.
+      let mut new_args = args.clone();
+      new_args[index]  = narrowed_arg;
+      let mut narrowed = DagNode::make(dag_node.top_symbol, new_args);
+      narrowed.structural_hash();
+      narrowings.push((RcCell::new(narrowed), unifier));
+    }
+  }
+  narrowings
+}
+
+/// A fresh variable of `module` with the sort of `variable` that is not in `used`, reusing one made before if there is
+/// one. See the module documentation.
+fn fresh_variable(variable: SymbolPtr, module: &Module, used: &HashSet<SymbolPtr>) -> SymbolPtr {
+  let mut fresh_variables = module.fresh_variables.borrow_mut();
+  let reusable = fresh_variables.iter().find(|(original, fresh)| *original == variable && !used.contains(fresh));
+  if let Some((_, fresh)) = reusable {
+    return *fresh;
+  }
+  let fresh = new_fresh_variable(variable);
+  fresh_variables.push((variable, fresh));
+  fresh
+}
+
+/// A new variable with the sort of `variable`, which the caller owns.
+fn new_fresh_variable(variable: SymbolPtr) -> SymbolPtr {
+  let variable  = unsafe{ &*variable };
+  let subscript = NEXT_FRESH_VARIABLE.fetch_add(1, Ordering::Relaxed);
+  let name      = format!("{}{}", variable.name, int_to_subscript(subscript as i64));
+  let mut fresh = Symbol::new(IString::from(name.as_str()));
+  fresh.arity         = variable.arity;
+  fresh.symbol_type   = variable.symbol_type;
+  fresh.theory_symbol = symbol_for_symbol_type(&variable.symbol_type);
+  fresh.sort_spec     = variable.range_sort().map(|sort| Box::new(SortSpec::Sort(sort)));
+  heap_construct!(fresh)
+}
+
+/// Inserts the variables occurring in `node` into `variables`.
+fn add_dag_variables(node: &RcDagNode, variables: &mut HashSet<SymbolPtr>) {
+  let dag_node = node.borrow();
+  if unsafe{ (*dag_node.top_symbol).is_variable() } {
+    variables.insert(dag_node.top_symbol);
+  }
+  for arg in dag_node.args.iter() {
+    add_dag_variables(arg, variables);
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use crate::{
+    core::command::Command,
+    parser::parse_to_module_with_commands
+  };
+  use super::*;
+
+  #[test]
+  fn narrowing_unifies_at_every_position() {
+    let (module, commands) = parse_to_module_with_commands(
+      "
+      variable X;
+      variable Y;
+      symbol f;
+      symbol g;
+      symbol h;
+
+      rule f(g(X)) => h(X) [narrowing];
+
+      reduce f(Y);
+      reduce g(f(Y));
+      reduce f(X);
+      reduce f(f(g(a)));
+      "
+    ).unwrap();
+    let rule = &module.rules[0];
+    assert!(rule.is_narrowing());
+    let subjects: Vec<RcDagNode> = commands.iter()
+                                           .map(|command| match command {
+                                             Command::Reduce(term) => term.dagify(),
+                                             _ => unreachable!()
+                                           })
+                                           .collect();
+    let x = module.symbols[&IString::from("X")];
+    let y = module.symbols[&IString::from("Y")];
+
+    // At the top, `Y` is bound to `g(X₁)` for the fresh variable `X₁` of the result `h(X₁)`.
+    let narrowings = narrow_one_step(&subjects[0], rule, &module);
+    assert_eq!(narrowings.len(), 1);
+    let (narrowed, unifier) = &narrowings[0];
+    let fresh = narrowed.borrow().args[0].clone();
+    assert_eq!(narrowed.borrow().to_string(), format!("h({})", fresh.borrow()));
+    assert!(unsafe{ (*fresh.borrow().top_symbol).is_variable() } && fresh.borrow().top_symbol != x);
+    assert!(unifier.value(y).unwrap().borrow().args[0].borrow().equals(&fresh.borrow()));
+    assert_eq!(unifier.iter().count(), 1);
+
+    // Below the top, with the rest of the term instantiated too.
+    let narrowings = narrow_one_step(&subjects[1], rule, &module);
+    assert_eq!(narrowings.len(), 1);
+    assert!(narrowings[0].0.borrow().to_string().starts_with("g(h(X"));
+
+    // The variables of the rule are renamed apart from the subject's, so `X` is not captured.
+    let narrowings = narrow_one_step(&subjects[2], rule, &module);
+    assert_eq!(narrowings.len(), 1);
+    assert!(narrowings[0].1.value(x).unwrap().borrow().to_string().starts_with("g(X"));
+
+    // A ground term narrows like it rewrites.
+    let narrowings: Vec<String> = narrow_one_step(&subjects[3], rule, &module)
+        .iter()
+        .map(|(narrowed, unifier)| {
+          assert!(unifier.is_empty());
+          narrowed.borrow().to_string()
+        })
+        .collect();
+    assert_eq!(narrowings, vec!["f(h(a))"]);
+  }
+
+  #[test]
+  fn fresh_variables_are_reused_and_rules_must_be_marked() {
+    let (module, commands) = parse_to_module_with_commands(
+      "
+      variable X;
+      variable Y;
+      symbol f;
+      symbol g;
+      symbol h;
+
+      rule f(g(X)) => h(X) [narrowing];
+      rule f(X) => X;
+
+      reduce f(Y);
+      "
+    ).unwrap();
+    let subject = match &commands[0] {
+      Command::Reduce(term) => term.dagify(),
+      _ => unreachable!()
+    };
+
+    let (narrowed, _) = narrow_one_step(&subject, &module.rules[0], &module).pop().unwrap();
+    for _ in 0..10 {
+      let (again, _) = narrow_one_step(&subject, &module.rules[0], &module).pop().unwrap();
+      assert!(again.borrow().equals(&narrowed.borrow()));
+    }
+    assert_eq!(module.fresh_variables.borrow().len(), 1);
+
+    // A fresh variable occurring in the subject is not reused.
+    let f       = module.symbols[&IString::from("f")];
+    let fresh   = narrowed.borrow().args[0].clone();
+    let subject = RcCell::new(DagNode::new(f, vec![fresh.clone()]));
+    let (result, unifier) = narrow_one_step(&subject, &module.rules[0], &module).pop().unwrap();
+    assert_eq!(module.fresh_variables.borrow().len(), 2);
+    assert!(!result.borrow().args[0].borrow().equals(&fresh.borrow()));
+    assert!(unifier.value(fresh.borrow().top_symbol).is_some());
+
+    // A rule without the `narrowing` attribute is not used for narrowing.
+    assert!(!module.rules[1].is_narrowing());
+    assert!(narrow_one_step(&subject, &module.rules[1], &module).is_empty());
+  }
+}
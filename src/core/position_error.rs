@@ -0,0 +1,43 @@
+/*!
+
+Replacing the subterm of a DAG at a position with `DagNode::replace_at(…)`, or applying a rule at a position with
+`RewritingContext::apply_rule_at(…)`, fails if the position does not exist, that is, if one of its argument indices
+is out of range for the subterm it indexes.
+
+*/
+
+use std::error::Error;
+use std::fmt::{Debug, Display, Formatter};
+
+use crate::core::apply_error::position_string;
+
+#[derive(Clone, Eq, PartialEq)]
+pub struct PositionError {
+  /// The position, a path of argument indices from the top of the term.
+  pub position : Vec<usize>,
+  /// The number of indices of the position that exist. The next index is out of range.
+  pub depth    : usize,
+  /// The number of arguments of the subterm at the first `depth` indices of the position.
+  pub arg_count: usize,
+}
+
+impl Display for PositionError {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    write!(
+      f,
+      "the term has no subterm at position {}: the subterm at {} has {} argument{}.",
+      position_string(&self.position),
+      position_string(&self.position[..self.depth]),
+      self.arg_count,
+      if self.arg_count == 1 { "" } else { "s" }
+    )
+  }
+}
+
+impl Debug for PositionError {
+  fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+    Display::fmt(self, f)
+  }
+}
+
+impl Error for PositionError{}
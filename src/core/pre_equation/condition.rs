@@ -6,8 +6,26 @@ apply. Conditions are like a "lite" version of `PreEquation`.
 
 */
 
-use crate::theory::term::BxTerm;
-use crate::core::sort::sort_spec::BxSortSpec;
+use crate::abstractions::{
+  HashMap,
+  HashSet
+};
+use crate::theory::{
+  symbol::SymbolPtr,
+  term::BxTerm
+};
+use crate::core::{
+  format::{
+    FormatStyle,
+    Formattable
+  },
+  rewriting_context::RewritingContext,
+  sort::{
+    sort_spec::BxSortSpec,
+    SortPtr
+  },
+  substitution::Substitution
+};
 
 pub type Conditions  = Vec<BxCondition>;
 pub type BxCondition = Box<Condition>;
@@ -37,3 +55,116 @@ pub enum Condition {
     rhs_term: BxTerm
   },
 }
+
+impl Condition {
+  /**
+  Checks whether the condition holds under `substitution`, reducing its terms in `context`. A match condition
+  extends `substitution` with the bindings of its pattern.
+
+  ToDo: Sort tests need the sort of a reduced term, and rewrite conditions need a rule search, neither of which is
+        implemented. Such conditions never hold.
+  */
+  pub fn check(&self, substitution: &mut Substitution, context: &mut RewritingContext) -> bool {
+    match self {
+
+      Condition::Equality { lhs_term, rhs_term } => {
+        let lhs = context.reduce(&lhs_term.instantiate(substitution));
+        let rhs = context.reduce(&rhs_term.instantiate(substitution));
+        let result = lhs.borrow().equals(&rhs.borrow());
+        result
+      }
+
+      Condition::Match { lhs_term, rhs_term } => {
+        let subject = context.reduce(&rhs_term.instantiate(substitution));
+        lhs_term.match_dag(&subject, substitution)
+      }
+
+      Condition::SortMembership { .. }
+      | Condition::Rewrite { .. } => false,
+
+    }
+  }
+
+  /// Inserts the (symbols of the) variables occurring in the condition into `variables`.
+  pub fn add_variables(&self, variables: &mut HashSet<SymbolPtr>) {
+    match self {
+
+      Condition::Equality { lhs_term, rhs_term }
+      | Condition::Match { lhs_term, rhs_term }
+      | Condition::Rewrite { lhs_term, rhs_term } => {
+        lhs_term.add_variables(variables);
+        rhs_term.add_variables(variables);
+      }
+
+      Condition::SortMembership { lhs_term, .. } => {
+        lhs_term.add_variables(variables);
+      }
+
+    }
+  }
+
+  /// Appends the variables of the condition not already in `variables`, in the order they occur in its text.
+  pub fn add_variables_in_order(&self, variables: &mut Vec<SymbolPtr>) {
+    match self {
+
+      Condition::Equality { lhs_term, rhs_term }
+      | Condition::Match { lhs_term, rhs_term }
+      | Condition::Rewrite { lhs_term, rhs_term } => {
+        lhs_term.add_variables_in_order(variables);
+        rhs_term.add_variables_in_order(variables);
+      }
+
+      Condition::SortMembership { lhs_term, .. } => {
+        lhs_term.add_variables_in_order(variables);
+      }
+
+    }
+  }
+
+  /// Inserts every symbol occurring in the condition into `symbols`.
+  pub fn add_symbols(&self, symbols: &mut HashSet<SymbolPtr>) {
+    match self {
+
+      Condition::Equality { lhs_term, rhs_term }
+      | Condition::Match { lhs_term, rhs_term }
+      | Condition::Rewrite { lhs_term, rhs_term } => {
+        lhs_term.add_symbols(symbols);
+        rhs_term.add_symbols(symbols);
+      }
+
+      Condition::SortMembership { lhs_term, .. } => {
+        lhs_term.add_symbols(symbols);
+      }
+
+    }
+  }
+
+  /// Inserts the sorts named by a sort test condition into `sorts`.
+  pub fn add_sorts(&self, sorts: &mut HashSet<SortPtr>) {
+    if let Condition::SortMembership { sort, .. } = self {
+      sort.add_sorts(sorts);
+    }
+  }
+}
+
+impl Condition {
+  /// The representation of the condition with the variables in `names` renamed. See `Term::repr_with_names(…)`.
+  pub fn repr_with_names(&self, style: FormatStyle, names: &HashMap<SymbolPtr, String>) -> String {
+    let latex = style == FormatStyle::Latex;
+    let (lhs_term, op, rhs) = match self {
+      Condition::Equality { lhs_term, rhs_term }   => (lhs_term, "=", rhs_term.repr_with_names(style, names)),
+      Condition::Match { lhs_term, rhs_term }      => (lhs_term, ":=", rhs_term.repr_with_names(style, names)),
+      Condition::Rewrite { lhs_term, rhs_term }    => {
+        (lhs_term, if latex { "\\Rightarrow" } else { "=>" }, rhs_term.repr_with_names(style, names))
+      }
+      Condition::SortMembership { lhs_term, sort } => (lhs_term, if latex { ":" } else { "::" }, sort.repr(style)),
+    };
+    format!("{} {} {}", lhs_term.repr_with_names(style, names), op, rhs)
+  }
+}
+
+impl Formattable for Condition {
+  fn repr(&self, style: FormatStyle) -> String {
+    self.repr_with_names(style, &HashMap::new())
+  }
+}
@@ -6,16 +6,46 @@ implemented.) The subclass is implemented as enum `PreEquationKind`.
 */
 
 pub mod condition;
+pub(crate) mod variable_scope_error;
+
+#[cfg(feature = "profiling")]
+use std::time::Instant;
 
 use enumflags2::{bitflags, BitFlags};
 
 use crate::{
-  abstractions::IString,
+  abstractions::{
+    join_iter,
+    HashMap,
+    HashSet,
+    IString
+  },
   core::{
-    pre_equation::condition::Conditions,
-    sort::sort::SortPtr
+    diagnostic::Span,
+    format::{
+      FormatStyle,
+      Formattable
+    },
+    pre_equation::{
+      condition::{
+        Condition,
+        Conditions
+      },
+      variable_scope_error::VariableScopeError
+    },
+    rewriting_context::RewritingContext,
+    sort::sort::SortPtr,
+    substitution::Substitution
+  },
+  theory::{
+    acu_theory::extend,
+    dag_node::RcDagNode,
+    symbol::SymbolPtr,
+    term::{
+      BxTerm,
+      Term
+    }
   },
-  theory::term::BxTerm,
 };
 use crate::core::sort::sort_spec::BxSortSpec;
 
@@ -42,6 +72,365 @@ pub struct PreEquation {
 
   pub lhs_term  : BxTerm,
   pub kind      : PreEquationKind,
+  /// The doc comment preceding the statement, if any.
+  pub doc       : Option<String>,
+  /// The span of the source text the statement was parsed from, if it was parsed.
+  pub span      : Option<Span>,
+}
+
+impl PreEquation {
+  /// A `nonexec` statement is never applied during ordinary reduction or search. It is only applied by a
+  /// `RewritingContext` that allows nonexec statements. See `RewritingContext::set_allow_nonexec(…)`.
+  #[inline(always)]
+  pub fn is_nonexec(&self) -> bool {
+    self.attributes.contains(PreEquationAttribute::NonExecute)
+  }
+
+  /// An `owise` equation is only tried on a term when no other equation applies to it. See
+  /// `RewritingContext::reduce(…)`.
+  #[inline(always)]
+  pub fn is_owise(&self) -> bool {
+    self.attributes.contains(PreEquationAttribute::Otherwise)
+  }
+
+  /// A `narrowing` rule is meant to be applied by narrowing. See the `narrowing` module.
+  #[inline(always)]
+  pub fn is_narrowing(&self) -> bool {
+    self.attributes.contains(PreEquationAttribute::Narrowing)
+  }
+
+  /// A malformed statement, e.g. one that fails `PreEquation::check_variable_scope(…)`, is never applied.
+  #[inline(always)]
+  pub fn is_bad(&self) -> bool {
+    self.attributes.contains(PreEquationAttribute::Bad)
+  }
+
+  /// Checks that every variable of the statement is bound before it is used. See the `variable_scope_error` module
+  /// for the rules.
+  pub fn check_variable_scope(&self) -> Result<(), VariableScopeError> {
+    let mut bound = HashSet::new();
+    self.lhs_term.add_variables(&mut bound);
+
+    for (condition_index, condition) in self.conditions.iter().enumerate() {
+      let unbound = |term: &Term, bound: &HashSet<SymbolPtr>| {
+        first_unbound_variable(term, bound).map(
+          | variable | VariableScopeError::UnboundInCondition { variable, condition_index }
+        )
+      };
+
+      let error = match condition.as_ref() {
+
+        Condition::Equality { lhs_term, rhs_term } => {
+          unbound(lhs_term, &bound).or_else(|| unbound(rhs_term, &bound))
+        }
+
+        Condition::SortMembership { lhs_term, .. } => unbound(lhs_term, &bound),
+
+        // The pattern is on the left of a match condition and on the right of a rewrite condition.
+        Condition::Match { lhs_term: pattern, rhs_term: term }
+        | Condition::Rewrite { lhs_term: term, rhs_term: pattern } => {
+          let error = unbound(term, &bound);
+          pattern.add_variables(&mut bound);
+          error
+        }
+
+      };
+
+      if let Some(error) = error {
+        return Err(error);
+      }
+    }
+
+    match &self.kind {
+      PreEquationKind::Equation { rhs_term } | PreEquationKind::Rule { rhs_term } => {
+        match first_unbound_variable(rhs_term, &bound) {
+          Some(variable) => Err(VariableScopeError::UnboundInRhs { variable }),
+          None           => Ok(())
+        }
+      }
+      PreEquationKind::Membership { .. } => Ok(())
+    }
+  }
+
+  /**
+  Applies the statement at the top of `subject`: if the left-hand side matches and every condition holds, returns the
+  instantiated right-hand side. Membership axioms have no right-hand side, and bad statements are never applied.
+
+  The arguments of `subject` are assumed to be reduced. Conditions are checked from left to right, and the bindings
+  of a match condition are visible to the conditions that follow it.
+  */
+  pub fn apply(&self, subject: &RcDagNode, context: &mut RewritingContext) -> Option<RcDagNode> {
+    self.apply_with_witness(subject, context).map(|(result, _)| result)
+  }
+
+  /// Like `PreEquation::apply(…)`, but also returns the substitution that justified the application: the bindings of
+  /// the left-hand side together with those made by match and rewrite conditions. This is the answer to "why did
+  /// this statement apply?"
+  pub fn apply_with_witness(
+    &self,
+    subject: &RcDagNode,
+    context: &mut RewritingContext
+  ) -> Option<(RcDagNode, Substitution)>
+  {
+    #[cfg(feature = "profiling")]
+    let start = Instant::now();
+    let result = self.match_and_check(subject, context);
+    #[cfg(feature = "profiling")]
+    context.module().profile_statement(self, |profile| profile.time += start.elapsed());
+    result
+  }
+
+  /// The body of `PreEquation::apply_with_witness(…)`, which times it when profiling.
+  fn match_and_check(&self, subject: &RcDagNode, context: &mut RewritingContext) -> Option<(RcDagNode, Substitution)> {
+    if self.is_bad() {
+      return None;
+    }
+    let rhs_term = match &self.kind {
+      PreEquationKind::Equation { rhs_term } | PreEquationKind::Rule { rhs_term } => rhs_term,
+      PreEquationKind::Membership { .. } => return None
+    };
+
+    // A pattern with AC symbols can match in several ways, and the condition is tried for each until it holds. An AC
+    // symbol on top can also match part of the subject, and the rest of the subject is put back around the result.
+    let substitution = Substitution::with_capacity(context.module().minimum_substitution_size());
+    for (mut substitution, extension) in self.lhs_term.match_with_extension(subject, substitution) {
+      if self.conditions.is_empty() || self.check_condition(&mut substitution, context) {
+        let result = rhs_term.instantiate(&substitution);
+        let result = match extension.is_empty() {
+          true  => result,
+          false => extend(self.lhs_term.top_symbol().unwrap(), result, extension)
+        };
+        return Some((result, substitution));
+      }
+    }
+    None
+  }
+
+  /// Whether the left-hand side matches `subject` and the condition then holds, which is how a membership axiom
+  /// applies. Bad statements never apply.
+  pub fn applies_to(&self, subject: &RcDagNode, context: &mut RewritingContext) -> bool {
+    if self.is_bad() {
+      return false;
+    }
+    let substitution = Substitution::with_capacity(context.module().minimum_substitution_size());
+    self.lhs_term
+        .match_all(subject, substitution)
+        .into_iter()
+        .any(|mut substitution| self.conditions.is_empty() || self.check_condition(&mut substitution, context))
+  }
+
+  /// Tries the condition of the statement under `substitution`, which holds the bindings of the left-hand side,
+  /// recording the trial in the condition statistics of `context`.
+  fn check_condition(&self, substitution: &mut Substitution, context: &mut RewritingContext) -> bool {
+    context.condition_stats.trials += 1;
+    for (i, condition) in self.conditions.iter().enumerate() {
+      let holds = condition.check(substitution, context);
+      #[cfg(feature = "profiling")]
+      context.module().profile_statement(
+        self,
+        |profile| match holds {
+          true  => profile.fragment_successes += 1,
+          false => profile.fragment_failures += 1
+        }
+      );
+      if !holds {
+        context.condition_stats.failures += 1;
+        if i > 0 {
+          context.condition_stats.backtracks += 1;
+        }
+        return false;
+      }
+    }
+    context.condition_stats.successes += 1;
+    true
+  }
+
+  /// The distinct variables of the statement in the order they first occur in its text: the left-hand side, the
+  /// right-hand side, then the conditions. Unlike `variable_count(…)`, the order does not depend on hashing.
+  pub fn variables(&self) -> Vec<SymbolPtr> {
+    let mut variables = Vec::new();
+    self.lhs_term.add_variables_in_order(&mut variables);
+    match &self.kind {
+      PreEquationKind::Equation { rhs_term } | PreEquationKind::Rule { rhs_term } => {
+        rhs_term.add_variables_in_order(&mut variables);
+      }
+      PreEquationKind::Membership { .. } => {}
+    }
+    for condition in self.conditions.iter() {
+      condition.add_variables_in_order(&mut variables);
+    }
+    variables
+  }
+
+  /**
+  Display names for the variables of the statement, in the order of `PreEquation::variables(…)`. A variable is shown
+  by its source name. A variable without one is named `x1`, `x2`, … by its position, skipping names the statement
+  already uses, so statements that differ only in their nameless variables print the same. `PreEquation::repr(…)`
+  shows the variables by these names.
+
+  ToDo: Every variable of a parsed module has a source name. Nameless variables will appear once statements are
+        constructed programmatically or variables are renamed apart.
+  */
+  pub fn canonical_variable_names(&self) -> Vec<(SymbolPtr, String)> {
+    let variables = self.variables();
+    let taken: HashSet<&str> = variables.iter().map(|variable| unsafe { (**variable).name.as_str() }).collect();
+
+    let mut next  = 0;
+    let mut fresh = || loop {
+      next += 1;
+      let name = format!("x{}", next);
+      if !taken.contains(name.as_str()) {
+        return name;
+      }
+    };
+
+    variables.iter()
+             .map(
+               | variable | {
+                 let name = unsafe { (**variable).name };
+                 match name.is_empty() {
+                   true  => (*variable, fresh()),
+                   false => (*variable, name.to_string())
+                 }
+               }
+             )
+             .collect()
+  }
+
+  /// Inserts every symbol occurring in the statement, including its conditions, into `symbols`.
+  pub fn add_symbols(&self, symbols: &mut HashSet<SymbolPtr>) {
+    self.lhs_term.add_symbols(symbols);
+    match &self.kind {
+      PreEquationKind::Equation { rhs_term } | PreEquationKind::Rule { rhs_term } => rhs_term.add_symbols(symbols),
+      PreEquationKind::Membership { .. } => {}
+    }
+    for condition in self.conditions.iter() {
+      condition.add_symbols(symbols);
+    }
+  }
+
+  /// Inserts the sorts named by the statement, i.e. by a membership axiom or a sort test condition, into `sorts`.
+  pub fn add_sorts(&self, sorts: &mut HashSet<SortPtr>) {
+    if let PreEquationKind::Membership { sort_spec } = &self.kind {
+      sort_spec.add_sorts(sorts);
+    }
+    for condition in self.conditions.iter() {
+      condition.add_sorts(sorts);
+    }
+  }
+
+  /**
+  Describes each pair of terms of the statement whose sorts must be in the same kind but are not: the two sides of an
+  equation or rule, the left-hand side and the sort of a membership axiom, and the two sides of each condition fragment.
+  A term whose sort is unknown is not checked. See `Module::sort_check(…)`.
+  */
+  pub fn kind_clashes(&self) -> Vec<String> {
+    let mut clashes = Vec::new();
+    let lhs_sort    = term_sort(&self.lhs_term);
+    match &self.kind {
+      PreEquationKind::Equation { rhs_term } | PreEquationKind::Rule { rhs_term } => {
+        check_kinds(lhs_sort, term_sort(rhs_term), "the left- and right-hand sides", &mut clashes);
+      }
+      PreEquationKind::Membership { sort_spec } => {
+        check_kinds(lhs_sort, sort_spec.range_sort(), "the left-hand side and the membership sort", &mut clashes);
+      }
+    }
+
+    for (index, condition) in self.conditions.iter().enumerate() {
+      let (lhs_sort, rhs_sort) = match condition.as_ref() {
+        Condition::Equality { lhs_term, rhs_term }
+        | Condition::Match { lhs_term, rhs_term }
+        | Condition::Rewrite { lhs_term, rhs_term } => (term_sort(lhs_term), term_sort(rhs_term)),
+        Condition::SortMembership { lhs_term, sort } => (term_sort(lhs_term), sort.range_sort())
+      };
+      check_kinds(lhs_sort, rhs_sort, &format!("the two sides of condition {}", index + 1), &mut clashes);
+    }
+    clashes
+  }
+
+  /// The number of distinct variables occurring anywhere in the statement, including its conditions. This is the
+  /// number of substitution slots needed to apply the statement.
+  pub fn variable_count(&self) -> usize {
+    let mut variables = HashSet::new();
+    self.lhs_term.add_variables(&mut variables);
+    match &self.kind {
+      PreEquationKind::Equation { rhs_term } | PreEquationKind::Rule { rhs_term } => {
+        rhs_term.add_variables(&mut variables);
+      }
+      PreEquationKind::Membership { .. } => {}
+    }
+    for condition in self.conditions.iter() {
+      condition.add_variables(&mut variables);
+    }
+    variables.len()
+  }
+}
+
+/// The sort of `term`, computed from the declarations of its symbols, if it is known. See `Symbol::result_sort(…)`.
+fn term_sort(term: &Term) -> Option<SortPtr> {
+  let arg_sorts: Vec<Option<SortPtr>> = term.arguments().iter().map(|arg| term_sort(arg)).collect();
+  unsafe{ (*term.top_symbol()?).result_sort(&arg_sorts) }
+}
+
+/// Records a clash if `left` and `right` are known sorts in different kinds.
+fn check_kinds(left: Option<SortPtr>, right: Option<SortPtr>, terms: &str, clashes: &mut Vec<String>) {
+  if let (Some(left), Some(right)) = (left, right) {
+    let (left, right) = unsafe{ (&*left, &*right) };
+    if !left.kind.is_null() && !right.kind.is_null() && left.kind != right.kind {
+      clashes.push(format!("{} have sorts {} and {}, which are in different kinds.", terms, left.name, right.name));
+    }
+  }
+}
+
+/// The least variable of `term` that is not in `bound`, so that the reported variable does not depend on hash order.
+fn first_unbound_variable(term: &Term, bound: &HashSet<SymbolPtr>) -> Option<SymbolPtr> {
+  let mut variables = HashSet::new();
+  term.add_variables(&mut variables);
+  variables.into_iter()
+           .filter(|variable| !bound.contains(variable))
+           .min_by(|a, b| unsafe{ (**a).cmp(&**b) })
+}
+
+impl Formattable for PreEquation {
+  /// Statements are printed as they are written in a module, e.g. `equation f(X) = X;`, with the variables named by
+  /// `PreEquation::canonical_variable_names(…)`. The `Latex` style omits the keyword and aligns on the operator
+  /// (`f(X) &= X`), so that statements can be set in an `align*` environment.
+  fn repr(&self, style: FormatStyle) -> String {
+    let latex = style == FormatStyle::Latex;
+    // Variables with a source name keep their own representation, which may be given by a `latex` attribute.
+    let names: HashMap<SymbolPtr, String> = self.canonical_variable_names()
+                                                .into_iter()
+                                                .filter(|(variable, _)| unsafe { (**variable).name.is_empty() })
+                                                .collect();
+    let (keyword, op, rhs) = match &self.kind {
+      PreEquationKind::Equation { rhs_term }    => ("equation", "=", rhs_term.repr_with_names(style, &names)),
+      PreEquationKind::Rule { rhs_term }        => ("rule", "=>", rhs_term.repr_with_names(style, &names)),
+      PreEquationKind::Membership { sort_spec } => ("membership", "::", sort_spec.repr(style)),
+    };
+    let op = match (latex, op) {
+      (true, "=>") => "\\Rightarrow",
+      (true, "::") => ":",
+      _            => op
+    };
+
+    let (and, if_keyword) = if latex { (" \\wedge ", " \\quad \\text{if } ") } else { (" ∧ ", " if ") };
+    let conditions = match self.conditions.is_empty() {
+      true  => String::new(),
+      false => {
+        let conditions = self.conditions.iter().map(|c| c.repr_with_names(style, &names));
+        let conditions = join_iter(conditions, |_| and.to_string());
+        format!("{}{}", if_keyword, conditions.collect::<String>())
+      }
+    };
+
+    if latex {
+      format!("{} &{} {}{}", self.lhs_term.repr_with_names(style, &names), op, rhs, conditions)
+    } else {
+      let label = self.name.map(|name| format!("[{}] ", name)).unwrap_or_default();
+      let lhs   = self.lhs_term.repr_with_names(style, &names);
+      format!("{} {}{} {} {}{};", keyword, label, lhs, op, rhs, conditions)
+    }
+  }
 }
 
 
@@ -62,3 +451,185 @@ pub enum PreEquationKind {
 
   // StrategyDefinition
 }
+
+
+#[cfg(test)]
+mod tests {
+  use crate::{
+    core::command::Command,
+    heap_construct,
+    parser::{
+      parse_to_module,
+      parse_to_module_with_commands
+    },
+    theory::{
+      symbol::Symbol,
+      symbol_type::CoreSymbolType,
+      term::tests::{
+        application,
+        symbol_term
+      }
+    }
+  };
+  use super::*;
+
+  #[test]
+  fn boolean_conditions_are_equalities_with_true() {
+    let module = parse_to_module(
+      "
+      variable x;
+      symbol f;
+      symbol lt;
+
+      eq lt(3, 10) = true;
+      eq f(x) = a if lt(x, 10);
+      "
+    ).unwrap();
+
+    let Condition::Equality { rhs_term, .. } = module.equations[1].conditions[0].as_ref() else {
+      panic!("expected an equality condition");
+    };
+    let rhs_symbol = unsafe{ &*rhs_term.top_symbol().unwrap() };
+    assert_eq!(rhs_symbol.symbol_type.core_type, CoreSymbolType::SystemTrue);
+    // The `true` of the equation for `lt` is the same built-in literal, so the condition can hold.
+    assert_eq!(module.eval("f(3)").unwrap().borrow().to_string(), "a");
+    assert_eq!(module.eval("f(12)").unwrap().borrow().to_string(), "f(12)");
+  }
+
+  #[test]
+  fn rewrite_conditions_bind_only_on_the_right() {
+    let module = parse_to_module(
+      "
+      variable X;
+      variable Y;
+      symbol f;
+      symbol g;
+
+      rule f(X) => g(Y) if f(X) => g(Y);
+      rule g(X) => X if f(Y) => g(X);
+      "
+    ).unwrap();
+
+    assert!(module.rules[0].check_variable_scope().is_ok());
+    assert!(!module.rules[0].is_bad());
+
+    let error = module.rules[1].check_variable_scope().unwrap_err();
+    assert_eq!(error.to_string(), "variable Y is used in condition 1 before it is bound.");
+    assert!(module.rules[1].is_bad());
+  }
+
+  #[test]
+  fn rhs_variables_must_be_bound() {
+    let module = parse_to_module(
+      "
+      variable X;
+      variable Y;
+      symbol f;
+
+      equation f(X) = Y if Y := f(X);
+      equation f(X) = Y;
+      "
+    ).unwrap();
+
+    assert!(module.equations[0].check_variable_scope().is_ok());
+    assert!(matches!(
+      module.equations[1].check_variable_scope(),
+      Err(VariableScopeError::UnboundInRhs { .. })
+    ));
+  }
+
+  #[test]
+  fn applied_rules_return_the_bindings_of_their_conditions() {
+    let (module, commands) = parse_to_module_with_commands(
+      "
+      variable X;
+      variable Y;
+      symbol f;
+      symbol g;
+      symbol h;
+
+      equation h(a) = b;
+      rule f(X) => g(Y) if Y := h(X);
+
+      reduce f(a);
+      "
+    ).unwrap();
+    let subject = match &commands[0] {
+      Command::Reduce(term) => term.dagify(),
+      _ => unreachable!()
+    };
+
+    let mut context = RewritingContext::new(&module);
+    let (result, witness) = module.rules[0].apply_with_witness(&subject, &mut context).unwrap();
+    assert_eq!(result.borrow().to_string(), "g(b)");
+    assert_eq!(witness.repr(FormatStyle::Default), "X := a, Y := b");
+  }
+
+  #[test]
+  fn variables_are_named_in_order_of_occurrence() {
+    let module = parse_to_module(
+      "
+      variable Z;
+      variable X;
+      variable Y;
+      symbol f;
+      symbol g;
+
+      rule f(Z, g(X)) => g(Y) if Y := f(X, Z);
+      "
+    ).unwrap();
+    let rule = &module.rules[0];
+
+    let names = || rule.canonical_variable_names().into_iter().map(|(_, name)| name).collect::<Vec<_>>();
+    assert_eq!(names(), vec!["Z", "X", "Y"]);
+    assert_eq!(names(), names());
+    assert_eq!(rule.repr(FormatStyle::Default), rule.repr(FormatStyle::Default));
+
+    // A nameless variable gets a positional name that does not clash with the others.
+    let mut variable = Symbol::new(IString::default());
+    variable.symbol_type.core_type = CoreSymbolType::Variable;
+    let mut x1 = Symbol::new(IString::from("x1"));
+    x1.symbol_type.core_type = CoreSymbolType::Variable;
+    let (variable, x1) = (heap_construct!(variable), heap_construct!(x1));
+    let g = module.symbols[&IString::from("g")];
+    let statement = PreEquation {
+      name      : None,
+      attributes: Default::default(),
+      conditions: vec![],
+      lhs_term  : application(g, vec![symbol_term(variable), symbol_term(x1)]),
+      kind      : PreEquationKind::Equation { rhs_term: symbol_term(x1) },
+      doc       : None,
+      span      : None,
+    };
+    let names: Vec<String> = statement.canonical_variable_names().into_iter().map(|(_, name)| name).collect();
+    assert_eq!(names, vec!["x2", "x1"]);
+    assert_eq!(statement.repr(FormatStyle::Default), "equation g(x2, x1) = x1;");
+  }
+
+  #[test]
+  fn alpha_equivalent_statements_print_the_same() {
+    let module = parse_to_module("symbol f; symbol g;").unwrap();
+    let (f, g) = (module.symbols[&IString::from("f")], module.symbols[&IString::from("g")]);
+    let nameless_variable = || {
+      let mut variable = Symbol::new(IString::default());
+      variable.symbol_type.core_type = CoreSymbolType::Variable;
+      heap_construct!(variable)
+    };
+    let rule = || {
+      let (x, y) = (nameless_variable(), nameless_variable());
+      PreEquation {
+        name      : Some(IString::from("swap")),
+        attributes: Default::default(),
+        conditions: vec![],
+        lhs_term  : application(f, vec![symbol_term(x), application(g, vec![symbol_term(y)])]),
+        kind      : PreEquationKind::Rule { rhs_term: application(f, vec![symbol_term(y), symbol_term(x)]) },
+        doc       : None,
+        span      : None,
+      }
+    };
+    let (first, second) = (rule(), rule());
+    assert_eq!(first.repr(FormatStyle::Default), "rule [swap] f(x1, g(x2)) => f(x2, x1);");
+    assert_eq!(first.repr(FormatStyle::Default), second.repr(FormatStyle::Default));
+    assert_eq!(first.repr(FormatStyle::SExpr), second.repr(FormatStyle::SExpr));
+  }
+}
@@ -0,0 +1,62 @@
+/*!
+
+Every variable of a statement must be bound before it is used. The left-hand side binds its variables, and conditions
+are checked from left to right:
+
+ - A match condition `p := t` and a rewrite condition `t => p` may bind new variables in the pattern `p`, but every
+   variable of the term `t` must already be bound.
+ - Every variable of an equality condition `t = t'` or a sort test `t :: s` must already be bound.
+ - Every variable of the right-hand side must be bound by the left-hand side or by a condition.
+
+A statement that violates these rules is malformed. See `PreEquation::check_variable_scope(…)`.
+
+*/
+
+use std::error::Error;
+use std::fmt::{Debug, Display, Formatter};
+use crate::theory::symbol::SymbolPtr;
+
+pub enum VariableScopeError {
+  /// A variable is used by a condition before any binding of it. Conditions are indexed from zero.
+  UnboundInCondition {
+    variable       : SymbolPtr,
+    condition_index: usize
+  },
+  /// A variable of the right-hand side is bound by neither the left-hand side nor a condition.
+  UnboundInRhs {
+    variable: SymbolPtr
+  }
+}
+
+impl Display for VariableScopeError {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    match self {
+
+      VariableScopeError::UnboundInCondition { variable, condition_index } => {
+        write!(
+          f,
+          "variable {} is used in condition {} before it is bound.",
+          unsafe{ (**variable).name },
+          condition_index + 1
+        )
+      }
+
+      VariableScopeError::UnboundInRhs { variable } => {
+        write!(
+          f,
+          "variable {} in the right-hand side is not bound by the left-hand side or a condition.",
+          unsafe{ (**variable).name }
+        )
+      }
+
+    }
+  }
+}
+
+impl Debug for VariableScopeError {
+  fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+    Display::fmt(self, f)
+  }
+}
+
+impl Error for VariableScopeError{}
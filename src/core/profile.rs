@@ -0,0 +1,130 @@
+/*!
+
+Profiling of the statements of a module, enabled with the `profiling` feature. While the feature is enabled, every
+module keeps a `ModuleProfile` that counts, for each of its equations and rules, the rewrites it made, the condition
+fragments it tried that held and failed, and the time spent trying to apply it. `Module::profile_report()` renders
+the counts as a table, busiest statement first, which is the first thing to look at when a reduction is slow.
+
+Statements are identified by their index in the order of `Module::statements(…)`. The time of a statement is
+inclusive: it includes the time spent reducing the instances of its conditions, which is also counted for the
+statements applied there.
+
+Without the feature, none of this is compiled, and reduction does no profiling work at all.
+
+*/
+
+use std::{
+  cmp::Reverse,
+  time::Duration
+};
+
+/// The counts for a single statement. See the module documentation.
+#[derive(Copy, Clone, Eq, PartialEq, Default, Debug)]
+pub struct StatementProfile {
+  /// The number of rewrites made with the statement.
+  pub rewrites          : u64,
+  /// The number of condition fragments of the statement that were tried and held.
+  pub fragment_successes: u64,
+  /// The number of condition fragments of the statement that were tried and failed.
+  pub fragment_failures : u64,
+  /// The time spent trying to apply the statement, matching and conditions included.
+  pub time              : Duration,
+}
+
+#[derive(Default)]
+pub struct ModuleProfile {
+  /// The counts of the statements, indexed by statement index. Grows as statements are profiled.
+  statements: Vec<StatementProfile>,
+}
+
+impl ModuleProfile {
+  /// The counts of the statement with index `statement_index`, which are zero if it has not been profiled.
+  pub fn statement(&self, statement_index: usize) -> StatementProfile {
+    self.statements.get(statement_index).copied().unwrap_or_default()
+  }
+
+  /// The counts of the statement with index `statement_index`, for updating.
+  pub(crate) fn statement_mut(&mut self, statement_index: usize) -> &mut StatementProfile {
+    if statement_index >= self.statements.len() {
+      self.statements.resize(statement_index + 1, StatementProfile::default());
+    }
+    &mut self.statements[statement_index]
+  }
+
+  /// The indices and counts of the statements that have been profiled, with the most rewrites first.
+  pub fn by_rewrites(&self) -> Vec<(usize, StatementProfile)> {
+    let mut statements: Vec<(usize, StatementProfile)> = self.statements
+                                                             .iter()
+                                                             .copied()
+                                                             .enumerate()
+                                                             .filter(|(_, profile)| *profile != Default::default())
+                                                             .collect();
+    // The sort is stable, so statements with equal counts stay in declaration order.
+    statements.sort_by_key(|(_, profile)| Reverse(profile.rewrites));
+    statements
+  }
+
+  pub fn clear(&mut self) {
+    self.statements.clear();
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use crate::{
+    core::command::Command,
+    parser::parse_to_module_with_commands
+  };
+  use super::*;
+
+  #[test]
+  fn reduction_counts_rewrites_per_statement() {
+    let (module, commands) = parse_to_module_with_commands(
+      "
+      variable N;
+      variable M;
+      symbol plus;
+      symbol s;
+      symbol even;
+
+      equation plus(zero, M) = M;
+      equation plus(s(N), M) = s(plus(N, M));
+      equation even(N) = true if plus(N, zero) = zero;
+      equation even(N) = false;
+
+      reduce plus(s(s(s(zero))), s(zero));
+      reduce even(s(zero));
+      "
+    ).unwrap();
+    let mut terms: Vec<_> = commands.into_iter()
+                                    .map(|command| match command {
+                                      Command::Reduce(term) => term,
+                                      _ => unreachable!()
+                                    })
+                                    .collect();
+    module.reduce(&mut terms[0]).unwrap();
+
+    let profile = module.profile.borrow().by_rewrites();
+    let rewrites: Vec<(usize, u64)> = profile.iter().map(|(index, profile)| (*index, profile.rewrites)).collect();
+    // The equations that were tried without applying are listed for the time spent trying them.
+    assert_eq!(rewrites, vec![(1, 3), (0, 1), (2, 0), (3, 0)]);
+    assert!(profile.iter().all(|(_, profile)| profile.time > Duration::ZERO));
+
+    module.clear_profile();
+    module.reduce(&mut terms[1]).unwrap();
+    let conditional = module.profile.borrow().statement(2);
+    assert_eq!((conditional.rewrites, conditional.fragment_successes, conditional.fragment_failures), (0, 0, 1));
+    assert_eq!(module.profile.borrow().statement(3).rewrites, 1);
+    // The reduction of the condition instance is counted for the equations it applied.
+    assert_eq!(module.profile.borrow().statement(1).rewrites, 1);
+
+    let report = module.profile_report();
+    let lines: Vec<&str> = report.lines().collect();
+    assert!(lines[0].trim_start().starts_with("rewrites"));
+    // Statements with equal rewrite counts are listed in declaration order.
+    assert_eq!(lines.len(), 5);
+    assert!(lines[1].ends_with("equation plus(zero, M) = M;"));
+    assert!(lines[4].trim_start().starts_with('0') && lines[4].ends_with("if plus(N, zero) = zero;"));
+  }
+}
@@ -0,0 +1,85 @@
+/*!
+
+A `ReduceCache` remembers the normal forms of the terms a module has reduced, so that reducing the same term again,
+as an interactive tool like a language server does, returns the earlier result without rewriting. Entries are keyed by
+the structural hash of the input and confirmed with `DagNode::equals`, so colliding hashes never return the wrong
+result. Entries hold their nodes by reference count, so cached results stay alive until they are evicted.
+
+The key does not include the settings of the `RewritingContext` doing the reduction, so a context whose reductions can
+differ from those of a fresh context, one with a debugger, a rewrite limit, or nonexec statements allowed, neither
+reads nor fills the cache. Such a context rewrites every time, and its debugger is shown every rewrite.
+
+The cache is disabled until it is given a limit with `Module::set_reduce_cache_limit(…)`. When the cache is full, it
+is cleared before a new entry is inserted, which keeps it bounded without tracking recency.
+
+*/
+
+use crate::{
+  abstractions::{
+    HashMap,
+    HashType
+  },
+  theory::dag_node::RcDagNode
+};
+
+#[derive(Default)]
+pub struct ReduceCache {
+  /// Pairs of an input and its normal form, bucketed by the structural hash of the input.
+  entries: HashMap<HashType, Vec<(RcDagNode, RcDagNode)>>,
+  len    : usize,
+  /// The maximum number of entries. Zero disables the cache.
+  limit  : usize,
+}
+
+impl ReduceCache {
+  /// The cached normal form of `subject`, if there is one.
+  pub fn get(&self, subject: &RcDagNode) -> Option<RcDagNode> {
+    let hash   = subject.borrow_mut().structural_hash();
+    let bucket = self.entries.get(&hash)?;
+    bucket.iter()
+          .find(|(input, _)| input.borrow().equals(&subject.borrow()))
+          .map(|(_, result)| result.clone())
+  }
+
+  /// Records `result` as the normal form of `subject`. Does nothing if the cache is disabled.
+  pub fn insert(&mut self, subject: &RcDagNode, result: &RcDagNode) {
+    if self.limit == 0 {
+      return;
+    }
+    if self.len >= self.limit {
+      self.clear();
+    }
+    let hash = subject.borrow_mut().structural_hash();
+    self.entries.entry(hash).or_default().push((subject.clone(), result.clone()));
+    self.len += 1;
+  }
+
+  pub fn clear(&mut self) {
+    self.entries.clear();
+    self.len = 0;
+  }
+
+  /// Sets the maximum number of entries, discarding the current entries if there are more than `limit`. A limit of
+  /// zero disables the cache.
+  pub fn set_limit(&mut self, limit: usize) {
+    self.limit = limit;
+    if self.len > limit {
+      self.clear();
+    }
+  }
+
+  #[inline(always)]
+  pub fn limit(&self) -> usize {
+    self.limit
+  }
+
+  #[inline(always)]
+  pub fn len(&self) -> usize {
+    self.len
+  }
+
+  #[inline(always)]
+  pub fn is_empty(&self) -> bool {
+    self.len == 0
+  }
+}
@@ -0,0 +1,39 @@
+/*!
+
+Reducing a term with `Module::reduce(…)` fails if the term does not belong to the module.
+
+*/
+
+use std::error::Error;
+use std::fmt::{Debug, Display, Formatter};
+
+use crate::abstractions::IString;
+
+#[derive(Clone, Eq, PartialEq)]
+pub enum ReduceError {
+  /// The top symbol of the term is not a symbol of the module, for example because the term was parsed with a
+  /// different module.
+  UndeclaredSymbol(IString),
+  /// The head of the term is itself an application rather than a symbol.
+  NonSymbolHead,
+}
+
+impl Display for ReduceError {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    match self {
+
+      ReduceError::UndeclaredSymbol(name) => write!(f, "the symbol {} is not declared in the module.", name),
+
+      ReduceError::NonSymbolHead => write!(f, "the head of the term is not a symbol."),
+
+    }
+  }
+}
+
+impl Debug for ReduceError {
+  fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+    Display::fmt(self, f)
+  }
+}
+
+impl Error for ReduceError{}
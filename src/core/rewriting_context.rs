@@ -0,0 +1,1158 @@
+/*!
+
+A `RewritingContext` holds the state of a reduction or rewrite in a module. Reducing terms, applying statements, and
+checking conditions all happen within a context, which also collects statistics about the work done.
+
+## Condition Statistics
+
+Each time the left-hand side of a conditional statement matches, the statement's condition is *tried*. A trial
+succeeds if every condition fragment holds and fails otherwise. When a fragment fails after earlier fragments have
+held, the solver *backtracks* into the earlier fragments to look for another solution. (Condition fragments have at
+most one solution, so a backtrack currently always ends the trial. A left-hand side with AC symbols can match in
+several ways, and each match is a separate trial.) These counts are available from
+`RewritingContext::condition_stats()` without enabling any tracing.
+
+## Reentrant Reduction
+
+Checking the condition of an equation reduces instances of the terms in the condition, and such an instance can be
+the very term the equation is being applied to, as in `equation f(X) = a if f(X) = b;`. A term whose reduction is in
+progress is marked `Reducing` and recorded in the context while equations are tried at its top. Reducing the marked
+node, or a structurally equal one, inside its own reduction returns it as it is, as if it were already being
+reduced, rather than recursing forever. So the condition above fails, and `f(c)` is a normal form.
+
+## Memoization
+
+The normal forms of terms whose top symbol is declared `memo` are recorded in the context's `MemoMap` and looked up
+before such a term is rewritten. Looking up a normal form is not a rewrite, so it does not add to the rewrite count
+and is not shown to the debugger. See the `memo_map` module.
+
+## Built-in Equality
+
+An application of one of the built-in equality predicates, as in `f(X) == g(Y)`, is reduced by reducing both
+arguments and comparing their normal forms, giving the built-in `true` or `false`. Evaluating the predicate is not
+the application of a statement, so it does not add to the rewrite count. See the `equality_symbol` module.
+
+## Membership Axioms
+
+Once a term is in normal form, the membership axioms for its top symbol are tried, and one that applies gives the
+term its smaller sort, which later matches see. The condition of a membership axiom is checked while the term is
+still marked `Reducing`, so a condition that reduces the term itself fails rather than recursing. See
+`RewritingContext::constrain_to_smaller_sort(…)`.
+
+## Frozen Arguments
+
+The arguments of a symbol listed in its `frozen(…)` attribute, as in `symbol f [frozen(1)];`, are never rewritten,
+neither by reduction nor by rules, and neither are their subterms. Reducing `f(g(a), h(b))` reduces `h(b)` but leaves
+`g(a)` as it is, even if an equation for `g` applies. Equations and rules still apply at the top of `f(…)`.
+
+## Iterated Symbols
+
+A tower of an iterated symbol, like `s¹⁰⁰(z)`, is rewritten as the nested applications it stands for. If there is an
+equation or membership axiom for the symbol, the tower is reduced one application at a time from the bottom up, and
+if there is a rule for the symbol, it is tried at each partial tower. Otherwise the partial towers cannot be redexes,
+and only the top of the tower and its argument are rewritten. See the `iterated_theory` module.
+
+## Bounded Reduction
+
+Reduction does not terminate if the equations are not terminating, as with `equation f(X) = f(f(X));`.
+`RewritingContext::reduce_bounded(…)` reduces with a bound on the number of rewrites. When the bound is reached, the
+context stops rewriting as if the debugger had aborted, and the partially reduced term is returned with
+`ReduceOutcome::HitBound`. Partially reduced terms are not marked `Reduced`, and neither is a term whose equations
+were being tried when the bound was reached in a condition, so reducing again with a fresh bound picks up where the
+last reduction stopped.
+
+## Nonexec Statements
+
+Statements marked `nonexec`, as in `equation f(X) = g(X) [nonexec];`, are skipped by reduction and rewriting, like
+Maude's statements without `StateFlag::AllowNonexec`. A context that should apply them anyway, as when a statement is
+applied explicitly rather than searched for, is made with `RewritingContext::set_allow_nonexec(true)`.
+
+## Snapshots
+
+`RewritingContext::snapshot()` saves the state of the context that rewriting changes: the rewrite count, the condition
+statistics, whether rewriting has stopped, and the memo map. `RewritingContext::restore(…)` puts the saved state back,
+so that a search or a strategy can rewrite speculatively and roll the context back if the attempt leads nowhere. The
+context holds no term or substitution to save: rewriting builds new nodes rather than changing the term it starts
+from, and the substitution of a statement application lives only as long as the application. The debugger and the
+setting for `nonexec` statements are not saved, and a snapshot is only taken between reductions.
+
+## Debugging
+
+A `Debugger` attached with `RewritingContext::set_debugger(…)` is consulted before every equation and rule
+application and can stop the context from rewriting any further. See the `debugger` module.
+
+*/
+
+use crate::{
+  abstractions::{
+    HashMap,
+    HashType,
+    IString,
+    RcCell
+  },
+  builtin::equality_symbol::evaluate_equality,
+  core::{
+    debugger::{
+      Debugger,
+      StepAction
+    },
+    memo_map::MemoMap,
+    module::Module,
+    position_error::PositionError,
+    pre_equation::{
+      PreEquation,
+      PreEquationKind
+    }
+  },
+  theory::{
+    dag_node::{
+      DagNode,
+      NodeList,
+      RcDagNode
+    },
+    dag_node_attributes::DagNodeAttribute,
+    iterated_theory::make_tower,
+    symbol::{
+      Symbol,
+      SymbolPtr
+    },
+    symbol_type::SymbolAttribute
+  }
+};
+
+/// Counts of the work done in a context, returned by `Module::reduce(…)`.
+#[derive(Copy, Clone, Eq, PartialEq, Default, Debug)]
+pub struct RewriteStats {
+  /// The number of equation and rule applications.
+  pub rewrites       : u64,
+  pub condition_stats: ConditionStats,
+}
+
+/// How a reduction with `RewritingContext::reduce_bounded(…)` ended.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum ReduceOutcome {
+  /// The term was reduced to normal form.
+  Completed,
+  /// The bound on the number of rewrites was reached before the term was reduced to normal form.
+  HitBound,
+  /// The debugger aborted the reduction.
+  Aborted,
+}
+
+/// Counts of condition trials. See the module documentation.
+#[derive(Copy, Clone, Eq, PartialEq, Default, Debug)]
+pub struct ConditionStats {
+  pub trials    : u64,
+  pub successes : u64,
+  pub failures  : u64,
+  pub backtracks: u64,
+}
+
+/// The state of a `RewritingContext` saved by `RewritingContext::snapshot()`. See the module documentation.
+#[derive(Clone)]
+pub struct ContextSnapshot {
+  stats    : RewriteStats,
+  aborted  : bool,
+  hit_bound: bool,
+  memo_map : MemoMap,
+}
+
+pub struct RewritingContext<'m> {
+  pub(crate) module         : &'m Module,
+  pub(crate) condition_stats: ConditionStats,
+  /// The number of equation and rule applications made in this context.
+  pub(crate) rewrite_count  : u64,
+  debugger                  : Option<Box<dyn Debugger + 'm>>,
+  /// Set when the debugger aborts or the rewrite limit is reached. No further rewrites are made in this context.
+  aborted                   : bool,
+  /// The rewrite count at which a bounded reduction stops. See the module documentation.
+  rewrite_limit             : Option<u64>,
+  /// Whether `aborted` was set by reaching the rewrite limit rather than by the debugger.
+  hit_bound                 : bool,
+  /// The nodes marked `Reducing`, bucketed by structural hash. See the module documentation.
+  reducing                  : HashMap<HashType, Vec<RcDagNode>>,
+  /// The normal forms of terms with memoized top symbols. See the module documentation.
+  memo_map                  : MemoMap,
+  /// Whether `nonexec` statements are applied. See the module documentation.
+  allow_nonexec             : bool,
+}
+
+impl<'m> RewritingContext<'m> {
+  pub fn new(module: &'m Module) -> RewritingContext<'m> {
+    RewritingContext {
+      module,
+      condition_stats: ConditionStats::default(),
+      rewrite_count  : 0,
+      debugger       : None,
+      aborted        : false,
+      rewrite_limit  : None,
+      hit_bound      : false,
+      reducing       : HashMap::new(),
+      memo_map       : MemoMap::default(),
+      allow_nonexec  : false,
+    }
+  }
+
+  /// Attaches a debugger to be consulted before every rewrite. See the `debugger` module.
+  pub fn set_debugger(&mut self, debugger: Box<dyn Debugger + 'm>) {
+    self.debugger = Some(debugger);
+  }
+
+  /// Makes the context apply `nonexec` statements along with the others, or skip them again. See the module
+  /// documentation.
+  pub fn set_allow_nonexec(&mut self, allow_nonexec: bool) {
+    self.allow_nonexec = allow_nonexec;
+  }
+
+  /// Whether `statement` may be applied in this context, which it may unless it is `nonexec` and the context does not
+  /// allow nonexec statements.
+  #[inline(always)]
+  pub fn is_executable(&self, statement: &PreEquation) -> bool {
+    self.allow_nonexec || !statement.is_nonexec()
+  }
+
+  /// Whether reduction in this context gives the same normal forms as in a fresh context, which it does unless the
+  /// context has a debugger, is bounded, or allows `nonexec` statements. Only such a context uses the module's reduce
+  /// cache. See the `reduce_cache` module.
+  pub(crate) fn reduces_like_fresh(&self) -> bool {
+    self.debugger.is_none() && self.rewrite_limit.is_none() && !self.allow_nonexec
+  }
+
+  /// Whether the debugger aborted rewriting in this context.
+  #[inline(always)]
+  pub fn is_aborted(&self) -> bool {
+    self.aborted
+  }
+
+  /// Asks the debugger, if there is one, whether `statement` may rewrite `redex`, and counts the rewrite if it may. A
+  /// rewrite beyond the rewrite limit is refused.
+  fn approve(&mut self, redex: &RcDagNode, statement: &PreEquation) -> bool {
+    if self.aborted {
+      return false;
+    }
+    if self.rewrite_limit.is_some_and(|limit| self.rewrite_count >= limit) {
+      self.aborted   = true;
+      self.hit_bound = true;
+      return false;
+    }
+    if let Some(debugger) = self.debugger.as_mut() {
+      match debugger.on_step(redex, statement) {
+        StepAction::Step     => {}
+        StepAction::Continue => self.debugger = None,
+        StepAction::Abort    => {
+          self.aborted = true;
+          return false;
+        }
+      }
+    }
+    self.rewrite_count += 1;
+    #[cfg(feature = "profiling")]
+    self.module.profile_statement(statement, |profile| profile.rewrites += 1);
+    true
+  }
+
+  #[inline(always)]
+  pub fn module(&self) -> &'m Module {
+    self.module
+  }
+
+  /// Saves the state of the context, to be put back by `RewritingContext::restore(…)`. See the module documentation.
+  pub fn snapshot(&self) -> ContextSnapshot {
+    debug_assert!(self.reducing.is_empty(), "a snapshot is taken during a reduction");
+    ContextSnapshot {
+      stats    : self.stats(),
+      aborted  : self.aborted,
+      hit_bound: self.hit_bound,
+      memo_map : self.memo_map.clone(),
+    }
+  }
+
+  /// Puts back the state saved in `snapshot`, undoing the effect of everything done in the context since.
+  pub fn restore(&mut self, snapshot: ContextSnapshot) {
+    self.rewrite_count   = snapshot.stats.rewrites;
+    self.condition_stats = snapshot.stats.condition_stats;
+    self.aborted         = snapshot.aborted;
+    self.hit_bound       = snapshot.hit_bound;
+    self.memo_map        = snapshot.memo_map;
+  }
+
+  /// The condition statistics accumulated by this context so far.
+  #[inline(always)]
+  pub fn condition_stats(&self) -> ConditionStats {
+    self.condition_stats
+  }
+
+  /// The number of equation and rule applications made in this context so far.
+  #[inline(always)]
+  pub fn rewrite_count(&self) -> u64 {
+    self.rewrite_count
+  }
+
+  /// The rewrite count and condition statistics accumulated by this context so far.
+  pub fn stats(&self) -> RewriteStats {
+    RewriteStats {
+      rewrites       : self.rewrite_count,
+      condition_stats: self.condition_stats,
+    }
+  }
+
+  /// Whether `node`, or a node structurally equal to it, is being reduced in this context.
+  fn is_reducing(&self, node: &RcDagNode, hash: HashType) -> bool {
+    node.borrow().attributes.contains(DagNodeAttribute::Reducing)
+        || self.reducing
+               .get(&hash)
+               .is_some_and(|bucket| bucket.iter().any(|other| other.borrow().equals(&node.borrow())))
+  }
+
+  /// Marks `node` as being reduced, or unmarks it.
+  fn set_reducing(&mut self, node: &RcDagNode, hash: HashType, reducing: bool) {
+    let bucket = self.reducing.entry(hash).or_default();
+    if reducing {
+      node.borrow_mut().attributes.insert(DagNodeAttribute::Reducing);
+      bucket.push(node.clone());
+    } else {
+      node.borrow_mut().attributes.remove(DagNodeAttribute::Reducing);
+      bucket.retain(|other| !RcCell::ptr_eq(other, node));
+      if bucket.is_empty() {
+        self.reducing.remove(&hash);
+      }
+    }
+  }
+
+  /**
+  Reduces `subject` to normal form with the equations of the module, reducing arguments before trying the equations at
+  the top. Equations are tried in declaration order, except that `owise` equations are only tried if no other equation
+  applies, and `nonexec` equations are skipped unless the context allows them. The returned node and all of its
+  subterms are marked `Reduced`, so shared subterms are only reduced once. If the debugger aborts, even while a
+  condition is being checked, the term is returned as it stands and is not marked. A term that is already being
+  reduced is returned as it is, and the normal form of a term with a memoized top symbol is looked up if it is known.
+  See the module documentation.
+
+  Reduction does not terminate if the equations are not terminating. See `RewritingContext::reduce_bounded(…)`.
+  */
+  pub fn reduce(&mut self, subject: &RcDagNode) -> RcDagNode {
+    if self.aborted || subject.borrow().attributes.contains(DagNodeAttribute::Reduced) {
+      return subject.clone();
+    }
+    if self.reduces_by_level(&subject.borrow()) {
+      return self.reduce_tower(subject);
+    }
+
+    let node = {
+      let dag_node = subject.borrow();
+      let frozen = unsafe{ &(*dag_node.top_symbol).frozen };
+      let args: Vec<RcDagNode> = dag_node.args
+                                         .iter()
+                                         .enumerate()
+                                         .map(
+                                           | (i, arg) | match frozen.contains(i) {
+                                             true  => arg.clone(),
+                                             false => self.reduce(arg)
+                                           }
+                                         )
+                                         .collect();
+      if args.iter().zip(dag_node.args.iter()).all(|(arg, old_arg)| RcCell::ptr_eq(arg, old_arg)) {
+        subject.clone()
+      } else {
+        let mut new_node = dag_node.with_args(args);
+        new_node.structural_hash();
+        RcCell::new(new_node)
+      }
+    };
+    self.reduce_top(node)
+  }
+
+  /// Whether `dag_node` is a tower that is reduced one application at a time, which it is if an equation or
+  /// membership axiom for its iterated symbol could apply to a partial tower inside it. See the `iterated_theory`
+  /// module.
+  fn reduces_by_level(&self, dag_node: &DagNode) -> bool {
+    let symbol = dag_node.top_symbol;
+    dag_node.exponent > 1
+        && !unsafe{ (*symbol).frozen.contains(0) }
+        && self.module
+               .equations
+               .iter()
+               .chain(self.module.membership.iter())
+               .any(|statement| self.is_executable(statement) && applies_to_symbol(statement, symbol))
+  }
+
+  /// Reduces the tower `subject` from the bottom up, reducing its argument and then each application on top of the
+  /// normal form of the applications below it.
+  fn reduce_tower(&mut self, subject: &RcDagNode) -> RcDagNode {
+    let (symbol, exponent, arg) = {
+      let dag_node = subject.borrow();
+      (dag_node.top_symbol, dag_node.exponent, dag_node.args[0].clone())
+    };
+    let mut result = self.reduce(&arg);
+    for applied in 0..exponent {
+      if self.aborted {
+        return make_tower(symbol, exponent - applied, result);
+      }
+      result = self.reduce_top(make_tower(symbol, 1, result));
+    }
+    result
+  }
+
+  /// Reduces `node`, whose arguments are reduced, by trying the equations at its top. See
+  /// `RewritingContext::reduce(…)`.
+  fn reduce_top(&mut self, node: RcDagNode) -> RcDagNode {
+    let hash = node.borrow_mut().structural_hash();
+    if self.aborted || self.is_reducing(&node, hash) {
+      return node;
+    }
+    if let Some(value) = equality_value(&node.borrow()) {
+      let literal    = if value { Symbol::true_literal() } else { Symbol::false_literal() };
+      let mut result = DagNode::new(literal, vec![]);
+      result.structural_hash();
+      result.attributes.insert(DagNodeAttribute::Reduced);
+      return RcCell::new(result);
+    }
+
+    let memoized = unsafe{ (*node.borrow().top_symbol).symbol_type.attributes.contains(SymbolAttribute::Memoized) };
+    if memoized {
+      if let Some(normal_form) = self.memo_map.get(&node) {
+        return normal_form;
+      }
+    }
+
+    self.set_reducing(&node, hash, true);
+    let module    = self.module;
+    let equations = module.equations.iter().filter(|equation| self.is_executable(equation)).collect::<Vec<_>>();
+    let ordinary  = equations.iter().filter(|equation| !equation.is_owise());
+    for equation in ordinary.chain(equations.iter().filter(|equation| equation.is_owise())) {
+      if let Some(result) = equation.apply(&node, self) {
+        self.set_reducing(&node, hash, false);
+        if !self.approve(&node, equation) {
+          return node;
+        }
+        let normal_form = self.reduce(&result);
+        if memoized && !self.aborted {
+          self.memo_map.insert(&node, &normal_form);
+        }
+        return normal_form;
+      }
+    }
+    // A condition that was cut short may have failed only because it was, so the node is not known to be a normal
+    // form and is returned as it stands.
+    if self.aborted {
+      self.set_reducing(&node, hash, false);
+      return node;
+    }
+    // The node is still marked as being reduced, so a membership condition that reduces the node itself terminates.
+    self.constrain_to_smaller_sort(&node);
+    self.set_reducing(&node, hash, false);
+
+    if !self.aborted {
+      node.borrow_mut().attributes.insert(DagNodeAttribute::Reduced);
+    }
+    node
+  }
+
+  /**
+  Lowers the sort of `subject`, a normal form, with the membership axioms of the module for its top symbol: an
+  axiom whose sort is below the current sort of `subject` and that applies to it gives `subject` its sort, and the
+  axioms are tried again until none lowers the sort any further. `nonexec` axioms are skipped unless the context
+  allows them. See the `dag_node` module documentation.
+  */
+  pub fn constrain_to_smaller_sort(&mut self, subject: &RcDagNode) {
+    let module      = self.module;
+    let top_symbol  = subject.borrow().top_symbol;
+    let memberships = module.membership.iter().filter(
+      |membership| self.is_executable(membership) && membership.lhs_term.top_symbol() == Some(top_symbol)
+    ).collect::<Vec<_>>();
+
+    'lowered: loop {
+      let sort = subject.borrow().sort();
+      let Some(sort) = sort else { break };
+      for membership in memberships.iter() {
+        let PreEquationKind::Membership { sort_spec } = &membership.kind else { continue };
+        let Some(target) = sort_spec.range_sort() else { continue };
+        let lower = target != sort && unsafe{ (*target).leq_sort(&*sort) };
+        if lower && membership.applies_to(subject, self) {
+          subject.borrow_mut().constrain_sort(target);
+          continue 'lowered;
+        }
+      }
+      break;
+    }
+  }
+
+  /**
+  Like `RewritingContext::reduce(…)`, but makes at most `max_rewrites` rewrites, returning the term as it stands when
+  the bound is reached together with how the reduction ended. The context can go on rewriting afterward. See the
+  module documentation.
+  */
+  pub fn reduce_bounded(&mut self, subject: &RcDagNode, max_rewrites: u64) -> (RcDagNode, ReduceOutcome) {
+    self.rewrite_limit = Some(self.rewrite_count.saturating_add(max_rewrites));
+    let result         = self.reduce(subject);
+    self.rewrite_limit = None;
+
+    let outcome = if self.hit_bound {
+      self.hit_bound = false;
+      self.aborted   = false;
+      ReduceOutcome::HitBound
+    } else if self.aborted {
+      ReduceOutcome::Aborted
+    } else {
+      ReduceOutcome::Completed
+    };
+    (result, outcome)
+  }
+
+  /**
+  Applies a single rule to `subject`, which is assumed to be reduced, returning `None` if no rule applies anywhere.
+  Positions are tried outermost first and then left to right, and at each position the rules are tried in
+  declaration order. `nonexec` rules are skipped unless the context allows them.
+
+  ToDo: Maude rewrites fairly, moving on from the position of the last rewrite. This always picks the first redex.
+  */
+  pub fn rewrite_step(&mut self, subject: &RcDagNode) -> Option<RcDagNode> {
+    if self.aborted {
+      return None;
+    }
+    let module = self.module;
+    let rules: Vec<&PreEquation> = module.rules.iter().filter(|rule| self.is_executable(rule)).collect();
+    for rule in rules {
+      if let Some(result) = rule.apply(subject, self) {
+        return self.approve(subject, rule).then_some(result);
+      }
+    }
+
+    let dag_node = subject.borrow();
+    if dag_node.exponent > 1 {
+      return self.rewrite_step_in_tower(&dag_node);
+    }
+    let args: NodeList = dag_node.iter_args().collect();
+    for (i, arg) in unfrozen_args(&dag_node, &args) {
+      if let Some(result) = self.rewrite_step(arg) {
+        return Some(rebuild(&dag_node, &args, i, result));
+      }
+    }
+    None
+  }
+
+  /// Applies a single rule strictly inside the tower `dag_node`, trying the partial towers from the tallest down and
+  /// then the argument at the bottom, which are the positions `rewrite_step(…)` would try in the applications the
+  /// tower stands for. See the `iterated_theory` module.
+  fn rewrite_step_in_tower(&mut self, dag_node: &DagNode) -> Option<RcDagNode> {
+    let symbol = dag_node.top_symbol;
+    if unsafe{ (*symbol).frozen.contains(0) } {
+      return None;
+    }
+    let module = self.module;
+    let arg    = &dag_node.args[0];
+    let rules: Vec<&PreEquation> = module.rules
+                                         .iter()
+                                         .filter(|rule| self.is_executable(rule) && applies_to_symbol(rule, symbol))
+                                         .collect();
+    if !rules.is_empty() {
+      for height in (1..dag_node.exponent).rev() {
+        let partial = make_tower(symbol, height, arg.clone());
+        for rule in rules.iter() {
+          if let Some(result) = rule.apply(&partial, self) {
+            return self.approve(&partial, rule).then(|| make_tower(symbol, dag_node.exponent - height, result));
+          }
+        }
+      }
+    }
+    let result = self.rewrite_step(arg)?;
+    Some(make_tower(symbol, dag_node.exponent, result))
+  }
+
+  /**
+  Applies one of `rules` to the subterm of `subject` at `position`, a path of argument indices from the top, and
+  rebuilds the ancestors of the rewritten subterm. The rules are tried in order, and the result is not reduced.
+  Returns `None` if none of the rules applies at the position, and fails if the position does not exist.
+  */
+  pub fn apply_rule_at(
+    &mut self,
+    rules: &[&PreEquation],
+    subject: &RcDagNode,
+    position: &[usize]
+  ) -> Result<Option<RcDagNode>, PositionError>
+  {
+    self.apply_rule_below(rules, subject, position, 0)
+  }
+
+  /// Applies one of `rules` at the rest of `position` after its first `depth` indices, which lead to `subject`.
+  fn apply_rule_below(
+    &mut self,
+    rules: &[&PreEquation],
+    subject: &RcDagNode,
+    position: &[usize],
+    depth: usize
+  ) -> Result<Option<RcDagNode>, PositionError>
+  {
+    let index = match position.get(depth) {
+      Some(index) => *index,
+      None => {
+        for rule in rules.iter() {
+          if let Some(result) = rule.apply(subject, self) {
+            return Ok(self.approve(subject, rule).then_some(result));
+          }
+        }
+        return Ok(None);
+      }
+    };
+
+    let dag_node = subject.borrow();
+    let args: NodeList = dag_node.iter_args().collect();
+    let arg = match args.get(index) {
+      Some(arg) => arg.clone(),
+      None      => return Err(PositionError { position: position.to_vec(), depth, arg_count: args.len() })
+    };
+    let Some(result) = self.apply_rule_below(rules, &arg, position, depth + 1)? else {
+      return Ok(None);
+    };
+    Ok(Some(rebuild(&dag_node, &args, index, result)))
+  }
+
+  /**
+  Every single rule application to `subject`, which is assumed to be reduced, as pairs of the index of the applied
+  rule in the module's `rules` and the (unreduced) result. Results are in the order `rewrite_step(…)` would try them,
+  so the first result, if any, is the one `rewrite_step(…)` returns.
+  */
+  pub fn rewrite_successors(&mut self, subject: &RcDagNode) -> Vec<(usize, RcDagNode)> {
+    self.successors(subject, None)
+  }
+
+  /// The results of `rewrite_successors(…)` from applications of the rules labeled `label`, in the same order. Only
+  /// those rules are tried.
+  pub fn labeled_successors(&mut self, subject: &RcDagNode, label: IString) -> Vec<RcDagNode> {
+    self.successors(subject, Some(label)).into_iter().map(|(_, result)| result).collect()
+  }
+
+  /// The successors of `rewrite_successors(…)`, restricted to the rules labeled `label` if it is given.
+  fn successors(&mut self, subject: &RcDagNode, label: Option<IString>) -> Vec<(usize, RcDagNode)> {
+    let module         = self.module;
+    let mut successors = Vec::new();
+    let rules: Vec<(usize, &PreEquation)> =
+        module.rules
+              .iter()
+              .enumerate()
+              .filter(|(_, rule)| has_label(rule, label) && self.is_executable(rule))
+              .collect();
+    for (rule_index, rule) in rules {
+      if let Some(result) = rule.apply(subject, self) {
+        successors.push((rule_index, result));
+      }
+    }
+
+    let dag_node = subject.borrow();
+    if dag_node.exponent > 1 {
+      successors.extend(self.successors_in_tower(&dag_node, label));
+      return successors;
+    }
+    let args: NodeList = dag_node.iter_args().collect();
+    for (i, arg) in unfrozen_args(&dag_node, &args) {
+      for (rule_index, result) in self.successors(arg, label) {
+        successors.push((rule_index, rebuild(&dag_node, &args, i, result)));
+      }
+    }
+    successors
+  }
+
+  /// The successors of `successors(…)` from rule applications strictly inside the tower `dag_node`, in the order of
+  /// `rewrite_step_in_tower(…)`.
+  fn successors_in_tower(&mut self, dag_node: &DagNode, label: Option<IString>) -> Vec<(usize, RcDagNode)> {
+    let symbol = dag_node.top_symbol;
+    if unsafe{ (*symbol).frozen.contains(0) } {
+      return vec![];
+    }
+    let module         = self.module;
+    let arg            = &dag_node.args[0];
+    let mut successors = Vec::new();
+    let rules: Vec<(usize, &PreEquation)> =
+        module.rules
+              .iter()
+              .enumerate()
+              .filter(|(_, rule)| has_label(rule, label) && self.is_executable(rule) && applies_to_symbol(rule, symbol))
+              .collect();
+    if !rules.is_empty() {
+      for height in (1..dag_node.exponent).rev() {
+        let partial = make_tower(symbol, height, arg.clone());
+        for (rule_index, rule) in rules.iter() {
+          if let Some(result) = rule.apply(&partial, self) {
+            successors.push((*rule_index, make_tower(symbol, dag_node.exponent - height, result)));
+          }
+        }
+      }
+    }
+    for (rule_index, result) in self.successors(arg, label) {
+      successors.push((rule_index, make_tower(symbol, dag_node.exponent, result)));
+    }
+    successors
+  }
+}
+
+/// Whether `rule` is labeled `label`, or any rule if no label is given.
+fn has_label(rule: &PreEquation, label: Option<IString>) -> bool {
+  label.is_none() || rule.name == label
+}
+
+/// Whether `statement` can apply to a term with `symbol` on top, which it can if the top symbol of its left-hand side
+/// is `symbol` or a variable.
+fn applies_to_symbol(statement: &PreEquation, symbol: SymbolPtr) -> bool {
+  statement.lhs_term
+           .top_symbol()
+           .is_some_and(|top_symbol| top_symbol == symbol || unsafe{ (*top_symbol).is_variable() })
+}
+
+/// The node `dag_node` with its argument `index` replaced by `result`, where `args` are the arguments of `dag_node` as
+/// given by `DagNode::iter_args()`.
+fn rebuild(dag_node: &DagNode, args: &[RcDagNode], index: usize, result: RcDagNode) -> RcDagNode {
+  let mut args = args.to_vec();
+  args[index]  = result;
+  let mut new_node = DagNode::make(dag_node.top_symbol, args);
+  new_node.structural_hash();
+  RcCell::new(new_node)
+}
+
+/// The value of `dag_node` if it is an application of a built-in equality predicate to normal forms. See the module
+/// documentation.
+fn equality_value(dag_node: &DagNode) -> Option<bool> {
+  let symbol = unsafe{ &*dag_node.top_symbol };
+  match (symbol.is_equality(), dag_node.args.as_slice()) {
+    (true, [lhs, rhs]) => Some(evaluate_equality(symbol, &lhs.borrow(), &rhs.borrow())),
+    _                  => None
+  }
+}
+
+/// The arguments `args` of `dag_node` that are not frozen, with their indices. See the module documentation.
+fn unfrozen_args<'a>(dag_node: &DagNode, args: &'a [RcDagNode]) -> impl Iterator<Item = (usize, &'a RcDagNode)> {
+  let frozen = unsafe{ &(*dag_node.top_symbol).frozen };
+  args.iter().enumerate().filter(|(i, _)| !frozen.contains(*i))
+}
+
+
+#[cfg(test)]
+mod tests {
+  use std::{
+    cell::RefCell,
+    rc::Rc
+  };
+
+  use crate::{
+    core::{
+      command::Command,
+      format::{
+        FormatStyle,
+        Formattable
+      }
+    },
+    parser::{
+      parse_to_module,
+      parse_to_module_with_commands,
+      parse_to_term
+    }
+  };
+  use super::*;
+
+  #[test]
+  fn condition_stats_count_trials_and_backtracks() {
+    let (module, commands) = parse_to_module_with_commands(
+      "
+      variable X;
+      variable Y;
+      symbol g;
+      symbol h;
+
+      equation g(a) = b;
+      equation h(X) = a if Y := g(X) /\\ Y = c;
+      equation h(X) = X if g(X) = b;
+
+      reduce h(a);
+      reduce h(c);
+      "
+    ).unwrap();
+    let terms: Vec<_> = commands.iter().map(
+      | command | match command {
+        Command::Reduce(term) => term.dagify(),
+        _ => unreachable!()
+      }
+    ).collect();
+
+    let mut context = RewritingContext::new(&module);
+    // The first equation's condition holds up to `Y = c`, then backtracks. The second equation's condition holds.
+    assert_eq!(context.reduce(&terms[0]).borrow().to_string(), "a");
+    assert_eq!(
+      context.condition_stats(),
+      ConditionStats { trials: 2, successes: 1, failures: 1, backtracks: 1 }
+    );
+
+    // `g(c)` is irreducible. The first condition backtracks from `Y = c` again, and the second fails immediately.
+    assert_eq!(context.reduce(&terms[1]).borrow().to_string(), "h(c)");
+    assert_eq!(
+      context.condition_stats(),
+      ConditionStats { trials: 4, successes: 1, failures: 3, backtracks: 2 }
+    );
+  }
+
+  /// Steps through the first `steps` rewrites, then aborts, recording the statements it was shown.
+  struct AbortAfter {
+    steps: usize,
+    seen : Rc<RefCell<Vec<String>>>,
+  }
+
+  impl Debugger for AbortAfter {
+    fn on_step(&mut self, redex: &RcDagNode, statement: &PreEquation) -> StepAction {
+      self.seen.borrow_mut().push(format!("{} by {}", redex.borrow(), statement.repr(FormatStyle::Default)));
+      match self.seen.borrow().len() > self.steps {
+        true  => StepAction::Abort,
+        false => StepAction::Step
+      }
+    }
+  }
+
+  #[test]
+  fn debugger_abort_stops_reduction() {
+    let (module, commands) = parse_to_module_with_commands(
+      "
+      symbol f;
+
+      equation a = b;
+      equation b = c;
+      equation c = d;
+      equation f(d) = d;
+
+      reduce f(a);
+      "
+    ).unwrap();
+    let term = match &commands[0] {
+      Command::Reduce(term) => term.dagify(),
+      _ => unreachable!()
+    };
+
+    let seen        = Rc::new(RefCell::new(vec![]));
+    let mut context = RewritingContext::new(&module);
+    context.set_debugger(Box::new(AbortAfter { steps: 2, seen: seen.clone() }));
+
+    let result = context.reduce(&term);
+    assert!(context.is_aborted());
+    assert_eq!(result.borrow().to_string(), "f(c)");
+    assert!(!result.borrow().attributes.contains(DagNodeAttribute::Reduced));
+    assert_eq!(context.rewrite_count(), 2);
+    assert_eq!(*seen.borrow(), vec!["a by equation a = b;", "b by equation b = c;", "c by equation c = d;"]);
+
+    // Continuing detaches the debugger, so reduction runs to normal form.
+    let mut context = RewritingContext::new(&module);
+    context.set_debugger(Box::new(|_: &RcDagNode, _: &PreEquation| StepAction::Continue));
+    assert_eq!(context.reduce(&term).borrow().to_string(), "d");
+    assert_eq!(context.rewrite_count(), 4);
+  }
+
+  #[test]
+  fn debugger_abort_in_condition_leaves_term_unreduced() {
+    let (module, commands) = parse_to_module_with_commands(
+      "
+      variable X;
+      symbol f;
+      symbol g;
+
+      equation f(X) = a if g(X) = b;
+      equation g(c) = b;
+
+      reduce f(c);
+      "
+    ).unwrap();
+    let term = match &commands[0] {
+      Command::Reduce(term) => term.dagify(),
+      _ => unreachable!()
+    };
+
+    // The only rewrite is the one in the condition, `g(c)` to `b`, and it is refused.
+    let seen        = Rc::new(RefCell::new(vec![]));
+    let mut context = RewritingContext::new(&module);
+    context.set_debugger(Box::new(AbortAfter { steps: 0, seen: seen.clone() }));
+    let result = context.reduce(&term);
+    assert!(context.is_aborted());
+    assert_eq!(result.borrow().to_string(), "f(c)");
+    assert!(!result.borrow().attributes.contains(DagNodeAttribute::Reduced));
+    assert_eq!(*seen.borrow(), vec!["g(c) by equation g(c) = b;"]);
+
+    // So a fresh context reduces it to normal form.
+    let mut context = RewritingContext::new(&module);
+    assert_eq!(context.reduce(&term).borrow().to_string(), "a");
+  }
+
+  #[test]
+  fn bounded_reduction_stops_nonterminating_equations() {
+    let (module, commands) = parse_to_module_with_commands(
+      "
+      variable X;
+      symbol f;
+      symbol g;
+
+      equation f(X) = f(f(X));
+      equation g(a) = b;
+
+      reduce f(a);
+      reduce g(a);
+      "
+    ).unwrap();
+    let terms: Vec<_> = commands.iter().map(
+      | command | match command {
+        Command::Reduce(term) => term.dagify(),
+        _ => unreachable!()
+      }
+    ).collect();
+
+    let mut context        = RewritingContext::new(&module);
+    let (partial, outcome) = context.reduce_bounded(&terms[0], 100);
+    assert_eq!(outcome, ReduceOutcome::HitBound);
+    assert_eq!(context.rewrite_count(), 100);
+    assert!(partial.borrow().to_string().starts_with("f(f("));
+    assert!(!partial.borrow().attributes.contains(DagNodeAttribute::Reduced));
+
+    // The context is not left aborted, and the bound only counts the rewrites of the bounded reduction.
+    assert!(!context.is_aborted());
+    assert_eq!(context.reduce_bounded(&terms[1], 1).1, ReduceOutcome::Completed);
+    assert_eq!(context.rewrite_count(), 101);
+    assert_eq!(context.reduce_bounded(&terms[0], 10).1, ReduceOutcome::HitBound);
+    assert_eq!(context.rewrite_count(), 111);
+  }
+
+  #[test]
+  fn bound_reached_in_condition_leaves_term_unreduced() {
+    let (module, commands) = parse_to_module_with_commands(
+      "
+      variable X;
+      symbol f;
+      symbol g;
+
+      equation f(X) = a if g(X) = b;
+      equation g(c) = d;
+      equation d = b;
+
+      reduce f(c);
+      "
+    ).unwrap();
+    let term = match &commands[0] {
+      Command::Reduce(term) => term.dagify(),
+      _ => unreachable!()
+    };
+
+    // The condition rewrites `g(c)` to `d`, and the bound is reached before `d` is rewritten to `b`.
+    let mut context        = RewritingContext::new(&module);
+    let (partial, outcome) = context.reduce_bounded(&term, 1);
+    assert_eq!(outcome, ReduceOutcome::HitBound);
+    assert_eq!(partial.borrow().to_string(), "f(c)");
+    assert!(!partial.borrow().attributes.contains(DagNodeAttribute::Reduced));
+
+    let (result, outcome) = context.reduce_bounded(&partial, 10);
+    assert_eq!(outcome, ReduceOutcome::Completed);
+    assert_eq!(result.borrow().to_string(), "a");
+  }
+
+  #[test]
+  fn frozen_arguments_are_not_rewritten() {
+    let (module, commands) = parse_to_module_with_commands(
+      "
+      symbol f [frozen(1)];
+      symbol g;
+      symbol h;
+
+      equation g(a) = c;
+      equation h(b) = c;
+      rule g(c) => d;
+      rule h(c) => d;
+
+      reduce f(g(a), h(b));
+      reduce f(g(c), h(c));
+      "
+    ).unwrap();
+    let terms: Vec<_> = commands.iter().map(
+      | command | match command {
+        Command::Reduce(term) => term.dagify(),
+        _ => unreachable!()
+      }
+    ).collect();
+    assert!(unsafe{ (*module.symbols[&"f".into()]).frozen.contains(0) });
+
+    let mut context = RewritingContext::new(&module);
+    assert_eq!(context.reduce(&terms[0]).borrow().to_string(), "f(g(a), c)");
+    assert_eq!(context.rewrite_count(), 1);
+    let reduced = context.reduce(&terms[1]);
+    assert_eq!(context.rewrite_step(&reduced).unwrap().borrow().to_string(), "f(g(c), d)");
+    assert_eq!(context.rewrite_successors(&reduced).len(), 1);
+
+    assert!(parse_to_module_with_commands("symbol f/1 [frozen(2)];").is_err());
+    assert!(parse_to_module_with_commands("symbol f [frozen(0)];").is_err());
+  }
+
+  #[test]
+  fn owise_equations_apply_only_when_no_other_equation_does() {
+    let (module, commands) = parse_to_module_with_commands(
+      "
+      variable N;
+      symbol f;
+      symbol g;
+
+      equation f(0) = a;
+      equation f(N) = b [owise];
+      // Declaration order does not matter.
+      equation g(N) = b [otherwise];
+      equation g(0) = a;
+
+      reduce f(0);
+      reduce f(5);
+      reduce g(0);
+      reduce g(5);
+      "
+    ).unwrap();
+    let results: Vec<String> = commands.iter()
+                                       .map(|command| match command {
+                                         Command::Reduce(term) => module.reduce_dag(&term.dagify()),
+                                         _ => unreachable!()
+                                       })
+                                       .map(|result| result.borrow().to_string())
+                                       .collect();
+    assert_eq!(results, vec!["a", "b", "a", "b"]);
+    assert!(module.equations[1].is_owise() && !module.equations[0].is_owise());
+  }
+
+  #[test]
+  fn nonexec_statements_apply_only_when_allowed() {
+    let (module, commands) = parse_to_module_with_commands(
+      "
+      variable X;
+      symbol f;
+
+      equation f(X) = b [nonexec];
+      equation f(c) = c;
+      rule a => b [nonexec];
+
+      reduce f(a);
+      "
+    ).unwrap();
+    let term = match &commands[0] {
+      Command::Reduce(term) => term,
+      _ => unreachable!()
+    };
+    assert!(module.equations[0].is_nonexec() && !module.equations[1].is_nonexec());
+
+    let mut context = RewritingContext::new(&module);
+    let reduced     = context.reduce(&term.dagify());
+    assert_eq!(reduced.borrow().to_string(), "f(a)");
+    assert!(context.rewrite_step(&reduced).is_none());
+
+    // Reduced nodes are marked, so the term is dagified again for the second context.
+    let mut context = RewritingContext::new(&module);
+    context.set_allow_nonexec(true);
+    assert_eq!(context.rewrite_step(&reduced).unwrap().borrow().to_string(), "f(b)");
+    assert_eq!(context.reduce(&term.dagify()).borrow().to_string(), "b");
+  }
+
+  #[test]
+  fn restoring_a_snapshot_rolls_the_context_back() {
+    let module = parse_to_module(
+      "
+      variable X;
+      symbol f [memo];
+      symbol g;
+      symbol d;
+
+      equation f(X) = g(X) if X = a;
+      equation f(d) = d;
+      equation g(a) = b;
+      "
+    ).unwrap();
+    let term = | source: &str | parse_to_term(source, &module).unwrap().dagify();
+
+    let mut context = RewritingContext::new(&module);
+    assert_eq!(context.reduce(&term("f(a)")).borrow().to_string(), "b");
+    let snapshot = context.snapshot();
+    let stats    = context.stats();
+
+    // A speculative reduction, and one that the debugger aborts.
+    assert_eq!(context.reduce(&term("f(d)")).borrow().to_string(), "d");
+    assert!(context.memo_map.get(&term("f(d)")).is_some());
+    context.set_debugger(Box::new(AbortAfter { steps: 0, seen: Rc::new(RefCell::new(vec![])) }));
+    assert_eq!(context.reduce(&term("g(a)")).borrow().to_string(), "g(a)");
+    assert!(context.is_aborted());
+    assert_ne!(context.stats(), stats);
+
+    context.restore(snapshot);
+    assert!(!context.is_aborted());
+    assert_eq!(context.stats(), stats);
+    assert!(context.memo_map.get(&term("f(a)")).is_some());
+    assert!(context.memo_map.get(&term("f(d)")).is_none());
+
+    // The debugger is not part of the state, so the one that aborted is replaced before rewriting again.
+    context.set_debugger(Box::new(AbortAfter { steps: 1, seen: Rc::new(RefCell::new(vec![])) }));
+    assert_eq!(context.reduce(&term("g(a)")).borrow().to_string(), "b");
+    assert_eq!(context.rewrite_count(), stats.rewrites + 1);
+  }
+
+  #[test]
+  fn memoized_symbols_are_reduced_once() {
+    // Reduces a call tree shaped like the computation of the 20th Fibonacci number, with and without memoization.
+    fn fib_tree(attributes: &str) -> (String, u64) {
+      let source = format!(
+        "
+        variable N;
+        symbol z;
+        symbol s;
+        symbol pair;
+        symbol fib {};
+
+        equation fib(z) = z;
+        equation fib(s(z)) = z;
+        equation fib(s(s(N))) = pair(fib(s(N)), fib(N));
+
+        reduce fib({}z{});
+        ",
+        attributes,
+        "s(".repeat(20),
+        ")".repeat(20)
+      );
+      let (module, commands) = parse_to_module_with_commands(&source).unwrap();
+      let term = match &commands[0] {
+        Command::Reduce(term) => term.dagify(),
+        _ => unreachable!()
+      };
+      let mut context = RewritingContext::new(&module);
+      let result      = context.reduce(&term).borrow().to_string();
+      (result, context.rewrite_count())
+    }
+
+    let (plain, plain_rewrites)       = fib_tree("");
+    let (memoized, memoized_rewrites) = fib_tree("[memo]");
+    assert_eq!(plain, memoized);
+    // The tree has fib(21) = 10946 leaves.
+    assert_eq!(plain.matches('z').count(), 10946);
+    // Every node of the call tree is a rewrite, but only one `fib(…)` of each size is with memoization.
+    assert_eq!(plain_rewrites, 2 * 10946 - 1);
+    assert_eq!(memoized_rewrites, 21);
+  }
+
+  #[test]
+  fn membership_axioms_lower_the_sorts_of_normal_forms() {
+    let module = parse_to_module(
+      "
+      sort Pos < Integer;
+      variable x;
+      variable P :: Pos;
+      symbol f :: Integer -> Integer;
+      symbol g :: Integer -> Integer;
+      symbol gt;
+
+      eq gt(3, 0) = true;
+      eq g(P) = 1;
+      mb f(x) :: Pos if gt(x, 0);
+      "
+    ).unwrap();
+    let sort_name = | node: &RcDagNode | node.borrow().sort().map(|sort| unsafe{ (*sort).name.as_str() });
+
+    // The sort of a term is lowered only once it is reduced and the condition of the membership holds.
+    let term = module.eval("f(3)").unwrap();
+    assert_eq!(sort_name(&term), Some("Pos"));
+    let unreduced = parse_to_term("f(3)", &module).unwrap().dagify();
+    assert_eq!(sort_name(&unreduced), Some("Integer"));
+    assert_eq!(sort_name(&module.eval("f(0)").unwrap()), Some("Integer"));
+
+    // Matching sees the lowered sort.
+    assert_eq!(module.eval("g(f(3))").unwrap().borrow().to_string(), "1");
+    assert_eq!(module.eval("g(f(0))").unwrap().borrow().to_string(), "g(f(0))");
+  }
+}
@@ -0,0 +1,222 @@
+/*!
+
+A `ModuleSignature` describes the sorts, symbols, and statements of a module as plain data, for tools like IDEs and
+documentation generators. It is a snapshot: it owns everything it contains and holds no pointers into the module, so
+it remains valid after the module is dropped and does not change with the module's internal representation.
+
+Sorts and symbols are listed in name order. Statements are listed in declaration order, equations first, then rules,
+then membership axioms.
+
+*/
+
+use crate::{
+  abstractions::IString,
+  core::{
+    format::{
+      FormatStyle,
+      Formattable
+    },
+    module::Module,
+    pre_equation::{
+      PreEquation,
+      PreEquationAttributes,
+      PreEquationKind
+    },
+    sort::sort_spec::SortSpec
+  },
+  theory::symbol_type::{
+    CoreSymbolType,
+    SymbolAttributes
+  }
+};
+
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct ModuleSignature {
+  pub name      : IString,
+  pub sorts     : Vec<SortSignature>,
+  pub symbols   : Vec<SymbolSignature>,
+  pub statements: Vec<StatementSummary>,
+}
+
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct SortSignature {
+  pub name      : IString,
+  /// The sorts declared as immediate supersorts of this sort, in name order.
+  pub supersorts: Vec<IString>,
+}
+
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct SymbolSignature {
+  pub name      : IString,
+  /// The arity of the symbol, which may be `VARIADIC` or `UNSPECIFIED`.
+  pub arity     : i16,
+  /// The sorts of the arguments, if the symbol was declared with a functor sort. A functor argument sort is given in
+  /// its printed form, e.g. `Nat -> Nat`.
+  pub domain    : Vec<String>,
+  /// The sort of the symbol, or of its result if it was declared with a functor sort.
+  pub range     : Option<String>,
+  pub core_type : CoreSymbolType,
+  pub attributes: SymbolAttributes,
+  pub doc       : Option<String>,
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum StatementKind {
+  Equation,
+  Rule,
+  Membership,
+}
+
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct StatementSummary {
+  pub kind           : StatementKind,
+  pub name           : Option<IString>,
+  /// The statement as it would be printed, e.g. `equation f(X) = X;`.
+  pub text           : String,
+  pub condition_count: usize,
+  pub attributes     : PreEquationAttributes,
+  pub doc            : Option<String>,
+}
+
+impl Module {
+  /// Returns a description of the module's sorts, symbols, and statements as plain data. See the `signature`
+  /// module.
+  pub fn signature(&self) -> ModuleSignature {
+    let mut sorts: Vec<SortSignature> = self.sorts.iter().map(
+      | (name, sort) | {
+        // The error sort of a kind is a supersort of its maximal sorts, but it is not declared.
+        let mut supersorts: Vec<IString> = unsafe {
+          let error_sort = match (*sort).kind.is_null() {
+            true  => std::ptr::null_mut(),
+            false => (*(*sort).kind).error_sort()
+          };
+          (*sort).supersorts.iter().filter(|s| **s != error_sort).map(|s| (**s).name).collect()
+        };
+        supersorts.sort();
+        SortSignature { name, supersorts }
+      }
+    ).collect();
+    sorts.sort_by_key(|sort| sort.name);
+
+    let mut symbols: Vec<SymbolSignature> = self.symbols.values().map(
+      | symbol | {
+        let symbol = unsafe { &**symbol };
+        // ToDo: Only the first declaration of an overloaded symbol is listed.
+        let (domain, range) = match symbol.sort_spec.as_deref() {
+          Some(SortSpec::Functor { arg_sorts, sort_spec }) => (
+            arg_sorts.iter().map(|arg_sort| arg_sort.repr(FormatStyle::Default)).collect(),
+            Some(sort_spec.repr(FormatStyle::Default))
+          ),
+          Some(sort_spec) => (vec![], Some(sort_spec.repr(FormatStyle::Default))),
+          None            => (vec![], None)
+        };
+
+        SymbolSignature {
+          name      : symbol.name,
+          arity     : symbol.arity,
+          domain,
+          range,
+          core_type : symbol.symbol_type.core_type,
+          attributes: symbol.symbol_type.attributes,
+          doc       : symbol.doc.clone(),
+        }
+      }
+    ).collect();
+    symbols.sort_by_key(|symbol| symbol.name);
+
+    let statements = self.equations.iter()
+                         .chain(self.rules.iter())
+                         .chain(self.membership.iter())
+                         .map(StatementSummary::new)
+                         .collect();
+
+    ModuleSignature {
+      name: self.name,
+      sorts,
+      symbols,
+      statements,
+    }
+  }
+}
+
+impl StatementSummary {
+  fn new(statement: &PreEquation) -> StatementSummary {
+    let kind = match statement.kind {
+      PreEquationKind::Equation { .. }   => StatementKind::Equation,
+      PreEquationKind::Rule { .. }       => StatementKind::Rule,
+      PreEquationKind::Membership { .. } => StatementKind::Membership,
+    };
+
+    StatementSummary {
+      kind,
+      name           : statement.name,
+      text           : statement.repr(FormatStyle::Default),
+      condition_count: statement.conditions.len(),
+      attributes     : statement.attributes,
+      doc            : statement.doc.clone(),
+    }
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use crate::{
+    parser::parse_to_module,
+    theory::{
+      symbol::VARIADIC,
+      symbol_type::SymbolAttribute
+    }
+  };
+  use super::*;
+
+  #[test]
+  fn signature_of_example1() {
+    let text      = std::fs::read_to_string("examples/example1.mod2").unwrap();
+    let module    = parse_to_module(text.as_str()).unwrap();
+    let signature = module.signature();
+
+    let sort_names: Vec<&str> = signature.sorts.iter().map(|sort| sort.name.as_str()).collect();
+    assert_eq!(sort_names, vec!["Int", "NzNat"]);
+
+    let symbol = |name: &str| signature.symbols.iter().find(|symbol| symbol.name == name).unwrap();
+    assert_eq!(symbol("f").arity, VARIADIC);
+    assert_eq!(symbol("g").arity, 2);
+    assert_eq!(symbol("h").arity, 3);
+    assert_eq!(symbol("h").range.as_deref(), Some("Int"));
+    assert_eq!(symbol("X").core_type, CoreSymbolType::Variable);
+    assert!(symbol("f").attributes.contains(SymbolAttribute::Commutative));
+
+    let kinds: Vec<StatementKind> = signature.statements.iter().map(|statement| statement.kind).collect();
+    assert_eq!(kinds, vec![StatementKind::Rule, StatementKind::Membership]);
+    assert_eq!(signature.statements[1].condition_count, 1);
+  }
+
+  #[test]
+  fn signature_lists_functor_sorts_and_subsorts() {
+    let module = parse_to_module(
+      "
+      sort Nat < Int;
+      /// The successor function.
+      symbol s :: Nat -> Nat;
+      symbol minus :: Int Int -> Int;
+      "
+    ).unwrap();
+    let signature = module.signature();
+
+    assert_eq!(
+      signature.sorts,
+      vec![
+        SortSignature { name: IString::from("Int"), supersorts: vec![] },
+        SortSignature { name: IString::from("Nat"), supersorts: vec![IString::from("Int")] },
+      ]
+    );
+
+    let minus = &signature.symbols[0];
+    assert_eq!(minus.name, "minus");
+    assert_eq!(minus.arity, 2);
+    assert_eq!(minus.domain, vec!["Int".to_string(), "Int".to_string()]);
+    assert_eq!(minus.range.as_deref(), Some("Int"));
+    assert_eq!(signature.symbols[1].doc.as_deref(), Some("The successor function."));
+  }
+}
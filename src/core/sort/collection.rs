@@ -2,13 +2,19 @@ use std::collections::{HashMap, HashSet};
 use std::collections::hash_map::{Entry, Iter};
 use std::iter::Map;
 use std::ops::Index;
-use crate::abstractions::{IString, heap_construct};
+use crate::abstractions::{IString, Graph, heap_construct};
 use crate::core::sort::{Sort, SortPtr};
+use crate::core::sort::sort_cycle_error::SortCycleError;
 
 /// A set of unique sorts with helper methods for creating new sorts. Helper collection only used during module construction.
+///
+/// The collection of a submodule can also see the sorts of its enclosing modules, which are found by name when the
+/// submodule has no sort of that name but are not part of the collection. See `SortCollection::within(…)`.
 #[derive(Default)]
 pub struct SortCollection {
-  sorts: HashMap<IString, SortPtr>
+  sorts    : HashMap<IString, SortPtr>,
+  /// The sorts of the enclosing modules, which belong to them.
+  enclosing: HashMap<IString, SortPtr>,
 }
 
 impl SortCollection {
@@ -16,7 +22,26 @@ impl SortCollection {
     Self::default()
   }
 
+  /// An empty collection that can see the sorts in `enclosing`, the visible sorts of the enclosing modules.
+  pub fn within(enclosing: HashMap<IString, SortPtr>) -> Self {
+    SortCollection {
+      sorts: HashMap::new(),
+      enclosing
+    }
+  }
+
+  /// The sort named `name`: a sort of the collection, else a sort of the enclosing modules, else a new sort of the
+  /// collection.
   pub fn get_or_create_sort(&mut self, name: IString) -> SortPtr {
+    match self.enclosing.get(&name) {
+      Some(sort) if !self.sorts.contains_key(&name) => *sort,
+      _ => self.declare_sort(name)
+    }
+  }
+
+  /// The sort of the collection named `name`, which is created if it does not exist. A sort declared in a submodule
+  /// hides any sort of the same name of the enclosing modules.
+  pub fn declare_sort(&mut self, name: IString) -> SortPtr {
     match self.sorts.entry(name) {
       Entry::Occupied(s) => s.get().clone(),
       Entry::Vacant(v) => {
@@ -27,6 +52,20 @@ impl SortCollection {
     }
   }
 
+  /// Declares the sort named `subsort` to be a subsort of the sort named `supersort`, declaring either sort if it does
+  /// not exist. Declaring the same relation twice has no further effect. The subsort relations of the enclosing
+  /// modules cannot be extended, so both sorts are sorts of the collection.
+  pub fn add_subsort(&mut self, subsort: IString, supersort: IString) {
+    let subsort   = self.declare_sort(subsort);
+    let supersort = self.declare_sort(supersort);
+    unsafe {
+      if !(*subsort).supersorts.contains(&supersort) {
+        (*subsort).supersorts.push(supersort);
+        (*supersort).subsorts.push(subsort);
+      }
+    }
+  }
+
   /// Given a list of sort names, inserts or creates a sort for each name.
   pub fn create_implicit_sorts(&mut self, sort_names: &mut HashSet<IString>) {
     for sort_name in sort_names.drain() {
@@ -34,14 +73,130 @@ impl SortCollection {
     }
   }
 
+  /**
+  Computes the transitive closure of the subsort relation declared by the `supersorts` of the sorts. In the returned
+  graph, the nodes are the sorts in name order, and there is an edge from one sort to another if the first is a
+  proper subsort of the second. If the declarations form a cycle, the sorts on the first cycle found are reported
+  instead, and no kinds should be computed.
+  */
+  pub fn compute_closure(&self) -> Result<Graph<SortPtr, ()>, SortCycleError> {
+    let mut sorts: Vec<(IString, SortPtr)> = self.iter().collect();
+    sorts.sort_by_key(|(name, _)| *name);
+    let index_of: HashMap<SortPtr, usize> = sorts.iter().enumerate().map(|(index, (_, sort))| (*sort, index)).collect();
+    let supersorts: Vec<Vec<usize>> = sorts.iter().map(
+      | (_, sort) | unsafe{ (**sort).supersorts.iter().map(|supersort| index_of[supersort]).collect() }
+    ).collect();
+
+    // Depth first search for a cycle. `path` holds the sorts on the way to the current one.
+    let mut finished = vec![false; sorts.len()];
+    let mut path     = Vec::new();
+    for start in 0..sorts.len() {
+      if let Some(cycle) = find_cycle(start, &supersorts, &mut finished, &mut path) {
+        return Err(SortCycleError { cycle: cycle.iter().map(|index| sorts[*index].0).collect() });
+      }
+    }
+
+    let mut closure = Graph::new();
+    for (_, sort) in sorts.iter() {
+      closure.add_node(*sort);
+    }
+    for subsort in 0..sorts.len() {
+      let mut reached = vec![false; sorts.len()];
+      let mut stack   = supersorts[subsort].clone();
+      while let Some(supersort) = stack.pop() {
+        if !reached[supersort] {
+          reached[supersort] = true;
+          closure.add_edge(subsort, supersort, ());
+          stack.extend(supersorts[supersort].iter().copied());
+        }
+      }
+    }
+    Ok(closure)
+  }
+
+  /// The sort of the collection named `name`, if there is one. The sorts of the enclosing modules are not included.
+  #[inline(always)]
+  pub fn get(&self, name: IString) -> Option<SortPtr> {
+    self.sorts.get(&name).copied()
+  }
+
+  /// Every sort the collection can see by name, its own and those of the enclosing modules it does not hide.
+  pub(crate) fn visible(&self) -> HashMap<IString, SortPtr> {
+    let mut visible = self.enclosing.clone();
+    visible.extend(self.iter());
+    visible
+  }
+
   #[inline(always)]
   pub fn len(&self) -> usize {
     self.sorts.len()
   }
-  /// Creates and returns an iterator over the `SortCollection`.
+  /// Creates and returns an iterator over the `SortCollection`, not including the sorts of enclosing modules.
   // Can we just stop to appreciate how stupid the return type of this method is? And how obnoxious it is to have to
   // specify it?
   pub(crate) fn iter(&self) -> Map<Iter<'_, IString, SortPtr>, fn((&IString, &SortPtr)) -> (IString, SortPtr)> {
     self.sorts.iter().map(|(istr, rcs)| (istr.clone(), *rcs))
   }
 }
+
+/// Searches the sorts above `sort` for a cycle, returning its indices, starting and ending with the same index.
+fn find_cycle(
+  sort      : usize,
+  supersorts: &[Vec<usize>],
+  finished  : &mut [bool],
+  path      : &mut Vec<usize>
+) -> Option<Vec<usize>>
+{
+  if finished[sort] {
+    return None;
+  }
+  if let Some(position) = path.iter().position(|other| *other == sort) {
+    let mut cycle = path[position..].to_vec();
+    cycle.push(sort);
+    return Some(cycle);
+  }
+
+  path.push(sort);
+  for supersort in supersorts[sort].iter() {
+    if let Some(cycle) = find_cycle(*supersort, supersorts, finished, path) {
+      return Some(cycle);
+    }
+  }
+  path.pop();
+  finished[sort] = true;
+  None
+}
+
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn declare_subsort(sorts: &mut SortCollection, subsort: &str, supersort: &str) {
+    let subsort   = sorts.get_or_create_sort(IString::from(subsort));
+    let supersort = sorts.get_or_create_sort(IString::from(supersort));
+    unsafe {
+      (*subsort).supersorts.push(supersort);
+      (*supersort).subsorts.push(subsort);
+    }
+  }
+
+  #[test]
+  fn closure_is_transitive_and_cycles_are_named() {
+    let mut sorts = SortCollection::new();
+    declare_subsort(&mut sorts, "A", "B");
+    declare_subsort(&mut sorts, "B", "C");
+
+    let closure = sorts.compute_closure().unwrap();
+    let names: Vec<&str> = closure.nodes().iter().map(|sort| unsafe{ (**sort).name.as_str() }).collect();
+    assert_eq!(names, vec!["A", "B", "C"]);
+    let mut edges: Vec<(usize, usize)> = closure.edges().iter().map(|edge| (edge.from, edge.to)).collect();
+    edges.sort();
+    assert_eq!(edges, vec![(0, 1), (0, 2), (1, 2)]);
+
+    declare_subsort(&mut sorts, "C", "A");
+    let error = sorts.compute_closure().err().unwrap();
+    assert_eq!(error.cycle, vec![IString::from("A"), IString::from("B"), IString::from("C"), IString::from("A")]);
+    assert_eq!(error.to_string(), "the subsort declarations form a cycle: A < B < C < A.");
+  }
+}
@@ -9,10 +9,14 @@ about sorts, kinds, and the subsort relation, and how they are represented in th
 ## Lifecycle and Ownership
 
 `Kind`s are owned by the `Module` in which they are defined (whether implicitly or explicitly).
-`Kind`s do not own their `Sort`s. As with the rest of the lattice infrastructure, once constructed,
+`Kind`s do not own their `Sort`s, with one exception: each `Kind` creates and owns an *error sort* at index 0, which
+is a supersort of every maximal sort of the `Kind`. As with the rest of the lattice infrastructure, once constructed,
 `Kind`s are immutable and live as long as their owning `Module`, and as long their associated
 `Sort`s. It is the responsibility of the owning `Module` to reclaim both `Kind`s and `Sort`s.
 
+Dropping a `Kind` detaches its error sort from the lattice, which is what allows the `Module` to recompute its
+`Kind`s after the subsort relation changes.
+
 
 ## Optimizations for Computing the Subsort Relation
 
@@ -28,14 +32,14 @@ maximal sort in a connected component if there's a cycle in the sort graph, as n
 considered a maximal sort because they all have another sort above them in the cycle. The existence of a cycle is an
 error state.
 
-Recall that a connected graph is acyclic if and only if it has $n-1$ edges, where $n$ is the number of its nodes. (Such
-a graph is, of course, a tree.) We use the proof of this fact as a poor man's cycle detection during `Kind` construction
-by keeping track of how many nodes we visit. If we visit more than the total number of nodes, the pigeonhole principle
-demands that we must have encountered the same node more than once.
+A `Kind` is constructed from the sorts of one connected component of the subsort relation, which the module finds with
+`Graph::connected_components(…)`. The sorts are then ordered so that every sort comes after its supersorts, starting
+from the maximal sorts. A sort on a cycle is never reached this way, because one of its supersorts is always
+waiting on it, so a cycle shows up as a component with more sorts than were ordered.
 
 We report two kinds of errors during construction of a kind:
  1. a cycle detected by the lack of maximal sorts (or really any sorts), and
- 2. a cycle detected due to pigeonhole principle (failure to linear order the sorts).
+ 2. a cycle detected by sorts left out of the order (failure to linear order the sorts).
 
 
 ## See Also...
@@ -56,15 +60,19 @@ use std::{
 };
 
 use crate::{
+  abstractions::IString,
   core::{
     sort::{
       sort::{
+        Sort,
         SortPtr,
         SortPtrs
       },
       kind_error::KindError
     }
-  }
+  },
+  heap_construct,
+  heap_destroy
 };
 
 // Convenience types
@@ -86,21 +94,30 @@ pub struct Kind {
 }
 
 impl Kind {
-  /// Returns a boxed Kind.
-  pub unsafe fn new(mut initial_sort: SortPtr) -> Result<BxKind, KindError> {
+  /// Returns a boxed Kind of the sorts of `component`, a connected component of the subsort relation. The first sort
+  /// of the component names the error sort and is the problem sort of an error.
+  pub unsafe fn new(component: &[SortPtr]) -> Result<BxKind, KindError> {
+    let initial_sort = component[0];
+    // The error sort is at index 0 and is a supersort of every maximal sort.
+    let error_sort: SortPtr = heap_construct!(
+      Sort::new(IString::from(format!("[{}]", (*initial_sort).name).as_str()))
+    );
 
     let mut kind: BxKind = Box::new(
       Kind {
         error_free        : true,
         maximal_sort_count: 0,
         visited_sort_count: 0,
-        sorts             : vec![initial_sort],
+        sorts             : vec![error_sort],
       }
     );
-    let mut visited_sort_count: u32 = 0;
+    (*error_sort).kind = kind.as_mut();
 
-    // Recursively call `register_connected_sorts` on sub- and supersorts.
-    kind.register_connected_sorts(initial_sort, &mut visited_sort_count);
+    for sort in component.iter() {
+      kind.register_sort(*sort);
+    }
+    let visited_sort_count  = component.len() as u32;
+    kind.visited_sort_count = visited_sort_count;
 
     if kind.maximal_sort_count == 0 {
       // ToDo: Recording the error here might not be necessary considering we are returning the `Kind` wrapped in an error.
@@ -118,14 +135,18 @@ impl Kind {
     }
 
     for i in 1..=kind.maximal_sort_count as usize {
-      (*kind.sorts[0]).insert_subsort(kind.sorts[i]);
+      (*error_sort).insert_subsort(kind.sorts[i]);
     }
 
-    for i in 1..kind.sorts.len() {
-      (*kind).process_subsorts((*kind).sorts[i]);
+    // Processing a sort can append more sorts, so the length must be checked on every iteration.
+    let mut i = 1;
+    while i < kind.sorts.len() {
+      kind.process_subsorts(kind.sorts[i]);
+      i += 1;
     }
 
-    if kind.sorts.len() != visited_sort_count as usize {
+    // The error sort is not one of the visited sorts.
+    if kind.sorts.len() != visited_sort_count as usize + 1 {
       kind.error_free = false;
       return Err(
         KindError::CycleDetected {
@@ -135,41 +156,29 @@ impl Kind {
       );
     }
 
-    for i in (0..visited_sort_count).rev() {
-      (*kind.sorts[i as usize]).compute_leq_sorts();
+    for i in (0..kind.sorts.len()).rev() {
+      (*kind.sorts[i]).compute_leq_sorts();
     }
 
     Ok(kind)
   }
 
-  /// A helper function for computing the closure of the kind. The `visited_sort_count` is for cycle detection. If we visit more nodes (sorts) than we have, one of the nodes must have been visited twice..
-  unsafe fn register_connected_sorts(&mut self, sort: SortPtr, visited_sort_count: &mut u32) {
-    (*sort).kind = self;
-    *visited_sort_count += 1;
-
-    { // Visit subsorts
-      let subsort_count = (*sort).subsorts.len();
-      for i in 0..subsort_count {
-        let s = (*sort).subsorts[i];
-        if (*s).kind.is_null() {
-          self.register_connected_sorts(s, visited_sort_count);
-        }
-      }
-    }
+  /// The error sort of the kind, a supersort of every maximal sort.
+  #[inline(always)]
+  pub fn error_sort(&self) -> SortPtr {
+    self.sorts[0]
+  }
 
-    { // Visit supersorts
-      let supersort_count = (*sort).supersorts.len();
-      if supersort_count == 0 {
-        (*sort).index_within_kind = self.append_sort(sort);
-      } else {
-        (*sort).unresolved_supersort_count = supersort_count;
-        for i in 0..supersort_count {
-          let s = (*sort).supersorts[i];
-          if (*s).kind.is_null() {
-            self.register_connected_sorts(s, visited_sort_count);
-          }
-        }
-      }
+  /// Makes `sort` a sort of the kind. A sort without supersorts is maximal and is ordered right away, and the others
+  /// wait until all of their supersorts are ordered. See `Kind::process_subsorts(…)`.
+  unsafe fn register_sort(&mut self, sort: SortPtr) {
+    (*sort).kind = self;
+    let supersort_count = (*sort).supersorts.len();
+    if supersort_count == 0 {
+      (*sort).index_within_kind = self.append_sort(sort);
+      self.maximal_sort_count  += 1;
+    } else {
+      (*sort).unresolved_supersort_count = supersort_count;
     }
   }
 
@@ -185,7 +194,7 @@ impl Kind {
       (**subsort).unresolved_supersort_count -= 1;
       if (**subsort).unresolved_supersort_count == 0 {
         // Finally add the current sort.
-        (**subsort).index_within_kind = self.append_sort(*subsort);;
+        (**subsort).index_within_kind = self.append_sort(*subsort);
       }
     }
   }
@@ -197,3 +206,18 @@ impl Kind {
   }
 
 }
+
+
+impl Drop for Kind {
+  /// The error sort is owned by the `Kind`. It is detached from the maximal sorts before it is destroyed.
+  fn drop(&mut self) {
+    if let Some(&error_sort) = self.sorts.first() {
+      unsafe {
+        for subsort in (*error_sort).subsorts.iter() {
+          (**subsort).supersorts.retain(|supersort| *supersort != error_sort);
+        }
+        heap_destroy!(error_sort);
+      }
+    }
+  }
+}
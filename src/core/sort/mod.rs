@@ -107,6 +107,10 @@ To compare two sorts `A` and `B` during runtime:
 3. otherwise, check whether either `A.subsorts.contains(B)` or `A.supersorts.contains(B)` (equivalently with roles of
    `A` and `B` swapped). This is the slow path.
 
+`Sort::fast_leq_sort(…)` implements this test using `leq_sorts` as the slow path. During kind closure each sort also
+records whether its `fast_compare_index` alone decides the test (`Sort::fast_geq_sufficient()`), which is the case for
+every sort of a linear kind, so that the common case never touches the `NatSet`.
+
 
  */
 
@@ -114,6 +118,7 @@ pub mod kind;
 pub mod sort;
 pub mod sort_spec;
 pub mod collection;
+pub mod sort_cycle_error;
 pub(crate) mod kind_error;
 
 pub use sort::*;
@@ -56,6 +56,9 @@ pub struct Sort {
 
   /// This is the index for which all sorts with `index >= fast_compare_index` are subsorts.
   fast_compare_index: usize,
+  /// True if no sort with index below `fast_compare_index` is a subsort other than `self`, so that `fast_geq(…)`
+  /// never has to consult `leq_sorts`.
+  fast_geq_sufficient: bool,
 
   /// Only used during `Kind` construction to compute `fast_compare_index`. Only when all
   /// supersorts have been assigned an `index_within_kind` can this `Sort`'s `index_within_kind`
@@ -78,6 +81,7 @@ impl Default for Sort {
       name                      : IString::default(),
       index_within_kind         : 0,
       fast_compare_index        : 0,
+      fast_geq_sufficient       : false,
       unresolved_supersort_count: 0,
       subsorts                  : SortPtrs::default(),
       supersorts                : SortPtrs::default(),
@@ -155,6 +159,17 @@ impl Sort {
     }
   }
 
+  /// Clears the information computed during `Kind` construction so that the `Kind`s can be recomputed. The
+  /// adjacency lists are left alone, as they are the specification of the subsort relation.
+  pub(crate) fn clear_kind_info(&mut self) {
+    self.kind                       = std::ptr::null_mut();
+    self.index_within_kind          = 0;
+    self.fast_compare_index         = 0;
+    self.fast_geq_sufficient        = false;
+    self.unresolved_supersort_count = 0;
+    self.leq_sorts.clear();
+  }
+
   /// Used during subsort relation closure, during `Kind` construction. Constructs `self.leq_sorts`.
   pub fn compute_leq_sorts(&mut self) {
     self.leq_sorts.insert(self.index_within_kind);
@@ -172,5 +187,90 @@ impl Sort {
         break;
       }
     }
+
+    // Subsorts always have a greater index, so only indices after `index_within_kind` need to be checked.
+    self.fast_geq_sufficient =
+        (self.index_within_kind + 1..self.fast_compare_index).all(|i| !self.leq_sorts.contains(i));
+  }
+
+  /// Is the sort with index `index` within this sort's kind a subsort of (or equal to) this sort? This is the fast
+  /// path of the subsort test, and it only consults `leq_sorts` if `fast_geq_sufficient()` is false.
+  #[inline(always)]
+  pub fn fast_geq(&self, index: usize) -> bool {
+    if index >= self.fast_compare_index {
+      true
+    } else if self.fast_geq_sufficient {
+      index == self.index_within_kind
+    } else {
+      self.leq_sorts.contains(index)
+    }
+  }
+
+  /// Whether `fast_geq(…)` is decided by index comparisons alone. True for every sort of a linear kind.
+  #[inline(always)]
+  pub fn fast_geq_sufficient(&self) -> bool {
+    self.fast_geq_sufficient
+  }
+
+  /// Is `self` a subsort of (or equal to) `other`? Sorts of different kinds are incomparable.
+  #[inline(always)]
+  pub fn fast_leq_sort(&self, other: &Sort) -> bool {
+    self.kind == other.kind && other.fast_geq(self.index_within_kind)
+  }
+
+  /// Is this the error sort of its kind? See `Kind::error_sort(…)`.
+  #[inline(always)]
+  pub fn is_error_sort(&self) -> bool {
+    !self.kind.is_null() && unsafe{ std::ptr::eq((*self.kind).error_sort(), self) }
+  }
+
+  /// Is `self` a subsort of (or equal to) `other`? This is the general test using `leq_sorts`. Prefer
+  /// `fast_leq_sort(…)`, which gives the same answer.
+  pub fn leq_sort(&self, other: &Sort) -> bool {
+    self.kind == other.kind && other.leq_sorts.contains(self.index_within_kind)
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use crate::parser::parse_to_module;
+  use super::*;
+
+  /// Is `sort` a subsort of `other`, following the supersort adjacency lists.
+  unsafe fn reaches(sort: SortPtr, other: SortPtr) -> bool {
+    sort == other || (*sort).supersorts.iter().any(|supersort| reaches(*supersort, other))
+  }
+
+  #[test]
+  fn fast_leq_sort_agrees_with_leq_sort() {
+    let module = parse_to_module(
+      "
+      sort A < B, C;
+      sort B < D;
+      sort C < D;
+      sort E < C;
+      sort F < E;
+      sort Nat < Int;
+      sort Int < Rat;
+      "
+    ).unwrap();
+
+    for kind in module.kinds.iter() {
+      for &sort in kind.sorts.iter() {
+        for &other in kind.sorts.iter() {
+          unsafe {
+            let expected = reaches(sort, other);
+            assert_eq!((*sort).leq_sort(&*other), expected, "{} <= {}", (*sort).name, (*other).name);
+            assert_eq!((*sort).fast_leq_sort(&*other), expected, "{} <= {}", (*sort).name, (*other).name);
+          }
+        }
+      }
+    }
+
+    // A linear kind never needs the `leq_sorts` set.
+    let nat = module.sorts.iter().find(|(name, _)| name == "Nat").unwrap().1;
+    let all_sufficient = unsafe { (*(*nat).kind).sorts.iter().all(|sort| (**sort).fast_geq_sufficient()) };
+    assert!(all_sufficient);
   }
 }
@@ -0,0 +1,32 @@
+/*!
+
+The subsort relation must be a partial order, so subsort declarations like `sort A < B; sort B < A;` that form a cycle
+are an error. See `SortCollection::compute_closure(…)`.
+
+*/
+
+use std::error::Error;
+use std::fmt::{Debug, Display, Formatter};
+
+use crate::abstractions::IString;
+
+#[derive(Clone, Eq, PartialEq)]
+pub struct SortCycleError {
+  /// The names of the sorts on the cycle, each a subsort of the next, starting and ending with the same sort.
+  pub cycle: Vec<IString>,
+}
+
+impl Display for SortCycleError {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    let cycle: Vec<&str> = self.cycle.iter().map(|name| name.as_str()).collect();
+    write!(f, "the subsort declarations form a cycle: {}.", cycle.join(" < "))
+  }
+}
+
+impl Debug for SortCycleError {
+  fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+    Display::fmt(self, f)
+  }
+}
+
+impl Error for SortCycleError{}
@@ -12,9 +12,20 @@ functors. `SortSpec`s are not named.
 */
 
 use crate::{
-  core::sort::{
-    Sort,
-    SortPtr
+  abstractions::{
+    join_iter,
+    HashSet
+  },
+  core::{
+    format::{
+      latex_escape,
+      FormatStyle,
+      Formattable
+    },
+    sort::{
+      Sort,
+      SortPtr
+    }
   },
   theory::symbol::UNSPECIFIED
 };
@@ -49,4 +60,86 @@ impl SortSpec {
       _ => UNSPECIFIED
     }
   }
+
+  /// The sort of a term whose symbol has this sort spec: the sort itself, or the result sort of a functor. `None` if
+  /// there is no such sort, as for `Any`, or if the result is itself a functor.
+  pub fn range_sort(&self) -> Option<SortPtr> {
+    match self {
+      SortSpec::Sort(sort) if unsafe{ (**sort).arity() } == 0 => Some(*sort),
+      SortSpec::Functor { sort_spec, .. } => match sort_spec.as_ref() {
+        SortSpec::Functor { .. } => None,
+        sort_spec                => sort_spec.range_sort()
+      },
+      _ => None
+    }
+  }
+
+  /// The sort of argument `index` of a functor, if the sort spec is a functor with a sort (rather than a functor) in
+  /// that position.
+  pub fn arg_sort(&self, index: usize) -> Option<SortPtr> {
+    match self {
+      SortSpec::Functor { arg_sorts, .. } => match arg_sorts.get(index)?.as_ref() {
+        SortSpec::Sort(sort) if unsafe{ (**sort).arity() } == 0 => Some(*sort),
+        _ => None
+      },
+      _ => None
+    }
+  }
+
+  /// Inserts every sort named in the sort spec, including the argument sorts of a functor, into `sorts`.
+  pub fn add_sorts(&self, sorts: &mut HashSet<SortPtr>) {
+    match self {
+      SortSpec::Sort(sort) => {
+        sorts.insert(*sort);
+      }
+      SortSpec::Functor { arg_sorts, sort_spec } => {
+        for arg_sort in arg_sorts.iter() {
+          arg_sort.add_sorts(sorts);
+        }
+        sort_spec.add_sorts(sorts);
+      }
+      SortSpec::Any | SortSpec::None => {}
+    }
+  }
+}
+
+impl Formattable for SortSpec {
+  fn repr(&self, style: FormatStyle) -> String {
+    match self {
+
+      SortSpec::Sort(sort) => {
+        let name = unsafe { (**sort).name };
+        match style {
+          FormatStyle::Latex => format!("\\mathrm{{{}}}", latex_escape(&name)),
+          _                  => name.to_string()
+        }
+      }
+
+      SortSpec::Functor { arg_sorts, sort_spec } => {
+        let arg_sorts = arg_sorts.iter().map(
+          | arg_sort | match arg_sort.as_ref() {
+            SortSpec::Functor { .. } => format!("({})", arg_sort.repr(style)),
+            _                        => arg_sort.repr(style)
+          }
+        );
+        let (separator, arrow) = match style {
+          FormatStyle::Latex => ("\\ ", "\\to"),
+          _                  => (" ", "->")
+        };
+        let arg_sorts = join_iter(arg_sorts, |_| separator.to_string()).collect::<String>();
+        format!("{} {} {}", arg_sorts, arrow, sort_spec.repr(style))
+      }
+
+      SortSpec::Any => match style {
+        FormatStyle::Latex => "\\mathrm{Any}".to_string(),
+        _                  => "Any".to_string()
+      },
+
+      SortSpec::None => match style {
+        FormatStyle::Latex => "\\mathrm{None}".to_string(),
+        _                  => "None".to_string()
+      },
+
+    }
+  }
 }
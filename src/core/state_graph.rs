@@ -0,0 +1,335 @@
+/*!
+
+The state graph of a term is the graph of terms reachable from it by rewriting. Each state is a term in equational
+normal form, and there is a transition from one state to another labeled by a rule if one application of the rule,
+followed by reduction, takes the first state to the second. The graph is a `Graph` whose nodes are the states and
+whose edges are labeled by the index of their rule in the module's `rules`.
+
+`Module::state_graph(…)` explores the graph breadth first from a starting term, up to a bound on the number of states,
+and `StateGraph::to_dot(…)` renders it in the Graphviz DOT language. `Module::state_graph_dot(…)` does both, which is
+convenient for looking at the behavior of a small rule system:
+
+```text
+dot -Tsvg graph.dot > graph.svg
+```
+
+`Module::search(…)` explores the same states lazily: it returns an iterator over the states reachable from a starting
+term, in breadth first order, that only rewrites as far as it is advanced. This is what a `search` command needs, and
+it works for rule systems with infinitely many reachable states, as long as the iterator is not run to the end.
+
+*/
+
+use crate::{
+  abstractions::{
+    Edge,
+    Graph,
+    HashMap,
+    HashType
+  },
+  core::{
+    format::{
+      FormatStyle,
+      Formattable
+    },
+    module::Module,
+    rewriting_context::RewritingContext
+  },
+  theory::{
+    dag_node::RcDagNode,
+    term::Term
+  }
+};
+
+/// An iterator over the states reachable from a starting state, each yielded once, in breadth first order. See
+/// `Module::search(…)`.
+pub struct StateSearch<'m> {
+  context    : RewritingContext<'m>,
+  /// The states discovered so far, in order of discovery, with their depths.
+  states     : Vec<(RcDagNode, usize)>,
+  /// Maps structural hashes to the indices of the states with that hash.
+  index_of   : HashMap<HashType, Vec<usize>>,
+  /// The index of the next state to yield.
+  next_yield : usize,
+  /// The index of the next state whose successors are to be discovered.
+  next_expand: usize,
+  depth_bound: Option<usize>,
+}
+
+impl<'m> StateSearch<'m> {
+  /// Discovers the successors of the next unexpanded state. Returns false if every state has been expanded.
+  fn expand(&mut self) -> bool {
+    let (state, depth) = match self.states.get(self.next_expand) {
+      Some((state, depth)) => (state.clone(), *depth),
+      None => return false
+    };
+    self.next_expand += 1;
+    if self.depth_bound.is_some_and(|bound| depth >= bound) {
+      return true;
+    }
+
+    for (_, successor) in self.context.rewrite_successors(&state) {
+      let successor = self.context.reduce(&successor);
+      let hash      = successor.borrow_mut().structural_hash();
+      let bucket    = self.index_of.entry(hash).or_default();
+      let states    = &self.states;
+      if !bucket.iter().any(|index| states[*index].0.borrow().equals(&successor.borrow())) {
+        bucket.push(self.states.len());
+        self.states.push((successor, depth + 1));
+      }
+    }
+    true
+  }
+}
+
+impl Iterator for StateSearch<'_> {
+  type Item = RcDagNode;
+
+  fn next(&mut self) -> Option<RcDagNode> {
+    while self.next_yield == self.states.len() {
+      if !self.expand() {
+        return None;
+      }
+    }
+    self.next_yield += 1;
+    Some(self.states[self.next_yield - 1].0.clone())
+  }
+}
+
+/// A transition from the state with index `from` to the state with index `to` by the rule whose index in the
+/// module's `rules` is the `label`.
+pub type Transition = Edge<usize>;
+
+pub struct StateGraph {
+  /// The states in the order they were discovered. The starting state is at index 0.
+  pub graph    : Graph<RcDagNode, usize>,
+  /// True if exploration stopped at the bound with transitions to new states left unexplored.
+  pub truncated: bool,
+}
+
+impl StateGraph {
+  #[inline(always)]
+  pub fn states(&self) -> &[RcDagNode] {
+    self.graph.nodes()
+  }
+
+  #[inline(always)]
+  pub fn transitions(&self) -> &[Transition] {
+    self.graph.edges()
+  }
+
+  /// Renders the graph in the Graphviz DOT language. States are labeled by their term and transitions by their rule
+  /// as it is written in `module`.
+  pub fn to_dot(&self, module: &Module) -> String {
+    let mut dot = String::from("digraph {\n");
+    for (index, state) in self.states().iter().enumerate() {
+      dot.push_str(
+        format!("  {} [label=\"{}\"];\n", index, dot_escape(&state.borrow().repr(FormatStyle::Default))).as_str()
+      );
+    }
+    for transition in self.transitions() {
+      let rule = module.rules[transition.label].repr(FormatStyle::Default);
+      dot.push_str(
+        format!("  {} -> {} [label=\"{}\"];\n", transition.from, transition.to, dot_escape(&rule)).as_str()
+      );
+    }
+    dot.push('}');
+    dot
+  }
+}
+
+impl Module {
+  /**
+  Explores the states reachable from `start` breadth first, visiting at most `bound` states. Transitions between
+  visited states are all recorded, even those found after the bound is reached. See the `state_graph` module.
+
+  ToDo: States are identified by structural equality. Nothing is done to recognize states that are equal modulo
+        axioms like commutativity.
+  */
+  pub fn state_graph(&self, start: &Term, bound: usize) -> StateGraph {
+    let mut context = RewritingContext::new(self);
+    let mut graph   = StateGraph {
+      graph    : Graph::new(),
+      truncated: false,
+    };
+    if bound == 0 {
+      graph.truncated = true;
+      return graph;
+    }
+
+    // Maps structural hashes to the indices of the states with that hash.
+    let mut index_of: HashMap<HashType, Vec<usize>> = HashMap::new();
+    let start = context.reduce(&start.dagify());
+    index_of.entry(start.borrow_mut().structural_hash()).or_default().push(0);
+    graph.graph.add_node(start);
+
+    let mut next = 0;
+    while next < graph.graph.node_count() {
+      let state = graph.graph.node(next).clone();
+      for (rule, successor) in context.rewrite_successors(&state) {
+        let successor = context.reduce(&successor);
+        let hash      = successor.borrow_mut().structural_hash();
+        let bucket    = index_of.entry(hash).or_default();
+
+        let known = bucket.iter()
+                          .copied()
+                          .find(|index| graph.graph.node(*index).borrow().equals(&successor.borrow()));
+        let to    = match known {
+          Some(index) => index,
+          None if graph.graph.node_count() < bound => {
+            let index = graph.graph.add_node(successor);
+            bucket.push(index);
+            index
+          }
+          None => {
+            graph.truncated = true;
+            continue;
+          }
+        };
+
+        graph.graph.add_edge(next, to, rule);
+      }
+      next += 1;
+    }
+
+    graph
+  }
+
+  /**
+  The states reachable from `start` by rules, each reduced to equational normal form, starting with `start` itself.
+  States are yielded in breadth first order as they are discovered, so states at depth `d`, those that take `d` rule
+  applications to reach, come before states at depth `d + 1`. If `depth_bound` is given, no state deeper than it is
+  yielded. See the `state_graph` module.
+  */
+  pub fn search(&self, start: &Term, depth_bound: Option<usize>) -> StateSearch<'_> {
+    let mut context = RewritingContext::new(self);
+    let start       = context.reduce(&start.dagify());
+    let hash        = start.borrow_mut().structural_hash();
+    StateSearch {
+      context,
+      states     : vec![(start, 0)],
+      index_of   : HashMap::from([(hash, vec![0])]),
+      next_yield : 0,
+      next_expand: 0,
+      depth_bound,
+    }
+  }
+
+  /// The state graph of `start`, visiting at most `bound` states, in the Graphviz DOT language. See
+  /// `Module::state_graph(…)`.
+  pub fn state_graph_dot(&self, start: &Term, bound: usize) -> String {
+    self.state_graph(start, bound).to_dot(self)
+  }
+}
+
+/// Escapes text for use in a double quoted DOT string.
+fn dot_escape(text: &str) -> String {
+  text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+
+#[cfg(test)]
+mod tests {
+  use crate::{
+    core::command::Command,
+    parser::parse_to_module_with_commands
+  };
+
+  #[test]
+  fn two_state_system_renders_both_states_and_transitions() {
+    let (module, commands) = parse_to_module_with_commands(
+      "
+      symbol f;
+
+      rule f(a) => f(b);
+      rule f(b) => f(a);
+      rule b => \"c\";
+
+      reduce f(a);
+      "
+    ).unwrap();
+    let start = match &commands[0] {
+      Command::Reduce(term) => term.as_ref(),
+      _ => unreachable!()
+    };
+
+    let bounded = module.state_graph(start, 2);
+    assert_eq!(bounded.states().len(), 2);
+    assert!(bounded.truncated);
+
+    assert_eq!(
+      module.state_graph_dot(start, 2),
+      concat!(
+        "digraph {\n",
+        "  0 [label=\"f(a)\"];\n",
+        "  1 [label=\"f(b)\"];\n",
+        "  0 -> 1 [label=\"rule f(a) => f(b);\"];\n",
+        "  1 -> 0 [label=\"rule f(b) => f(a);\"];\n",
+        "}"
+      )
+    );
+
+    let full = module.state_graph(start, 10);
+    assert_eq!(full.states().len(), 3);
+    assert!(!full.truncated);
+    assert!(full.to_dot(&module).contains("  2 [label=\"f(\\\"c\\\")\"];\n"));
+  }
+
+  #[test]
+  fn states_are_enumerated_in_breadth_first_order() {
+    let (module, commands) = parse_to_module_with_commands(
+      "
+      symbol f;
+
+      rule f(a) => f(b);
+      rule f(a) => f(c);
+      rule f(b) => f(c);
+      rule f(c) => f(a);
+
+      reduce f(a);
+      "
+    ).unwrap();
+    let start = match &commands[0] {
+      Command::Reduce(term) => term.as_ref(),
+      _ => unreachable!()
+    };
+
+    let graph  = module.state_graph(start, 10);
+    let states = graph.states().iter().map(|state| state.borrow().to_string()).collect::<Vec<_>>();
+    assert_eq!(states, vec!["f(a)", "f(b)", "f(c)"]);
+    assert_eq!(graph.transitions().len(), 4);
+    assert_eq!(graph.graph.labels(0, 2).copied().collect::<Vec<_>>(), vec![1]);
+    assert_eq!(graph.graph.edges_from(2).map(|transition| transition.to).collect::<Vec<_>>(), vec![0]);
+  }
+
+  #[test]
+  fn search_yields_states_lazily_by_depth() {
+    let (module, commands) = parse_to_module_with_commands(
+      "
+      variable X;
+      symbol f;
+      symbol s;
+
+      rule f(a) => f(b);
+      rule f(b) => f(c);
+      rule f(c) => f(a);
+      rule f(X) => g(s(X));
+      rule g(X) => g(s(X));
+
+      reduce f(a);
+      "
+    ).unwrap();
+    let start = match &commands[0] {
+      Command::Reduce(term) => term.as_ref(),
+      _ => unreachable!()
+    };
+    let states = | depth_bound: Option<usize>, count: usize | -> Vec<String> {
+      module.search(start, depth_bound).take(count).map(|state| state.borrow().to_string()).collect()
+    };
+
+    assert_eq!(states(Some(0), 10), vec!["f(a)"]);
+    assert_eq!(states(Some(1), 10), vec!["f(a)", "f(b)", "g(s(a))"]);
+    assert_eq!(states(Some(2), 10), vec!["f(a)", "f(b)", "g(s(a))", "f(c)", "g(s(b))", "g(s(s(a)))"]);
+    // Infinitely many states are reachable, and searching without a bound explores only as far as it is asked to.
+    assert_eq!(states(None, 7).last().unwrap(), "g(s(c))");
+  }
+}
@@ -0,0 +1,136 @@
+/*!
+
+A `Strategy` controls which rules are applied to a term and where, a small subset of Maude's strategy language. A
+strategy applied to a term has any number of results: none if it fails, and several if a rule applies at several
+positions.
+
+| Strategy      | Maude         | Results                                                     |
+|:--------------|:--------------|:------------------------------------------------------------|
+| `Rule(label)` | `label`       | One rule with the label applied once, anywhere in the term. |
+| `Top`         | `top(all)`    | Any rule applied once at the top of the term.               |
+| `Normalize`   | `reduce`      | The equational normal form of the term.                     |
+| `Seq(s, t)`   | `s ; t`       | `t` applied to every result of `s`.                         |
+| `Alt(s, t)`   | `s or-else t` | The results of `s`, or those of `t` if `s` has none.        |
+
+The results of rule applications are not reduced, so a strategy like `Seq(Rule(label), Normalize)` is needed to
+rewrite with a rule and then reduce the result. The positions and rules are tried in the order of
+`RewritingContext::rewrite_successors(…)`, and `nonexec` rules are only applied if the context allows them.
+`Rule(label)` only tries the rules with the label, so the rest of the module's rules cost nothing.
+
+*/
+
+use crate::{
+  abstractions::IString,
+  core::rewriting_context::RewritingContext,
+  theory::dag_node::RcDagNode
+};
+
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub enum Strategy {
+  /// Applies a rule with this label once, at any position.
+  Rule(IString),
+  /// Applies any rule once at the top of the term.
+  Top,
+  /// Reduces the term to equational normal form.
+  Normalize,
+  /// Applies the second strategy to every result of the first.
+  Seq(Box<Strategy>, Box<Strategy>),
+  /// Applies the first strategy, or the second if the first has no results.
+  Alt(Box<Strategy>, Box<Strategy>),
+}
+
+impl Strategy {
+  /// The results of applying the strategy to `term` within `context`, in the order they are found. See the module
+  /// documentation.
+  pub fn apply(&self, term: &RcDagNode, context: &mut RewritingContext) -> Vec<RcDagNode> {
+    match self {
+
+      Strategy::Rule(label) => context.labeled_successors(term, *label),
+
+      Strategy::Top => {
+        let module = context.module();
+        module.rules
+              .iter()
+              .filter(|rule| context.is_executable(rule))
+              .collect::<Vec<_>>()
+              .into_iter()
+              .filter_map(|rule| rule.apply(term, context))
+              .collect()
+      }
+
+      Strategy::Normalize => vec![context.reduce(term)],
+
+      Strategy::Seq(first, second) => {
+        first.apply(term, context)
+             .iter()
+             .flat_map(|result| second.apply(result, context))
+             .collect()
+      }
+
+      Strategy::Alt(first, second) => {
+        let results = first.apply(term, context);
+        if results.is_empty() {
+          second.apply(term, context)
+        } else {
+          results
+        }
+      }
+
+    }
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use crate::parser::{
+    parse_to_module,
+    parse_to_term
+  };
+  use super::*;
+
+  fn results(strategy: &Strategy, source: &str) -> Vec<String> {
+    let module = parse_to_module(
+      "
+      variable X;
+      symbol f/2;
+
+      rule [ab] a => b;
+      rule [bc] b => c;
+      eq g(X) = X;
+      "
+    ).unwrap();
+    let term        = parse_to_term(source, &module).unwrap().dagify();
+    let mut context = RewritingContext::new(&module);
+    strategy.apply(&term, &mut context).iter().map(|result| result.borrow().to_string()).collect()
+  }
+
+  fn rule(label: &str) -> Box<Strategy> {
+    Box::new(Strategy::Rule(IString::from(label)))
+  }
+
+  #[test]
+  fn rules_apply_once_and_normalize_reduces_fully() {
+    assert_eq!(results(&Strategy::Rule(IString::from("ab")), "f(a, g(b))"), vec!["f(b, g(b))"]);
+    assert_eq!(results(&Strategy::Rule(IString::from("bc")), "f(b, g(b))"), vec!["f(c, g(b))", "f(b, g(c))"]);
+    assert!(results(&Strategy::Rule(IString::from("cd")), "f(a, g(b))").is_empty());
+
+    assert!(results(&Strategy::Top, "f(a, g(b))").is_empty());
+    assert_eq!(results(&Strategy::Top, "a"), vec!["b"]);
+
+    assert_eq!(results(&Strategy::Normalize, "f(a, g(b))"), vec!["f(a, b)"]);
+  }
+
+  #[test]
+  fn strategies_compose_in_sequence_and_as_alternatives() {
+    let strategy = Strategy::Seq(rule("ab"), rule("bc"));
+    assert_eq!(results(&strategy, "f(a, g(b))"), vec!["f(c, g(b))", "f(b, g(c))"]);
+
+    let strategy = Strategy::Seq(rule("bc"), Box::new(Strategy::Normalize));
+    assert_eq!(results(&strategy, "f(a, g(b))"), vec!["f(a, c)"]);
+
+    let strategy = Strategy::Alt(Box::new(Strategy::Top), rule("ab"));
+    assert_eq!(results(&strategy, "f(a, g(b))"), vec!["f(b, g(b))"]);
+    assert_eq!(results(&strategy, "a"), vec!["b"]);
+  }
+}
@@ -0,0 +1,130 @@
+/*!
+
+A `Substitution` binds variables to the DAG nodes they are matched against. Matching a pattern extends a substitution,
+and instantiating a term replaces its bound variables with their bindings.
+
+Maude stores bindings in a vector indexed by the variable's index within its statement. Variables are not indexed in
+this codebase, so bindings are keyed by the variable's symbol. Substitutions are still sized with
+`Module::minimum_substitution_size()` so that they do not grow while a statement is applied.
+
+*/
+
+use crate::{
+  abstractions::{
+    join_iter,
+    HashMap,
+    IString
+  },
+  core::format::{
+    FormatStyle,
+    Formattable
+  },
+  theory::{
+    dag_node::RcDagNode,
+    symbol::SymbolPtr
+  }
+};
+
+#[derive(Clone, Default)]
+pub struct Substitution {
+  bindings: HashMap<SymbolPtr, RcDagNode>,
+}
+
+impl Substitution {
+  /// Creates an empty substitution with room for `size` bindings.
+  pub fn with_capacity(size: usize) -> Substitution {
+    Substitution {
+      bindings: HashMap::with_capacity(size)
+    }
+  }
+
+  /// The binding of `variable`, if it is bound.
+  #[inline(always)]
+  pub fn value(&self, variable: SymbolPtr) -> Option<RcDagNode> {
+    self.bindings.get(&variable).cloned()
+  }
+
+  /// Binds `variable` to `value`, replacing any previous binding.
+  #[inline(always)]
+  pub fn bind(&mut self, variable: SymbolPtr, value: RcDagNode) {
+    self.bindings.insert(variable, value);
+  }
+
+  #[inline(always)]
+  pub fn is_empty(&self) -> bool {
+    self.bindings.is_empty()
+  }
+
+  /// An iterator over the bindings as pairs of a variable and its value, in no particular order.
+  pub fn iter(&self) -> impl Iterator<Item = (SymbolPtr, RcDagNode)> + '_ {
+    self.bindings.iter().map(|(variable, value)| (*variable, value.clone()))
+  }
+
+  /// The bindings keyed by variable name, for callers that know variables by name rather than by symbol. A
+  /// substitution binds the variables of a single statement or command, so their names are distinct.
+  pub fn to_name_map(&self) -> HashMap<IString, RcDagNode> {
+    self.iter().map(|(variable, value)| (unsafe { (*variable).name }, value)).collect()
+  }
+}
+
+/// Substitutions are equal if they bind the same variables to structurally equal nodes.
+impl PartialEq for Substitution {
+  fn eq(&self, other: &Substitution) -> bool {
+    self.bindings.len() == other.bindings.len()
+        && self.bindings.iter().all(
+          | (variable, value) | match other.bindings.get(variable) {
+            Some(other_value) => value.borrow().equals(&other_value.borrow()),
+            None              => false
+          }
+        )
+  }
+}
+
+impl Formattable for Substitution {
+  /// Bindings are printed in variable order, e.g. `X := a, Y := f(b)`.
+  fn repr(&self, style: FormatStyle) -> String {
+    let mut bindings: Vec<_> = self.bindings.iter().collect();
+    bindings.sort_by(|(variable, _), (other, _)| unsafe { (***variable).cmp(&***other) });
+
+    let bindings = bindings.iter().map(
+      | (variable, value) | format!("{} := {}", unsafe { (***variable).repr(style) }, value.borrow().repr(style))
+    );
+    join_iter(bindings, |_| ", ".to_string()).collect()
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use crate::{
+    core::command::Command,
+    parser::parse_to_module_with_commands
+  };
+  use super::*;
+
+  #[test]
+  fn bindings_convert_to_a_name_map() {
+    let (module, commands) = parse_to_module_with_commands(
+      "
+      variable X;
+      variable Y;
+      symbol f;
+      symbol g;
+
+      match f(X, g(Y)) with f(a, g(g(b)));
+      "
+    ).unwrap();
+    let (pattern, subject) = match &commands[0] {
+      Command::Match { pattern, subject } => (pattern, module.reduce_dag(&subject.dagify())),
+      _ => unreachable!()
+    };
+    let mut substitution = Substitution::default();
+    assert!(pattern.match_dag(&subject, &mut substitution));
+    assert_eq!(substitution.iter().count(), 2);
+
+    let names = substitution.to_name_map();
+    assert_eq!(names.len(), 2);
+    assert_eq!(names[&IString::from("X")].borrow().to_string(), "a");
+    assert_eq!(names[&IString::from("Y")].borrow().to_string(), "g(b)");
+  }
+}
@@ -0,0 +1,91 @@
+/*!
+
+Subsumption compares terms with variables by generality. A pattern *subsumes* a term if the term is an instance of
+it, that is, if the pattern matches the term when the variables of the term are read as constants. So `f(X)`
+subsumes `f(a)` and `f(Y)`, but `f(a)` subsumes neither `f(X)` nor `f(b)`. Two terms that subsume each other are
+*variants*: they are equal up to the renaming of their variables.
+
+`subsumes(…)` tests whether a pattern subsumes a term, and `variant_leq(…)` orders two terms by generality, which is
+what a variant generator needs to discard the variants that a more general variant already covers.
+
+*/
+
+use std::cmp::Ordering;
+
+use crate::{
+  core::substitution::Substitution,
+  theory::{
+    dag_node::RcDagNode,
+    term::Term
+  }
+};
+
+/// Whether `subject` is an instance of `pattern`. See the module documentation.
+pub fn subsumes(pattern: &Term, subject: &RcDagNode) -> bool {
+  !pattern.match_all(subject, Substitution::default()).is_empty()
+}
+
+/**
+Compares `a` and `b` by generality: `Less` if `a` is a proper instance of `b`, `Greater` if `b` is a proper instance
+of `a`, `Equal` if they are variants, and `None` if neither is an instance of the other. See the module
+documentation.
+*/
+pub fn variant_leq(a: &RcDagNode, b: &RcDagNode) -> Option<Ordering> {
+  let a_is_instance = subsumes(&Term::from_dag(&b.borrow()), a);
+  let b_is_instance = subsumes(&Term::from_dag(&a.borrow()), b);
+  match (a_is_instance, b_is_instance) {
+    (true, true)   => Some(Ordering::Equal),
+    (true, false)  => Some(Ordering::Less),
+    (false, true)  => Some(Ordering::Greater),
+    (false, false) => None
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use crate::{
+    core::command::Command,
+    parser::parse_to_module_with_commands
+  };
+  use super::*;
+
+  #[test]
+  fn instances_are_subsumed_by_more_general_terms() {
+    let (_module, commands) = parse_to_module_with_commands(
+      "
+      variable X;
+      variable Y;
+      symbol f;
+      symbol g;
+
+      reduce f(X);
+      reduce f(a);
+      reduce f(Y);
+      reduce g(X, a);
+      reduce g(b, Y);
+      reduce g(X, X);
+      reduce g(a, a);
+      "
+    ).unwrap();
+    let terms: Vec<&Term> = commands.iter()
+                                    .map(|command| match command {
+                                      Command::Reduce(term) => term.as_ref(),
+                                      _ => unreachable!()
+                                    })
+                                    .collect();
+    let dags: Vec<RcDagNode> = terms.iter().map(|term| term.dagify()).collect();
+
+    assert!(subsumes(terms[0], &dags[1]));
+    assert!(!subsumes(terms[1], &dags[0]));
+    assert!(subsumes(terms[5], &dags[6]));
+    assert!(!subsumes(terms[5], &dags[3]));
+
+    assert_eq!(variant_leq(&dags[1], &dags[0]), Some(Ordering::Less));
+    assert_eq!(variant_leq(&dags[0], &dags[1]), Some(Ordering::Greater));
+    assert_eq!(variant_leq(&dags[0], &dags[2]), Some(Ordering::Equal));
+    // Neither `g(X, a)` nor `g(b, Y)` is an instance of the other, though they unify.
+    assert_eq!(variant_leq(&dags[3], &dags[4]), None);
+    assert_eq!(variant_leq(&dags[1], &dags[3]), None);
+  }
+}
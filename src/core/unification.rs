@@ -0,0 +1,205 @@
+/*!
+
+Unification finds a substitution under which two terms, both of which may contain variables, become equal. Where
+matching only binds the variables of a pattern, unification binds variables on both sides, so `f(X, b)` and `f(a, Y)`
+unify with `X := a, Y := b`. It is the basis of narrowing, which rewrites a term with variables by unifying it with
+the lefthand sides of rules.
+
+`unify(…)` computes a most general unifier with the usual algorithm. Variables bound along the way are looked through
+when they are met again, and a variable is never bound to a term that contains it, so `X` and `f(X)` do not unify.
+Once the terms are unified, the bindings are resolved against each other, so that no binding mentions a bound
+variable.
+
+A variable declared with a sort is only bound to a term of a subsort of it, as for matching. Two variables are
+unified by binding the one of the larger sort to the other.
+
+ToDo: Two variables of incomparable sorts can only be unified by a fresh variable of a common subsort, which is not
+      implemented, so they do not unify.
+ToDo: Applications of AC symbols are unified as if the symbols were free, so unifiers modulo AC are missed.
+
+*/
+
+use crate::{
+  abstractions::RcCell,
+  core::substitution::Substitution,
+  theory::{
+    dag_node::RcDagNode,
+    symbol::SymbolPtr,
+    term::sort_admits
+  }
+};
+
+/**
+The most general extension of `substitution` under which `left` and `right` are equal, or `None` if there is none.
+Bindings already in `substitution` are respected. See the module documentation.
+*/
+pub fn unify(left: &RcDagNode, right: &RcDagNode, mut substitution: Substitution) -> Option<Substitution> {
+  let mut pending = vec![(left.clone(), right.clone())];
+  while let Some((left, right)) = pending.pop() {
+    let left  = walk(&left, &substitution);
+    let right = walk(&right, &substitution);
+    if RcCell::ptr_eq(&left, &right) {
+      continue;
+    }
+    let left_symbol  = left.borrow().top_symbol;
+    let right_symbol = right.borrow().top_symbol;
+
+    match unsafe{ ((*left_symbol).is_variable(), (*right_symbol).is_variable()) } {
+
+      (true, true) => {
+        if unsafe{ *left_symbol == *right_symbol } {
+          continue;
+        }
+        if sort_admits(unsafe{ (*left_symbol).range_sort() }, &right) {
+          substitution.bind(left_symbol, right);
+        } else if sort_admits(unsafe{ (*right_symbol).range_sort() }, &left) {
+          substitution.bind(right_symbol, left);
+        } else {
+          return None;
+        }
+      }
+
+      (true, false) => bind_variable(left_symbol, right, &mut substitution)?,
+
+      (false, true) => bind_variable(right_symbol, left, &mut substitution)?,
+
+      (false, false) => {
+        let left_node  = left.borrow();
+        let right_node = right.borrow();
+        if unsafe{ *left_symbol != *right_symbol } || left_node.arg_count() != right_node.arg_count() {
+          return None;
+        }
+        // The arguments of towers of iterated symbols are expanded, so towers of different heights unify as nested
+        // applications do.
+        pending.extend(left_node.iter_args().zip(right_node.iter_args()));
+      }
+
+    }
+  }
+
+  let mut resolved = Substitution::with_capacity(substitution.iter().count());
+  for (variable, value) in substitution.iter() {
+    resolved.bind(variable, resolve(&value, &substitution));
+  }
+  Some(resolved)
+}
+
+/// Binds `variable` to `value`, unless `value` contains the variable or does not have the variable's sort.
+fn bind_variable(variable: SymbolPtr, value: RcDagNode, substitution: &mut Substitution) -> Option<()> {
+  if occurs(variable, &value, substitution) || !sort_admits(unsafe{ (*variable).range_sort() }, &value) {
+    return None;
+  }
+  substitution.bind(variable, value);
+  Some(())
+}
+
+/// Follows the bindings of bound variables from `node` until it reaches a node that is not a bound variable.
+fn walk(node: &RcDagNode, substitution: &Substitution) -> RcDagNode {
+  let mut node = node.clone();
+  loop {
+    let top_symbol = node.borrow().top_symbol;
+    match unsafe{ (*top_symbol).is_variable() } {
+      true  => match substitution.value(top_symbol) {
+        Some(value) => node = value,
+        None        => return node
+      },
+      false => return node
+    }
+  }
+}
+
+/// Whether `variable` occurs in `node` once bound variables are replaced by their bindings.
+fn occurs(variable: SymbolPtr, node: &RcDagNode, substitution: &Substitution) -> bool {
+  let node     = walk(node, substitution);
+  let dag_node = node.borrow();
+  match unsafe{ (*dag_node.top_symbol).is_variable() } {
+    true  => unsafe{ *dag_node.top_symbol == *variable },
+    false => dag_node.args.iter().any(|arg| occurs(variable, arg, substitution))
+  }
+}
+
+/// `node` with its bound variables replaced by their bindings, which are resolved in turn. Subterms without bound
+/// variables are shared with `node`.
+pub(crate) fn resolve(node: &RcDagNode, substitution: &Substitution) -> RcDagNode {
+  let node     = walk(node, substitution);
+  let dag_node = node.borrow();
+  let args: Vec<RcDagNode> = dag_node.args.iter().map(|arg| resolve(arg, substitution)).collect();
+  if args.iter().zip(dag_node.args.iter()).all(|(arg, original)| RcCell::ptr_eq(arg, original)) {
+    drop(dag_node);
+    return node;
+  }
+  let mut resolved = dag_node.with_args(args);
+  resolved.structural_hash();
+  RcCell::new(resolved)
+}
+
+
+#[cfg(test)]
+mod tests {
+  use crate::{
+    core::{
+      command::Command,
+      format::{
+        FormatStyle,
+        Formattable
+      }
+    },
+    parser::parse_to_module_with_commands
+  };
+  use super::*;
+
+  /// Unifies the pattern and subject of each `match` command.
+  fn unify_commands(source: &str) -> Vec<Option<String>> {
+    let (_module, commands) = parse_to_module_with_commands(source).unwrap();
+    commands.iter()
+            .map(|command| match command {
+              Command::Match { pattern, subject } => {
+                unify(&pattern.dagify(), &subject.dagify(), Substitution::default())
+                    .map(|unifier| unifier.repr(FormatStyle::Default))
+              }
+              _ => unreachable!()
+            })
+            .collect()
+  }
+
+  #[test]
+  fn unifiers_bind_variables_on_both_sides() {
+    let unifiers = unify_commands(
+      "
+      variable X;
+      variable Y;
+      variable Z;
+      symbol f;
+      symbol g;
+
+      match f(X, b) with f(a, Y);
+      match g(X, Y) with g(Y, c);
+      match f(X, Y) with f(Z, Z);
+      match f(X, g(X)) with f(X, g(X));
+      "
+    );
+    assert_eq!(unifiers[0].as_deref(), Some("X := a, Y := b"));
+    // Bindings are resolved, so no binding mentions a bound variable.
+    assert_eq!(unifiers[1].as_deref(), Some("X := c, Y := c"));
+    assert_eq!(unifiers[2].as_deref(), Some("X := Z, Y := Z"));
+    assert_eq!(unifiers[3].as_deref(), Some(""));
+  }
+
+  #[test]
+  fn clashes_and_cycles_do_not_unify() {
+    let unifiers = unify_commands(
+      "
+      variable X;
+      variable Y;
+      symbol f;
+      symbol g;
+
+      match f(a) with g(a);
+      match f(X, b) with f(a, c);
+      match X with f(X);
+      match g(X, Y) with g(Y, f(X));
+      "
+    );
+    assert_eq!(unifiers, vec![None, None, None, None]);
+  }
+}
@@ -8,19 +8,43 @@ Attribute :=
     "assoc" | "associative"
     | "comm" | "commutative"
     | "ctor" | "constructor"
+    | "memo" | "memoized"
+    | "iter" | "iterated"
     | "id" "(" Term ")"
+    | "left" "id" "(" Term ")"
+    | "right" "id" "(" Term ")"
+    | "latex" "(" StringLiteral ")"
+    | "frozen" "(" NaturalNumber+ ")"
+    | ("prec" | "precedence") NaturalNumber
+    | "gather" "(" GatherPattern+ ")"
     ;
 
+GatherPattern := "e" | "E" | "&" ;
+
 AttributeSpec := "[" AttributeList "]" ;
 ```
 
 */
 
 use crate::{
-  parser::ast::BxTermAST,
-  theory::symbol_type::{
-    SymbolAttribute,
-    SymbolAttributes
+  abstractions::{
+    HashMap,
+    IString,
+    NaturalNumber
+  },
+  parser::{
+    ast::BxTermAST,
+    parse_error::ParseErrorKind
+  },
+  theory::{
+    symbol::{
+      Gathering,
+      SymbolPtr
+    },
+    symbol_type::{
+      SymbolAttribute,
+      SymbolAttributes
+    }
   }
 };
 
@@ -30,8 +54,22 @@ pub(crate) enum AttributeAST {
   Associative,
   Commutative,
   Constructor,
-  Identity(BxTermAST)
-  // ToDo: Figure out how to keep track of the identity element. (How does Maude do this?)
+  /// Normal forms of terms with the symbol on top are remembered. See the `memo_map` module.
+  Memoized,
+  /// Towers of applications of the unary symbol are stored compactly. See the `iterated_theory` module.
+  Iterated,
+  /// An identity element on both sides. The identity elements of a symbol are collapsed by `Term::normalize(…)`.
+  Identity(BxTermAST),
+  LeftIdentity(BxTermAST),
+  RightIdentity(BxTermAST),
+  /// A LaTeX macro used to render the symbol. See `Symbol::repr_application(…)`.
+  Latex(String),
+  /// The 1-based positions of the arguments that are never rewritten, as in `frozen(1 2)`.
+  Frozen(Vec<NaturalNumber>),
+  /// The precedence of an infix symbol, as in `prec 33`. See the `mixfix` module.
+  Precedence(NaturalNumber),
+  /// The gathering of the arguments of an infix symbol, as in `gather (E e)`. See the `mixfix` module.
+  Gather(Vec<Gathering>),
 }
 
 impl AttributeAST {
@@ -39,19 +77,91 @@ impl AttributeAST {
   /// static method.
   fn to_attributes(&self) -> SymbolAttributes {
     match self {
-      AttributeAST::Associative => SymbolAttribute::Associative.into(),
-      AttributeAST::Commutative => SymbolAttribute::Commutative.into(),
-      AttributeAST::Constructor => SymbolAttribute::Constructor.into(),
-      AttributeAST::Identity(_) => SymbolAttribute::LeftIdentity | SymbolAttribute::RightIdentity,
+      AttributeAST::Associative      => SymbolAttribute::Associative.into(),
+      AttributeAST::Commutative      => SymbolAttribute::Commutative.into(),
+      AttributeAST::Constructor      => SymbolAttribute::Constructor.into(),
+      AttributeAST::Memoized         => SymbolAttribute::Memoized.into(),
+      AttributeAST::Iterated         => SymbolAttribute::Iterated.into(),
+      AttributeAST::Identity(_)      => SymbolAttribute::LeftIdentity | SymbolAttribute::RightIdentity,
+      AttributeAST::LeftIdentity(_)  => SymbolAttribute::LeftIdentity.into(),
+      AttributeAST::RightIdentity(_) => SymbolAttribute::RightIdentity.into(),
+      AttributeAST::Latex(_)         => SymbolAttribute::Latex.into(),
+      AttributeAST::Frozen(_)        => SymbolAttribute::Frozen.into(),
+      AttributeAST::Precedence(_)    => SymbolAttribute::Precedence.into(),
+      AttributeAST::Gather(_)        => SymbolAttribute::Gather.into(),
     }
   }
 
   /// Converts a vector of `TheoryAttributeAST` values into a `TheoryAttributes` (one byte bitflag).
-  pub fn construct_attributes(attribute_ast: &Vec<AttributeAST>) -> SymbolAttributes {
+  pub fn construct_attributes(attribute_ast: &[AttributeAST]) -> SymbolAttributes {
     let mut attributes = SymbolAttributes::empty();
     for attribute in attribute_ast {
       attributes |= attribute.to_attributes();
     }
     attributes
   }
+
+  /// Groups the infix terms of the identity element, if `self` has one. See `TermAST::resolve_infix(…)`.
+  pub fn resolve_infix(&mut self, symbols: &HashMap<IString, SymbolPtr>) -> Result<(), ParseErrorKind> {
+    match self {
+      AttributeAST::Identity(identity)
+      | AttributeAST::LeftIdentity(identity)
+      | AttributeAST::RightIdentity(identity) => identity.resolve_infix(symbols),
+      _ => Ok(())
+    }
+  }
+
+  /// The identity element of the last identity attribute in `attribute_ast`, if any.
+  pub fn identity(attribute_ast: &[AttributeAST]) -> Option<&BxTermAST> {
+    attribute_ast.iter().rev().find_map(
+      | attribute | match attribute {
+        AttributeAST::Identity(identity)
+        | AttributeAST::LeftIdentity(identity)
+        | AttributeAST::RightIdentity(identity) => Some(identity),
+        _ => None
+      }
+    )
+  }
+
+  /// The 1-based positions of every `frozen(…)` attribute in `attribute_ast`.
+  pub fn frozen_positions(attribute_ast: &[AttributeAST]) -> Vec<NaturalNumber> {
+    attribute_ast.iter()
+                 .flat_map(
+                   | attribute | match attribute {
+                     AttributeAST::Frozen(positions) => positions.clone(),
+                     _ => vec![]
+                   }
+                 )
+                 .collect()
+  }
+
+  /// The precedence of the last `prec` attribute in `attribute_ast`, if any.
+  pub fn precedence(attribute_ast: &[AttributeAST]) -> Option<NaturalNumber> {
+    attribute_ast.iter().rev().find_map(
+      | attribute | match attribute {
+        AttributeAST::Precedence(precedence) => Some(*precedence),
+        _ => None
+      }
+    )
+  }
+
+  /// The patterns of the last `gather(…)` attribute in `attribute_ast`, if any.
+  pub fn gather(attribute_ast: &[AttributeAST]) -> Option<&Vec<Gathering>> {
+    attribute_ast.iter().rev().find_map(
+      | attribute | match attribute {
+        AttributeAST::Gather(gather) => Some(gather),
+        _ => None
+      }
+    )
+  }
+
+  /// The LaTeX macro of the last `latex(…)` attribute in `attribute_ast`, if any.
+  pub fn latex_macro(attribute_ast: &[AttributeAST]) -> Option<String> {
+    attribute_ast.iter().rev().find_map(
+      | attribute | match attribute {
+        AttributeAST::Latex(latex) => Some(latex.clone()),
+        _ => None
+      }
+    )
+  }
 }
@@ -0,0 +1,62 @@
+/*!
+
+Commands that exercise the module they appear in. See the `core::command` module.
+
+```ebnf
+Command := ("reduce" | "red") Term ";"
+         | "match" Term "with" Term ";"
+         ;
+```
+
+*/
+
+use crate::{
+  abstractions::{
+    HashMap,
+    IString
+  },
+  core::command::Command,
+  parser::{
+    ast::BxTermAST,
+    parse_error::ParseErrorKind
+  },
+  theory::symbol::SymbolPtr
+};
+
+pub(crate) type BxCommandAST = Box<CommandAST>;
+
+pub(crate) enum CommandAST {
+  Reduce(BxTermAST),
+  Match {
+    pattern: BxTermAST,
+    subject: BxTermAST
+  },
+}
+
+impl CommandAST {
+  /// Groups the infix terms of the command. See `TermAST::resolve_infix(…)`.
+  pub fn resolve_infix(&mut self, symbols: &HashMap<IString, SymbolPtr>) -> Result<(), ParseErrorKind> {
+    match self {
+      CommandAST::Reduce(term) => term.resolve_infix(symbols),
+      CommandAST::Match { pattern, subject } => {
+        pattern.resolve_infix(symbols)?;
+        subject.resolve_infix(symbols)
+      }
+    }
+  }
+
+  pub fn construct(&self, symbols: &mut HashMap<IString, SymbolPtr>) -> Command {
+    match self {
+
+      CommandAST::Reduce(term) => Command::Reduce(Box::new(term.construct(symbols))),
+
+      CommandAST::Match { pattern, subject } => {
+        Command::Match {
+          pattern: Box::new(pattern.construct(symbols)),
+          subject: Box::new(subject.construct(symbols)),
+        }
+      }
+
+    }
+  }
+}
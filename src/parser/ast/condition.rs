@@ -33,9 +33,12 @@ use crate::{
     IString
   },
   core::pre_equation::condition::Condition,
-  parser::ast::{
-    BxSortSpecAST,
-    BxTermAST
+  parser::{
+    ast::{
+      BxSortSpecAST,
+      BxTermAST
+    },
+    parse_error::ParseErrorKind
   },
   theory::{
     symbol::SymbolPtr,
@@ -60,6 +63,20 @@ pub(crate) enum ConditionAST {
 }
 
 impl ConditionAST {
+  /// Groups the infix terms of the condition. See `TermAST::resolve_infix(…)`.
+  pub fn resolve_infix(&mut self, symbols: &HashMap<IString, SymbolPtr>) -> Result<(), ParseErrorKind> {
+    match self {
+      ConditionAST::Equality { lhs, rhs }
+      | ConditionAST::Match { lhs, rhs }
+      | ConditionAST::Rewrite { lhs, rhs } => {
+        lhs.resolve_infix(symbols)?;
+        rhs.resolve_infix(symbols)
+      }
+      ConditionAST::SortMembership { lhs, .. } => lhs.resolve_infix(symbols),
+      ConditionAST::Boolean(term) => term.resolve_infix(symbols)
+    }
+  }
+
   pub fn construct(
     &self,
     symbols: &mut HashMap<IString, SymbolPtr>,
@@ -0,0 +1,91 @@
+/*!
+
+The addition of a single item to an existing `Module`, as `Module::add_statement(…)` does. The item is constructed
+against the sorts and symbols of the module the way `ModuleAST::construct_module(…)` constructs the items of a
+module, except that its infix terms are grouped with the operators of the module as they are, so an item cannot
+change the grouping of the terms that precede it.
+
+*/
+
+use crate::{
+  core::{
+    edit_error::EditError,
+    module::Module
+  },
+  parser::ast::{
+    attribute::AttributeAST,
+    construct_symbol_from_decl,
+    ItemAST
+  },
+  theory::symbol_type::CoreSymbolType
+};
+
+impl ItemAST {
+  /// Adds the item to `module`, consuming `self`. The caller completes the edit. See `Module::add_statement(…)`.
+  pub fn add_to(self, module: &mut Module) -> Result<(), EditError> {
+    match self {
+
+      ItemAST::Submodule(_) => return Err(EditError::Unsupported("submodule")),
+
+      ItemAST::Command(_) => return Err(EditError::Unsupported("command")),
+
+      ItemAST::SortDecl(sort_decl) => module.declare_subsorts(&sort_decl.sorts_lt, &sort_decl.sorts_gt)?,
+
+      ItemAST::VarDecl(mut var_decl) => {
+        construct_symbol_from_decl(
+          &mut module.symbols,
+          &mut module.sorts,
+          var_decl.take_parts(),
+          CoreSymbolType::Variable
+        )?;
+      }
+
+      ItemAST::SymDecl(mut sym_decl) => {
+        for attribute in sym_decl.attributes.iter_mut() {
+          attribute.resolve_infix(&module.symbols)?;
+        }
+        construct_symbol_from_decl(
+          &mut module.symbols,
+          &mut module.sorts,
+          sym_decl.take_parts(),
+          CoreSymbolType::Standard
+        )?;
+        if let Some(identity) = AttributeAST::identity(&sym_decl.attributes) {
+          let identity = Box::new(identity.construct(&mut module.symbols));
+          unsafe{ (*module.symbols[&sym_decl.name]).identity = Some(identity); }
+        }
+      }
+
+      ItemAST::Rule(mut rule_decl) => {
+        rule_decl.lhs.resolve_infix(&module.symbols)?;
+        rule_decl.rhs.resolve_infix(&module.symbols)?;
+        for condition in rule_decl.conditions.iter_mut().flatten() {
+          condition.resolve_infix(&module.symbols)?;
+        }
+        let rule = rule_decl.construct(&mut module.symbols, &mut module.sorts);
+        module.insert_statement(rule);
+      }
+
+      ItemAST::Equation(mut eq_decl) => {
+        eq_decl.lhs.resolve_infix(&module.symbols)?;
+        eq_decl.rhs.resolve_infix(&module.symbols)?;
+        for condition in eq_decl.conditions.iter_mut().flatten() {
+          condition.resolve_infix(&module.symbols)?;
+        }
+        let equation = eq_decl.construct(&mut module.symbols, &mut module.sorts);
+        module.insert_statement(equation);
+      }
+
+      ItemAST::Membership(mut mb_decl) => {
+        mb_decl.lhs.resolve_infix(&module.symbols)?;
+        for condition in mb_decl.conditions.iter_mut().flatten() {
+          condition.resolve_infix(&module.symbols)?;
+        }
+        let membership = mb_decl.construct(&mut module.symbols, &mut module.sorts);
+        module.insert_statement(membership);
+      }
+
+    }
+    Ok(())
+  }
+}
@@ -0,0 +1,147 @@
+/*!
+
+Infix operators. A symbol whose name is an operator between underscores, like `_+_`, can be applied as `a + b` as
+well as `_+_(a, b)`. How a term like `a + b * c` groups depends on the `prec` and `gather` attributes of its
+operators, which are not known until every symbol is declared, so the parser records the operands and operators of
+the term as a flat `TermAST::Infix`, and `TermAST::resolve_infix(…)` groups it into applications once they are.
+
+```ebnf
+InfixOp   := [+*\-^%~!|?@$=]+ ("/" [+*\-^%~!|?@$=]+)* ;
+InfixName := "_" [+*\-^%~!|?@$=/]+ "_" ;
+
+Term := PrimaryTerm (InfixOp PrimaryTerm)* ;
+```
+
+An operator that is also a token of the language, like `=` or `/`, is read as that token, so `a = b` in a condition
+is not an application of `_=_`, but longer operators like `==` are read as operators.
+
+## Precedence and Gathering
+
+As in Maude, every term has a precedence: that of its operator if it is an infix application written without
+parentheses, and 0 otherwise. Lower precedences bind more tightly. `symbol _+_ [prec 33];` and `symbol _*_ [prec 31];`
+make `a + b * c` group as `a + (b * c)`. An operator without a `prec` attribute has precedence `DEFAULT_PRECEDENCE`.
+
+The `gather` attribute bounds the precedences of the arguments of an infix operator, with one pattern per argument:
+`e` for a precedence less than the operator's, `E` for one at most the operator's, and `&` for any precedence. So
+`gather (E e)` makes an operator group to the left, `a - b - c` being `(a - b) - c`, and `gather (e E)` makes it
+group to the right. An operator without a `gather` attribute gathers `(E E)` and groups to the right. A term that
+cannot be grouped at all, like `a - b - c` if `_-_` gathers `(e e)`, is a `ParseErrorKind::InfixGrouping` error.
+
+*/
+
+use std::{
+  iter::Peekable,
+  vec::IntoIter
+};
+
+use crate::{
+  builtin::equality_symbol::{
+    is_equality_name,
+    EQUALITY_PRECEDENCE
+  },
+  abstractions::{
+    HashMap,
+    IString,
+    NaturalNumber
+  },
+  parser::{
+    ast::{
+      BxTermAST,
+      TermAST
+    },
+    parse_error::ParseErrorKind
+  },
+  theory::symbol::{
+    Gathering,
+    SymbolPtr,
+    DEFAULT_PRECEDENCE
+  }
+};
+
+impl TermAST {
+  /// Groups every `TermAST::Infix` of the term into applications of its operators, according to the precedences and
+  /// gathering of the operators in `symbols`. See the module documentation.
+  pub fn resolve_infix(&mut self, symbols: &HashMap<IString, SymbolPtr>) -> Result<(), ParseErrorKind> {
+    match self {
+
+      TermAST::Application { head, tail } => {
+        head.resolve_infix(symbols)?;
+        for term in tail.iter_mut() {
+          term.resolve_infix(symbols)?;
+        }
+      }
+
+      TermAST::Infix { operands, operators } => {
+        for operand in operands.iter_mut() {
+          operand.resolve_infix(symbols)?;
+        }
+        let mut grouper = InfixGrouper {
+          operands : std::mem::take(operands).into_iter(),
+          operators: std::mem::take(operators).into_iter().peekable(),
+          symbols
+        };
+        let (term, _) = grouper.group(&|_| true)?;
+        if let Some(operator) = grouper.operators.next() {
+          return Err(ParseErrorKind::InfixGrouping(operator));
+        }
+        *self = *term;
+      }
+
+      _ => {}
+
+    }
+    Ok(())
+  }
+}
+
+/// Groups the operands and operators of a `TermAST::Infix` by precedence climbing.
+struct InfixGrouper<'a> {
+  operands : IntoIter<BxTermAST>,
+  operators: Peekable<IntoIter<IString>>,
+  symbols  : &'a HashMap<IString, SymbolPtr>
+}
+
+impl InfixGrouper<'_> {
+  /// The precedence and the left and right gathering of `operator`.
+  fn syntax(&self, operator: IString) -> (NaturalNumber, Gathering, Gathering) {
+    match self.symbols.get(&operator) {
+      Some(symbol) => {
+        let symbol        = unsafe{ &**symbol };
+        let (left, right) = symbol.infix_gather();
+        (symbol.infix_precedence(), left, right)
+      }
+      None if is_equality_name(&operator) => (EQUALITY_PRECEDENCE, Gathering::LessOrEqual, Gathering::LessOrEqual),
+      None => (DEFAULT_PRECEDENCE, Gathering::LessOrEqual, Gathering::LessOrEqual)
+    }
+  }
+
+  /// Groups the next operand with the operators that follow it, for as long as the operators can take the term
+  /// grouped so far as their left argument and `admits` their precedence. Returns the term and its precedence.
+  fn group(&mut self, admits: &dyn Fn(NaturalNumber) -> bool) -> Result<(BxTermAST, NaturalNumber), ParseErrorKind> {
+    // The parser gives an `Infix` one more operand than it has operators.
+    let mut term       = self.operands.next().unwrap();
+    let mut precedence = 0;
+
+    while let Some(&operator) = self.operators.peek() {
+      let (operator_precedence, left, right) = self.syntax(operator);
+      if !admits(operator_precedence) || !left.admits(precedence, operator_precedence) {
+        break;
+      }
+      self.operators.next();
+
+      let (argument, argument_precedence) = self.group(&|p| right.admits(p, operator_precedence))?;
+      if !right.admits(argument_precedence, operator_precedence) {
+        return Err(ParseErrorKind::InfixGrouping(operator));
+      }
+      term = Box::new(
+        TermAST::Application {
+          head: Box::new(TermAST::Identifier(operator)),
+          tail: vec![term, argument]
+        }
+      );
+      precedence = operator_precedence;
+    }
+
+    Ok((term, precedence))
+  }
+}
@@ -10,6 +10,8 @@ The `Module` node's `Module::construct(..)` method implements code to convert th
 
 ```ebnf
 Identifier := [a-zA-Z][a-zA-Z_]* ;
+InfixOp    := [+*\-^%~!|?@$=]+ ("/" [+*\-^%~!|?@$=]+)* ;  # not beginning with "=-"
+InfixName  := "_" InfixOp "_" ;
 AndOp      := r"/\" | "∧" | "⋀" ;
 OrOp       := r"\/" | "∨" | "⋁" ;
 ArrowOp    := "->" ;
@@ -17,15 +19,17 @@ SortOp  := "::" ;
 RuleOp     := "=>" ;
 EqualOp    := "="  ;
 MatchOp    := ":=" ;
+DocComment := ("///" [^\n\r]*)+ ;
 
 # Syntactic Rules
 
 Module := Item* ;
 
-Item := Declaration
+Item := DocComment? (Declaration
       | Submodule
+      | Command
       # | Statement
-      ;
+      ) ;
 
 Declaration := VariableDeclaration
             | SymbolDeclaration
@@ -39,20 +43,24 @@ SortDeclaration := "sort" SortList ("<" SortList)? ";" ;
 
 SortList := Identifier ("," Identifier)* ;
 
-SymbolDeclaration := ("symbol"|"sym") Identifier ("/" NaturalNumber)? (SortOp SortSpec)? ConditionSpec? AttributeSpec? ";" ;
+SymbolDeclaration := ("symbol"|"sym") (Identifier | InfixName) ("/" NaturalNumber)? (SortOp SortSpec)? ConditionSpec? AttributeSpec? ";" ;
 
 VariableDeclaration := ("variable"|"var") Identifier ("/" NaturalNumber)? (SortOp SortSpec)? ConditionSpec? AttributeSpec? ";" ;
 
 #Operator := ("operator"|"op") Identifier ("/" NaturalNumber)? (SortOp SortSpec)? ConditionSpec? AttributeSpec? ";" ;
 
-RuleDeclaration := ("rule" | "rl") Term RuleOp Term ConditionSpec? ";" ;
+RuleDeclaration := ("rule" | "rl") Label? Term RuleOp Term ConditionSpec? StatementAttributeSpec? ";" ;
 
-EquationDeclaration := ("equation" | "eq") Term EqualOp Term ConditionSpec? ";" ;
+EquationDeclaration := ("equation" | "eq") Label? Term EqualOp Term ConditionSpec? StatementAttributeSpec? ";" ;
 
-MembershipDeclaration := ("membership" | "mb") Term SortOp SortSpec ConditionSpec? ";" ;
+MembershipDeclaration := ("membership" | "mb") Label? Term SortOp SortSpec ConditionSpec? ";" ;
 
 Submodule := "mod" Identifier "{" Module "}" ;
 
+Command := ("reduce" | "red") Term ";"
+         | "match" Term "with" Term ";"
+         ;
+
 # Statement := BindStatement
 #            | ReduceStatement
 #            | MatchStatement
@@ -62,9 +70,12 @@ Submodule := "mod" Identifier "{" Module "}" ;
 #            | ReplaceAllStatement
 #            ;
 
-Term :=
+Term := PrimaryTerm (InfixOp PrimaryTerm)* ;
+
+PrimaryTerm :=
     Identifier
-    | Term "(" Term ("," Term)* ")"
+    | InfixName
+    | PrimaryTerm "(" Term ("," Term)* ")"
     | "(" Term ")"
     ;
 
@@ -78,11 +89,21 @@ Attribute :=
     "assoc" | "associative"
     | "comm" | "commutative"
     | "ctor" | "constructor"
+    | "memo" | "memoized"
+    | "iter" | "iterated"
     | "id" "(" Term ")"
+    | "left" "id" "(" Term ")"
+    | "right" "id" "(" Term ")"
+    | ("prec" | "precedence") NaturalNumber
+    | "gather" "(" ("e" | "E" | "&")+ ")"
     ;
 
 AttributeSpec := "[" AttributeList "]" ;
 
+StatementAttribute := "owise" | "otherwise" | "narrowing" | "nonexec" ;
+
+StatementAttributeSpec := "[" StatementAttribute ("," StatementAttribute)* "]" ;
+
 ConditionSpec := "if" Condition (AndOp Condition)* ;
 
 Condition :=
@@ -107,10 +128,23 @@ RewriteCondition := Term RuleOp Term ;
 
 (The `#` symbol begins a comment, disabling those lines it comments out.)
 
+The statement attributes of an equation or rule are `owise`, `narrowing`, and `nonexec`. A `nonexec` statement is
+only applied by a context made with `RewritingContext::set_allow_nonexec(true)`. Membership axioms take no statement
+attributes.
+
 */
 
 
-use crate::abstractions::IString;
+use crate::abstractions::{HashMap, IString};
+use crate::core::diagnostic::Span;
+use crate::core::pre_equation::{
+  condition::Conditions,
+  PreEquation,
+  PreEquationAttribute,
+  PreEquationKind
+};
+use crate::core::sort::collection::SortCollection;
+use crate::theory::symbol::SymbolPtr;
 
 mod module;
 mod term;
@@ -118,6 +152,9 @@ mod sort_spec;
 mod attribute;
 mod condition;
 mod symbol_decl;
+mod command;
+mod infix;
+mod edit;
 
 pub use module::*;
 pub use sort_spec::*;
@@ -125,6 +162,7 @@ pub use term::*;
 pub use attribute::*;
 pub use condition::*;
 pub use symbol_decl::*;
+pub use command::*;
 
 /// An item is anything that lives in a module.
 pub(crate) enum ItemAST {
@@ -134,7 +172,27 @@ pub(crate) enum ItemAST {
   SortDecl(BxSortDeclarationAST),
   Rule(BxRuleDeclarationAST),
   Equation(BxEquationDeclarationAST),
-  Membership(BxMembershipDeclarationAST)
+  Membership(BxMembershipDeclarationAST),
+  Command(BxCommandAST)
+}
+
+impl ItemAST {
+  /// Attaches the doc comment preceding the item to its declaration.
+  pub fn with_doc(mut self, doc: Option<String>) -> ItemAST {
+    match &mut self {
+      ItemAST::VarDecl(decl)    => decl.doc = doc,
+      ItemAST::SymDecl(decl)    => decl.doc = doc,
+      ItemAST::Rule(decl)       => decl.doc = doc,
+      ItemAST::Equation(decl)   => decl.doc = doc,
+      ItemAST::Membership(decl) => decl.doc = doc,
+      // ToDo: Sorts and modules have nowhere to keep documentation yet, so their doc comments are discarded. Commands
+      //       are not documented.
+      ItemAST::Submodule(_)
+      | ItemAST::SortDecl(_)
+      | ItemAST::Command(_)     => {}
+    }
+    self
+  }
 }
 
 /// A sort declaration has the form
@@ -147,29 +205,116 @@ pub(crate) struct SortDeclarationAST {
 }
 
 /// Declaration of the form
-///     RuleDeclaration := ("rule" | "rl") Term RuleOp Term ConditionSpec? ";" ;
+///     RuleDeclaration := ("rule" | "rl") Label? Term RuleOp Term ConditionSpec? StatementAttributeSpec? ";" ;
 pub(crate) type BxRuleDeclarationAST = Box<RuleDeclarationAST>;
 pub(crate) struct RuleDeclarationAST {
+  pub name      : Option<IString>,
   pub lhs       : BxTermAST,
   pub rhs       : BxTermAST,
-  pub conditions: Option<Vec<ConditionAST>>
+  pub conditions: Option<Vec<ConditionAST>>,
+  pub attributes: Vec<PreEquationAttribute>,
+  pub doc       : Option<String>,
+  pub span      : Span,
+}
+
+impl RuleDeclarationAST {
+  /// Constructs the rule, consuming `self`. Its infix terms must already be grouped.
+  pub fn construct(self, symbols: &mut HashMap<IString, SymbolPtr>, sorts: &mut SortCollection) -> PreEquation {
+    let lhs  = self.lhs.construct(symbols);
+    let rhs  = self.rhs.construct(symbols);
+    let rule = PreEquationKind::Rule{
+      rhs_term: Box::new(rhs),
+    };
+
+    PreEquation{
+      name      : self.name,
+      attributes: self.attributes.into_iter().collect(),
+      conditions: construct_conditions(self.conditions, symbols, sorts),
+      lhs_term  : Box::new(lhs),
+      kind      : rule,
+      doc       : self.doc,
+      span      : Some(self.span),
+    }
+  }
 }
 
 /// Declaration of the form
-///     EquationDeclaration := ("equation" | "eq") Term EqualOp Term ConditionSpec? ";" ;
+///     EquationDeclaration := ("equation" | "eq") Label? Term EqualOp Term ConditionSpec? StatementAttributeSpec? ";" ;
 pub(crate) type BxEquationDeclarationAST = Box<EquationDeclarationAST>;
 pub(crate) struct EquationDeclarationAST {
+  pub name      : Option<IString>,
   pub lhs       : BxTermAST,
   pub rhs       : BxTermAST,
-  pub conditions: Option<Vec<ConditionAST>>
+  pub conditions: Option<Vec<ConditionAST>>,
+  pub attributes: Vec<PreEquationAttribute>,
+  pub doc       : Option<String>,
+  pub span      : Span,
+}
+
+impl EquationDeclarationAST {
+  /// Constructs the equation, consuming `self`. Its infix terms must already be grouped.
+  pub fn construct(self, symbols: &mut HashMap<IString, SymbolPtr>, sorts: &mut SortCollection) -> PreEquation {
+    let lhs      = self.lhs.construct(symbols);
+    let rhs      = self.rhs.construct(symbols);
+    let equation = PreEquationKind::Equation{
+      rhs_term: Box::new(rhs),
+    };
+
+    PreEquation{
+      name      : self.name,
+      attributes: self.attributes.into_iter().collect(),
+      conditions: construct_conditions(self.conditions, symbols, sorts),
+      lhs_term  : Box::new(lhs),
+      kind      : equation,
+      doc       : self.doc,
+      span      : Some(self.span),
+    }
+  }
 }
 
 
 /// Declaration of the form
-///     MembershipDeclaration := ("membership" | "mb") Term SortOp SortSpec ConditionSpec? ";" ;
+///     MembershipDeclaration := ("membership" | "mb") Label? Term SortOp SortSpec ConditionSpec? ";" ;
 pub(crate) type BxMembershipDeclarationAST = Box<MembershipDeclarationAST>;
 pub(crate) struct MembershipDeclarationAST {
+  pub name      : Option<IString>,
   pub lhs       : BxTermAST,
   pub rhs       : BxSortSpecAST,
-  pub conditions: Option<Vec<ConditionAST>>
+  pub conditions: Option<Vec<ConditionAST>>,
+  pub doc       : Option<String>,
+  pub span      : Span,
+}
+
+impl MembershipDeclarationAST {
+  /// Constructs the membership axiom, consuming `self`. Its infix terms must already be grouped.
+  pub fn construct(self, symbols: &mut HashMap<IString, SymbolPtr>, sorts: &mut SortCollection) -> PreEquation {
+    let lhs             = self.lhs.construct(symbols);
+    let rhs             = self.rhs.construct(sorts);
+    let membership_kind = PreEquationKind::Membership{
+      sort_spec: rhs,
+    };
+
+    PreEquation{
+      name      : self.name,
+      attributes: Default::default(),
+      conditions: construct_conditions(self.conditions, symbols, sorts),
+      lhs_term  : Box::new(lhs),
+      kind      : membership_kind,
+      doc       : self.doc,
+      span      : Some(self.span),
+    }
+  }
+}
+
+/// Constructs the conditions of a statement, of which there may be none.
+fn construct_conditions(
+  conditions: Option<Vec<ConditionAST>>,
+  symbols   : &mut HashMap<IString, SymbolPtr>,
+  sorts     : &mut SortCollection
+) -> Conditions
+{
+  conditions.unwrap_or_default()
+            .into_iter()
+            .map(|c| Box::new(c.construct(symbols, sorts)))
+            .collect()
 }
@@ -1,17 +1,39 @@
+/*!
+
+The construction of a `Module` from its AST.
+
+## Submodules
+
+A submodule, declared as `mod Name { … }`, is constructed once its enclosing module is, and is owned by it. Names are
+scoped lexically: a sort or symbol name that the submodule does not declare refers to the sort or symbol of that
+name of the nearest enclosing module that has one, before an implicit sort or free symbol is created in the
+submodule. So a submodule can declare `symbol g :: A -> A;` with the sort `A` of its parent, and the two modules share
+the `SortPtr`. A declaration in a submodule hides the sort or symbol of the same name of the enclosing modules, and
+the subsort declarations of a submodule only relate its own sorts.
+
+The sorts and symbols of the enclosing modules remain theirs. They are not listed among the sorts and symbols of the
+submodule, and the submodule does not reclaim them.
+
+ToDo: The commands of a submodule are discarded.
+
+*/
+
 use std::collections::HashMap;
 
 use crate::{
   abstractions::IString,
   core::{
-    pre_equation::{
-      PreEquation,
-      PreEquationKind,
-      condition::Conditions
+    command::Command,
+    pre_equation::PreEquation,
+    sort::{
+      collection::SortCollection,
+      SortPtr
     },
-    sort::collection::SortCollection,
     module::Module
   },
   parser::ast::{
+    attribute::AttributeAST,
+    command::BxCommandAST,
     symbol_decl::{
       BxSymbolDeclarationAST,
       BxVariableDeclarationAST
@@ -23,6 +45,7 @@ use crate::{
     BxSortDeclarationAST,
     ItemAST
   },
+  parser::parse_error::ParseErrorKind,
   theory::{
     symbol::SymbolPtr,
     symbol_type::CoreSymbolType
@@ -39,8 +62,27 @@ pub(crate) struct ModuleAST {
 
 impl ModuleAST {
 
-  /// Constructs a `Module` representation of `self`, consuming `self`.
-  pub fn construct_module(mut self) -> Module {
+  /// Constructs a `Module` representation of `self`, consuming `self`. Any commands are discarded.
+  pub fn construct_module(self) -> Result<Module, ParseErrorKind> {
+    self.construct_module_with_commands().map(|(module, _)| module)
+  }
+
+  /// Constructs a `Module` representation of `self`, consuming `self`, together with the commands of the module in
+  /// the order they appear.
+  pub fn construct_module_with_commands(self) -> Result<(Module, Vec<Command>), ParseErrorKind> {
+    self.construct_within(HashMap::new(), &HashMap::new())
+  }
+
+  /// Constructs the module like `ModuleAST::construct_module_with_commands(…)`, as a submodule of the modules whose
+  /// visible sorts and symbols are `enclosing_sorts` and `enclosing_symbols`. See the module documentation.
+  ///
+  /// ToDo: Nothing constructed before an error is reclaimed.
+  fn construct_within(
+    mut self,
+    enclosing_sorts  : HashMap<IString, SortPtr>,
+    enclosing_symbols: &HashMap<IString, SymbolPtr>
+  ) -> Result<(Module, Vec<Command>), ParseErrorKind>
+  {
     // The items of the module are binned according to type before processing.
     let mut modules   : Vec<BxModuleAST>                = Vec::new();
     let mut var_decls : Vec<BxVariableDeclarationAST>   = Vec::new();
@@ -49,6 +91,7 @@ impl ModuleAST {
     let mut rule_decls: Vec<BxRuleDeclarationAST>       = Vec::new();
     let mut eq_decls  : Vec<BxEquationDeclarationAST>   = Vec::new();
     let mut mb_decls  : Vec<BxMembershipDeclarationAST> = Vec::new();
+    let mut commands  : Vec<BxCommandAST>               = Vec::new();
 
     for item in self.items.drain(..) {
       match item {
@@ -58,11 +101,11 @@ impl ModuleAST {
         ItemAST::SortDecl(i)   => sort_decls.push(i),
         ItemAST::Rule(i)       => rule_decls.push(i),
         ItemAST::Equation(i)   => eq_decls.push(i),
-        ItemAST::Membership(i) => mb_decls.push(i)
+        ItemAST::Membership(i) => mb_decls.push(i),
+        ItemAST::Command(i)    => commands.push(i)
       }
     }
 
-    // Todo: How submodules work determines how we construct modules. See [the Design doc](doc/DesignNotes.md).
     /*
     Sorts can be declared explicitly, or they can be implicitly declared by being referenced without declaration.
     The transitive closure of the subsort relation and the construction of the connected components is done in
@@ -71,151 +114,126 @@ impl ModuleAST {
     Every sort that is encountered is checked to see if it has already been created. If it has, the existing sort
     object is fetched. Otherwise, the sort is created.
     */
-    let mut sorts  : SortCollection              = SortCollection::new();
+    let mut sorts  : SortCollection              = SortCollection::within(enclosing_sorts);
     let mut symbols: HashMap<IString, SymbolPtr> = HashMap::new();
 
     // Sort Declarations
     for sort_decl in sort_decls.iter() {
       for subsort_name in sort_decl.sorts_lt.iter() {
         // Get or insert new subsort.
-        let subsort = sorts.get_or_create_sort(*subsort_name);
+        sorts.declare_sort(*subsort_name);
         for supersort_name in sort_decl.sorts_gt.iter() {
-          assert_ne!(*subsort_name, *supersort_name, "sort declared as a subsort of itself");
-
-          // Get or insert new supersort.
-          let supersort = sorts.get_or_create_sort(*supersort_name);
-          // ToDo: Check that this constraint has not already been declared by checking that `subsort.supersorts` does
-          //       not already contain `supersort` (and vice versa).
-          unsafe {
-            (*subsort).supersorts.push(supersort);
-            (*supersort).subsorts.push(subsort);
+          if *subsort_name == *supersort_name {
+            return Err(ParseErrorKind::SelfSubsort(*subsort_name));
           }
+          sorts.add_subsort(*subsort_name, *supersort_name);
         }
       }
     }
+    sorts.compute_closure().map_err(ParseErrorKind::SubsortCycle)?;
 
     // Variable Declarations
-    for var_decl in var_decls {
+    for mut var_decl in var_decls {
       construct_symbol_from_decl(
         &mut symbols,
         &mut sorts,
-        var_decl.name,
-        var_decl.sort_spec,
-        var_decl.arity,
-        var_decl.attributes,
+        var_decl.take_parts(),
         CoreSymbolType::Variable
-      );
+      )?;
     }
 
     // Symbol Declarations
-    for sym_decl in sym_decls {
+    for sym_decl in sym_decls.iter_mut() {
       construct_symbol_from_decl(
         &mut symbols,
         &mut sorts,
-        sym_decl.name,
-        sym_decl.sort_spec,
-        sym_decl.arity,
-        sym_decl.attributes,
+        sym_decl.take_parts(),
         CoreSymbolType::Standard
-      );
+      )?;
     }
 
-
-    // Rule Declarations
-    let mut rules: Vec<PreEquation> = Vec::new();
-    for rule_decl in rule_decls {
-      let lhs  = rule_decl.lhs.construct(&mut symbols);
-      let rhs  = rule_decl.rhs.construct(&mut symbols);
-      let rule = PreEquationKind::Rule{
-        rhs_term: Box::new(rhs),
-      };
-      let conditions: Conditions
-          = rule_decl.conditions
-                     .unwrap_or_default()
-                     .into_iter()
-                     .map(|c| Box::new(c.construct(&mut symbols, &mut sorts)))
-                     .collect();
-
-      let pre_equation = PreEquation{
-        name      : None,
-        attributes: Default::default(),
-        conditions,
-        lhs_term  : Box::new(lhs),
-        kind      : rule,
-      };
-
-      rules.push(pre_equation);
+    // The symbols of the enclosing modules that are not hidden are visible from here on. They are removed again once
+    // the module is constructed, because they belong to the enclosing modules.
+    let mut imported: Vec<IString> = Vec::new();
+    for (name, symbol) in enclosing_symbols.iter() {
+      if !symbols.contains_key(name) {
+        symbols.insert(*name, *symbol);
+        imported.push(*name);
+      }
     }
 
+    // Infix terms are grouped once every symbol is declared, because the grouping depends on the precedence and
+    // gather attributes of the operators. See the `infix` module.
+    for sym_decl in sym_decls.iter_mut() {
+      for attribute in sym_decl.attributes.iter_mut() {
+        attribute.resolve_infix(&symbols)?;
+      }
+    }
+    for rule_decl in rule_decls.iter_mut() {
+      rule_decl.lhs.resolve_infix(&symbols)?;
+      rule_decl.rhs.resolve_infix(&symbols)?;
+      for condition in rule_decl.conditions.iter_mut().flatten() {
+        condition.resolve_infix(&symbols)?;
+      }
+    }
+    for eq_decl in eq_decls.iter_mut() {
+      eq_decl.lhs.resolve_infix(&symbols)?;
+      eq_decl.rhs.resolve_infix(&symbols)?;
+      for condition in eq_decl.conditions.iter_mut().flatten() {
+        condition.resolve_infix(&symbols)?;
+      }
+    }
+    for mb_decl in mb_decls.iter_mut() {
+      mb_decl.lhs.resolve_infix(&symbols)?;
+      for condition in mb_decl.conditions.iter_mut().flatten() {
+        condition.resolve_infix(&symbols)?;
+      }
+    }
+    for command in commands.iter_mut() {
+      command.resolve_infix(&symbols)?;
+    }
 
-    // Equation Declarations
-    let mut equations: Vec<PreEquation> = Vec::new();
-    for eq_decl in eq_decls {
-      let lhs      = eq_decl.lhs.construct(&mut symbols);
-      let rhs      = eq_decl.rhs.construct(&mut symbols);
-      let equation = PreEquationKind::Equation{
-        rhs_term: Box::new(rhs),
-      };
-      let conditions: Conditions
-          = eq_decl.conditions
-                   .unwrap_or_default()
-                   .into_iter()
-                   .map(|c| Box::new(c.construct(&mut symbols, &mut sorts)))
-                   .collect();
-
-      let pre_equation = PreEquation{
-        name      : None,
-        attributes: Default::default(),
-        conditions,
-        lhs_term  : Box::new(lhs),
-        kind      : equation,
-      };
-
-      equations.push(pre_equation);
+    // Identity elements are constructed once every symbol is declared, because they can be any term.
+    for sym_decl in sym_decls.iter() {
+      if let Some(identity) = AttributeAST::identity(&sym_decl.attributes) {
+        let identity = Box::new(identity.construct(&mut symbols));
+        unsafe{ (*symbols[&sym_decl.name]).identity = Some(identity); }
+      }
     }
 
 
-    // Membership Axiom Declarations
-    let mut membership: Vec<PreEquation> = Vec::new();
-    for mb_decl in mb_decls {
-      let lhs        = mb_decl.lhs.construct(&mut symbols);
-      let rhs        = mb_decl.rhs.construct(&mut sorts);
-      let membership = PreEquationKind::Membership{
-        sort_spec: rhs,
-      };
-      let conditions: Conditions
-          = mb_decl.conditions
-                   .unwrap_or_default()
-                   .into_iter()
-                   .map(|c| Box::new(c.construct(&mut symbols, &mut sorts)))
-                   .collect();
-
-      let pre_equation = PreEquation{
-        name      : None,
-        attributes: Default::default(),
-        conditions,
-        lhs_term  : Box::new(lhs),
-        kind      : membership,
-      };
-
-      equations.push(pre_equation);
+    // Statements
+    let rules: Vec<PreEquation> =
+        rule_decls.into_iter().map(|decl| decl.construct(&mut symbols, &mut sorts)).collect();
+    let equations: Vec<PreEquation> =
+        eq_decls.into_iter().map(|decl| decl.construct(&mut symbols, &mut sorts)).collect();
+    let membership: Vec<PreEquation> =
+        mb_decls.into_iter().map(|decl| decl.construct(&mut symbols, &mut sorts)).collect();
+
+    // Commands
+    let commands: Vec<Command> = commands.iter().map(|command| command.construct(&mut symbols)).collect();
+
+    let visible_symbols = symbols.clone();
+    for name in imported {
+      symbols.remove(&name);
     }
 
-    let mut new_module = Module{
-      name      : Default::default(),
-      submodules: vec![],
-      status    : Default::default(),
-      sorts,
-      kinds     : vec![],
-      symbols,
-
-      rules,
-      equations,
-      membership,
-    };
-    unsafe {
-      new_module.compute_kind_closures();
+    // `Module` implements `Drop`, so the remaining fields cannot be moved out of a default module with `..`.
+    let mut new_module = Module::default();
+    new_module.name       = self.name;
+    new_module.sorts      = sorts;
+    new_module.symbols    = symbols;
+    new_module.rules      = rules;
+    new_module.equations  = equations;
+    new_module.membership = membership;
+    new_module.close_theory();
+
+    // Submodules
+    for module in modules {
+      let (submodule, _) = module.construct_within(new_module.sorts.visible(), &visible_symbols)?;
+      new_module.submodules.push(Box::new(submodule));
     }
-    new_module
+
+    Ok((new_module, commands))
   }
 }
@@ -9,6 +9,8 @@ use crate::{abstractions::{
   HashMap,
   Integer,
   IString,
+  NatSet,
+  NaturalNumber,
   rc_cell,
   RcCell
 }, heap_construct, parser::ast::{
@@ -18,14 +20,20 @@ use crate::{abstractions::{
   symbol::{
     SymbolPtr,
     Symbol,
-    symbol_for_symbol_type
+    is_infix_name,
+    symbol_for_symbol_type,
+    UNINDEXED
   },
   symbol_type::{
     CoreSymbolType,
     SymbolType
   },
 }};
-use crate::core::sort::collection::SortCollection;
+use crate::core::sort::{
+  collection::SortCollection,
+  sort_spec::SortSpec
+};
+use crate::parser::parse_error::ParseErrorKind;
 
 pub(crate) type BxSymbolDeclarationAST = Box<SymbolDeclarationAST>;
 
@@ -34,6 +42,7 @@ pub(crate) struct SymbolDeclarationAST {
   pub attributes: Vec<AttributeAST>,
   pub arity     : Integer,               // -1 means variadic
   pub sort_spec : Option<BxSortSpecAST>, // Empty is the special "None" sort.
+  pub doc       : Option<String>,
 }
 
 pub(crate) type BxVariableDeclarationAST = Box<VariableDeclarationAST>;
@@ -43,6 +52,42 @@ pub(crate) struct VariableDeclarationAST {
   pub attributes: Vec<AttributeAST>,
   pub arity     : Integer,               // -1 means variadic, -2 means unspecified
   pub sort_spec : Option<BxSortSpecAST>, // Empty is the special "Any" sort
+  pub doc       : Option<String>,
+}
+
+/// The parts of a variable or symbol declaration that `construct_symbol_from_decl(…)` constructs the symbol from.
+pub(crate) struct DeclarationParts<'a> {
+  pub name      : IString,
+  pub sort_spec : Option<BxSortSpecAST>,
+  pub arity     : Integer,
+  pub attributes: &'a [AttributeAST],
+  pub doc       : Option<String>,
+}
+
+impl SymbolDeclarationAST {
+  /// The parts of the declaration, taking its sort spec and doc comment.
+  pub fn take_parts(&mut self) -> DeclarationParts<'_> {
+    DeclarationParts {
+      name      : self.name,
+      sort_spec : self.sort_spec.take(),
+      arity     : self.arity,
+      attributes: &self.attributes,
+      doc       : self.doc.take(),
+    }
+  }
+}
+
+impl VariableDeclarationAST {
+  /// The parts of the declaration, taking its sort spec and doc comment.
+  pub fn take_parts(&mut self) -> DeclarationParts<'_> {
+    DeclarationParts {
+      name      : self.name,
+      sort_spec : self.sort_spec.take(),
+      arity     : self.arity,
+      attributes: &self.attributes,
+      doc       : self.doc.take(),
+    }
+  }
 }
 
 
@@ -50,56 +95,89 @@ pub(crate) struct VariableDeclarationAST {
 pub fn construct_symbol_from_decl(
   symbols         : &mut HashMap<IString, SymbolPtr>,
   sorts           : &mut SortCollection,
-  name            : IString,
-  sort_spec       : Option<BxSortSpecAST>,
-  arity           : i16,
-  attributes_ast  : Vec<AttributeAST>,
+  declaration     : DeclarationParts,
   core_symbol_type: CoreSymbolType,
-)
+) -> Result<(), ParseErrorKind>
 {
-  let sort_spec = sort_spec.map(|s| s.construct(sorts));
+  let DeclarationParts { name, sort_spec, arity, attributes: attributes_ast, doc } = declaration;
+  let sort_spec  = sort_spec.map(|s| s.construct(sorts));
+  let attributes = AttributeAST::construct_attributes(attributes_ast);
+  let entry      = match symbols.entry(name) {
+    Entry::Occupied(o) => {
+      let symbol = unsafe{ &mut **o.get() };
+      return match sort_spec {
+        Some(sort_spec) if symbol.accepts_overload(&sort_spec, core_symbol_type, attributes) => {
+          symbol.overloads.push(sort_spec);
+          Ok(())
+        }
+        _ => Err(ParseErrorKind::DuplicateSymbol(name))
+      };
+    }
+    Entry::Vacant(v) => v
+  };
+
   // If an explicit arity is given, use it.
   let arity = match &sort_spec {
     None => arity,
     Some(sort_spec) => {
+      if let SortSpec::Functor { arg_sorts, .. } = sort_spec.as_ref() {
+        if arity >= 0 && arity as usize != arg_sorts.len() {
+          return Err(
+            ParseErrorKind::ArityMismatch {
+              symbol   : name,
+              declared : arity,
+              sort_spec: sort_spec.arity()
+            }
+          );
+        }
+      }
       max(arity, sort_spec.arity())
     }
   };
 
+  // Frozen positions are 1-based in the source and 0-based in the symbol.
+  let mut frozen = NatSet::new();
+  for position in AttributeAST::frozen_positions(attributes_ast) {
+    if position == 0 || (arity >= 0 && position > arity as NaturalNumber) {
+      return Err(ParseErrorKind::FrozenPosition { symbol: name, position });
+    }
+    frozen.insert(position as usize - 1);
+  }
+
+  // Only an infix symbol gathers its arguments, and it has two.
+  let gather = AttributeAST::gather(attributes_ast).cloned().unwrap_or_default();
+  if !gather.is_empty() && (!is_infix_name(&name) || gather.len() != 2) {
+    return Err(ParseErrorKind::InvalidGather(name));
+  }
+  let precedence = AttributeAST::precedence(attributes_ast);
+
   // Construct the symbol type.
-  let attributes  = AttributeAST::construct_attributes(&attributes_ast);
+  let latex       = AttributeAST::latex_macro(attributes_ast);
   let symbol_type = SymbolType {
     core_type: core_symbol_type,
     attributes,
   };
   let theory_symbol = symbol_for_symbol_type(&symbol_type);
 
-  match symbols.entry(name) {
-
-    Entry::Occupied(s) => {
-      // ToDo: Under what circumstances would a symbol already exist? If the symbol is already declared, this
-      //       should be a duplicate declaration and thus an error.
-      panic!("duplicate symbol declaration")
-      // let mut symbol       = s.get().borrow_mut();
-      // symbol.arity         = arity;
-      // symbol.sort_spec     = sort_spec;
-      // symbol.symbol_type   = symbol_type;
-      // symbol.theory_symbol = Some(theory_symbol);
-    },
-
-    Entry::Vacant(v) => {
-      // The symbol doesn't exist. Create it.
-      let mut s = heap_construct!(
-            Symbol{
-              name,
-              arity,
-              symbol_type,
-              sort_spec,
-              theory_symbol: Some(theory_symbol),
-            }
-          );
-      v.insert(s);
-    }
-
-  };
+  // The symbol doesn't exist. Create it.
+  let s = heap_construct!(
+        Symbol{
+          name,
+          arity,
+          symbol_type,
+          sort_spec,
+          overloads          : vec![],
+          index_within_module: UNINDEXED,
+          hash_seed          : 0,
+          identity           : None,
+          doc,
+          latex,
+          frozen,
+          precedence,
+          gather,
+          theory_symbol,
+        }
+      );
+  entry.insert(s);
+  Ok(())
 }
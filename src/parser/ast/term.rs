@@ -11,7 +11,13 @@ use crate::{abstractions::{
   rc_cell,
   NaturalNumber
 }, builtin::{
+  equality_symbol::{
+    is_equality_name,
+    EqualitySymbol,
+    NOT_EQUAL_NAME
+  },
   integer_symbol::IntegerSymbol,
+  rational_symbol::RationalSymbol,
   string_symbol::StringSymbol
 }, heap_construct, theory::{
   symbol::{
@@ -25,6 +31,44 @@ use crate::{abstractions::{
   }
 }};
 
+/// Resolves the escape sequences `\\`, `\"`, `\n`, and `\t` in the text between the quotes of a string literal.
+/// Any other escaped character stands for itself.
+pub(crate) fn unescape_string_literal(text: &str) -> String {
+  let mut result = String::with_capacity(text.len());
+  let mut chars  = text.chars();
+  while let Some(c) = chars.next() {
+    if c != '\\' {
+      result.push(c);
+      continue;
+    }
+    match chars.next() {
+      Some('n')   => result.push('\n'),
+      Some('t')   => result.push('\t'),
+      Some(other) => result.push(other),
+      None        => result.push('\\'),
+    }
+  }
+  result
+}
+
+/// A new symbol named `name`, which is a built-in equality predicate if `name` is the name of one.
+fn new_symbol(name: IString) -> SymbolPtr {
+  match is_equality_name(&name) {
+    true  => heap_construct!(EqualitySymbol::new(name.as_str() == NOT_EQUAL_NAME)),
+    false => heap_construct!(Symbol::new(name))
+  }
+}
+
+/// Whether `name` is the name of a built-in boolean literal, `true` or `false`.
+fn is_boolean_literal(name: &str) -> bool {
+  name == "true" || name == "false"
+}
+
+/// Whether `name` is the name of a built-in symbol, which a module can use without declaring it.
+fn is_builtin_name(name: &str) -> bool {
+  is_boolean_literal(name) || is_equality_name(name)
+}
+
 pub(crate) type BxTermAST = Box<TermAST>;
 pub(crate) enum TermAST {
   /// An identifier is a variable or symbol.
@@ -36,25 +80,84 @@ pub(crate) enum TermAST {
     tail: Vec<BxTermAST>
   },
 
+  /// Operands separated by infix operators, as in `a + b * c`, before they are grouped by
+  /// `TermAST::resolve_infix(…)`. The operators are the names of their symbols, like `_+_`. See the `infix` module.
+  Infix{
+    operands : Vec<BxTermAST>,
+    operators: Vec<IString>
+  },
+
   // Literals are converted into symbols. See `symbol_type.rs`.
   StringLiteral(String),
   NaturalNumber(NaturalNumber),
+  /// A fraction with a nonzero denominator, as in `1/3`.
+  Rational(NaturalNumber, NaturalNumber),
 }
 
 impl TermAST {
+
+  /**
+  Constructs the term like `TermAST::construct(…)`, but only from the symbols already in `symbols`, which are not
+  modified. The first name that is not among them is returned as the error instead of being made a new symbol. A
+  built-in equality predicate that is not in `symbols` is taken from `builtins`, where it is made on first use.
+  */
+  pub fn construct_existing(
+    &self,
+    symbols : &HashMap<IString, SymbolPtr>,
+    builtins: &mut HashMap<IString, SymbolPtr>
+  ) -> Result<Term, IString>
+  {
+    if let Some(name) = self.unknown_identifier(symbols) {
+      return Err(name);
+    }
+    let mut resolve = | name: IString | match symbols.get(&name) {
+      Some(symbol)                      => Some(*symbol),
+      None if is_boolean_literal(&name) => None,
+      None                              => Some(*builtins.entry(name).or_insert_with(|| new_symbol(name)))
+    };
+    Ok(self.construct_with(&mut resolve))
+  }
+
+  /// The first identifier of the term, in left-to-right order, that is not in `symbols`.
+  fn unknown_identifier(&self, symbols: &HashMap<IString, SymbolPtr>) -> Option<IString> {
+    match self {
+      TermAST::Identifier(name) if !symbols.contains_key(name) && !is_builtin_name(name) => Some(*name),
+      TermAST::Application { head, tail } => {
+        head.unknown_identifier(symbols)
+            .or_else(|| tail.iter().find_map(|t| t.unknown_identifier(symbols)))
+      }
+      _ => None
+    }
+  }
+
+  /**
+  Constructs the `Term` the AST represents, looking its names up in `symbols`. A name that is not in `symbols` is made
+  a new symbol and added to `symbols`, except for `true` and `false`, which are the built-in boolean literals. The
+  names `_==_` and `_=/=_` are made the built-in equality predicates, unless the module declares symbols of those
+  names, and are added to `symbols` like any other new symbol, so that a module has one of each, which it frees when
+  it is dropped. The built-in `true` is also the implicit right-hand side of a boolean condition, as in
+  `if lt(X, 10)`, so an equation `lt(X, Y) = true` can satisfy the condition.
+  */
   pub fn construct(&self, symbols: &mut HashMap<IString, SymbolPtr>) -> Term {
+    let mut resolve = | name: IString | match symbols.entry(name) {
+      Entry::Occupied(s)                            => Some(*s.get()),
+      Entry::Vacant(_) if is_boolean_literal(&name) => None,
+      Entry::Vacant(v)                              => Some(*v.insert(new_symbol(name)))
+    };
+    self.construct_with(&mut resolve)
+  }
+
+  /// Constructs the term, resolving each name to its symbol with `resolve`, which gives `None` for the built-in
+  /// boolean literals.
+  fn construct_with(&self, resolve: &mut dyn FnMut(IString) -> Option<SymbolPtr>) -> Term {
     // ToDo: How do we construct term attributes.
 
     match self {
 
       TermAST::Identifier(name) => {
-        let symbol: SymbolPtr = match symbols.entry(*name) {
-          Entry::Occupied(s) => *s.get(),
-          Entry::Vacant(v) => {
-            let s = heap_construct!(Symbol::new(*name));
-            v.insert(s);
-            s
-          }
+        let Some(symbol) = resolve(*name) else {
+          let literal = if name.as_str() == "true" { Term::true_literal() } else { Term::false_literal() };
+          return *literal;
         };
         Term {
           term_node : TermNode::Symbol(symbol),
@@ -66,8 +169,8 @@ impl TermAST {
 
         Term {
           term_node: TermNode::Application {
-            head: Box::new(head.construct(symbols)),
-            tail: tail.into_iter().map(|t| Box::new(t.construct(symbols))).collect(),
+            head: Box::new(head.construct_with(resolve)),
+            tail: tail.iter().map(|t| Box::new(t.construct_with(resolve))).collect(),
           },
           attributes: TermAttributes::default()
         }
@@ -94,6 +197,19 @@ impl TermAST {
         }
       }
 
+      TermAST::Rational(numerator, denominator) => {
+        let symbol = heap_construct!(RationalSymbol::new(*numerator, *denominator));
+
+        Term {
+          term_node: TermNode::Symbol(symbol),
+          attributes: TermAttributes::default()
+        }
+      }
+
+      TermAST::Infix { .. } => {
+        unreachable!("infix terms are grouped by `TermAST::resolve_infix(…)` before they are constructed")
+      }
+
     }
   }
 }
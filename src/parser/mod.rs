@@ -7,12 +7,394 @@ checking uniqueness, types, etc.
 
 mod ast;
 mod parser;
+pub mod parse_error;
+pub mod sexpr;
+
+use lalrpop_util::lexer::Token;
+
+use crate::{
+  core::{
+    command::Command,
+    edit_error::EditError,
+    module::Module
+  },
+  parser::ast::ItemAST,
+  theory::term::Term
+};
+
+pub use parse_error::ParseErrorKind;
+pub use sexpr::parse_sexpr;
+use parse_error::find_unclosed_delimiter;
+
+pub type ParseError<'input> = lalrpop_util::ParseError<usize, Token<'input>, ParseErrorKind>;
+
+/// Parses `input` and constructs the `Module` it defines.
+pub fn parse_to_module(input: &str) -> Result<Module, ParseError<'_>> {
+  let parser = parser::ModuleParser::new();
+  let module_ast = parser.parse(input, input).map_err(|error| explain_syntax_error(input, error))?;
+  module_ast.construct_module().map_err(|error| ParseError::User { error })
+}
+
+/// Parses `input` and constructs the `Module` it defines, together with the commands that appear in it.
+pub fn parse_to_module_with_commands(input: &str) -> Result<(Module, Vec<Command>), ParseError<'_>> {
+  let parser = parser::ModuleParser::new();
+  let module_ast = parser.parse(input, input).map_err(|error| explain_syntax_error(input, error))?;
+  module_ast.construct_module_with_commands().map_err(|error| ParseError::User { error })
+}
+
+/// Parses `input` as a single term over the symbols of `module`. Unlike the terms of a module, the term cannot
+/// introduce symbols: a name that is not a symbol of `module` is a `ParseErrorKind::UnknownSymbol` error.
+pub fn parse_to_term<'input>(input: &'input str, module: &Module) -> Result<Term, ParseError<'input>> {
+  let parser   = parser::TermParser::new();
+  let mut term_ast = parser.parse(input, input).map_err(|error| explain_syntax_error(input, error))?;
+  term_ast.resolve_infix(&module.symbols).map_err(|error| ParseError::User { error })?;
+  term_ast.construct_existing(&module.symbols, &mut module.builtin_symbols.borrow_mut())
+          .map_err(|name| ParseError::User { error: ParseErrorKind::UnknownSymbol(name) })
+}
+
+/// Parses `input` as exactly one item of a module, like a declaration or an equation, to be added to an existing
+/// module. See `Module::add_statement(…)`.
+pub(crate) fn parse_item(input: &str) -> Result<ItemAST, EditError> {
+  let parser = parser::ModuleParser::new();
+  let mut module_ast = parser.parse(input, input).map_err(|error| explain_syntax_error(input, error))?;
+  if module_ast.items.len() != 1 {
+    return Err(EditError::ItemCount(module_ast.items.len()));
+  }
+  Ok(module_ast.items.pop().unwrap())
+}
+
+/// Replaces a syntax error caused by an unterminated string literal or an unmatched parenthesis, which LALRPOP
+/// reports wherever it happens to notice, with an error giving the position of the opening delimiter.
+fn explain_syntax_error<'input>(input: &'input str, error: ParseError<'input>) -> ParseError<'input> {
+  match find_unclosed_delimiter(input) {
+    Some(kind) => ParseError::User { error: kind },
+    None       => error
+  }
+}
 
 
 #[cfg(test)]
 mod tests {
+  use crate::{
+    abstractions::{
+      IString,
+      RcCell
+    },
+    core::{
+      command::Command,
+      format::{
+        FormatStyle,
+        Formattable
+      },
+      pre_equation::PreEquationKind
+    }
+  };
   use super::*;
 
+  #[test]
+  fn doc_comments_attach_to_declarations() {
+    let module = parse_to_module(
+      "
+      // An ordinary comment is not documentation.
+      symbol g;
+
+      /// Doubles its argument.
+      ///   Indentation after the first space is kept.
+      symbol f;
+
+      /// Unfolds `f`.
+      equation f(g) = g(g);
+      "
+    ).unwrap();
+
+    let f = module.symbols[&IString::from("f")];
+    let g = module.symbols[&IString::from("g")];
+    unsafe {
+      assert_eq!(
+        (*f).doc.as_deref(),
+        Some("Doubles its argument.\n  Indentation after the first space is kept.")
+      );
+      assert_eq!((*g).doc, None);
+    }
+    assert_eq!(module.equations[0].doc.as_deref(), Some("Unfolds `f`."));
+  }
+
+  #[test]
+  fn statements_record_their_source_spans() {
+    let source = "
+      symbol f;
+      variable X;
+
+      rule f(X) => X;
+      /// Documented.
+      rule [double]
+        f(f(X)) => f(X);
+      equation f(g) = g;
+    ";
+    let module = parse_to_module(source).unwrap();
+    let span   = module.rules[1].span.unwrap();
+    // The span of the second rule starts at its keyword, after its doc comment, and covers both of its lines.
+    assert_eq!(&source[span.start..span.end], "rule [double]\n        f(f(X)) => f(X);");
+    assert_eq!((span.line, span.column), (7, 7));
+
+    let span = module.equations[0].span.unwrap();
+    assert_eq!(&source[span.start..span.end], "equation f(g) = g;");
+    assert!(module.statements().all(|statement| statement.span.is_some()));
+  }
+
+  #[test]
+  fn rational_literals_are_reduced_fractions() {
+    let (_module, commands) = parse_to_module_with_commands(
+      "
+      symbol f;
+
+      reduce f(1/3, 2/6, 1/2, 3/1, 3);
+      "
+    ).unwrap();
+    let term = match &commands[0] {
+      Command::Reduce(term) => term.dagify(),
+      _ => unreachable!()
+    };
+    assert_eq!(term.borrow().to_string(), "f(1/3, 1/3, 1/2, 3/1, 3)");
+    assert_eq!(term.borrow().repr(FormatStyle::Debug), "f(Rational(1/3), Rational(1/3), Rational(1/2), Rational(3/1), \
+                                                         NaturalNumber(3))");
+    let args = &term.borrow().args;
+    // Equal fractions are one shared constant, and a whole rational is not the natural number.
+    assert!(RcCell::ptr_eq(&args[0], &args[1]));
+    assert!(!args[0].borrow().equals(&args[2].borrow()));
+    assert!(!args[3].borrow().equals(&args[4].borrow()));
+    assert_ne!(args[3].borrow_mut().structural_hash(), args[4].borrow_mut().structural_hash());
+
+    assert!(matches!(
+      parse_to_module("symbol f; equation f(1/0) = f;"),
+      Err(ParseError::User { error: ParseErrorKind::ZeroDenominator(1) })
+    ));
+  }
+
+  #[test]
+  fn only_symbols_are_applied() {
+    for source in ["reduce f(a)(b);", "reduce (f(a))(b);", "reduce 1(a);", "reduce \"s\"(a);"] {
+      assert!(matches!(
+        parse_to_module_with_commands(source),
+        Err(ParseError::User { error: ParseErrorKind::ApplicationHead })
+      ));
+    }
+    // A parenthesized symbol is still a symbol.
+    let (_, commands) = parse_to_module_with_commands("reduce (f)(a);").unwrap();
+    let term = match &commands[0] {
+      Command::Reduce(term) => term.dagify(),
+      _ => unreachable!()
+    };
+    assert_eq!(term.borrow().to_string(), "f(a)");
+  }
+
+  #[test]
+  fn submodules_see_the_sorts_and_symbols_of_their_parents() {
+    let (module, _) = parse_to_module_with_commands(
+      "
+      sort A;
+      symbol a :: A;
+      symbol h;
+
+      mod Sub {
+        symbol g :: A -> A;
+        symbol h :: A;
+        equation g(a) = h;
+
+        mod Inner {
+          equation g(h) = a;
+        }
+      }
+      "
+    ).unwrap();
+    assert_eq!(module.submodules.len(), 1);
+    let sub = &module.submodules[0];
+    assert_eq!(sub.name, IString::from("Sub"));
+
+    // `g` is declared with the parent's sort, and `a` is the parent's symbol.
+    let sort_a = module.sort_named("A").unwrap();
+    let g      = sub.symbol_named("g").unwrap();
+    assert_eq!(unsafe{ (*g).range_sort() }, Some(sort_a));
+    assert!(sub.sort_named("A").is_none() && sub.symbol_named("a").is_none());
+    let equation = &sub.equations[0];
+    assert_eq!(equation.lhs_term.to_string(), "g(a)");
+    let rhs = match &equation.kind {
+      PreEquationKind::Equation { rhs_term } => rhs_term.top_symbol(),
+      _ => unreachable!()
+    };
+    // The submodule's `h` hides the parent's.
+    assert_eq!(rhs, sub.symbol_named("h"));
+    assert_ne!(rhs, module.symbol_named("h"));
+
+    // Scopes nest, and the nearest declaration is found.
+    let inner = &sub.submodules[0];
+    assert_eq!(inner.equations[0].lhs_term.to_string(), "g(h)");
+    assert!(inner.symbols().next().is_none());
+    let lhs = inner.equations[0].lhs_term.dagify();
+    assert_eq!(lhs.borrow().args[0].borrow().top_symbol, sub.symbol_named("h").unwrap());
+
+    assert_eq!(sub.reduce_dag(&equation.lhs_term.dagify()).borrow().to_string(), "h");
+  }
+
+  #[test]
+  fn construction_errors_carry_their_context() {
+    let user_error = |input| match parse_to_module(input) {
+      Err(ParseError::User { error }) => error,
+      Err(error)                      => panic!("expected a user error, got {}", error),
+      Ok(_)                           => panic!("expected an error")
+    };
+
+    assert_eq!(user_error("symbol f/70000;"), ParseErrorKind::ArityTooLarge(70000));
+    assert_eq!(user_error("symbol f; variable f;"), ParseErrorKind::DuplicateSymbol(IString::from("f")));
+    assert_eq!(user_error("sort A < B, A;"), ParseErrorKind::SelfSubsort(IString::from("A")));
+    assert_eq!(
+      user_error("sort A < B; sort C < A; sort B < A;").to_string(),
+      "the subsort declarations form a cycle: A < B < A."
+    );
+    assert_eq!(
+      user_error("symbol f/2 :: A -> B;"),
+      ParseErrorKind::ArityMismatch { symbol: IString::from("f"), declared: 2, sort_spec: 1 }
+    );
+    // Syntax errors are still reported by the parser itself.
+    assert!(matches!(parse_to_module("symbol ;"), Err(ParseError::UnrecognizedToken { .. })));
+  }
+
+  #[test]
+  fn infix_terms_group_by_precedence_and_gathering() {
+    let (module, commands) = parse_to_module_with_commands(
+      "
+      symbol _+_ [prec 33];
+      symbol _*_ [prec 31];
+      symbol _-_ [prec 33, gather (E e)];
+      symbol _^_ [gather (e E)];
+
+      reduce a + b * c;
+      reduce a * b + c;
+      reduce (a + b) * c;
+      reduce a - b - c;
+      reduce a ^ b ^ c;
+      reduce f(a - b * c, _+_(a, b));
+      equation double(x) = x + x;
+      "
+    ).unwrap();
+    let terms: Vec<String> = commands.iter()
+                                     .map(|command| match command {
+                                       Command::Reduce(term) => term.to_string(),
+                                       _ => unreachable!()
+                                     })
+                                     .collect();
+    assert_eq!(
+      terms,
+      vec![
+        "_+_(a, _*_(b, c))",
+        "_+_(_*_(a, b), c)",
+        "_*_(_+_(a, b), c)",
+        "_-_(_-_(a, b), c)",
+        "_^_(a, _^_(b, c))",
+        "f(_-_(a, _*_(b, c)), _+_(a, b))",
+      ]
+    );
+    let PreEquationKind::Equation { rhs_term } = &module.equations[0].kind else { unreachable!() };
+    assert_eq!(rhs_term.to_string(), "_+_(x, x)");
+    assert_eq!(parse_to_term("a + b * c", &module).unwrap().to_string(), "_+_(a, _*_(b, c))");
+
+    let user_error = |input| match parse_to_module(input) {
+      Err(ParseError::User { error }) => error,
+      _                               => panic!("expected a user error")
+    };
+    assert_eq!(
+      user_error("symbol _-_ [gather (e e)]; reduce a - b - c;"),
+      ParseErrorKind::InfixGrouping(IString::from("_-_"))
+    );
+    assert_eq!(user_error("symbol f [gather (E e)];"), ParseErrorKind::InvalidGather(IString::from("f")));
+    assert_eq!(user_error("symbol _+_ [gather (E)];"), ParseErrorKind::InvalidGather(IString::from("_+_")));
+    assert_eq!(user_error("symbol _+_ [gather (E x)];"), ParseErrorKind::GatherPattern(IString::from("x")));
+  }
+
+  #[test]
+  fn overloaded_declarations_are_merged_into_one_symbol() {
+    let (module, commands) = parse_to_module_with_commands(
+      "
+      sort A;
+      sort B;
+      sort C;
+      sort X;
+      sort Y;
+      sort Z;
+      symbol f :: A A -> X;
+      symbol f :: B B -> Y;
+      symbol f :: C C -> Z;
+      symbol p :: B;
+      symbol q :: B;
+
+      reduce f(p, q);
+      "
+    ).unwrap();
+
+    let f = unsafe{ &*module.symbols[&IString::from("f")] };
+    let declarations: Vec<String> = f.declarations()
+                                     .map(|declaration| declaration.repr(FormatStyle::Default))
+                                     .collect();
+    assert_eq!(declarations, vec!["A A -> X", "B B -> Y", "C C -> Z"]);
+
+    let term = match &commands[0] {
+      Command::Reduce(term) => term.dagify(),
+      _ => unreachable!()
+    };
+    let sort = term.borrow().sort().map(|sort| unsafe{ (*sort).name.as_str() });
+    assert_eq!(sort, Some("Y"));
+
+    // Only functor sorts of the same arity overload a symbol.
+    assert!(parse_to_module("symbol f :: A -> B; symbol f :: A A -> B;").is_err());
+    assert!(parse_to_module("symbol f :: A; symbol f :: B;").is_err());
+  }
+
+  #[test]
+  fn unclosed_delimiters_are_reported_where_they_open() {
+    let message = |input| parse_to_module(input).err().unwrap().to_string();
+
+    assert_eq!(
+      message("symbol f;\nreduce f(\"abc);\n// A comment.\n"),
+      "unterminated string literal starting at 2:10."
+    );
+    assert_eq!(
+      message("symbol f;\n/* An unmatched ( in a comment. */\nequation f(g(a) = a;\n"),
+      "unmatched '(' opened at 3:11."
+    );
+    // A parenthesis inside a string literal does not count.
+    assert_eq!(message("reduce f(\"(\";"), "unmatched '(' opened at 1:9.");
+  }
+
+  #[test]
+  fn commands_run_against_their_module() {
+    let (module, commands) = parse_to_module_with_commands(
+      "
+      variable X;
+      variable Y;
+      symbol plus;
+      symbol s;
+
+      equation plus(X, z) = X;
+      equation plus(X, s(Y)) = s(plus(X, Y));
+
+      reduce plus(s(z), s(s(z)));
+      match s(X) with plus(s(z), s(z));
+      match plus(X, Y) with plus(z, s(z));
+      "
+    ).unwrap();
+
+    let results: Vec<String> = module.run_commands(&commands).iter().map(|result| result.to_string()).collect();
+    assert_eq!(
+      results,
+      vec![
+        "result: s(s(s(z)))",
+        "match: X := s(z)",
+        // The subject is reduced to `s(z)` before it is matched.
+        "no match",
+      ]
+    );
+  }
+
   #[test]
   fn test_ex1() {
     let path = "examples/example1.mod2";
@@ -24,7 +406,7 @@ mod tests {
     };
 
     let parser = parser::ModuleParser::new();
-    let result =  parser.parse(text.as_str());
+    let result =  parser.parse(text.as_str(), text.as_str());
     match result {
       Ok(_) => {
         println!("SUCCESS!");
@@ -0,0 +1,205 @@
+/*!
+
+Errors detected by the parser beyond the syntax errors that LALRPOP reports itself, either in a grammar action or
+while constructing the `Module` from the AST. They are reported as the `ParseError::User` variant of `ParseError`.
+
+Unterminated string literals and unmatched parentheses are syntax errors, but LALRPOP reports them as an unexpected
+token or end of input somewhere after the mistake. When parsing fails, the input is scanned for them, and if one is
+found it is reported instead, with the position of the opening delimiter.
+
+*/
+
+use std::error::Error;
+use std::fmt::{Debug, Display, Formatter};
+
+use crate::{
+  abstractions::{
+    IString,
+    NaturalNumber
+  },
+  core::{
+    diagnostic::line_and_column,
+    sort::sort_cycle_error::SortCycleError
+  }
+};
+
+#[derive(Clone, Eq, PartialEq)]
+pub enum ParseErrorKind {
+  /// An application whose head is not a symbol, as in `f(a)(b)` or `1(a)`. Curried applications have no meaning.
+  ApplicationHead,
+  /// An explicit arity, as in `symbol f/70000;`, that is too large to be represented.
+  ArityTooLarge(NaturalNumber),
+  /// The explicit arity of a symbol disagrees with the number of arguments of its functor sort.
+  ArityMismatch {
+    symbol   : IString,
+    declared : i16,
+    sort_spec: i16
+  },
+  /// A symbol or variable declared more than once.
+  DuplicateSymbol(IString),
+  /// A `frozen(…)` position that is zero or beyond the arity of the symbol, as in `symbol f/1 [frozen(2)];`.
+  FrozenPosition {
+    symbol  : IString,
+    position: NaturalNumber
+  },
+  /// A `gather(…)` pattern other than `e`, `E`, and `&`.
+  GatherPattern(IString),
+  /// Operands of the infix operator that cannot be grouped as the precedences and gather attributes of the operators
+  /// require, as in `a - b - c` if `_-_` gathers `(e e)`.
+  InfixGrouping(IString),
+  /// A `gather(…)` attribute of a symbol that is not an infix symbol like `_+_`, or that does not have one pattern for
+  /// each of the two arguments.
+  InvalidGather(IString),
+  /// A sort declared as a subsort of itself, as in `sort A < A;`.
+  SelfSubsort(IString),
+  /// Subsort declarations that form a cycle, as in `sort A < B; sort B < A;`.
+  SubsortCycle(SortCycleError),
+  /// A name in a term parsed against an existing module, as by `parse_to_term(…)`, that is not a symbol of the
+  /// module.
+  UnknownSymbol(IString),
+  /// A rational literal with a zero denominator, as in `1/0`.
+  ZeroDenominator(NaturalNumber),
+  /// A string literal with no closing quote. The position of the opening quote is given as a 1-based line and column.
+  UnterminatedString {
+    line  : usize,
+    column: usize
+  },
+  /// A `(` with no matching `)`. The position of the `(` is given as a 1-based line and column.
+  UnmatchedParenthesis {
+    line  : usize,
+    column: usize
+  },
+}
+
+impl Display for ParseErrorKind {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    match self {
+
+      ParseErrorKind::ApplicationHead => write!(f, "only a symbol can be applied to arguments."),
+
+      ParseErrorKind::ArityTooLarge(arity) => write!(f, "the arity {} is too large.", arity),
+
+      ParseErrorKind::ArityMismatch { symbol, declared, sort_spec } => {
+        write!(
+          f,
+          "symbol {} is declared with arity {}, but its sort takes {} arguments.",
+          symbol,
+          declared,
+          sort_spec
+        )
+      }
+
+      ParseErrorKind::DuplicateSymbol(name) => write!(f, "symbol {} is declared more than once.", name),
+
+      ParseErrorKind::FrozenPosition { symbol, position } => {
+        write!(f, "symbol {} has no argument {} to freeze.", symbol, position)
+      }
+
+      ParseErrorKind::GatherPattern(pattern) => {
+        write!(f, "{} is not a gather pattern. The patterns are e, E, and &.", pattern)
+      }
+
+      ParseErrorKind::InfixGrouping(operator) => {
+        write!(
+          f,
+          "the operands of {} cannot be grouped as the precedences and gathering of the operators require.",
+          operator
+        )
+      }
+
+      ParseErrorKind::InvalidGather(name) => {
+        write!(f, "symbol {} must be an infix symbol with one gather pattern for each of its 2 arguments.", name)
+      }
+
+      ParseErrorKind::SelfSubsort(name) => write!(f, "sort {} is declared as a subsort of itself.", name),
+
+      ParseErrorKind::SubsortCycle(error) => Display::fmt(error, f),
+
+      ParseErrorKind::UnknownSymbol(name) => write!(f, "the symbol {} is not declared in the module.", name),
+
+      ParseErrorKind::ZeroDenominator(numerator) => {
+        write!(f, "the rational literal {}/0 has a zero denominator.", numerator)
+      }
+
+      ParseErrorKind::UnterminatedString { line, column } => {
+        write!(f, "unterminated string literal starting at {}:{}.", line, column)
+      }
+
+      ParseErrorKind::UnmatchedParenthesis { line, column } => {
+        write!(f, "unmatched '(' opened at {}:{}.", line, column)
+      }
+
+    }
+  }
+}
+
+impl Debug for ParseErrorKind {
+  fn fmt(&self, f: &mut Formatter) -> std::fmt::Result {
+    Display::fmt(self, f)
+  }
+}
+
+impl Error for ParseErrorKind{}
+
+/**
+Looks for an unterminated string literal or an unmatched `(` in `input`, skipping comments. Either one makes LALRPOP
+report an unexpected token or end of input far from the actual mistake, so when parsing fails the parser reports
+what this finds instead. An unterminated string is reported in preference to an unmatched parenthesis, since the
+string swallows the rest of the input.
+*/
+pub(crate) fn find_unclosed_delimiter(input: &str) -> Option<ParseErrorKind> {
+  let mut open_parentheses: Vec<usize> = Vec::new();
+  let mut chars = input.char_indices().peekable();
+
+  while let Some((offset, c)) = chars.next() {
+    match c {
+
+      '/' if chars.peek().map(|(_, next)| *next) == Some('/') => {
+        while chars.next_if(|(_, c)| *c != '\n' && *c != '\r').is_some() {}
+      }
+
+      '/' if chars.peek().map(|(_, next)| *next) == Some('*') => {
+        chars.next();
+        let mut previous = ' ';
+        for (_, c) in chars.by_ref() {
+          if previous == '*' && c == '/' {
+            break;
+          }
+          previous = c;
+        }
+      }
+
+      '"' => {
+        let mut terminated = false;
+        while let Some((_, c)) = chars.next() {
+          match c {
+            '\\' => { chars.next(); }
+            '"'  => {
+              terminated = true;
+              break;
+            }
+            _    => {}
+          }
+        }
+        if !terminated {
+          let (line, column) = line_and_column(input, offset);
+          return Some(ParseErrorKind::UnterminatedString { line, column });
+        }
+      }
+
+      '(' => open_parentheses.push(offset),
+
+      ')' => { open_parentheses.pop(); }
+
+      _ => {}
+
+    }
+  }
+
+  open_parentheses.last().map(
+    | offset | {
+      let (line, column) = line_and_column(input, *offset);
+      ParseErrorKind::UnmatchedParenthesis { line, column }
+    }
+  )
+}
@@ -0,0 +1,230 @@
+/*!
+
+A reader for the S-expressions written by the `FormatStyle::SExpr` style, so that tools can hand terms back to the
+engine in the form it gives them out. `parse_sexpr(…)` reads a term of a module from its S-expression:
+
+```text
+Term := Atom | Number | String | "(" "var" Atom ")" | "(" Atom Term+ ")" ;
+```
+
+An atom names a symbol of the module and a `(var X)` form names a variable of the module. An atom is quoted with
+`|…|` if it contains whitespace or characters special to S-expressions, and a quoted `|var|` names a symbol rather than
+introducing a variable. A number is a natural number literal like `3` or a rational literal like `1/3`, and a string
+is a double quoted string literal in which `\` escapes the next character.
+
+ToDo: Float literals are not read.
+
+*/
+
+use std::{
+  iter::Peekable,
+  str::Chars
+};
+
+use crate::{
+  abstractions::IString,
+  builtin::{
+    integer_symbol::IntegerSymbol,
+    rational_symbol::RationalSymbol,
+    string_symbol::StringSymbol
+  },
+  core::{
+    format::reads_as_number,
+    module::Module
+  },
+  heap_construct,
+  theory::{
+    symbol::SymbolPtr,
+    term::{
+      BxTerm,
+      Term,
+      TermAttributes,
+      TermNode
+    }
+  }
+};
+
+/**
+The term of `module` written as the S-expression `text`, or `None` if `text` is not a single S-expression or names a
+symbol or variable the module does not have. See the module documentation.
+*/
+pub fn parse_sexpr(text: &str, module: &Module) -> Option<BxTerm> {
+  let mut chars = text.chars().peekable();
+  let term      = read_term(&mut chars, module)?;
+  skip_whitespace(&mut chars);
+  match chars.peek() {
+    None    => Some(term),
+    Some(_) => None
+  }
+}
+
+fn read_term(chars: &mut Peekable<Chars>, module: &Module) -> Option<BxTerm> {
+  skip_whitespace(chars);
+  match *chars.peek()? {
+
+    '(' => {
+      chars.next();
+      let (head, quoted) = read_atom(chars)?;
+      if head == "var" && !quoted {
+        let (name, _) = read_atom(chars)?;
+        skip_whitespace(chars);
+        return match chars.next()? {
+          ')' => lookup(&name, true, module).map(symbol_term),
+          _   => None
+        };
+      }
+
+      let head     = lookup(&head, false, module)?;
+      let mut tail = Vec::new();
+      loop {
+        skip_whitespace(chars);
+        if *chars.peek()? == ')' {
+          chars.next();
+          break;
+        }
+        tail.push(read_term(chars, module)?);
+      }
+      if tail.is_empty() {
+        return None;
+      }
+      Some(Box::new(
+        Term {
+          term_node : TermNode::Application { head: symbol_term(head), tail },
+          attributes: TermAttributes::default()
+        }
+      ))
+    }
+
+    '"' => {
+      chars.next();
+      let text = read_delimited(chars, '"')?;
+      Some(symbol_term(heap_construct!(StringSymbol::new(text))))
+    }
+
+    _ => {
+      let (name, quoted) = read_atom(chars)?;
+      if quoted || !reads_as_number(&name) {
+        return lookup(&name, false, module).map(symbol_term);
+      }
+      let symbol = match name.split_once('/') {
+        None                           => IntegerSymbol::new(name.parse().ok()?),
+        Some((numerator, denominator)) => match denominator.parse().ok()? {
+          0           => return None,
+          denominator => RationalSymbol::new(numerator.parse().ok()?, denominator)
+        }
+      };
+      Some(symbol_term(heap_construct!(symbol)))
+    }
+
+  }
+}
+
+/// Reads a possibly quoted atom, returning its name and whether it was quoted.
+fn read_atom(chars: &mut Peekable<Chars>) -> Option<(String, bool)> {
+  skip_whitespace(chars);
+  if *chars.peek()? == '|' {
+    chars.next();
+    return read_delimited(chars, '|').map(|name| (name, true));
+  }
+
+  let mut name = String::new();
+  while let Some(&c) = chars.peek() {
+    if c.is_whitespace() || matches!(c, '(' | ')' | '"' | '|' | ';') {
+      break;
+    }
+    name.push(c);
+    chars.next();
+  }
+  match name.is_empty() {
+    true  => None,
+    false => Some((name, false))
+  }
+}
+
+/// Reads the text up to the unescaped `delimiter` and consumes the delimiter. A `\` escapes the character after it.
+fn read_delimited(chars: &mut Peekable<Chars>, delimiter: char) -> Option<String> {
+  let mut text = String::new();
+  loop {
+    match chars.next()? {
+      '\\'                => text.push(chars.next()?),
+      c if c == delimiter => return Some(text),
+      c                   => text.push(c)
+    }
+  }
+}
+
+fn skip_whitespace(chars: &mut Peekable<Chars>) {
+  while chars.next_if(|c| c.is_whitespace()).is_some() {}
+}
+
+/// The symbol `name` of `module` if it is a variable exactly when `variable` is true.
+fn lookup(name: &str, variable: bool, module: &Module) -> Option<SymbolPtr> {
+  let symbol = *module.symbols.get(&IString::from(name))?;
+  match unsafe{ (*symbol).is_variable() } == variable {
+    true  => Some(symbol),
+    false => None
+  }
+}
+
+fn symbol_term(symbol: SymbolPtr) -> BxTerm {
+  Box::new(
+    Term {
+      term_node : TermNode::Symbol(symbol),
+      attributes: TermAttributes::default()
+    }
+  )
+}
+
+
+#[cfg(test)]
+mod tests {
+  use std::cmp::Ordering;
+
+  use crate::{
+    core::{
+      command::Command,
+      format::{
+        FormatStyle,
+        Formattable
+      }
+    },
+    parser::parse_to_module_with_commands,
+    theory::symbol::Symbol
+  };
+  use super::*;
+
+  #[test]
+  fn s_expressions_read_back_as_the_terms_they_render() {
+    let (mut module, commands) = parse_to_module_with_commands(
+      "
+      variable x;
+      symbol f;
+      symbol g;
+
+      reduce f(\"hi\", 1, g(x));
+      "
+    ).unwrap();
+    let term = match &commands[0] {
+      Command::Reduce(term) => term,
+      _ => unreachable!()
+    };
+
+    let text = term.repr(FormatStyle::SExpr);
+    assert_eq!(text, "(f \"hi\" 1 (g (var x)))");
+    let read = parse_sexpr(&text, &module).unwrap();
+    assert_eq!(read.compare(term), Ordering::Equal);
+    assert_eq!(read.structural_hash(), term.structural_hash());
+    assert_eq!(parse_sexpr(" ( g  \"a \\\" b\" ) ", &module).unwrap().to_string(), "g(\"a \\\" b\")");
+
+    // `var` is a keyword of the language, but a module built in code can have a symbol named `var`, which is quoted so
+    // that it is not mistaken for a variable.
+    module.symbols.insert(IString::from("var"), heap_construct!(Symbol::new(IString::from("var"))));
+    let text = "(|var| (g (var x)))";
+    assert_eq!(parse_sexpr(text, &module).unwrap().repr(FormatStyle::SExpr), text);
+
+    // Undeclared names, variables written as symbols, and malformed text are rejected.
+    for text in ["(h 1)", "x", "(var g)", "(f)", "(f 1", "f 1", "(var x y)"] {
+      assert!(parse_sexpr(text, &module).is_none(), "{} was read", text);
+    }
+  }
+}
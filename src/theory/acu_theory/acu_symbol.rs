@@ -0,0 +1,13 @@
+use crate::theory::symbol::TheorySymbol;
+
+/// The theory symbol of a symbol declared `assoc` and `comm`. Matching modulo associativity and commutativity is done
+/// by `acu_theory::match_ac(…)`, and statements with the symbol on top of their left-hand sides are matched with an
+/// extension by `acu_theory::match_ac_with_extension(…)`.
+#[derive(Copy, Clone, Default)]
+pub struct ACUSymbol {}
+
+impl TheorySymbol for ACUSymbol {
+  fn has_extension(&self) -> bool {
+    true
+  }
+}
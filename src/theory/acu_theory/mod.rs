@@ -0,0 +1,720 @@
+/*!
+
+The theory of symbols that are associative and commutative, declared as in `symbol f [assoc, comm];`, and optionally
+have an identity element, as in `symbol f [assoc, comm, id(e)];`. Under these axioms the nesting and order of the
+arguments of `f` do not matter, so `f(f(a, b), c)` and `f(c, a, b)` are the same term, and `f(a, e)` is `a`. An AC
+application is treated as the multiset of its arguments, with nested applications of the same symbol flattened into
+it.
+
+## Canonical Form
+
+An AC node is put into a canonical form whenever it is constructed by `make_ac(…)`: nested applications of the symbol
+are flattened into it, identity elements are dropped, and the arguments are sorted by `DagNode::compare`, with equal
+arguments merged into one argument with a multiplicity. So AC-equal nodes are structurally equal and have equal
+hashes, and they are shared by the DAG and found by the caches and by nonlinear patterns. If dropping identity
+elements leaves a single argument, the application collapses to it, and if it leaves none, to the identity element.
+`Term::normalize()` puts terms into the same form. See the `dag_node` module.
+
+## Matching
+
+An AC pattern can match a subject in several ways. `Term::match_all(…)` enumerates every match, and
+`Term::match_dag(…)` takes the first one. The non-variable arguments of the pattern are matched first, each against
+one argument of the subject, and the arguments of the subject that remain are then distributed among the variables
+of the pattern. Matching `f(X, a, b)` against `f(b, c, a, d)` binds `X` to `f(c, d)`.
+
+The distribution works on multiplicities rather than on individual arguments: each distinct argument of the subject
+is split among the variables, where a variable occurring `k` times in the pattern takes a multiple of `k` of its
+copies. The copies of an argument are never told apart, so the matches are found without trying each subset of the
+arguments, and each match is found once. A variable takes at least one argument, unless the symbol has an identity,
+in which case a variable that takes none is bound to the identity element. So `f(X, X, Y)` matches `f(a, a, a)` only
+with `X := a, Y := a`, and with an identity `e` also with `X := e, Y := f(a, a, a)`. A variable that takes several
+arguments is bound to the AC application of the symbol to them. With an identity, a pattern also matches a subject
+that is not an application of the symbol, as if it were the only argument.
+
+## Extension
+
+An equation or rule whose left-hand side has an AC symbol on top also applies to part of a subject, as in Maude. The
+left-hand side is matched by `match_ac_with_extension(…)`, which lets an implicit extension variable take the
+arguments of the subject the pattern leaves over. The right-hand side then replaces the part that matched, and the
+extension is put back beside it by `extend(…)`. So with `equation f(a, a) = b;`, the term `f(a, a, c)` reduces to
+`f(b, c)`. A match with an extension must match at least two arguments of the subject, counting copies, since a
+match of a single argument is a match at that argument itself. Matches without an extension are tried first.
+
+*/
+
+pub(crate) mod acu_symbol;
+
+use std::cmp::Ordering;
+
+use crate::{
+  abstractions::{
+    hash2,
+    HashType,
+    RcCell
+  },
+  core::substitution::Substitution,
+  theory::{
+    dag_node::{
+      DagNode,
+      DagPair,
+      RcDagNode
+    },
+    symbol::SymbolPtr,
+    term::{
+      BxTerm,
+      Term,
+      TermNode
+    }
+  }
+};
+
+/// Folds the hash of an argument with hash `arg_hash` and multiplicity `multiplicity` into `hash`, the hash of the
+/// node so far. An argument of multiplicity 1 is folded in like an argument of any other symbol.
+pub(crate) fn hash_multiplicity(hash: HashType, arg_hash: HashType, multiplicity: u32) -> HashType {
+  let hash = hash2(hash, arg_hash);
+  match multiplicity > 1 {
+    true  => hash2(hash, multiplicity),
+    false => hash
+  }
+}
+
+/**
+The node for the AC symbol `symbol` applied to the arguments `pairs` with their multiplicities, in the canonical form
+described in the module documentation. The hash is not computed.
+*/
+pub(crate) fn make_ac(symbol: SymbolPtr, pairs: Vec<DagPair>) -> DagNode {
+  let identity      = unsafe{ (*symbol).identity.as_deref() };
+  let mut flattened = Vec::with_capacity(pairs.len());
+  let mut dropped   = false;
+  for pair in pairs {
+    add_pair(symbol, identity, pair, &mut flattened, &mut dropped);
+  }
+
+  flattened.sort_by(|pair: &DagPair, other: &DagPair| pair.dag_node.borrow().compare(&other.dag_node.borrow()));
+  let mut merged: Vec<DagPair> = Vec::with_capacity(flattened.len());
+  for pair in flattened {
+    match merged.last_mut() {
+      Some(last) if last.dag_node.borrow().equals(&pair.dag_node.borrow()) => last.multiplicity += pair.multiplicity,
+      _ => merged.push(pair)
+    }
+  }
+
+  match (merged.as_slice(), identity) {
+    ([], Some(identity))                               => identity.dagify().borrow().shallow_copy(),
+    ([single], _) if dropped && single.multiplicity == 1 => single.dag_node.borrow().shallow_copy(),
+    _ => {
+      let (args, multiplicities) = merged.into_iter().map(|pair| (pair.dag_node, pair.multiplicity)).unzip();
+      let mut node        = DagNode::new(symbol, args);
+      node.multiplicities = multiplicities;
+      node
+    }
+  }
+}
+
+/// Pushes `pair` onto `flattened`, flattening it if it is an application of `symbol` and dropping it, and setting
+/// `dropped`, if it is `identity`.
+fn add_pair(
+  symbol   : SymbolPtr,
+  identity : Option<&Term>,
+  pair     : DagPair,
+  flattened: &mut Vec<DagPair>,
+  dropped  : &mut bool
+)
+{
+  let (nested, is_identity) = {
+    let dag_node = pair.dag_node.borrow();
+    let nested   = unsafe{ *dag_node.top_symbol == *symbol } && !dag_node.args.is_empty();
+    let nested   = nested.then(|| dag_node.pairs().collect::<Vec<_>>());
+    (nested, identity.is_some_and(|identity| is_term(&dag_node, identity)))
+  };
+  match nested {
+    Some(nested) => {
+      for nested_pair in nested {
+        let multiplicity = nested_pair.multiplicity * pair.multiplicity;
+        add_pair(symbol, identity, DagPair { multiplicity, ..nested_pair }, flattened, dropped);
+      }
+    }
+    None if is_identity => *dropped = true,
+    None                => flattened.push(pair)
+  }
+}
+
+/// Whether `dag_node` represents `term`.
+pub(crate) fn is_term(dag_node: &DagNode, term: &Term) -> bool {
+  if term.top_symbol() != Some(dag_node.top_symbol) || dag_node.arg_count() != term.arguments().len() {
+    return false;
+  }
+  dag_node.iter_args().zip(term.arguments()).all(|(arg, arg_term)| is_term(&arg.borrow(), arg_term))
+}
+
+/**
+Puts `tail`, the normalized arguments of an application of the AC symbol `symbol`, into the canonical form of the
+module documentation, returning the term the application collapses to, if it collapses.
+*/
+pub(crate) fn normalize_ac_arguments(symbol: SymbolPtr, tail: &mut Vec<BxTerm>) -> Option<BxTerm> {
+  let identity      = unsafe{ (*symbol).identity.as_deref() };
+  let mut flattened = Vec::with_capacity(tail.len());
+  for arg in tail.drain(..) {
+    let nested = arg.top_symbol() == Some(symbol) && !arg.arguments().is_empty();
+    match arg.term_node {
+      TermNode::Application { tail: nested_tail, .. } if nested => flattened.extend(nested_tail),
+      _ => flattened.push(arg)
+    }
+  }
+
+  let count       = flattened.len();
+  let is_identity = | term: &Term | identity.is_some_and(
+    |identity| term.structural_hash() == identity.structural_hash() && term.compare(identity) == Ordering::Equal
+  );
+  flattened.retain(|arg| !is_identity(arg));
+  let dropped = flattened.len() < count;
+  flattened.sort_by(|arg, other| arg.compare(other));
+
+  match (flattened.len(), identity) {
+    (0, Some(identity)) => Some(identity.deep_copy()),
+    (1, _) if dropped   => flattened.pop(),
+    _ => {
+      *tail = flattened;
+      None
+    }
+  }
+}
+
+/// A match of a pattern against part of a subject: the substitution, and the arguments of the subject left for the
+/// extension, which are empty if the pattern matched the whole subject.
+pub(crate) type PartialMatch = (Substitution, Vec<DagPair>);
+
+/**
+Every extension of `substitution` under which the application `pattern` of the AC symbol `symbol` matches `subject`
+modulo associativity and commutativity, and the identity of `symbol` if it has one. See the module documentation.
+*/
+pub(crate) fn match_ac(
+  symbol: SymbolPtr,
+  pattern: &Term,
+  subject: &RcDagNode,
+  substitution: Substitution
+) -> Vec<Substitution>
+{
+  match_ac_pairs(symbol, pattern, subject, substitution, false).into_iter().map(|(solution, _)| solution).collect()
+}
+
+/**
+Every match of the application `pattern` of the AC symbol `symbol` against `subject` like `match_ac(…)`, together
+with every match against part of `subject`, which leaves the rest of its arguments for the extension. Matches without
+an extension come first. See the module documentation.
+*/
+pub(crate) fn match_ac_with_extension(
+  symbol: SymbolPtr,
+  pattern: &Term,
+  subject: &RcDagNode,
+  substitution: Substitution
+) -> Vec<PartialMatch>
+{
+  let is_application = {
+    let dag_node = subject.borrow();
+    !dag_node.args.is_empty() && unsafe{ *dag_node.top_symbol == *symbol }
+  };
+  if !is_application {
+    return match_ac_pairs(symbol, pattern, subject, substitution, false);
+  }
+
+  let copies      = |pairs: &[DagPair]| pairs.iter().map(|pair| pair.multiplicity as u64).sum::<u64>();
+  let all         = copies(&subject.borrow().pairs().collect::<Vec<_>>());
+  let mut matches = match_ac_pairs(symbol, pattern, subject, substitution, true);
+  matches.retain(|(_, extension)| extension.is_empty() || all - copies(extension) >= 2);
+  matches.sort_by_key(|(_, extension)| !extension.is_empty());
+  matches
+}
+
+/// The result of a statement with `symbol` on top of its left-hand side, whose right-hand side instantiated to
+/// `result`, with the arguments of the subject in `extension` put back beside it. See the module documentation.
+pub(crate) fn extend(symbol: SymbolPtr, result: RcDagNode, mut extension: Vec<DagPair>) -> RcDagNode {
+  if extension.is_empty() {
+    return result;
+  }
+  extension.push(DagPair { dag_node: result, multiplicity: 1 });
+  make_ac_node(symbol, extension)
+}
+
+/// The matches of `match_ac(…)`, or of `match_ac_with_extension(…)` if `extension` is set, before matches of a single
+/// argument are removed.
+fn match_ac_pairs(
+  symbol: SymbolPtr,
+  pattern: &Term,
+  subject: &RcDagNode,
+  substitution: Substitution,
+  extension: bool
+) -> Vec<PartialMatch>
+{
+  let Some(remaining) = subject_pairs(symbol, subject) else {
+    return vec![];
+  };
+
+  let (variables, others): (Vec<&Term>, Vec<&Term>) =
+      flatten_term_arguments(symbol, pattern).into_iter().partition(|arg| is_variable(arg));
+  // Each distinct variable with the number of times it occurs.
+  let mut counted: Vec<(&Term, u32)> = Vec::with_capacity(variables.len());
+  for variable in variables {
+    match counted.iter_mut().find(|(other, _)| other.top_symbol() == variable.top_symbol()) {
+      Some((_, count)) => *count += 1,
+      None             => counted.push((variable, 1))
+    }
+  }
+
+  let mut solutions = Vec::new();
+  match_arguments(symbol, &others, &counted, remaining, substitution, extension, &mut solutions);
+
+  // Equal non-variable arguments of the pattern can match the same arguments of the subject in either order. The
+  // substitution determines which arguments are matched, so it also determines the extension.
+  let mut distinct: Vec<PartialMatch> = Vec::with_capacity(solutions.len());
+  for solution in solutions {
+    if !distinct.iter().any(|(other, _)| *other == solution.0) {
+      distinct.push(solution);
+    }
+  }
+  distinct
+}
+
+/// The arguments of `subject` with their multiplicities, as an argument of a pattern with the AC symbol `symbol` on top
+/// sees them. A subject that is not an application of `symbol` is its own single argument if `symbol` has an identity,
+/// or none if it is the identity itself, and does not match otherwise.
+fn subject_pairs(symbol: SymbolPtr, subject: &RcDagNode) -> Option<Vec<DagPair>> {
+  let dag_node = subject.borrow();
+  if unsafe{ *dag_node.top_symbol == *symbol } && !dag_node.args.is_empty() {
+    return Some(dag_node.pairs().collect());
+  }
+  let identity = unsafe{ (*symbol).identity.as_deref() }?;
+  match is_term(&dag_node, identity) {
+    true  => Some(vec![]),
+    false => Some(vec![DagPair { dag_node: subject.clone(), multiplicity: 1 }])
+  }
+}
+
+/// The arguments of the pattern `term` with nested applications of `symbol` flattened into them.
+fn flatten_term_arguments(symbol: SymbolPtr, term: &Term) -> Vec<&Term> {
+  let mut arguments = Vec::new();
+  for arg in term.arguments() {
+    match (&arg.term_node, arg.top_symbol()) {
+      (TermNode::Application { .. }, Some(top_symbol)) if unsafe{ *top_symbol == *symbol } => {
+        arguments.extend(flatten_term_arguments(symbol, arg));
+      }
+      _ => arguments.push(arg.as_ref())
+    }
+  }
+  arguments
+}
+
+fn is_variable(term: &Term) -> bool {
+  matches!(term.term_node, TermNode::Symbol(symbol) if unsafe{ (*symbol).is_variable() })
+}
+
+/// The node for `symbol` applied to `pairs`, or the single argument itself if there is one copy of one argument. The
+/// hash is computed.
+fn make_ac_node(symbol: SymbolPtr, mut pairs: Vec<DagPair>) -> RcDagNode {
+  if let [DagPair { multiplicity: 1, .. }] = pairs.as_slice() {
+    return pairs.pop().unwrap().dag_node;
+  }
+  let mut node = make_ac(symbol, pairs);
+  node.structural_hash();
+  RcCell::new(node)
+}
+
+/// Matches each of `patterns` against a copy of a distinct argument of `remaining`, then distributes what is left
+/// among `variables`, and the extension if there is one, pushing every complete match onto `solutions`.
+fn match_arguments(
+  symbol: SymbolPtr,
+  patterns: &[&Term],
+  variables: &[(&Term, u32)],
+  remaining: Vec<DagPair>,
+  substitution: Substitution,
+  extension: bool,
+  solutions: &mut Vec<PartialMatch>
+)
+{
+  let (pattern, patterns) = match patterns.split_first() {
+    Some(split) => split,
+    None => return assign_variables(symbol, variables, remaining, substitution, extension, solutions)
+  };
+
+  for i in 0..remaining.len() {
+    if remaining[i].multiplicity == 0 {
+      continue;
+    }
+    for solution in pattern.match_all(&remaining[i].dag_node, substitution.clone()) {
+      let mut rest = remaining.clone();
+      rest[i].multiplicity -= 1;
+      match_arguments(symbol, patterns, variables, rest, solution, extension, solutions);
+    }
+  }
+}
+
+/// Distributes `remaining` among `variables`, each with the number of times it occurs in the pattern, and the
+/// extension if there is one, pushing every complete match onto `solutions`. A variable that is already bound takes
+/// the arguments its binding flattens to.
+fn assign_variables(
+  symbol: SymbolPtr,
+  variables: &[(&Term, u32)],
+  mut remaining: Vec<DagPair>,
+  substitution: Substitution,
+  extension: bool,
+  solutions: &mut Vec<PartialMatch>
+)
+{
+  let mut unbound = Vec::with_capacity(variables.len());
+  for &(variable, count) in variables {
+    let Some(value) = substitution.value(variable.top_symbol().unwrap()) else {
+      unbound.push((variable, count));
+      continue;
+    };
+    let pairs = subject_pairs(symbol, &value).unwrap_or_else(
+      || vec![DagPair { dag_node: value.clone(), multiplicity: 1 }]
+    );
+    for pair in pairs {
+      let taken = pair.multiplicity * count;
+      match remaining.iter_mut().find(|other| other.dag_node.borrow().equals(&pair.dag_node.borrow())) {
+        Some(other) if other.multiplicity >= taken => other.multiplicity -= taken,
+        _ => return
+      }
+    }
+  }
+  remaining.retain(|pair| pair.multiplicity > 0);
+
+  let mut distribution = Distribution {
+    symbol,
+    variables: &unbound,
+    remaining: &remaining,
+    counts   : vec![vec![0; remaining.len()]; unbound.len()],
+    extension: extension.then(|| vec![0; remaining.len()]),
+    solutions,
+  };
+  distribution.distribute(0, 0, remaining.first().map_or(0, |pair| pair.multiplicity), substitution);
+}
+
+/// The state of distributing the arguments of a subject among the unbound variables of a pattern. See
+/// `assign_variables(…)`.
+struct Distribution<'a> {
+  symbol   : SymbolPtr,
+  /// The unbound variables, each with the number of times it occurs in the pattern.
+  variables: &'a [(&'a Term, u32)],
+  remaining: &'a [DagPair],
+  /// `counts[v][i]` is the number of copies of `remaining[i]` given to `variables[v]` so far.
+  counts   : Vec<Vec<u32>>,
+  /// If there is an extension, the number of copies of each argument of `remaining` left for it.
+  extension: Option<Vec<u32>>,
+  solutions: &'a mut Vec<PartialMatch>,
+}
+
+impl Distribution<'_> {
+  /// Gives copies of `remaining[argument]`, of which `left` are not given yet, to `variables[variable]` and the
+  /// variables after it, then goes on to the next argument. The extension takes whatever copies the variables do not.
+  fn distribute(&mut self, argument: usize, variable: usize, left: u32, substitution: Substitution) {
+    if argument == self.remaining.len() {
+      return self.bind(substitution);
+    }
+    if variable == self.variables.len() {
+      if let Some(extension) = self.extension.as_mut() {
+        extension[argument] = left;
+      }
+      if left == 0 || self.extension.is_some() {
+        let next = self.remaining.get(argument + 1).map_or(0, |pair| pair.multiplicity);
+        self.distribute(argument + 1, 0, next, substitution);
+      }
+      return;
+    }
+    // A variable occurring `count` times takes a multiple of `count` copies, one copy per occurrence.
+    let count = self.variables[variable].1;
+    for given in 0..=left / count {
+      self.counts[variable][argument] = given;
+      self.distribute(argument, variable + 1, left - given * count, substitution.clone());
+    }
+    self.counts[variable][argument] = 0;
+  }
+
+  /// Binds each variable to the arguments given to it, pushing the match onto the solutions if every variable has a
+  /// binding of its sort.
+  fn bind(&mut self, substitution: Substitution) {
+    let identity     = unsafe{ (*self.symbol).identity.as_deref() };
+    let mut solution = substitution;
+    for (&(variable, _), counts) in self.variables.iter().zip(self.counts.iter()) {
+      let pairs: Vec<DagPair> =
+          self.remaining
+              .iter()
+              .zip(counts.iter())
+              .filter(|(_, given)| **given > 0)
+              .map(|(pair, given)| DagPair { dag_node: pair.dag_node.clone(), multiplicity: *given })
+              .collect();
+      let value = match (pairs.is_empty(), identity) {
+        (false, _)             => make_ac_node(self.symbol, pairs),
+        (true, Some(identity)) => identity.dagify(),
+        (true, None)           => return
+      };
+      match variable.match_all(&value, solution).pop() {
+        Some(extended) => solution = extended,
+        None           => return
+      }
+    }
+    let extension = self.extension.iter().flat_map(
+      | left | self.remaining.iter().zip(left.iter()).filter(|(_, left)| **left > 0).map(
+        | (pair, left) | DagPair { dag_node: pair.dag_node.clone(), multiplicity: *left }
+      )
+    ).collect();
+    self.solutions.push((solution, extension));
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::{
+    abstractions::IString,
+    core::{
+      command::Command,
+      format::{
+        FormatStyle,
+        Formattable
+      }
+    },
+    parser::parse_to_module_with_commands,
+    theory::term::tests::assert_term_dag_hash_consistent
+  };
+  use super::*;
+
+  fn matches(input: &str) -> Vec<String> {
+    let (module, commands) = parse_to_module_with_commands(input).unwrap();
+    let (pattern, subject) = match &commands[0] {
+      Command::Match { pattern, subject } => (pattern, module.reduce_dag(&subject.dagify())),
+      _ => unreachable!()
+    };
+    pattern.match_all(&subject, Substitution::default())
+           .iter()
+           .map(|solution| solution.repr(FormatStyle::Default))
+           .collect()
+  }
+
+  #[test]
+  fn ac_patterns_match_in_any_order() {
+    let solutions = matches(
+      "
+      variable X;
+      symbol f [assoc, comm];
+
+      match f(X, a, b) with f(b, c, a, d);
+      "
+    );
+    assert_eq!(solutions, vec!["X := f(c, d)"]);
+
+    // Nested applications are flattened, on both sides.
+    let solutions = matches(
+      "
+      variable X;
+      symbol f [assoc, comm];
+
+      match f(a, f(X, b)) with f(f(b, c), a);
+      "
+    );
+    assert_eq!(solutions, vec!["X := c"]);
+  }
+
+  #[test]
+  fn every_distribution_among_variables_is_enumerated() {
+    let solutions = matches(
+      "
+      variable X;
+      variable Y;
+      symbol f [assoc, comm];
+
+      match f(X, Y) with f(a, b, c);
+      "
+    );
+    assert_eq!(solutions.len(), 6);
+    assert!(solutions.contains(&"X := f(a, c), Y := b".to_string()));
+
+    // A repeated variable takes equal parts.
+    let solutions = matches(
+      "
+      variable X;
+      symbol f [assoc, comm];
+
+      match f(X, X, c) with f(a, b, c, b, a);
+      "
+    );
+    assert_eq!(solutions, vec!["X := f(a, b)"]);
+  }
+
+  #[test]
+  fn ac_equations_apply_modulo_ac() {
+    let (module, commands) = parse_to_module_with_commands(
+      "
+      variable X;
+      symbol f [assoc, comm];
+
+      equation f(X, zero) = X;
+
+      reduce f(a, zero, b);
+      "
+    ).unwrap();
+    let results: Vec<String> = module.run_commands(&commands).iter().map(|result| result.to_string()).collect();
+    assert_eq!(results, vec!["result: f(a, b)"]);
+  }
+
+  #[test]
+  fn statements_apply_to_part_of_an_ac_subject() {
+    let (module, commands) = parse_to_module_with_commands(
+      "
+      variable X;
+      symbol f [assoc, comm];
+      symbol g [assoc, comm];
+      symbol h [assoc, comm, id(e)];
+
+      equation f(a, a) = b;
+      equation g(X, X) = X;
+      equation h(c, X) = d if X = a;
+      rule [swap] f(b, c) => f(a, d);
+
+      reduce f(a, a, c);
+      reduce f(a, a, a, a, a);
+      reduce g(a, b, c, a, b);
+      reduce h(c, a, c);
+      reduce h(c, c);
+      reduce f(a, a, c, c);
+      "
+    ).unwrap();
+    let results: Vec<String> = module.run_commands(&commands).iter().map(|result| result.to_string()).collect();
+    assert_eq!(
+      results,
+      vec![
+        "result: f(b, c)",
+        "result: f(a, b, b)",
+        "result: g(a, b, c)",
+        "result: h(c, d)",
+        "result: h(c, c)",
+        "result: f(b, c, c)"
+      ]
+    );
+
+    // Rules apply to part of a subject too.
+    let term = match &commands[5] {
+      Command::Reduce(term) => term,
+      _ => unreachable!()
+    };
+    assert_eq!(module.rewrite(term, 1).borrow().to_string(), "f(a, c, d)");
+
+    // Matching with an extension leaves the arguments that are not matched, and never matches a single argument.
+    // A match of the whole subject comes first.
+    let pattern  = &module.equations[2].lhs_term;
+    let subject  = module.eval("h(c, b, b)").unwrap();
+    let partials: Vec<(String, Vec<u32>)> =
+        pattern.match_with_extension(&subject, Substitution::default())
+               .iter()
+               .map(
+                 | (solution, extension) | {
+                   (solution.repr(FormatStyle::Default), extension.iter().map(|pair| pair.multiplicity).collect())
+                 }
+               )
+               .collect();
+    // `X := e` would leave both copies of `b` for the extension and match `c` alone.
+    assert_eq!(partials, vec![("X := h(b, b)".to_string(), vec![]), ("X := b".to_string(), vec![1])]);
+  }
+
+  #[test]
+  fn ac_nodes_are_flattened_sorted_and_merged() {
+    let (module, commands) = parse_to_module_with_commands(
+      "
+      variable X;
+      symbol f [assoc, comm];
+      symbol g;
+
+      equation g(X, X) = same;
+
+      reduce f(b, f(a, b));
+      "
+    ).unwrap();
+    let node = module.eval("f(b, f(a, b))").unwrap();
+    assert_eq!(node.borrow().to_string(), "f(a, b, b)");
+    assert_eq!(node.borrow().multiplicities, vec![1, 2]);
+    let other = module.eval("f(f(b, b), a)").unwrap();
+    assert!(node.borrow().equals(&other.borrow()));
+    assert_eq!(node.borrow_mut().structural_hash(), other.borrow_mut().structural_hash());
+
+    let mut term = match &commands[0] {
+      Command::Reduce(term) => term.deep_copy(),
+      _ => unreachable!()
+    };
+    term.normalize();
+    assert_eq!(term.to_string(), "f(a, b, b)");
+    assert_term_dag_hash_consistent(&term);
+
+    // Nonlinear patterns see AC-equal arguments as equal.
+    assert_eq!(module.eval("g(f(a, b), f(b, a))").unwrap().borrow().to_string(), "same");
+  }
+
+  #[test]
+  fn identity_elements_are_dropped() {
+    let (module, commands) = parse_to_module_with_commands(
+      "
+      variable X;
+      variable Y;
+      symbol f [assoc, comm, id(e)];
+
+      match f(X, a) with a;
+      match f(X, X, Y) with f(b, b, b);
+      "
+    ).unwrap();
+    let eval = | source: &str | module.eval(source).unwrap().borrow().to_string();
+    assert_eq!(eval("f(a, e, a)"), "f(a, a)");
+    assert_eq!(eval("f(e, a)"), "a");
+    assert_eq!(eval("f(e, f(e, e))"), "e");
+
+    let solutions: Vec<Vec<String>> = commands.iter()
+                                              .map(|command| match command {
+                                                Command::Match { pattern, subject } => {
+                                                  pattern.match_all(&subject.dagify(), Substitution::default())
+                                                         .iter()
+                                                         .map(|solution| solution.repr(FormatStyle::Default))
+                                                         .collect()
+                                                }
+                                                _ => unreachable!()
+                                              })
+                                              .collect();
+    assert_eq!(solutions[0], vec!["X := e"]);
+    assert_eq!(solutions[1].len(), 2);
+    assert!(solutions[1].contains(&"X := b, Y := b".to_string()));
+    assert!(solutions[1].contains(&"X := e, Y := f(b, b, b)".to_string()));
+  }
+
+  #[test]
+  fn matching_works_on_multiplicities() {
+    let (module, commands) = parse_to_module_with_commands(
+      "
+      variable X;
+      symbol f [assoc, comm];
+
+      match f(X, X, a) with f(a, b);
+      "
+    ).unwrap();
+    let pattern = match &commands[0] {
+      Command::Match { pattern, .. } => pattern,
+      _ => unreachable!()
+    };
+    let f = module.symbols[&IString::from("f")];
+    let a = module.eval("a").unwrap();
+    let b = module.eval("b").unwrap();
+
+    // The arguments are not enumerated copy by copy.
+    let mut subject = make_ac(
+      f,
+      vec![DagPair { dag_node: a.clone(), multiplicity: 1 }, DagPair { dag_node: b.clone(), multiplicity: 1_000_000 }]
+    );
+    subject.structural_hash();
+    let solutions = pattern.match_all(&RcCell::new(subject), Substitution::default());
+    assert_eq!(solutions.len(), 1);
+    let binding = solutions[0].value(module.symbols[&IString::from("X")]).unwrap();
+    assert_eq!(binding.borrow().multiplicities, vec![500_000]);
+
+    // A subject with more arguments than there are bits in a word.
+    let args = (0..100).map(|i| module.eval(&i.to_string()).unwrap()).chain(std::iter::once(a)).collect();
+    let mut subject = DagNode::make(f, args);
+    subject.structural_hash();
+    let solutions = pattern.match_all(&RcCell::new(subject), Substitution::default());
+    assert!(solutions.is_empty());
+  }
+}
@@ -0,0 +1,10 @@
+use crate::theory::symbol::TheorySymbol;
+
+/// The theory symbol of a binary symbol declared `comm` but not `assoc`. Applications of such a symbol are stored with
+/// their arguments in canonical order and matched by `c_theory::match_commutative(…)`.
+#[derive(Copy, Clone, Default)]
+pub struct CSymbol {}
+
+impl TheorySymbol for CSymbol {
+
+}
@@ -0,0 +1,167 @@
+/*!
+
+The theory of binary symbols that are commutative but not associative, declared as in `symbol f [comm];`. Under
+commutativity the order of the two arguments of `f` does not matter, so `f(a, b)` and `f(b, a)` are the same term.
+Nested applications are not flattened as in the `acu_theory`: `f(f(a, b), c)` and `f(a, f(b, c))` are different.
+
+A node of a commutative symbol is put into canonical form whenever it is constructed, with its arguments ordered by
+`DagNode::compare`. Commutatively equal nodes are therefore structurally equal and have equal hashes, and
+`DagNode::equals` needs no special case for them. `Term::normalize` orders the arguments of terms the same way, so
+that a term and its DAG have the same hash.
+
+## Matching
+
+A commutative pattern `f(P, Q)` matches a subject `f(s, t)` if `P` matches `s` and `Q` matches `t`, or `P` matches
+`t` and `Q` matches `s`. `Term::match_all(…)` enumerates the matches of both orderings, so matching `f(X, a)` against
+`f(a, b)` binds `X` to `b`.
+
+ToDo: Applications of a commutative symbol to other than two arguments are left as they are and matched syntactically.
+
+*/
+
+pub(crate) mod c_symbol;
+
+use std::cmp::Ordering;
+
+use crate::{
+  core::substitution::Substitution,
+  theory::{
+    dag_node::{
+      DagNode,
+      NodeList,
+      RcDagNode
+    },
+    symbol::SymbolPtr,
+    term::Term
+  }
+};
+
+/// The node for `symbol` applied to `args`, with the arguments in canonical order if there are two of them. The hash
+/// is not computed.
+pub(crate) fn make_commutative(symbol: SymbolPtr, mut args: NodeList) -> DagNode {
+  if args.len() == 2 && args[0].borrow().compare(&args[1].borrow()) == Ordering::Greater {
+    args.swap(0, 1);
+  }
+  DagNode::new(symbol, args)
+}
+
+/**
+Every extension of `substitution` under which the application `pattern` of the commutative symbol `symbol` matches
+`subject` modulo commutativity. See the module documentation.
+*/
+pub(crate) fn match_commutative(
+  symbol: SymbolPtr,
+  pattern: &Term,
+  subject: &RcDagNode,
+  substitution: Substitution
+) -> Vec<Substitution>
+{
+  let (first, second) = match pattern.arguments() {
+    [first, second] => (first, second),
+    _               => return vec![]
+  };
+  let (left, right) = {
+    let dag_node = subject.borrow();
+    if unsafe{ *dag_node.top_symbol != *symbol } || dag_node.args.len() != 2 {
+      return vec![];
+    }
+    (dag_node.args[0].clone(), dag_node.args[1].clone())
+  };
+
+  let mut solutions: Vec<Substitution> = Vec::new();
+  for (left, right) in [(&left, &right), (&right, &left)] {
+    if !first.admits_at(symbol, 0, left) || !second.admits_at(symbol, 1, right) {
+      continue;
+    }
+    for partial in first.match_all(left, substitution.clone()) {
+      for solution in second.match_all(right, partial) {
+        // Both orderings give the same matches if the arguments of the subject are equal.
+        if !solutions.contains(&solution) {
+          solutions.push(solution);
+        }
+      }
+    }
+  }
+  solutions
+}
+
+
+#[cfg(test)]
+mod tests {
+  use crate::{
+    core::{
+      command::Command,
+      format::{
+        FormatStyle,
+        Formattable
+      }
+    },
+    parser::parse_to_module_with_commands
+  };
+  use super::*;
+
+  #[test]
+  fn commutative_patterns_match_either_ordering() {
+    let (_module, commands) = parse_to_module_with_commands(
+      "
+      variable X;
+      variable Y;
+      symbol f [comm];
+
+      match f(X, a) with f(a, b);
+      match f(X, a) with f(b, a);
+      match f(X, Y) with f(a, b);
+      match f(X, X) with f(a, b);
+      "
+    ).unwrap();
+    let solutions: Vec<Vec<String>> = commands.iter()
+                                              .map(|command| match command {
+                                                Command::Match { pattern, subject } => {
+                                                  pattern.match_all(&subject.dagify(), Substitution::default())
+                                                         .iter()
+                                                         .map(|solution| solution.repr(FormatStyle::Default))
+                                                         .collect()
+                                                }
+                                                _ => unreachable!()
+                                              })
+                                              .collect();
+    assert_eq!(solutions[0], vec!["X := b"]);
+    assert_eq!(solutions[1], vec!["X := b"]);
+    assert_eq!(solutions[2].len(), 2);
+    assert!(solutions[3].is_empty());
+  }
+
+  #[test]
+  fn commutative_nodes_are_constructed_in_canonical_order() {
+    let (module, commands) = parse_to_module_with_commands(
+      "
+      variable X;
+      symbol f [comm];
+      symbol g;
+
+      equation g(f(a, X)) = X;
+
+      reduce f(b, a);
+      reduce f(a, b);
+      reduce g(f(b, a));
+      "
+    ).unwrap();
+    let terms: Vec<RcDagNode> = commands.iter()
+                                        .map(|command| match command {
+                                          Command::Reduce(term) => term.dagify(),
+                                          _ => unreachable!()
+                                        })
+                                        .collect();
+    assert!(terms[0].borrow().equals(&terms[1].borrow()));
+    assert_eq!(terms[0].borrow_mut().structural_hash(), terms[1].borrow_mut().structural_hash());
+    assert_eq!(terms[0].borrow().to_string(), "f(a, b)");
+
+    // The arguments are put back into order when they are rewritten.
+    let (a, b)  = (terms[0].borrow().args[0].clone(), terms[0].borrow().args[1].clone());
+    let rebuilt = terms[0].borrow().with_args(vec![b, a]);
+    assert!(rebuilt.equals(&terms[0].borrow()));
+
+    let (result, _) = module.reduce(&mut Term::from_dag(&terms[2].borrow())).unwrap();
+    assert_eq!(result.borrow().to_string(), "b");
+  }
+}
@@ -2,11 +2,84 @@
 
 To allow for sharing of common subexpressions (Cons hashing), terms are transformed into a directed acyclic graph (DAG).
 
+## Comparison and Hashing
+
+The structural hash of a `DagNode` is computed from the hash value of its top symbol and the structural hashes of
+its arguments, and is cached in the node, which is marked with `DagNodeAttribute::HashValid`. Structurally equal
+nodes have equal hashes, so `DagNode::equals` can reject nodes with different cached hashes without recursing into
+their arguments. Hashes say nothing about order, so `DagNode::compare` always does the full comparison.
+
+## Iterated Symbols
+
+A node of a symbol declared `iter` stands for a tower of applications of the symbol, whose height is the node's
+`exponent`. The hash of such a node is computed from the exponent in constant time, and `Term::structural_hash()`
+hashes the tower of applications in a term the same way, so the two agree. See the `iterated_theory` module.
+
+## Commutative Symbols
+
+The arguments of a node of a symbol that is commutative but not associative are kept in canonical order, so that
+commutatively equal nodes are structurally equal. See the `c_theory` module.
+
+## Associative-Commutative Symbols
+
+A node of a symbol declared `assoc` and `comm` is kept flattened and sorted, with equal arguments stored once together
+with their multiplicity, so `f(b, f(a, b))` is the node `f(a, b, b)` with the arguments `a` and `b` of multiplicities
+1 and 2. Identity elements are dropped. `DagNode::pairs()` gives the arguments with their multiplicities, and
+`DagNode::iter_args()` repeats each argument as many times as its multiplicity. The hash of such a node hashes each
+argument once with its multiplicity, and `Term::structural_hash()` hashes a run of equal arguments in the same way.
+The sort of the node is the sort of the nested binary applications it stands for. See the `acu_theory` module.
+
+## Identity Elements
+
+A node of a symbol with an identity element that is not AC drops the identity elements among its arguments when it
+is made by `DagNode::make(…)`, as `Term::normalize()` does for terms, and collapses to its argument if a single one is
+left. So an instance of a right-hand side collapses during reduction just as a parsed term does.
+
+## Sorts
+
+`DagNode::sort()` computes the sort of a node from the sorts of its arguments and the declarations of its top symbol.
+If the top symbol has declarations but none of them fits the sorts of the arguments, as for `f(true, 1)` when `f` is
+declared `Integer Integer -> Integer`, the node is *error sorted*: its sort is the error sort of the kind of the
+declared result, which is above every sort of the kind. A node with an error sorted argument is error sorted itself,
+since no declaration takes the error sort. The sort of a node whose top symbol has no declarations is unknown.
+
+A membership axiom like `mb f(x) :: Pos if positive(x);` can give a node a smaller sort than its declarations do.
+When a `RewritingContext` reduces a node to normal form, it tries the membership axioms for its top symbol, and each
+one that applies lowers the node's `sort_index`, the index within its kind of the smallest sort found so far.
+`DagNode::sort()` returns that sort in place of the declared one. See `RewritingContext::constrain_to_smaller_sort(…)`.
+
+## Positions
+
+A position is a path of argument indices from the top of a term, so in `f(g(a, b), c)` the position `[0, 1]` is `b`
+and `[]` is the whole term. `DagNode::replace_at(…)` replaces the subterm at a position, rebuilding only the nodes on
+the path to it and sharing the rest with the original. The argument of a tower of an iterated symbol is the tower one
+lower, as for `DagNode::iter_args(…)`.
+
+## Size and Depth
+
+`DagNode::size()` counts the nodes of the term a node represents, counting a shared subterm once for every occurrence,
+and `DagNode::depth()` is the length of its longest path from the top to a constant. Both are cached in the node, in a
+`Cell` so that they can be computed through a shared reference, and the cache is cleared along with the hash when the
+arguments change. `DagNode::size_unique()` counts each shared node once.
+A tower `sⁿ(z)` of an iterated symbol counts as the `n` nested applications it stands for.
+
 */
 
-use crate::abstractions::RcCell;
-use crate::theory::dag_node_attributes::DagNodeAttributes;
-use crate::theory::symbol::SymbolPtr;
+use std::cell::Cell;
+use std::cmp::Ordering;
+use std::fmt::{Display, Formatter};
+
+use crate::abstractions::{hash2, int_to_superscript, HashSet, HashType, IString, NaturalNumber, RcCell};
+use crate::core::format::{FormatStyle, Formattable};
+use crate::core::position_error::PositionError;
+use crate::core::sort::SortPtr;
+use crate::theory::acu_theory::{hash_multiplicity, is_term, make_ac};
+use crate::theory::c_theory::make_commutative;
+use crate::theory::dag_node_attributes::{DagNodeAttribute, DagNodeAttributes};
+use crate::theory::iterated_theory::{make_iterated, make_tower, tower_hash};
+use crate::theory::symbol::{Symbol, SymbolPtr};
+use crate::theory::symbol_type::SymbolAttribute;
+use crate::theory::term::Term;
 
 pub type RcDagNode = RcCell<DagNode>;
 pub type NodeList  = Vec<RcDagNode>;
@@ -21,16 +94,673 @@ pub struct DagNode {
   pub(crate) top_symbol: SymbolPtr,
   pub(crate) args:       NodeList,
   pub(crate) attributes: DagNodeAttributes,
+  /// The index within its kind of the sort given to the node by a membership axiom, or -1 if it has none.
   pub(crate) sort_index: i32,
-  pub(crate) hash:       u32,
+  pub(crate) hash:       HashType,
+  /// The number of times the top symbol is applied to the single argument, if the top symbol is iterated, and 1
+  /// otherwise. See the module documentation.
+  pub(crate) exponent:   NaturalNumber,
+  /// The multiplicity of each of `args`, if the top symbol is AC, and empty otherwise. See the module documentation.
+  pub(crate) multiplicities: Vec<u32>,
+  /// The cached size and depth, if they have been computed. See the module documentation.
+  size_cache:            Cell<Option<(usize, usize)>>,
 }
 
 impl DagNode {
-  /// Returns an iterator over `(RcDagNode, u32)` pairs for the arguments.
+  /// Creates a new `DagNode` with the given top symbol and arguments. The hash is not computed.
+  pub fn new(top_symbol: SymbolPtr, args: NodeList) -> DagNode {
+    DagNode {
+      top_symbol,
+      args,
+      attributes: DagNodeAttributes::default(),
+      sort_index: -1,
+      hash      : 0,
+      exponent  : 1,
+      multiplicities: vec![],
+      size_cache: Cell::new(None),
+    }
+  }
+
+  /// The node for the application of `top_symbol` to `args`, put into the form of the theory of `top_symbol`: an
+  /// application of an iterated symbol to a tower of the same symbol becomes a taller tower, the arguments of an AC
+  /// symbol are flattened, sorted, and merged into multiplicities, identity elements are dropped, and the arguments
+  /// of a commutative symbol are put into canonical order. The hash is not computed.
+  pub fn make(top_symbol: SymbolPtr, mut args: NodeList) -> DagNode {
+    let symbol = unsafe{ &*top_symbol };
+    if symbol.is_iterated() && args.len() == 1 {
+      return make_iterated(top_symbol, 1, args[0].clone());
+    } else if symbol.is_ac() {
+      return make_ac(top_symbol, args.into_iter().map(|dag_node| DagPair { dag_node, multiplicity: 1 }).collect());
+    }
+
+    if let Some(identity) = symbol.identity.as_deref() {
+      if let Some(collapsed) = drop_identities(symbol, identity, &mut args) {
+        return collapsed.borrow().shallow_copy();
+      }
+    }
+    if symbol.is_c() {
+      make_commutative(top_symbol, args)
+    } else {
+      DagNode::new(top_symbol, args)
+    }
+  }
+
+  /// A node with the top symbol of `self` and the arguments `args`, which correspond to `self.args`, as when the
+  /// arguments are reduced. The tower of an iterated symbol keeps its height, and combines with the new argument if
+  /// that is a tower of the same symbol. The arguments of an AC node keep their multiplicities. Otherwise the node is
+  /// made by `DagNode::make(…)`. The hash is not computed.
+  pub fn with_args(&self, args: NodeList) -> DagNode {
+    if self.exponent > 1 {
+      make_iterated(self.top_symbol, self.exponent, args[0].clone())
+    } else if unsafe{ (*self.top_symbol).is_ac() } {
+      let pairs = args.into_iter()
+                      .enumerate()
+                      .map(|(index, dag_node)| DagPair { dag_node, multiplicity: self.multiplicity(index) })
+                      .collect();
+      make_ac(self.top_symbol, pairs)
+    } else {
+      DagNode::make(self.top_symbol, args)
+    }
+  }
+
+  /// A new node with the same top symbol, arguments, and structural attributes as `self`, as when an AC application
+  /// collapses to one of its arguments. The arguments are shared.
+  pub(crate) fn shallow_copy(&self) -> DagNode {
+    let mut node = DagNode::new(self.top_symbol, self.args.clone());
+    node.attributes     = self.attributes & !DagNodeAttribute::TransientFlags;
+    node.sort_index     = self.sort_index;
+    node.hash           = self.hash;
+    node.exponent       = self.exponent;
+    node.multiplicities = self.multiplicities.clone();
+    node.size_cache     = self.size_cache.clone();
+    node
+  }
+
+  /// The multiplicity of argument `index`, which is 1 unless the node is an AC node. See the module documentation.
+  #[inline(always)]
+  pub fn multiplicity(&self, index: usize) -> u32 {
+    self.multiplicities.get(index).copied().unwrap_or(1)
+  }
+
+  /// The arguments of the node with their multiplicities. See the module documentation.
+  pub fn pairs(&self) -> impl Iterator<Item = DagPair> + '_ {
+    self.args
+        .iter()
+        .enumerate()
+        .map(|(index, dag_node)| DagPair { dag_node: dag_node.clone(), multiplicity: self.multiplicity(index) })
+  }
+
+  /// The number of arguments of the term the node represents, counting each argument of an AC node as many times as
+  /// its multiplicity.
+  pub fn arg_count(&self) -> usize {
+    match self.multiplicities.is_empty() {
+      true  => self.args.len(),
+      false => self.multiplicities.iter().map(|multiplicity| *multiplicity as usize).sum()
+    }
+  }
+
+  /// The sort of the node, computed from the sorts of its arguments and the declarations of its top symbol. The error
+  /// sort if no declaration of the top symbol fits, and `None` if the top symbol has no declarations. See the module
+  /// documentation and `Symbol::result_sort(…)`.
+  pub fn sort(&self) -> Option<SortPtr> {
+    let top_symbol  = unsafe{ &*self.top_symbol };
+    let result_sort = | arg_sorts: &[Option<SortPtr>] | {
+      top_symbol.result_sort(arg_sorts).or_else(
+        || top_symbol.range_sort()
+                     .filter(|range| unsafe{ !(**range).kind.is_null() })
+                     .map(|range| unsafe{ (*(*range).kind).error_sort() })
+      )
+    };
+    let mut sort = match self.arg_count() > 2 && !self.multiplicities.is_empty() {
+      true  => self.ac_sort(result_sort),
+      false => {
+        let arg_sorts: Vec<Option<SortPtr>> = self.args.iter().map(|arg| arg.borrow().sort()).collect();
+        result_sort(&arg_sorts)
+      }
+    };
+    // Each application in a tower takes the sort of the one below it. `sorts[i]` is the sort of the partial tower of
+    // `i + 1` applications. Once a sort repeats, the rest of the tower cycles through the sorts since the repeat.
+    let mut sorts = vec![sort];
+    while (sorts.len() as NaturalNumber) < self.exponent {
+      let next = result_sort(&[sort]);
+      if let Some(start) = sorts.iter().position(|earlier| *earlier == next) {
+        let period = (sorts.len() - start) as NaturalNumber;
+        sort       = sorts[start + ((self.exponent - 1 - start as NaturalNumber) % period) as usize];
+        break;
+      }
+      sorts.push(next);
+      sort = next;
+    }
+    sort.map(|sort| self.constrained_sort(sort))
+  }
+
+  /// The sort of an AC node as nested binary applications, where `result_sort` gives the sort of an application to
+  /// arguments of the given sorts. Once another copy of an argument leaves the sort unchanged, so does every further
+  /// copy, so the sort is computed without going through each copy.
+  fn ac_sort(&self, result_sort: impl Fn(&[Option<SortPtr>]) -> Option<SortPtr>) -> Option<SortPtr> {
+    let mut sort = None;
+    let mut first = true;
+    for pair in self.pairs() {
+      let arg_sort = pair.dag_node.borrow().sort();
+      for _ in 0..pair.multiplicity {
+        if first {
+          sort  = arg_sort;
+          first = false;
+          continue;
+        }
+        let next = result_sort(&[sort, arg_sort]);
+        if next == sort {
+          break;
+        }
+        sort = next;
+      }
+    }
+    sort
+  }
+
+  /// The sort given to the node by a membership axiom if it is below `sort`, the sort of the node by its
+  /// declarations, and `sort` otherwise.
+  fn constrained_sort(&self, sort: SortPtr) -> SortPtr {
+    let kind = unsafe{ (*sort).kind };
+    if self.sort_index < 0 || kind.is_null() {
+      return sort;
+    }
+    match unsafe{ (&*kind).sorts.get(self.sort_index as usize) } {
+      Some(&constrained) if unsafe{ (*constrained).leq_sort(&*sort) } => constrained,
+      _ => sort
+    }
+  }
+
+  /// Records that a membership axiom gives the node `sort`, which is below the sort it has so far.
+  pub(crate) fn constrain_sort(&mut self, sort: SortPtr) {
+    self.sort_index = unsafe{ (*sort).index_within_kind } as i32;
+  }
+
+  /// Whether no declaration of the top symbol fits the sorts of the arguments. See the module documentation.
+  pub fn is_error_sorted(&self) -> bool {
+    self.sort().is_some_and(|sort| unsafe{ (*sort).is_error_sort() })
+  }
+
+  /// Appends `child` to the arguments of the node, invalidating the cached hash. Inserting more arguments than a
+  /// fixed-arity top symbol takes is a construction bug, which is caught by a debug assertion. See
+  /// `DagNode::try_insert_child(…)` for the checked version.
+  pub fn insert_child(&mut self, child: RcDagNode) {
+    debug_assert!(
+      !self.is_full(),
+      "inserted too many arguments into a node with top symbol {}", unsafe{ &(*self.top_symbol).name }
+    );
+    self.args.push(child);
+    if !self.multiplicities.is_empty() {
+      self.multiplicities.push(1);
+    }
+    self.attributes.remove(DagNodeAttribute::HashValid);
+    self.size_cache.set(None);
+  }
+
+  /// Appends `child` to the arguments of the node like `DagNode::insert_child(…)`, but if the top symbol has a fixed
+  /// arity that the node already has, `child` is given back instead.
+  pub fn try_insert_child(&mut self, child: RcDagNode) -> Result<(), RcDagNode> {
+    if self.is_full() {
+      return Err(child);
+    }
+    self.insert_child(child);
+    Ok(())
+  }
+
+  /// Whether the top symbol has a fixed arity and the node already has that many arguments.
+  fn is_full(&self) -> bool {
+    let arity = unsafe{ (*self.top_symbol).arity };
+    arity >= 0 && self.args.len() >= arity as usize
+  }
+
+  /// Returns an iterator over the arguments. The argument of a tower `s³(z)` of an iterated symbol is the tower
+  /// `s²(z)`, which is only constructed when the iterator reaches it. An argument of an AC node is repeated as many
+  /// times as its multiplicity.
+  #[inline(always)]
+  pub fn iter_args(&self) -> Box<dyn Iterator<Item = RcDagNode> + '_> {
+    if self.exponent > 1 {
+      Box::new(std::iter::once_with(|| make_tower(self.top_symbol, self.exponent - 1, self.args[0].clone())))
+    } else if !self.multiplicities.is_empty() {
+      Box::new(self.pairs().flat_map(|pair| std::iter::repeat_n(pair.dag_node, pair.multiplicity as usize)))
+    } else {
+      Box::new(self.args.iter().cloned())
+    }
+  }
+
+  /// The term with the subterm at `position` replaced by `replacement`. Nodes off the path to the position are shared
+  /// with `self`. Fails if the position does not exist. See the module documentation.
+  pub fn replace_at(&self, position: &[usize], replacement: RcDagNode) -> Result<RcDagNode, PositionError> {
+    self.replace_below(position, 0, replacement)
+  }
+
+  /// Replaces the subterm at the rest of `position` after its first `depth` indices, which lead to `self`.
+  fn replace_below(
+    &self,
+    position   : &[usize],
+    depth      : usize,
+    replacement: RcDagNode
+  ) -> Result<RcDagNode, PositionError>
+  {
+    let index = match position.get(depth) {
+      Some(index) => *index,
+      None        => return Ok(replacement)
+    };
+    let mut args: NodeList = self.iter_args().collect();
+    let arg = match args.get(index) {
+      Some(arg) => arg.clone(),
+      None      => return Err(PositionError { position: position.to_vec(), depth, arg_count: args.len() })
+    };
+    args[index] = arg.borrow().replace_below(position, depth + 1, replacement)?;
+
+    // Only the application on top of a tower is rebuilt, since the argument is the rest of the tower.
+    let mut node = DagNode::make(self.top_symbol, args);
+    node.structural_hash();
+    Ok(RcCell::new(node))
+  }
+
+  /// Returns the structural hash of the node, computing and caching it (and the hashes of its arguments) if it is not
+  /// already valid.
+  pub fn structural_hash(&mut self) -> HashType {
+    if !self.attributes.contains(DagNodeAttribute::HashValid) {
+      let symbol_hash = unsafe{ (*self.top_symbol).hash_value() };
+      self.hash = match self.exponent > 1 {
+        true  => tower_hash(symbol_hash, self.exponent, self.args[0].borrow_mut().structural_hash()),
+        false => self.pairs().fold(
+          symbol_hash,
+          |hash, pair| hash_multiplicity(hash, pair.dag_node.borrow_mut().structural_hash(), pair.multiplicity)
+        )
+      };
+      self.attributes.insert(DagNodeAttribute::HashValid);
+    }
+    self.hash
+  }
+
+  /// The number of nodes in the term the node represents, counting shared subterms once per occurrence, computing and
+  /// caching it (and the sizes of its arguments) if it is not already valid. See the module documentation.
+  pub fn size(&self) -> usize {
+    self.size_and_depth().0
+  }
+
+  /// The number of nodes on the longest path from the node to a constant, which has depth 1, computing and caching it
+  /// like `DagNode::size()`.
+  pub fn depth(&self) -> usize {
+    self.size_and_depth().1
+  }
+
+  fn size_and_depth(&self) -> (usize, usize) {
+    if let Some(size_and_depth) = self.size_cache.get() {
+      return size_and_depth;
+    }
+    let mut size  = 0usize;
+    let mut depth = 0usize;
+    for (index, arg) in self.args.iter().enumerate() {
+      let (arg_size, arg_depth) = arg.borrow().size_and_depth();
+      // Shared subterms make the size exponential in the depth in the worst case.
+      size  = size.saturating_add(arg_size.saturating_mul(self.multiplicity(index) as usize));
+      depth = depth.max(arg_depth);
+    }
+    let exponent       = usize::try_from(self.exponent).unwrap_or(usize::MAX);
+    let size_and_depth = (size.saturating_add(exponent), depth.saturating_add(exponent));
+    self.size_cache.set(Some(size_and_depth));
+    size_and_depth
+  }
+
+  /// The number of distinct nodes in the term the node represents, counting a subterm shared by several nodes once.
+  pub fn size_unique(&self) -> usize {
+    fn visit(node: &DagNode, visited: &mut HashSet<*const DagNode>) -> usize {
+      let exponent = usize::try_from(node.exponent).unwrap_or(usize::MAX);
+      node.args.iter().fold(exponent, |size, arg| {
+        let arg = arg.borrow();
+        match visited.insert(&*arg as *const DagNode) {
+          true  => size.saturating_add(visit(&arg, visited)),
+          false => size
+        }
+      })
+    }
+    visit(self, &mut HashSet::new())
+  }
+
+  /// The distinct variables of the term the node represents, with their declared sorts, in the order they first
+  /// occur from left to right, like `Term::variables(…)`. A shared subterm is visited once.
+  pub fn variables(&self) -> Vec<(IString, Option<SortPtr>)> {
+    fn visit(node: &DagNode, visited: &mut HashSet<*const DagNode>, variables: &mut Vec<SymbolPtr>) {
+      let symbol = node.top_symbol;
+      if unsafe{ (*symbol).is_variable() } && !variables.contains(&symbol) {
+        variables.push(symbol);
+      }
+      for arg in node.args.iter() {
+        let arg = arg.borrow();
+        if visited.insert(&*arg as *const DagNode) {
+          visit(&arg, visited, variables);
+        }
+      }
+    }
+    let mut variables = Vec::new();
+    visit(self, &mut HashSet::new(), &mut variables);
+    variables.into_iter().map(|variable| unsafe{ ((*variable).name, (*variable).range_sort()) }).collect()
+  }
+
+  /// Clears the transient attributes of the node, leaving the structural attributes `Ground` and `HashValid`. The
+  /// arguments of the node are not affected. See the `dag_node_attributes` module.
+  #[inline(always)]
+  pub fn clear_transient_flags(&mut self) {
+    self.attributes &= !DagNodeAttribute::TransientFlags;
+  }
+
+  /// Returns the cached structural hash, if it is valid.
   #[inline(always)]
-  fn iter_args(&self) -> Box<dyn Iterator<Item = RcDagNode> + '_> {
-    Box::new(self.dag_node_members().args.iter().cloned())
+  pub fn cached_hash(&self) -> Option<HashType> {
+    if self.attributes.contains(DagNodeAttribute::HashValid) {
+      Some(self.hash)
+    } else {
+      None
+    }
+  }
+
+  /// A total order on structurally distinct nodes: top symbols are compared first, then arguments from left to right.
+  pub fn compare(&self, other: &DagNode) -> Ordering {
+    if std::ptr::eq(self, other) {
+      return Ordering::Equal;
+    }
+    let (symbol, other_symbol) = unsafe{ (&*self.top_symbol, &*other.top_symbol) };
+    symbol.cmp(other_symbol)
+          .then_with(|| self.exponent.cmp(&other.exponent))
+          .then_with(|| self.compare_arguments(other))
+  }
+
+  /// Compares the arguments of two nodes having the same top symbol, as the sequences of arguments of the terms they
+  /// represent. The arguments of AC nodes are compared a run of copies at a time.
+  fn compare_arguments(&self, other: &DagNode) -> Ordering {
+    self.arg_count().cmp(&other.arg_count()).then_with(|| {
+      // The index of the current argument of each node, and how many of its copies are left to compare.
+      let (mut index, mut other_index) = (0, 0);
+      let (mut left, mut other_left)   = (self.multiplicity(0), other.multiplicity(0));
+      while index < self.args.len() && other_index < other.args.len() {
+        let (arg, other_arg) = (&self.args[index], &other.args[other_index]);
+        if !RcCell::ptr_eq(arg, other_arg) {
+          let result = arg.borrow().compare(&other_arg.borrow());
+          if result != Ordering::Equal {
+            return result;
+          }
+        }
+        let compared = left.min(other_left);
+        left       -= compared;
+        other_left -= compared;
+        if left == 0 {
+          index += 1;
+          left   = self.multiplicity(index);
+        }
+        if other_left == 0 {
+          other_index += 1;
+          other_left   = other.multiplicity(other_index);
+        }
+      }
+      Ordering::Equal
+    })
+  }
+
+  /// Structural equality. Nodes with different cached hashes are unequal, which is checked before doing the full
+  /// comparison.
+  pub fn equals(&self, other: &DagNode) -> bool {
+    if std::ptr::eq(self, other) {
+      return true;
+    }
+    if let (Some(hash), Some(other_hash)) = (self.cached_hash(), other.cached_hash()) {
+      if hash != other_hash {
+        return false;
+      }
+    }
+    self.compare(other) == Ordering::Equal
   }
+}
+
+impl Formattable for DagNode {
+  fn repr(&self, style: FormatStyle) -> String {
+    if self.args.is_empty() {
+      unsafe{ (*self.top_symbol).repr(style) }
+    } else if self.exponent > 1 {
+      let symbol = unsafe{ (*self.top_symbol).repr(style) };
+      format!("{}{}({})", symbol, int_to_superscript(self.exponent as i64), self.args[0].borrow().repr(style))
+    } else {
+      let args: Vec<String> = self.iter_args().map(|arg| arg.borrow().repr(style)).collect();
+      unsafe{ (*self.top_symbol).repr_application(&args, style) }
+    }
+  }
+}
+
+impl Display for DagNode {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    write!(f, "{}", self.repr(FormatStyle::Default))
+  }
+}
+
+/// Drops the arguments of an application of `symbol`, which has the identity element `identity` and is not AC, that
+/// are the identity, as `Term::normalize()` does: an identity is dropped if it has an argument to its right and
+/// `symbol` has a left identity, or an argument to its left and `symbol` has a right identity. Returns the argument
+/// the application collapses to if a single one is left.
+fn drop_identities(symbol: &Symbol, identity: &Term, args: &mut NodeList) -> Option<RcDagNode> {
+  let left          = symbol.symbol_type.attributes.contains(SymbolAttribute::LeftIdentity);
+  let right         = symbol.symbol_type.attributes.contains(SymbolAttribute::RightIdentity);
+  let mut collapsed = false;
+  let mut index     = 0;
+  while args.len() > 1 && index < args.len() {
+    if is_term(&args[index].borrow(), identity) && ((left && index + 1 < args.len()) || (right && index > 0)) {
+      args.remove(index);
+      collapsed = true;
+    } else {
+      index += 1;
+    }
+  }
+  match collapsed && args.len() == 1 {
+    true  => args.pop(),
+    false => None
+  }
+}
+
+
+#[cfg(test)]
+mod tests {
+  use crate::{
+    abstractions::IString,
+    core::command::Command,
+    heap_construct,
+    parser::parse_to_module_with_commands,
+    theory::symbol::Symbol
+  };
+  use super::*;
+
+  fn leaf(name: &str) -> RcDagNode {
+    RcCell::new(DagNode::new(heap_construct!(Symbol::new(IString::from(name))), vec![]))
+  }
+
+  #[test]
+  fn equals_agrees_with_structure() {
+    let f = heap_construct!(Symbol::new(IString::from("f")));
+    let a = leaf("a");
+    let b = leaf("b");
+    let mut fab     = DagNode::new(f, vec![a.clone(), b.clone()]);
+    let mut fab_too = DagNode::new(f, vec![leaf("a"), leaf("b")]);
+    let mut fba     = DagNode::new(f, vec![b.clone(), a.clone()]);
 
+    // Without cached hashes, the full comparison is done.
+    assert!(fab.equals(&fab_too));
+    assert!(!fab.equals(&fba));
 
+    assert_eq!(fab.structural_hash(), fab_too.structural_hash());
+    assert_ne!(fab.structural_hash(), fba.structural_hash());
+    assert!(fab.equals(&fab_too));
+    assert!(!fab.equals(&fba));
+    assert_eq!(fab.compare(&fba), fba.compare(&fab).reverse());
+  }
+
+  #[test]
+  fn equals_short_circuits_on_unequal_hashes() {
+    let f = heap_construct!(Symbol::new(IString::from("f")));
+    let mut node  = DagNode::new(f, vec![leaf("a")]);
+    let mut other = DagNode::new(f, vec![leaf("a")]);
+    node.structural_hash();
+    other.structural_hash();
+
+    // Force the cached hashes to disagree. A structurally equal node is then rejected without a full comparison.
+    other.hash = node.hash.wrapping_add(1);
+    assert_eq!(node.compare(&other), Ordering::Equal);
+    assert!(!node.equals(&other));
+  }
+
+  #[test]
+  fn shared_subterms_count_once_in_the_unique_size() {
+    let f = heap_construct!(Symbol::new(IString::from("f")));
+    let g = leaf("g");
+    let mut diamond = DagNode::new(f, vec![g.clone(), g.clone()]);
+    assert_eq!(diamond.size(), 3);
+    assert_eq!(diamond.size_unique(), 2);
+    assert_eq!(diamond.depth(), 2);
+
+    // Inserting a child invalidates the cached size and depth.
+    diamond.insert_child(RcCell::new(DagNode::new(f, vec![leaf("a")])));
+    assert_eq!(diamond.cached_hash(), None);
+    assert_eq!(diamond.size(), 5);
+    assert_eq!(diamond.size_unique(), 4);
+    assert_eq!(diamond.depth(), 3);
+
+    // A tower counts as the applications it stands for.
+    let tower = make_iterated(heap_construct!(Symbol::new(IString::from("s"))), 1000, g);
+    assert_eq!(tower.size(), 1001);
+    assert_eq!(tower.size_unique(), 1001);
+    assert_eq!(tower.depth(), 1001);
+  }
+
+  #[test]
+  fn children_cannot_exceed_the_arity_of_the_top_symbol() {
+    let mut f = Symbol::new(IString::from("f"));
+    f.arity   = 2;
+    let mut node = DagNode::new(heap_construct!(f), vec![leaf("a")]);
+    let hash     = node.structural_hash();
+
+    assert!(node.try_insert_child(leaf("b")).is_ok());
+    assert_eq!(node.cached_hash(), None);
+    assert_ne!(node.structural_hash(), hash);
+
+    let rejected = node.try_insert_child(leaf("c")).err().unwrap();
+    assert_eq!(rejected.borrow().to_string(), "c");
+    assert_eq!(node.args.len(), 2);
+
+    // A symbol of unspecified arity takes any number of arguments.
+    let mut g = DagNode::new(heap_construct!(Symbol::new(IString::from("g"))), vec![]);
+    for _ in 0..3 {
+      assert!(g.try_insert_child(leaf("a")).is_ok());
+    }
+  }
+
+  #[test]
+  #[should_panic(expected = "too many arguments")]
+  #[cfg(debug_assertions)]
+  fn inserting_too_many_children_panics_in_debug_builds() {
+    let mut f = Symbol::new(IString::from("f"));
+    f.arity   = 0;
+    DagNode::new(heap_construct!(f), vec![]).insert_child(leaf("a"));
+  }
+
+  #[test]
+  fn clearing_transient_flags_keeps_structural_flags() {
+    let f = heap_construct!(Symbol::new(IString::from("f")));
+    let mut node = DagNode::new(f, vec![leaf("a")]);
+    node.structural_hash();
+    node.attributes |= DagNodeAttribute::Reduced | DagNodeAttribute::Copied | DagNodeAttribute::Unstackable
+        | DagNodeAttribute::Ground;
+
+    node.clear_transient_flags();
+    assert_eq!(node.attributes, DagNodeAttribute::Ground | DagNodeAttribute::HashValid);
+    assert_eq!(node.cached_hash(), Some(node.structural_hash()));
+  }
+
+  #[test]
+  fn replacing_at_a_position_shares_the_rest_of_the_term() {
+    let f = heap_construct!(Symbol::new(IString::from("f")));
+    let g = heap_construct!(Symbol::new(IString::from("g")));
+    let (a, b, c) = (leaf("a"), leaf("b"), leaf("c"));
+    let gab  = RcCell::new(DagNode::new(g, vec![a.clone(), b.clone()]));
+    let term = DagNode::new(f, vec![gab.clone(), c.clone()]);
+
+    let result = term.replace_at(&[0, 1], leaf("X")).unwrap();
+    assert_eq!(result.borrow().to_string(), "f(g(a, X), c)");
+    assert!(RcCell::ptr_eq(&result.borrow().args[1], &c));
+    assert!(RcCell::ptr_eq(&result.borrow().args[0].borrow().args[0], &a));
+    // The original is unchanged.
+    assert_eq!(term.to_string(), "f(g(a, b), c)");
+    assert!(RcCell::ptr_eq(&term.args[0], &gab));
+
+    assert_eq!(term.replace_at(&[], b.clone()).unwrap().borrow().to_string(), "b");
+    let error = term.replace_at(&[0, 2], leaf("X")).err().unwrap();
+    assert_eq!((error.depth, error.arg_count), (1, 2));
+    assert_eq!(
+      term.replace_at(&[1, 0], leaf("X")).err().unwrap().to_string(),
+      "the term has no subterm at position [1, 0]: the subterm at [1] has 0 arguments."
+    );
+  }
+
+  #[test]
+  fn ill_sorted_terms_are_error_sorted() {
+    let (module, commands) = parse_to_module_with_commands(
+      "
+      sort Bool;
+      sort Integer;
+      symbol t :: Bool;
+      symbol one :: Integer;
+      symbol f :: Integer Integer -> Integer;
+      symbol g :: Integer -> Integer;
+      variable X :: Integer;
+      variable Y :: Integer;
+
+      equation f(X, Y) = one;
+
+      reduce f(t, one);
+      reduce g(f(t, one));
+      reduce g(f(one, one));
+      "
+    ).unwrap();
+    let results: Vec<RcDagNode> = commands.iter()
+                                          .map(|command| match command {
+                                            Command::Reduce(term) => module.reduce_dag(&term.dagify()),
+                                            _ => unreachable!()
+                                          })
+                                          .collect();
+
+    // The equation does not apply to the ill-sorted term, which is left in the error sort of its kind.
+    assert_eq!(results[0].borrow().to_string(), "f(t, one)");
+    assert!(results[0].borrow().is_error_sorted());
+    let sort = results[0].borrow().sort().unwrap();
+    assert!(unsafe{ (*sort).is_error_sort() && (*sort).kind == (*module.sorts.get("Integer".into()).unwrap()).kind });
+    // The error sort propagates to the enclosing term.
+    assert!(results[1].borrow().is_error_sorted());
+    assert_eq!(results[2].borrow().to_string(), "g(one)");
+    assert!(!results[2].borrow().is_error_sorted());
+  }
+
+  #[test]
+  fn identities_collapse_in_instances_of_right_hand_sides() {
+    let (module, _) = parse_to_module_with_commands(
+      "
+      variable X;
+      symbol f [id(e)];
+      symbol l [left id(e)];
+      symbol g;
+      symbol h;
+
+      equation g(X) = f(X, e);
+      equation h(a) = ok;
+      equation h(X) = l(X, e);
+      "
+    ).unwrap();
+    let eval = | source: &str | module.eval(source).unwrap().borrow().to_string();
+    assert_eq!(eval("g(a)"), "a");
+    assert_eq!(eval("h(g(a))"), "ok");
+    // A left identity only collapses with an argument to its right.
+    assert_eq!(eval("h(ok)"), "l(ok, e)");
+
+    let f    = module.symbols[&IString::from("f")];
+    let e    = module.eval("e").unwrap();
+    let node = DagNode::make(f, vec![e.clone(), e.clone()]);
+    assert_eq!(node.to_string(), "e");
+  }
 }
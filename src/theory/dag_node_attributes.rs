@@ -1,5 +1,14 @@
 /*!
 Boolean `DagNode` attributes.
+
+The attributes are either structural or transient. The *structural* attributes, `Ground` and `HashValid`, describe
+the term the node represents, so they stay valid for as long as the node's arguments do not change. The *transient*
+attributes, `Reduced`, `Unrewritable`, `Unstackable`, `Copied`, and `Reducing`, record the progress of a particular
+reduction, rewrite, or copy operation, and they are meaningless outside of it. `DagNode::clear_transient_flags(…)`
+clears the transient attributes so that a node can be reused, for example by a node pool or in another module.
+
+`RewritingFlags` are the attributes that a copy of a node inherits from the original. They are the transient
+attributes other than `Copied`, together with `Ground`.
 */
 
 use enumflags2::{bitflags, BitFlags, make_bitflags};
@@ -15,6 +24,7 @@ pub enum DagNodeAttribute {
   Unstackable,  // Unrewritable and all subterms unstackable or frozen
   Ground,       // No variables occur below this node
   HashValid,    // Node has a valid hash value (storage is theory dependent)
+  Reducing,     // Equations are being tried at the top of this node, so its reduction is in progress
 }
 
 pub type DagNodeAttributes = BitFlags<DagNodeAttribute, u32>;
@@ -34,6 +44,14 @@ impl DagNodeAttribute {
     }
   );
 
+  /// The attributes describing the state of an operation on the node rather than the node itself. See the module
+  /// documentation.
+  pub const TransientFlags: DagNodeAttributes = make_bitflags!(
+    DagNodeAttribute::{
+      Reduced | Copied | Unrewritable | Unstackable | Reducing
+    }
+  );
+
   pub fn set_copied_flags(flags: &mut DagNodeAttributes, other_flags: DagNodeAttributes) {
     *flags |= DagNodeAttribute::RewritingFlags & other_flags;
   }
@@ -0,0 +1,91 @@
+/*!
+
+A `DagNodeCache` implements hash consing: it maps structurally equal DAG nodes to a single shared node. Nodes are
+bucketed by structural hash, and a hash hit is only a candidate. The nodes of a bucket are compared with
+`DagNode::equals`, so nodes with colliding hashes are never shared.
+
+The cache is used while dagifying a term, so that repeated subterms of the term, including repeated built-in
+constants like the two `1`s of `f(1, 1)`, become a single node. `DagNodeCache::cache_stats(…)` counts the lookups
+that found a node, those that did not, and those that found only nodes with a colliding hash. The hash is
+configurable with `DagNodeCache::with_hasher(…)`, which is how a cache with deliberately colliding hashes is tested.
+
+*/
+
+use crate::{
+  abstractions::{
+    HashMap,
+    HashType,
+    RcCell
+  },
+  theory::dag_node::{
+    DagNode,
+    RcDagNode
+  }
+};
+
+/// The outcomes of the lookups of a `DagNodeCache`.
+#[derive(Copy, Clone, Eq, PartialEq, Default, Debug)]
+pub struct CacheStats {
+  /// Lookups that found a structurally equal node.
+  pub hits      : usize,
+  /// Lookups that cached a new node.
+  pub misses    : usize,
+  /// The misses that found nodes with the same hash, none of them structurally equal.
+  pub collisions: usize,
+}
+
+pub struct DagNodeCache {
+  nodes : HashMap<HashType, Vec<RcDagNode>>,
+  hasher: fn(&mut DagNode) -> HashType,
+  stats : CacheStats,
+}
+
+impl Default for DagNodeCache {
+  fn default() -> Self {
+    DagNodeCache::with_hasher(DagNode::structural_hash)
+  }
+}
+
+impl DagNodeCache {
+  /// An empty cache that buckets nodes by `hasher` instead of `DagNode::structural_hash`. Nodes are only given their
+  /// structural hashes if `hasher` computes them.
+  pub fn with_hasher(hasher: fn(&mut DagNode) -> HashType) -> Self {
+    DagNodeCache {
+      nodes: HashMap::default(),
+      hasher,
+      stats: CacheStats::default(),
+    }
+  }
+
+  /// Returns the cached node structurally equal to `dag_node` if there is one. Otherwise, `dag_node` is cached and
+  /// returned.
+  pub fn get_or_insert(&mut self, mut dag_node: DagNode) -> RcDagNode {
+    let bucket = self.nodes.entry((self.hasher)(&mut dag_node)).or_default();
+    if let Some(cached) = bucket.iter().find(|cached| cached.borrow().equals(&dag_node)) {
+      self.stats.hits += 1;
+      return cached.clone();
+    }
+
+    self.stats.misses += 1;
+    if !bucket.is_empty() {
+      self.stats.collisions += 1;
+    }
+    let dag_node = RcCell::new(dag_node);
+    bucket.push(dag_node.clone());
+    dag_node
+  }
+
+  /// The outcomes of the lookups so far.
+  pub fn cache_stats(&self) -> CacheStats {
+    self.stats
+  }
+
+  /// The number of distinct nodes in the cache.
+  pub fn len(&self) -> usize {
+    self.nodes.values().map(|bucket| bucket.len()).sum()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.nodes.is_empty()
+  }
+}
@@ -0,0 +1,10 @@
+use crate::theory::symbol::TheorySymbol;
+
+/// The theory symbol of a unary symbol declared `iter`. Applications of such a symbol are stored with an exponent and
+/// matched by `iterated_theory::match_iterated(…)`.
+#[derive(Copy, Clone, Default)]
+pub struct IteratedSymbol {}
+
+impl TheorySymbol for IteratedSymbol {
+
+}
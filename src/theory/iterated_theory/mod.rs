@@ -0,0 +1,286 @@
+/*!
+
+The theory of unary symbols declared `iter`, like the successor symbol in `symbol s [iter];`. A tower of applications
+of such a symbol is stored as a single node with an exponent, so `s(s(s(z)))` is one node for `s³(z)` over the node
+for `z`, and `s¹⁰⁰⁰⁰⁰⁰(z)` takes two nodes rather than a million. A node is put into this form whenever it is
+constructed, so a node of an iterated symbol never has an argument with the same top symbol, and structurally equal
+towers have equal exponents and arguments.
+
+The arguments of an iterated node are expanded lazily by `DagNode::iter_args()`: the argument of `s³(z)` is `s²(z)`,
+a node that is only created when it is asked for.
+
+## Matching
+
+A pattern with an iterated symbol on top is a tower too. Matching the pattern `s(s(X))` against the subject `s⁵(z)`
+peels the two applications of `s` off the subject and matches `X` against what is left, binding `X` to `s³(z)`. A
+pattern tower that is taller than the subject's does not match.
+
+## Rewriting
+
+A tower is rewritten as the nested applications it stands for. Reduction reduces a tower of a symbol that has
+equations one application at a time from the bottom up, so `s(s(s(z)))` reduces to `s(s(a))` with the equation
+`s(z) = a`. Rules are tried at the partial towers from the tallest down, and the position `[0]` in `s³(z)` is `s²(z)`.
+A partial tower is only constructed if a statement for the iterated symbol could apply to it, so a tall tower of a
+symbol without statements is rewritten in time independent of its height. See the `rewriting_context` module.
+
+## Hashing and Sorts
+
+The structural hash of a tower is computed from the hash of its argument and its exponent in constant time by
+`tower_hash(…)`, rather than by hashing each application. The sort of a tower is the sort of its argument carried up
+through the declarations of the symbol. The sorts of the partial towers repeat after at most as many applications as
+there are sorts, so `DagNode::sort()` finds the sort at the top from the cycle they fall into.
+
+*/
+
+pub(crate) mod iterated_symbol;
+
+use crate::{
+  abstractions::{
+    hash2,
+    HashType,
+    NaturalNumber,
+    RcCell
+  },
+  core::substitution::Substitution,
+  theory::{
+    dag_node::{
+      DagNode,
+      RcDagNode
+    },
+    symbol::SymbolPtr,
+    term::{
+      Term,
+      TermNode
+    }
+  }
+};
+
+/**
+The node for `symbol` applied `exponent` times to `arg`, which must be at least one. If `arg` is itself a tower of
+`symbol`, the towers are combined, so that the result is in the form described in the module documentation. The hash
+is not computed.
+*/
+pub(crate) fn make_iterated(symbol: SymbolPtr, exponent: NaturalNumber, arg: RcDagNode) -> DagNode {
+  debug_assert!(exponent > 0, "an iterated symbol must be applied at least once");
+  let (exponent, arg) = {
+    let dag_node = arg.borrow();
+    match unsafe{ *dag_node.top_symbol == *symbol } && dag_node.args.len() == 1 {
+      true  => (exponent + dag_node.exponent, dag_node.args[0].clone()),
+      false => (exponent, arg.clone())
+    }
+  };
+  let mut node  = DagNode::new(symbol, vec![arg]);
+  node.exponent = exponent;
+  node
+}
+
+/// The node made by `make_iterated(…)`, with its hash computed.
+pub(crate) fn make_tower(symbol: SymbolPtr, exponent: NaturalNumber, arg: RcDagNode) -> RcDagNode {
+  let mut node = make_iterated(symbol, exponent, arg);
+  node.structural_hash();
+  RcCell::new(node)
+}
+
+/// The structural hash of the tower of `exponent` applications of the symbol with hash `symbol_hash` to an argument
+/// with hash `arg_hash`. A single application hashes like an application of any other symbol.
+pub(crate) fn tower_hash(symbol_hash: HashType, exponent: NaturalNumber, arg_hash: HashType) -> HashType {
+  let hash = hash2(symbol_hash, arg_hash);
+  match exponent > 1 {
+    true  => hash2(hash, (exponent ^ (exponent >> 32)) as HashType),
+    false => hash
+  }
+}
+
+/**
+Every extension of `substitution` under which the application `pattern` of the iterated symbol `symbol` matches
+`subject`. See the module documentation.
+*/
+pub(crate) fn match_iterated(
+  symbol: SymbolPtr,
+  pattern: &Term,
+  subject: &RcDagNode,
+  substitution: Substitution
+) -> Vec<Substitution>
+{
+  let (height, inner_pattern) = pattern_tower(symbol, pattern);
+  let inner_subject = {
+    let dag_node = subject.borrow();
+    if unsafe{ *dag_node.top_symbol != *symbol } || dag_node.args.len() != 1 || dag_node.exponent < height {
+      return vec![];
+    }
+    match dag_node.exponent - height {
+      0        => dag_node.args[0].clone(),
+      exponent => make_tower(symbol, exponent, dag_node.args[0].clone())
+    }
+  };
+  if !inner_pattern.admits_at(symbol, 0, &inner_subject) {
+    return vec![];
+  }
+  inner_pattern.match_all(&inner_subject, substitution)
+}
+
+/// The number of applications of `symbol` stacked at the top of `pattern`, and the pattern under them.
+pub(crate) fn pattern_tower(symbol: SymbolPtr, pattern: &Term) -> (NaturalNumber, &Term) {
+  let mut height = 0;
+  let mut term   = pattern;
+  while let TermNode::Application { tail, .. } = &term.term_node {
+    match term.top_symbol() {
+      Some(top_symbol) if unsafe{ *top_symbol == *symbol } && tail.len() == 1 => {
+        height += 1;
+        term    = tail[0].as_ref();
+      }
+      _ => break
+    }
+  }
+  (height, term)
+}
+
+
+#[cfg(test)]
+mod tests {
+  use crate::{
+    abstractions::IString,
+    core::{
+      apply_error::ApplyError,
+      command::Command,
+      format::{
+        FormatStyle,
+        Formattable
+      },
+      rewriting_context::RewritingContext
+    },
+    parser::parse_to_module_with_commands,
+    theory::term::tests::assert_term_dag_hash_consistent
+  };
+  use super::*;
+
+  #[test]
+  fn towers_are_stored_as_single_nodes() {
+    let (module, commands) = parse_to_module_with_commands(
+      "
+      variable X;
+      symbol s [iter];
+
+      match s(s(X)) with s(s(s(s(s(z)))));
+      "
+    ).unwrap();
+    let (pattern, subject) = match &commands[0] {
+      Command::Match { pattern, subject } => (pattern, subject.dagify()),
+      _ => unreachable!()
+    };
+    assert_eq!(subject.borrow().exponent, 5);
+    assert_eq!(subject.borrow().to_string(), "s⁵(z)");
+    // The argument is expanded on demand.
+    let args: Vec<String> = subject.borrow().iter_args().map(|arg| arg.borrow().to_string()).collect();
+    assert_eq!(args, vec!["s⁴(z)"]);
+
+    let solutions: Vec<String> = pattern.match_all(&subject, Substitution::default())
+                                        .iter()
+                                        .map(|solution| solution.repr(FormatStyle::Default))
+                                        .collect();
+    assert_eq!(solutions, vec!["X := s³(z)"]);
+
+    let s = module.symbols[&IString::from("s")];
+    let z = subject.borrow().args[0].clone();
+    assert!(pattern.match_all(&RcCell::new(make_iterated(s, 1, z.clone())), Substitution::default()).is_empty());
+
+    // Nested towers are combined.
+    let tower = RcCell::new(make_iterated(s, 3, z.clone()));
+    let mut combined = make_iterated(s, 2, tower);
+    assert_eq!(combined.exponent, 5);
+    assert!(combined.equals(&subject.borrow()));
+    assert_eq!(combined.structural_hash(), subject.borrow_mut().structural_hash());
+  }
+
+  #[test]
+  fn tall_towers_take_constant_space() {
+    let (module, commands) = parse_to_module_with_commands(
+      "
+      variable X;
+      symbol s [iter];
+
+      match s(s(X)) with zero;
+      "
+    ).unwrap();
+    let (pattern, zero) = match &commands[0] {
+      Command::Match { pattern, subject } => (pattern, subject.dagify()),
+      _ => unreachable!()
+    };
+    let s     = module.symbols[&IString::from("s")];
+    let tower = RcCell::new(make_iterated(s, 1_000_000, zero.clone()));
+    assert!(RcCell::ptr_eq(&tower.borrow().args[0], &zero));
+
+    let solution = pattern.match_all(&tower, Substitution::default()).pop().unwrap();
+    let binding  = solution.value(module.symbols[&IString::from("X")]).unwrap();
+    assert_eq!(binding.borrow().exponent, 999_998);
+    assert_eq!(binding.borrow().to_string(), "s⁹⁹⁹⁹⁹⁸(zero)");
+  }
+
+  #[test]
+  fn statements_apply_inside_towers() {
+    let (module, commands) = parse_to_module_with_commands(
+      "
+      symbol s [iter];
+      symbol t [iter];
+
+      equation s(z) = a;
+      rule [lower] t(t(a)) => b;
+
+      reduce t(t(t(a)));
+      "
+    ).unwrap();
+    assert_eq!(module.eval("s(s(s(z)))").unwrap().borrow().to_string(), "s²(a)");
+    assert_eq!(module.eval("s(s(s(b)))").unwrap().borrow().to_string(), "s³(b)");
+
+    let term = match &commands[0] {
+      Command::Reduce(term) => term,
+      _ => unreachable!()
+    };
+    assert_eq!(module.rewrite(term, 1).borrow().to_string(), "t(b)");
+    let mut context = RewritingContext::new(&module);
+    let successors: Vec<String> = context.rewrite_successors(&term.dagify())
+                                         .iter()
+                                         .map(|(_, result)| result.borrow().to_string())
+                                         .collect();
+    assert_eq!(successors, vec!["t(b)"]);
+
+    // The argument of `t³(a)` is `t²(a)`.
+    assert_eq!(module.apply_rule("lower", term, &[0]).unwrap().borrow().to_string(), "t(b)");
+    assert!(matches!(module.apply_rule("lower", term, &[0, 0]), Err(ApplyError::NoMatch { .. })));
+    assert!(matches!(module.apply_rule("lower", term, &[0, 0, 0, 0]), Err(ApplyError::InvalidPosition(_))));
+  }
+
+  #[test]
+  fn tall_towers_hash_and_sort_in_constant_time() {
+    let (module, commands) = parse_to_module_with_commands(
+      "
+      sort A;
+      sort B;
+      symbol a :: A;
+      symbol s :: A -> B [iter];
+      symbol s :: B -> A [iter];
+
+      reduce s(s(s(a)));
+      "
+    ).unwrap();
+    let term = match &commands[0] {
+      Command::Reduce(term) => term,
+      _ => unreachable!()
+    };
+    assert_term_dag_hash_consistent(term);
+    let dag = term.dagify();
+    let sort_name = | node: &DagNode | unsafe{ (*node.sort().unwrap()).name.to_string() };
+    assert_eq!(sort_name(&dag.borrow()), "B");
+
+    let s = module.symbols[&IString::from("s")];
+    let a = dag.borrow().args[0].clone();
+    let mut even = make_iterated(s, 1_000_000, a.clone());
+    let mut odd  = make_iterated(s, 1_000_001, a.clone());
+    assert_eq!(sort_name(&even), "A");
+    assert_eq!(sort_name(&odd), "B");
+    assert_ne!(even.structural_hash(), odd.structural_hash());
+    // The hash of a tower does not depend on how it was built.
+    let mut split = make_iterated(s, 999_999, RcCell::new(make_iterated(s, 1, a)));
+    assert_eq!(split.structural_hash(), even.structural_hash());
+  }
+}
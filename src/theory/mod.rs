@@ -5,9 +5,13 @@ The generic traits and common implementations for equational theories. Concrete
 
 pub mod symbol;
 pub mod free_theory;
+pub mod acu_theory;
+pub mod c_theory;
+pub mod iterated_theory;
 pub mod variable_theory;
 pub mod term;
 pub mod symbol_type;
 pub mod dag_node;
+pub mod dag_node_cache;
 pub mod dag_node_attributes;
 
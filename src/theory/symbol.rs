@@ -4,26 +4,62 @@ A symbol is a name that cannot be rebound (or unbound). It is considered a concr
 an equational theory. The `Symbol` struct contains the common implementation of all symbols and defines the API for
 symbols. The `Symbol` struct delegates to a `TheorySymbol` for theory-specific implementation.
 
+## Identity
+
+A `SymbolPtr` is a raw pointer, and pointer equality is not symbol equality: every occurrence of a literal like `3`
+has its own `Symbol`. Wherever symbols are compared for meaning, as in matching or in `DagNode::equals`, they are
+compared with `Symbol::compare(…)` (equivalently `==` on `Symbol`), which uses the symbol's module, its
+`index_within_module`, its core type, and its name, and never its address. `Hash` agrees with `==`.
+
+Pointers are only compared where identity is what is meant. Substitutions and the variable sets of a statement are
+keyed by `SymbolPtr`, which is sound because a module holds exactly one `Symbol` per declared variable.
+
 */
 
 use std::cell::RefCell;
 use std::cmp::Ordering;
+use std::fmt::{Display, Formatter};
+use std::hash::{Hash, Hasher};
 use std::rc::Rc;
 
 use crate::{abstractions::{
+  hash2,
+  join_iter,
+  HashType,
   IString,
+  NatSet,
+  NaturalNumber,
   RcCell
 }, heap_construct, rc_cell, theory::{
+  acu_theory::acu_symbol::ACUSymbol,
+  c_theory::c_symbol::CSymbol,
+  iterated_theory::iterated_symbol::IteratedSymbol,
   free_theory::free_symbol::FreeSymbol,
   symbol_type::{
     CoreSymbolType,
-    SymbolAttribute
-    ,
+    SymbolAttribute,
+    SymbolAttributes,
     SymbolType
   },
+  term::BxTerm,
   variable_theory::variable_symbol::VariableSymbol
 }};
-use crate::core::sort::sort_spec::BxSortSpec;
+use crate::core::{
+  format::{
+    latex_escape,
+    sexpr_atom,
+    sexpr_string,
+    FormatStyle,
+    Formattable
+  },
+  sort::{
+    sort_spec::{
+      BxSortSpec,
+      SortSpec
+    },
+    SortPtr
+  }
+};
 
 pub type SymbolPtr = *mut Symbol;
 
@@ -32,12 +68,73 @@ pub type SymbolPtr = *mut Symbol;
 pub const VARIADIC   : i16 = -1;
 pub const UNSPECIFIED: i16 = -2;
 
+/// The `index_within_module` of a symbol that has not been assigned an index, including every literal symbol.
+pub const UNINDEXED: u32 = u32::MAX;
+
+/// The precedence of an infix symbol like `_+_` that has no `prec` attribute, as in Maude. Lower precedences bind
+/// more tightly.
+pub const DEFAULT_PRECEDENCE: NaturalNumber = 41;
+
+/// Whether `name` is the name of an infix operator like `_+_`.
+pub fn is_infix_name(name: &str) -> bool {
+  name.len() > 2 && name.starts_with('_') && name.ends_with('_')
+}
+
+/**
+A pattern of a symbol's `gather` attribute, which bounds the precedence of the term that an argument of an infix
+symbol can be when it is written without parentheses. A term written with parentheses, and every term that is not
+an infix application, has precedence 0.
+*/
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum Gathering {
+  /// `e`: the argument's precedence is less than the symbol's.
+  Less,
+  /// `E`: the argument's precedence is at most the symbol's.
+  LessOrEqual,
+  /// `&`: the argument can have any precedence.
+  Any,
+}
+
+impl Gathering {
+  /// Whether an argument of precedence `argument_precedence` can be gathered by a symbol of precedence `precedence`.
+  pub fn admits(self, argument_precedence: NaturalNumber, precedence: NaturalNumber) -> bool {
+    match self {
+      Gathering::Less        => argument_precedence < precedence,
+      Gathering::LessOrEqual => argument_precedence <= precedence,
+      Gathering::Any         => true
+    }
+  }
+}
+
 pub struct Symbol {
   pub name       : IString,
   pub arity      : i16, // -1 means variadic, -2 means unspecified
   pub symbol_type: SymbolType,
   // ToDo: Should `sort_spec` be a member of `SymbolType`?
   pub sort_spec  : Option<BxSortSpec>,
+  /// The declarations of an overloaded symbol after the first, which is `sort_spec`. See `Symbol::result_sort(…)`.
+  pub overloads  : Vec<BxSortSpec>,
+  /// Assigned by `Module::close_theory(…)`. Unique among the symbols of a module, so it makes `Symbol::compare(…)` a
+  /// total order independent of names and allocation addresses.
+  pub index_within_module: u32,
+  /// Mixed into the symbol's hash so that hashes are scoped to the owning module. Assigned by
+  /// `Module::close_theory(…)`. Literal symbols belong to no module and are unseeded (zero).
+  pub hash_seed  : HashType,
+  /// The identity element given by the symbol's `id(…)`, `left id(…)`, or `right id(…)` attribute, if any. See
+  /// `Term::normalize(…)`.
+  pub identity   : Option<BxTerm>,
+  /// The doc comment preceding the symbol's declaration, if any.
+  pub doc        : Option<String>,
+  /// The LaTeX macro given by the symbol's `latex(…)` attribute, if any. See `Symbol::repr_application(…)`.
+  pub latex      : Option<String>,
+  /// The 0-based indices of the arguments given by the symbol's `frozen(…)` attribute, which are never rewritten.
+  /// See `RewritingContext::reduce(…)`.
+  pub frozen     : NatSet,
+  /// The precedence given by the symbol's `prec` attribute, if any. See `Symbol::infix_precedence(…)`.
+  pub precedence : Option<NaturalNumber>,
+  /// The patterns given by the symbol's `gather` attribute, one per argument, or empty if there is none. See
+  /// `Symbol::infix_gather(…)`.
+  pub gather     : Vec<Gathering>,
 
   /// The theory-specific implementation of a symbol. (An alternative design is used for `PreEquation`, where the
   /// subtype is implemented as an enum.)
@@ -53,6 +150,15 @@ impl Symbol {
       arity        : UNSPECIFIED,
       symbol_type  : SymbolType::default(),
       sort_spec    : None,
+      overloads    : vec![],
+      index_within_module: UNINDEXED,
+      hash_seed          : 0,
+      identity           : None,
+      doc          : None,
+      latex        : None,
+      frozen       : Default::default(),
+      precedence   : None,
+      gather       : vec![],
       theory_symbol: None,
     }
   }
@@ -71,6 +177,15 @@ impl Symbol {
             attributes: Default::default(),
           },
           sort_spec    : None,
+          overloads    : vec![],
+          index_within_module: UNINDEXED,
+          hash_seed          : 0,
+          identity           : None,
+          doc          : None,
+          latex        : None,
+          frozen       : Default::default(),
+          precedence   : None,
+          gather       : vec![],
           theory_symbol: None,
     });
 
@@ -88,27 +203,261 @@ impl Symbol {
             attributes: Default::default(),
           },
           sort_spec    : None,
+          overloads    : vec![],
+          index_within_module: UNINDEXED,
+          hash_seed          : 0,
+          identity           : None,
+          doc          : None,
+          latex        : None,
+          frozen       : Default::default(),
+          precedence   : None,
+          gather       : vec![],
           theory_symbol: None,
     });
 
     false_symbol
   }
 
+  /// Whether the symbol is an infix operator like `_+_`, which can be applied as `a + b` as well as `_+_(a, b)`.
+  pub fn is_infix(&self) -> bool {
+    is_infix_name(&self.name)
+  }
+
+  /// The precedence of the symbol as an infix operator: that of its `prec` attribute, or `DEFAULT_PRECEDENCE`.
+  pub fn infix_precedence(&self) -> NaturalNumber {
+    self.precedence.unwrap_or(DEFAULT_PRECEDENCE)
+  }
+
+  /// The gathering of the left and right arguments of the symbol as an infix operator: that of its `gather`
+  /// attribute, or `(E E)`.
+  pub fn infix_gather(&self) -> (Gathering, Gathering) {
+    match self.gather.as_slice() {
+      [left, right] => (*left, *right),
+      _             => (Gathering::LessOrEqual, Gathering::LessOrEqual)
+    }
+  }
+
+  /// The hash value of the symbol used in structural hashes. Consistent with `Symbol`'s `Eq` implementation, as equal
+  /// symbols have equal names.
+  #[inline(always)]
+  pub fn hash_value(&self) -> HashType {
+    let hash = self.name.precomputed_hash();
+    let hash = (hash ^ (hash >> 32)) as HashType;
+    // Literals of different types can have the same text, e.g. `1` and `"1"`. Symbols of different modules can have
+    // the same name.
+    match self.is_literal() {
+      true  => hash2(hash, self.symbol_type.core_type as HashType),
+      false => match self.hash_seed {
+        0    => hash,
+        seed => hash2(hash, seed)
+      }
+    }
+  }
+
+  /**
+  Compares symbols by `index_within_module`, then by core symbol type, then by name, then by module (its hash seed).
+  Symbols of a module whose theory is closed have distinct indices, and symbols of different modules are never equal.
+  Literal symbols are never indexed and belong to no module, and they are ordered by their type and value, so two
+  literals compare equal exactly when they denote the same constant.
+  */
+  pub fn compare(&self, other: &Symbol) -> Ordering {
+    self.index_within_module
+        .cmp(&other.index_within_module)
+        .then_with(|| self.symbol_type.core_type.cmp(&other.symbol_type.core_type))
+        .then_with(|| self.name.cmp(&other.name))
+        .then_with(|| self.hash_seed.cmp(&other.hash_seed))
+  }
+
+  /// Is this symbol associative and commutative? Such a symbol is matched modulo AC. See the `acu_theory` module.
+  #[inline(always)]
+  pub fn is_ac(&self) -> bool {
+    self.symbol_type.attributes.contains(SymbolAttribute::Associative)
+        && self.symbol_type.attributes.contains(SymbolAttribute::Commutative)
+  }
+
+  /// Is this symbol commutative but not associative? Such a symbol is matched modulo commutativity. See the `c_theory`
+  /// module.
+  #[inline(always)]
+  pub fn is_c(&self) -> bool {
+    self.symbol_type.attributes.contains(SymbolAttribute::Commutative)
+        && !self.symbol_type.attributes.contains(SymbolAttribute::Associative)
+  }
+
+  /// Is this symbol iterated? Towers of applications of such a symbol are stored compactly. See the
+  /// `iterated_theory` module.
+  #[inline(always)]
+  pub fn is_iterated(&self) -> bool {
+    self.symbol_type.attributes.contains(SymbolAttribute::Iterated)
+  }
+
+  /// The declared sort of the symbol, or of its result if it was declared with a functor sort. See
+  /// `SortSpec::range_sort(…)`.
+  #[inline(always)]
+  pub fn range_sort(&self) -> Option<SortPtr> {
+    self.sort_spec.as_ref().and_then(|sort_spec| sort_spec.range_sort())
+  }
+
+  /// The sort specs of every declaration of the symbol, in declaration order.
+  pub fn declarations(&self) -> impl Iterator<Item = &SortSpec> + '_ {
+    self.sort_spec.iter().chain(self.overloads.iter()).map(|sort_spec| sort_spec.as_ref())
+  }
+
+  /// Whether a further declaration with the given sort spec, core type, and attributes overloads the symbol. Only
+  /// symbols with functor sorts can be overloaded, and every declaration must have the same arity and attributes.
+  pub fn accepts_overload(
+    &self,
+    sort_spec       : &SortSpec,
+    core_symbol_type: CoreSymbolType,
+    attributes      : SymbolAttributes,
+  ) -> bool
+  {
+    let is_functor = | sort_spec: &SortSpec | matches!(sort_spec, SortSpec::Functor { .. });
+    core_symbol_type == CoreSymbolType::Standard
+        && self.symbol_type.core_type == CoreSymbolType::Standard
+        && self.symbol_type.attributes == attributes
+        && is_functor(sort_spec)
+        && self.sort_spec.as_deref().is_some_and(|first| is_functor(first) && first.arity() == sort_spec.arity())
+  }
+
+  /**
+  The sort of an application of the symbol to arguments of sorts `arg_sorts`, where `None` is an unknown sort that
+  fits any declared argument sort. Of the declarations whose argument sorts are supersorts of `arg_sorts`, the one
+  with the least range sort is used, so that with `f :: A A -> X` and `f :: B B -> Y` for `B < A` and `Y < X`, the
+  sort of `f(b, b)` is `Y`. If no range sort is below the others, the first declaration that fits is used.
+  */
+  pub fn result_sort(&self, arg_sorts: &[Option<SortPtr>]) -> Option<SortPtr> {
+    let fits = | declaration: &SortSpec | match declaration {
+      SortSpec::Functor { arg_sorts: declared, .. } => {
+        declared.len() == arg_sorts.len()
+            && arg_sorts.iter().enumerate().all(
+              | (index, arg_sort) | match (declaration.arg_sort(index), arg_sort) {
+                (Some(declared), Some(arg_sort)) => unsafe{ (**arg_sort).leq_sort(&*declared) },
+                _ => true
+              }
+            )
+      }
+      _ => arg_sorts.is_empty()
+    };
+
+    let ranges: Vec<SortPtr> = self.declarations().filter(|declaration| fits(declaration))
+                                                  .filter_map(SortSpec::range_sort)
+                                                  .collect();
+    ranges.iter()
+          .copied()
+          .find(|range| ranges.iter().all(|other| unsafe{ (**range).leq_sort(&**other) }))
+          .or(ranges.first().copied())
+  }
+
+  /// Is this symbol a variable?
+  #[inline(always)]
+  pub fn is_variable(&self) -> bool {
+    self.symbol_type.core_type == CoreSymbolType::Variable
+  }
+
+  /// Is this symbol a built-in literal constant, i.e. a string, number, or boolean value?
+  pub fn is_literal(&self) -> bool {
+    matches!(
+      self.symbol_type.core_type,
+      CoreSymbolType::String
+      | CoreSymbolType::Float
+      | CoreSymbolType::NaturalNumber
+      | CoreSymbolType::Rational
+      | CoreSymbolType::SystemTrue
+      | CoreSymbolType::SystemFalse
+    )
+  }
+
+  /// Is this symbol one of the built-in equality predicates `_==_` and `_=/=_`? See the `equality_symbol` module.
+  #[inline(always)]
+  pub fn is_equality(&self) -> bool {
+    self.symbol_type.core_type == CoreSymbolType::Equality
+  }
+
+  /**
+  Renders an application of this symbol to arguments that have already been rendered in `style`.
+
+  In the `SExpr` style, the application is rendered as `(f a b)`. In the `Latex` style, a LaTeX macro containing the
+  placeholders `#1`, `#2`, … has each placeholder replaced by the corresponding argument, so `latex("#1 \\oplus #2")`
+  renders infix. The macro is expanded in a single pass, so a placeholder inside an argument is left alone. A macro
+  without placeholders is applied to its parenthesized arguments just like a name.
+  */
+  pub fn repr_application(&self, args: &[String], style: FormatStyle) -> String {
+    if style == FormatStyle::Latex {
+      if let Some(latex) = self.latex.as_ref().filter(|latex| latex.contains('#')) {
+        return expand_placeholders(latex, args);
+      }
+    }
+
+    if style == FormatStyle::SExpr {
+      return format!("({} {})", self.repr(style), args.join(" "));
+    }
+
+    let args = join_iter(args.iter().cloned(), |_| ", ".to_string()).collect::<String>();
+    format!("{}({})", self.repr(style), args)
+  }
+
+}
+
+impl Formattable for Symbol {
+  /// Built-in literals are printed as their value, e.g. `42` or `"text"`. Only the `Debug` style shows the wrapping
+  /// symbol type, e.g. `NaturalNumber(42)`.
+  fn repr(&self, style: FormatStyle) -> String {
+    if style == FormatStyle::Latex {
+      if let Some(latex) = &self.latex {
+        return latex.clone();
+      }
+      return match self.symbol_type.core_type {
+        CoreSymbolType::String           => format!("\\texttt{{\"{}\"}}", latex_escape(&self.name)),
+        CoreSymbolType::Variable
+        | CoreSymbolType::NaturalNumber
+        | CoreSymbolType::Rational
+        | CoreSymbolType::Float          => latex_escape(&self.name),
+        _                                => format!("\\mathrm{{{}}}", latex_escape(&self.name)),
+      };
+    }
+
+    if style == FormatStyle::SExpr {
+      return match self.symbol_type.core_type {
+        CoreSymbolType::String        => sexpr_string(&self.name),
+        CoreSymbolType::Variable      => format!("(var {})", sexpr_atom(&self.name)),
+        CoreSymbolType::NaturalNumber
+        | CoreSymbolType::Rational
+        | CoreSymbolType::Float       => self.name.to_string(),
+        _                             => sexpr_atom(&self.name),
+      };
+    }
+
+    let name = match self.symbol_type.core_type {
+      CoreSymbolType::String => sexpr_string(&self.name),
+      _                      => self.name.to_string()
+    };
+
+    if style == FormatStyle::Debug && self.is_literal() {
+      format!("{:?}({})", self.symbol_type.core_type, name)
+    } else {
+      name
+    }
+  }
+}
+
+impl Display for Symbol {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    write!(f, "{}", self.repr(FormatStyle::Default))
+  }
 }
 
 //  region Order and Equality impls
 impl PartialOrd for Symbol {
   #[inline(always)]
   fn partial_cmp(&self, other: &Symbol) -> Option<Ordering> {
-    let result = self.name.cmp(&other.name);
-    Some(result)
+    Some(self.cmp(other))
   }
 }
 
 impl Ord for Symbol {
   #[inline(always)]
   fn cmp(&self, other: &Symbol) -> Ordering {
-    self.name.cmp(&other.name)
+    self.compare(other)
   }
 }
 
@@ -117,7 +466,17 @@ impl Eq for Symbol {}
 impl PartialEq for Symbol {
   #[inline(always)]
   fn eq(&self, other: &Symbol) -> bool {
-    self.name == other.name
+    self.compare(other) == Ordering::Equal
+  }
+}
+
+impl Hash for Symbol {
+  /// Hashes the fields `Symbol::compare(…)` uses, so that equal symbols at different addresses hash alike.
+  fn hash<H: Hasher>(&self, state: &mut H) {
+    self.index_within_module.hash(state);
+    self.symbol_type.core_type.hash(state);
+    self.name.hash(state);
+    self.hash_seed.hash(state);
   }
 }
 // endregion
@@ -125,32 +484,124 @@ impl PartialEq for Symbol {
 
 /// Equational theory-specific implementations implement the `TheorySymbol` trait.
 pub trait TheorySymbol {
-
+  /// Whether an equation or rule with the symbol on top of its left-hand side also applies to part of a subject,
+  /// leaving the rest of the subject's arguments for an implicit extension variable, as for AC symbols. See the
+  /// `acu_theory` module.
+  fn has_extension(&self) -> bool {
+    false
+  }
 }
 
 
-pub fn symbol_for_symbol_type(symbol_type: &SymbolType) -> Box<dyn TheorySymbol> {
+/// Returns the theory-specific implementation for a symbol of type `symbol_type`, or `None` if its theory is not
+/// implemented. A symbol without a theory symbol can still be declared, printed, and introspected.
+pub fn symbol_for_symbol_type(symbol_type: &SymbolType) -> Option<Box<dyn TheorySymbol>> {
   // Variable trumps all.
   if symbol_type.core_type == CoreSymbolType::Variable {
-    Box::new(VariableSymbol::default())
+    Some(Box::new(VariableSymbol::default()))
   }
   else if symbol_type.attributes.contains(SymbolAttribute::Associative) {
-    if symbol_type.attributes.contains(SymbolAttribute::Commutative) {
-      // ACU Theory
-      unimplemented!("ACU Theory is not implemented")
-    }
-    else {
-      // AU Theory
-      unimplemented!("AU Theory is not implemented")
+    match symbol_type.attributes.contains(SymbolAttribute::Commutative) {
+      true  => Some(Box::new(ACUSymbol::default())),
+      // ToDo: The AU theory is not implemented.
+      false => None
     }
   }
   else if symbol_type.attributes.contains(SymbolAttribute::Commutative) {
-    // CUI Theory
-    unimplemented!("CUI Theory is not implemented")
+    // ToDo: Identity and idempotence, the U and I of CUI, are not implemented.
+    Some(Box::new(CSymbol::default()))
+  }
+  else if symbol_type.attributes.contains(SymbolAttribute::Iterated) {
+    Some(Box::new(IteratedSymbol::default()))
   }
   else {
     // Free Theory
-    Box::new(FreeSymbol::default())
+    Some(Box::new(FreeSymbol::default()))
   }
 }
 
+/**
+Replaces each placeholder `#n` in `template` with `args[n - 1]`. The longest run of digits naming an argument is taken,
+so `#10` is the tenth argument when there are ten. A `#` that names no argument is kept as it is.
+*/
+fn expand_placeholders(template: &str, args: &[String]) -> String {
+  let mut result = String::with_capacity(template.len());
+  let mut rest   = template;
+  while let Some(position) = rest.find('#') {
+    result.push_str(&rest[..position]);
+    rest = &rest[position + 1..];
+    let digits = rest.bytes().take_while(u8::is_ascii_digit).count();
+    let index  = (1..=digits).rev().find_map(
+      | length | {
+        let index: usize = rest[..length].parse().ok()?;
+        (1..=args.len()).contains(&index).then_some((index, length))
+      }
+    );
+    match index {
+      Some((index, length)) => {
+        result.push_str(&args[index - 1]);
+        rest = &rest[length..];
+      }
+      None => result.push('#')
+    }
+  }
+  result.push_str(rest);
+  result
+}
+
+
+#[cfg(test)]
+mod tests {
+  use std::hash::DefaultHasher;
+
+  use crate::{
+    builtin::integer_symbol::IntegerSymbol,
+    parser::parse_to_module,
+    theory::dag_node::DagNode
+  };
+  use super::*;
+
+  fn hash_of(symbol: &Symbol) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    symbol.hash(&mut hasher);
+    hasher.finish()
+  }
+
+  #[test]
+  fn symbols_compare_by_meaning_not_address() {
+    // Two allocations of the same literal.
+    let three       = heap_construct!(IntegerSymbol::new(3));
+    let three_again = heap_construct!(IntegerSymbol::new(3));
+    assert_ne!(three, three_again);
+    unsafe {
+      assert!(*three == *three_again);
+      assert_eq!(hash_of(&*three), hash_of(&*three_again));
+      assert_eq!((*three).hash_value(), (*three_again).hash_value());
+      assert!(DagNode::new(three, vec![]).equals(&DagNode::new(three_again, vec![])));
+    }
+
+    // The same symbol reached through the module and through a statement.
+    let module = parse_to_module("symbol f; equation f(a) = a;").unwrap();
+    let from_module    = module.symbols[&IString::from("f")];
+    let from_statement = module.equations[0].lhs_term.top_symbol().unwrap();
+    unsafe {
+      assert!(*from_module == *from_statement);
+      assert_eq!(hash_of(&*from_module), hash_of(&*from_statement));
+    }
+
+    // Equally named symbols of different modules are different symbols.
+    let other = parse_to_module("symbol f; equation f(a) = a;").unwrap();
+    unsafe {
+      assert!(*from_module != *other.symbols[&IString::from("f")]);
+    }
+  }
+
+  #[test]
+  fn placeholders_are_expanded_in_one_pass() {
+    let args = |args: &[&str]| args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>();
+    assert_eq!(expand_placeholders("#1 \\oplus #2", &args(&["#2", "b"])), "#2 \\oplus b");
+    assert_eq!(expand_placeholders("#2^{#1}", &args(&["x_{#1}", "#1"])), "#1^{x_{#1}}");
+    assert_eq!(expand_placeholders("#10 #1", &args(&["a"; 10])), "a a");
+    assert_eq!(expand_placeholders("#10 #3", &args(&["a", "b"])), "a0 #3");
+  }
+}
@@ -18,7 +18,7 @@ pub struct SymbolType {
 /// The most important `CoreSymbolType`s are `Standard` and `Variable`.
 ///
 /// Most of the `CoreSymbolType`s are unimplemented symbol types that are used in Maude.
-#[derive(Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Default, Debug)]
+#[derive(Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Default, Debug)]
 pub enum CoreSymbolType {
   #[default]
   Standard,
@@ -49,6 +49,7 @@ pub enum CoreSymbolType {
   MetaLevelOp,
   Loop,
   NaturalNumber, // Succ,
+  Rational,
   Minus,
   NumberOp,
   ACUNumberOp,
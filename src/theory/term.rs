@@ -5,20 +5,63 @@ A term is a concrete instance of a constant symbol or application of a function.
 Example: A function symbol `f` and constant symbol `x` can be used to form the term `f(f(x), x)`.
 While there is only a single `Symbol` for `f`, there are two (sub)`Term`s in which `f` appears. Likewise for `x`.
 
+The head of an application is always a symbol. The parser rejects applications of other terms, like `f(a)(b)`, with
+`ParseErrorKind::ApplicationHead`.
 
 */
 
+use std::cmp::Ordering;
+use std::fmt::{Display, Formatter};
 use std::rc::Rc;
 
 use enumflags2::{bitflags, BitFlags};
 
 use crate::{
-  abstractions::NatSet,
+  abstractions::{
+    hash2,
+    join_iter,
+    HashMap,
+    HashSet,
+    HashType,
+    IString,
+    NatSet,
+    NaturalNumber,
+    RcCell
+  },
+  core::{
+    format::{
+      latex_escape,
+      sexpr_atom,
+      FormatStyle,
+      Formattable
+    },
+    sort::SortPtr,
+    substitution::Substitution
+  },
   theory::{
+    dag_node::{
+      DagNode,
+      RcDagNode
+    },
+    acu_theory::{
+      hash_multiplicity,
+      match_ac,
+      match_ac_with_extension,
+      normalize_ac_arguments,
+      PartialMatch
+    },
+    c_theory::match_commutative,
+    iterated_theory::{
+      match_iterated,
+      pattern_tower,
+      tower_hash
+    },
+    dag_node_cache::DagNodeCache,
     symbol::{
       SymbolPtr,
       Symbol
-    }
+    },
+    symbol_type::SymbolAttribute
   }
 };
 
@@ -44,6 +87,585 @@ impl Term {
       attributes: TermAttributes::default()
     })
   }
+
+  /// The symbol at the top of the term, if the head of the term is a symbol.
+  pub fn top_symbol(&self) -> Option<SymbolPtr> {
+    match &self.term_node {
+      TermNode::Symbol(symbol) => Some(*symbol),
+      TermNode::Application { head, .. } => {
+        match head.term_node {
+          TermNode::Symbol(symbol) => Some(symbol),
+          _ => None
+        }
+      }
+    }
+  }
+
+  /// Computes the structural hash of the term. This must agree with `DagNode::structural_hash` so that a term and its
+  /// DAG have the same hash.
+  pub fn structural_hash(&self) -> HashType {
+    match &self.term_node {
+
+      TermNode::Symbol(symbol) => unsafe{ (**symbol).hash_value() },
+
+      TermNode::Application { head, tail } => {
+        if let Some((symbol, height, inner)) = self.tower() {
+          return tower_hash(unsafe{ (*symbol).hash_value() }, height, inner.structural_hash());
+        }
+        self.hash_arguments(head.structural_hash(), tail, Term::structural_hash)
+      }
+
+    }
+  }
+
+  /// Folds the hashes of the arguments `tail` of `self`, given by `arg_hash`, into `hash`. A run of equal arguments of
+  /// an AC symbol is hashed once with its length, like an argument of an AC node with its multiplicity.
+  fn hash_arguments(
+    &self,
+    mut hash    : HashType,
+    tail        : &[BxTerm],
+    mut arg_hash: impl FnMut(&Term) -> HashType
+  ) -> HashType
+  {
+    let ac        = self.top_symbol().is_some_and(|symbol| unsafe{ (*symbol).is_ac() });
+    let mut index = 0;
+    while index < tail.len() {
+      let mut multiplicity = 1;
+      while ac
+          && index + multiplicity < tail.len()
+          && tail[index + multiplicity].compare(&tail[index]) == Ordering::Equal
+      {
+        multiplicity += 1;
+      }
+      hash   = hash_multiplicity(hash, arg_hash(&tail[index]), multiplicity as u32);
+      index += multiplicity;
+    }
+    hash
+  }
+
+  /// If the term is an application of an iterated symbol, the symbol, the number of its applications stacked at the
+  /// top of the term, and the term under them. These are the top symbol, exponent, and argument of the term's node.
+  fn tower(&self) -> Option<(SymbolPtr, NaturalNumber, &Term)> {
+    let symbol = self.top_symbol()?;
+    match self.arguments().len() == 1 && unsafe{ (*symbol).is_iterated() } {
+      true  => {
+        let (height, inner) = pattern_tower(symbol, self);
+        Some((symbol, height, inner))
+      }
+      false => None
+    }
+  }
+
+  /// Constructs the term a DAG represents, the inverse of `Term::dagify()`. Shared nodes become separate subterms, so
+  /// the term owns all of its parts and does not keep any node of the DAG alive.
+  pub fn from_dag(dag_node: &DagNode) -> BxTerm {
+    let term = |term_node| Box::new(Term { term_node, attributes: TermAttributes::default() });
+    if dag_node.args.is_empty() {
+      return term(TermNode::Symbol(dag_node.top_symbol));
+    }
+
+    let mut tail: Vec<BxTerm> = match dag_node.exponent > 1 {
+      true  => vec![Term::from_dag(&dag_node.args[0].borrow())],
+      false => dag_node.iter_args().map(|arg| Term::from_dag(&arg.borrow())).collect()
+    };
+    // A tower of an iterated symbol is expanded into its applications.
+    for _ in 1..dag_node.exponent {
+      let head = term(TermNode::Symbol(dag_node.top_symbol));
+      tail     = vec![term(TermNode::Application { head, tail })];
+    }
+    let head = term(TermNode::Symbol(dag_node.top_symbol));
+    term(TermNode::Application { head, tail })
+  }
+
+  /// A copy of the term that shares no parts with it.
+  pub fn deep_copy(&self) -> BxTerm {
+    self.substitute(&HashMap::new())
+  }
+
+  /// A copy of the term in which each variable whose name is bound in `bindings` is replaced by a copy of its binding.
+  /// Unlike `Term::instantiate(…)`, the result is a term, so nothing is shared and the term can be modified, as when
+  /// constructing statements from a template.
+  pub fn substitute(&self, bindings: &HashMap<IString, BxTerm>) -> BxTerm {
+    let term_node = match &self.term_node {
+
+      TermNode::Symbol(symbol) => {
+        let binding = match unsafe{ (**symbol).is_variable() } {
+          true  => bindings.get(unsafe{ &(**symbol).name }),
+          false => None
+        };
+        match binding {
+          Some(binding) => return binding.deep_copy(),
+          None          => TermNode::Symbol(*symbol)
+        }
+      }
+
+      TermNode::Application { head, tail } => {
+        // The head is a symbol, not a variable to replace, even if it has the name of one.
+        TermNode::Application {
+          head: head.deep_copy(),
+          tail: tail.iter().map(|arg| arg.substitute(bindings)).collect()
+        }
+      }
+
+    };
+    Box::new(Term { term_node, attributes: self.attributes })
+  }
+
+  /// Constructs the DAG representation of the term. Structurally equal subterms become a single shared node. The DAG
+  /// node hashes are computed.
+  pub fn dagify(&self) -> RcDagNode {
+    self.instantiate(&Substitution::default())
+  }
+
+  /// Constructs the DAG representation of the term like `Term::dagify(…)`, sharing nodes through `cache`, which can
+  /// be shared between terms and inspected afterward.
+  pub fn dagify_with_cache(&self, cache: &mut DagNodeCache) -> RcDagNode {
+    self.instantiate_with_cache(&Substitution::default(), cache)
+  }
+
+  /// Constructs the DAG representation of the term with each variable bound in `substitution` replaced by its
+  /// binding. Unbound variables become variable nodes. Structurally equal subterms that are constructed become a
+  /// single shared node. The DAG node hashes are computed.
+  pub fn instantiate(&self, substitution: &Substitution) -> RcDagNode {
+    self.instantiate_with_cache(substitution, &mut DagNodeCache::default())
+  }
+
+  fn instantiate_with_cache(&self, substitution: &Substitution, cache: &mut DagNodeCache) -> RcDagNode {
+    let top_symbol = self.top_symbol().expect("the parser only accepts applications of symbols");
+    let args = match &self.term_node {
+      TermNode::Symbol(_) => {
+        if let Some(value) = substitution.value(top_symbol) {
+          return value;
+        }
+        vec![]
+      }
+      TermNode::Application { tail, .. } => {
+        tail.iter().map(|arg| arg.instantiate_with_cache(substitution, cache)).collect()
+      }
+    };
+
+    cache.get_or_insert(DagNode::make(top_symbol, args))
+  }
+
+  /**
+  Matches `self` as a pattern against `subject`, extending `substitution` with the bindings of the pattern's
+  variables. A variable that is already bound only matches a subject equal to its binding. If there are several
+  matches, as there can be for a pattern with an AC symbol, the first one of `Term::match_all(…)` is taken. If the
+  match fails, `substitution` is unchanged.
+
+  A variable declared with a sort only matches a subject whose sort is a subsort of it. A variable declared without
+  a sort matches a subject of any sort in the kind of its position, that is, of the corresponding argument sort of
+  the enclosing symbol.
+
+  ToDo: The sort of a subject is computed from the declarations of its symbols by `DagNode::sort()` each time it is
+        needed. Subjects of unknown sort, and positions without a declared argument sort, are not checked.
+  */
+  pub fn match_dag(&self, subject: &RcDagNode, substitution: &mut Substitution) -> bool {
+    match self.match_all(subject, substitution.clone()).into_iter().next() {
+      Some(solution) => {
+        *substitution = solution;
+        true
+      }
+      None => false
+    }
+  }
+
+  /// Every extension of `substitution` under which `self` matches `subject`. Applications of free symbols match
+  /// syntactically, applications of AC symbols match modulo AC, applications of commutative symbols match in either
+  /// order, and towers of iterated symbols match by height. See the `acu_theory`, `c_theory`, and `iterated_theory`
+  /// modules.
+  pub fn match_all(&self, subject: &RcDagNode, mut substitution: Substitution) -> Vec<Substitution> {
+    let top_symbol = match self.top_symbol() {
+      Some(top_symbol) => top_symbol,
+      None => return vec![]
+    };
+
+    match &self.term_node {
+
+      TermNode::Symbol(symbol) if unsafe{ (**symbol).is_variable() } => {
+        match substitution.value(*symbol) {
+          Some(value) if RcCell::ptr_eq(&value, subject) || value.borrow().equals(&subject.borrow()) => {
+            vec![substitution]
+          }
+          Some(_) => vec![],
+          None if !sort_admits(unsafe{ (**symbol).range_sort() }, subject) => vec![],
+          None => {
+            substitution.bind(*symbol, subject.clone());
+            vec![substitution]
+          }
+        }
+      }
+
+      TermNode::Symbol(_) => {
+        let dag_node = subject.borrow();
+        match dag_node.args.is_empty() && unsafe{ *dag_node.top_symbol == *top_symbol } {
+          true  => vec![substitution],
+          false => vec![]
+        }
+      }
+
+      TermNode::Application { .. } if unsafe{ (*top_symbol).is_ac() } => {
+        match_ac(top_symbol, self, subject, substitution)
+      }
+
+      TermNode::Application { tail, .. } if tail.len() == 2 && unsafe{ (*top_symbol).is_c() } => {
+        match_commutative(top_symbol, self, subject, substitution)
+      }
+
+      TermNode::Application { tail, .. } if tail.len() == 1 && unsafe{ (*top_symbol).is_iterated() } => {
+        match_iterated(top_symbol, self, subject, substitution)
+      }
+
+      TermNode::Application { tail, .. } => {
+        let dag_node = subject.borrow();
+        if unsafe{ *dag_node.top_symbol != *top_symbol } || dag_node.args.len() != tail.len() {
+          return vec![];
+        }
+        let mut solutions = vec![substitution];
+        for (index, (pattern, subject_arg)) in tail.iter().zip(dag_node.args.iter()).enumerate() {
+          if !pattern.admits_at(top_symbol, index, subject_arg) {
+            return vec![];
+          }
+          solutions = solutions.into_iter().flat_map(|solution| pattern.match_all(subject_arg, solution)).collect();
+        }
+        solutions
+      }
+
+    }
+  }
+
+  /// Every match of `self`, the left-hand side of an equation or rule, against `subject` like `Term::match_all(…)`,
+  /// and, if the top symbol of `self` has an extension, every match against part of `subject` together with the
+  /// arguments of `subject` left for the extension. See the `acu_theory` module.
+  pub fn match_with_extension(&self, subject: &RcDagNode, substitution: Substitution) -> Vec<PartialMatch> {
+    let extension = match (&self.term_node, self.top_symbol()) {
+      (TermNode::Application { .. }, Some(symbol)) => {
+        unsafe{ (*symbol).theory_symbol.as_ref() }.is_some_and(|theory_symbol| theory_symbol.has_extension())
+      }
+      _ => false
+    };
+    match extension {
+      true  => match_ac_with_extension(self.top_symbol().unwrap(), self, subject, substitution),
+      false => self.match_all(subject, substitution).into_iter().map(|solution| (solution, vec![])).collect()
+    }
+  }
+
+  /// If `self` is a variable declared without a sort, whether `subject` is in the kind of argument `index` of
+  /// `symbol`, which is where `self` occurs.
+  pub(crate) fn admits_at(&self, symbol: SymbolPtr, index: usize, subject: &RcDagNode) -> bool {
+    let sortless_variable = matches!(
+      self.term_node,
+      TermNode::Symbol(variable) if unsafe{ (*variable).is_variable() && (*variable).range_sort().is_none() }
+    );
+    if !sortless_variable {
+      return true;
+    }
+    let arg_sort = unsafe{ (*symbol).sort_spec.as_ref().and_then(|sort_spec| sort_spec.arg_sort(index)) };
+    match (arg_sort, subject_sort(subject)) {
+      (Some(arg_sort), Some(sort)) => unsafe{ (*arg_sort).kind == (*sort).kind },
+      _ => true
+    }
+  }
+
+  /// A total order on terms: top symbols are compared first, then arguments from left to right. Towers of an
+  /// iterated symbol are compared by height before the terms under them. Agrees with `DagNode::compare` on the
+  /// dagified terms. A term with a head that is not a symbol, which the parser does not produce, is ordered by
+  /// comparing heads recursively and then arguments.
+  pub fn compare(&self, other: &Term) -> Ordering {
+    let (symbol, other_symbol) = match (self.top_symbol(), other.top_symbol()) {
+      (Some(symbol), Some(other_symbol)) => unsafe{ (&*symbol, &*other_symbol) },
+      // At least one head is not a symbol and so is smaller than the term, so the recursion terminates.
+      _ => return self.head().compare(other.head()).then_with(|| self.compare_arguments(other))
+    };
+    if let (Some((_, height, inner)), Some((_, other_height, other_inner))) = (self.tower(), other.tower()) {
+      return symbol.cmp(other_symbol)
+                   .then_with(|| height.cmp(&other_height))
+                   .then_with(|| inner.compare(other_inner));
+    }
+    symbol.cmp(other_symbol).then_with(|| self.compare_arguments(other))
+  }
+
+  /// Compares the arguments of `self` and `other`, first by number and then from left to right.
+  fn compare_arguments(&self, other: &Term) -> Ordering {
+    let (args, other_args) = (self.arguments(), other.arguments());
+    args.len().cmp(&other_args.len()).then_with(|| {
+      args.iter()
+          .zip(other_args.iter())
+          .map(|(arg, other_arg)| arg.compare(other_arg))
+          .find(|result| *result != Ordering::Equal)
+          .unwrap_or(Ordering::Equal)
+    })
+  }
+
+  /// The head of the term if it is an application, and the term itself if it is a symbol.
+  #[inline(always)]
+  fn head(&self) -> &Term {
+    match &self.term_node {
+      TermNode::Symbol(_)                => self,
+      TermNode::Application { head, .. } => head
+    }
+  }
+
+  /// The arguments of the term, which are empty unless the term is an application.
+  #[inline(always)]
+  pub fn arguments(&self) -> &[BxTerm] {
+    match &self.term_node {
+      TermNode::Symbol(_)                => &[],
+      TermNode::Application { tail, .. } => tail
+    }
+  }
+
+  /**
+  Puts the term into a canonical form, normalizing the arguments first. The two arguments of a binary `Commutative`
+  symbol are ordered by `Term::compare`, so that `f(a, b)` and `f(b, a)` normalize to the same term and thus have the
+  same hash and share in the DAG. This is the order `DagNode::compare` gives the arguments of a commutative node, so
+  the term and its DAG agree. See the `c_theory` module.
+
+  The identity element of a symbol with an identity collapses: an argument equal to the identity is dropped if it has
+  an argument to its right and the symbol has a left identity, or an argument to its left and the symbol has a right
+  identity. If a single argument is left, the application collapses to it. For `symbol f [assoc, id(e)];`, the term
+  `f(e, x, e)` normalizes to `x`, and `f(e, e)` to `e`.
+
+  The arguments of an AC symbol are put into the canonical form of AC nodes: nested applications of the symbol are
+  flattened, identity elements are dropped wherever they are, and the arguments are sorted by `Term::compare`. See
+  the `acu_theory` module.
+
+  Term hashes are computed from the structure of the term rather than cached, so they reflect the new order.
+  `DagNode::make(…)` collapses identities the same way, so instances of right-hand sides collapse during reduction.
+  */
+  pub fn normalize(&mut self) {
+    if let TermNode::Application { head, tail } = &mut self.term_node {
+      for arg in tail.iter_mut() {
+        arg.normalize();
+      }
+
+      if let TermNode::Symbol(symbol) = head.term_node {
+        if unsafe{ (*symbol).is_ac() } {
+          if let Some(collapsed) = normalize_ac_arguments(symbol, tail) {
+            *self = *collapsed;
+          }
+          return;
+        }
+      }
+
+      let symbol = match head.term_node {
+        TermNode::Symbol(symbol) => unsafe{ symbol.as_ref() },
+        _                        => None
+      };
+      if let Some((symbol, identity)) = symbol.and_then(|symbol| Some((symbol, symbol.identity.as_deref()?))) {
+        let left        = symbol.symbol_type.attributes.contains(SymbolAttribute::LeftIdentity);
+        let right       = symbol.symbol_type.attributes.contains(SymbolAttribute::RightIdentity);
+        let is_identity = | term: &Term | {
+          term.structural_hash() == identity.structural_hash() && term.compare(identity) == Ordering::Equal
+        };
+
+        let mut collapsed = false;
+        let mut index     = 0;
+        while tail.len() > 1 && index < tail.len() {
+          if is_identity(&tail[index]) && ((left && index + 1 < tail.len()) || (right && index > 0)) {
+            tail.remove(index);
+            collapsed = true;
+          } else {
+            index += 1;
+          }
+        }
+        if collapsed && tail.len() == 1 {
+          *self = *tail.pop().unwrap();
+          return;
+        }
+      }
+
+      let commutative = symbol.is_some_and(
+        |symbol| symbol.symbol_type.attributes.contains(SymbolAttribute::Commutative)
+      );
+      if commutative && tail.len() == 2 && tail[0].compare(&tail[1]) == Ordering::Greater {
+        tail.swap(0, 1);
+      }
+    }
+  }
+
+  /// Inserts the (symbols of the) variables occurring in `self` into `variables`.
+  pub fn add_variables(&self, variables: &mut HashSet<SymbolPtr>) {
+    match &self.term_node {
+
+      TermNode::Symbol(symbol) => {
+        if unsafe{ (**symbol).is_variable() } {
+          variables.insert(*symbol);
+        }
+      }
+
+      TermNode::Application { head, tail } => {
+        head.add_variables(variables);
+        for arg in tail.iter() {
+          arg.add_variables(variables);
+        }
+      }
+
+    }
+  }
+
+  /// Appends the variables of `self` that are not already in `variables`, in the order they first occur from left to
+  /// right.
+  pub fn add_variables_in_order(&self, variables: &mut Vec<SymbolPtr>) {
+    match &self.term_node {
+
+      TermNode::Symbol(symbol) => {
+        if unsafe{ (**symbol).is_variable() } && !variables.contains(symbol) {
+          variables.push(*symbol);
+        }
+      }
+
+      TermNode::Application { head, tail } => {
+        head.add_variables_in_order(variables);
+        for arg in tail.iter() {
+          arg.add_variables_in_order(variables);
+        }
+      }
+
+    }
+  }
+
+  /// The distinct variables of the term, with their declared sorts, in the order they first occur from left to
+  /// right. A variable declared without a sort has no sort, and a ground term has no variables.
+  pub fn variables(&self) -> Vec<(IString, Option<SortPtr>)> {
+    let mut variables = Vec::new();
+    self.add_variables_in_order(&mut variables);
+    variables.into_iter().map(|variable| unsafe{ ((*variable).name, (*variable).range_sort()) }).collect()
+  }
+
+  /// Inserts every symbol occurring in the term, including variables, into `symbols`.
+  pub fn add_symbols(&self, symbols: &mut HashSet<SymbolPtr>) {
+    match &self.term_node {
+
+      TermNode::Symbol(symbol) => {
+        symbols.insert(*symbol);
+      }
+
+      TermNode::Application { head, tail } => {
+        head.add_symbols(symbols);
+        for arg in tail.iter() {
+          arg.add_symbols(symbols);
+        }
+      }
+
+    }
+  }
+
+  /// The number of nodes of the term tree, counting each occurrence of a repeated subterm separately. A term built by
+  /// substituting a subterm into itself repeatedly has a size exponential in the number of substitutions.
+  pub fn compute_size(&self) -> usize {
+    match &self.term_node {
+      TermNode::Symbol(_) => 1,
+      TermNode::Application { tail, .. } => 1 + tail.iter().map(|arg| arg.compute_size()).sum::<usize>()
+    }
+  }
+
+  /// An estimate of the number of nodes of `self.dagify()`, which shares structurally equal subterms, computed without
+  /// constructing the DAG. Subterms are identified by structural hash, so a hash collision can make the estimate too
+  /// small. Comparing this to `compute_size()` detects terms that should be dagified before they are processed further.
+  pub fn estimated_dag_size(&self) -> usize {
+    let mut hashes = HashSet::new();
+    self.add_subterm_hashes(&mut hashes);
+    hashes.len()
+  }
+
+  /// Inserts the structural hash of every subterm of `self` into `hashes`, returning the hash of `self`. Each hash is
+  /// computed once from the hashes of the arguments.
+  fn add_subterm_hashes(&self, hashes: &mut HashSet<HashType>) -> HashType {
+    let hash = match &self.term_node {
+      TermNode::Symbol(symbol) => unsafe{ (**symbol).hash_value() },
+      // A tower is a single node over the term under it.
+      TermNode::Application { .. } if self.tower().is_some() => {
+        let (symbol, height, inner) = self.tower().unwrap();
+        tower_hash(unsafe{ (*symbol).hash_value() }, height, inner.add_subterm_hashes(hashes))
+      }
+      // The arguments in a run of equal arguments of an AC symbol have the same subterms, so only the first is visited.
+      TermNode::Application { head, tail } => {
+        self.hash_arguments(head.structural_hash(), tail, |arg| arg.add_subterm_hashes(hashes))
+      }
+    };
+    hashes.insert(hash);
+    hash
+  }
+
+  /// A term is linear if no variable occurs in it more than once. Matching a nonlinear pattern requires checking
+  /// that the subterms bound to the repeated variable are equal.
+  pub fn is_linear(&self) -> bool {
+    self.variables_are_distinct(&mut HashSet::new())
+  }
+
+  /// Inserts the variables of `self` into `seen`, returning false as soon as one is already there.
+  fn variables_are_distinct(&self, seen: &mut HashSet<SymbolPtr>) -> bool {
+    match &self.term_node {
+
+      TermNode::Symbol(symbol) => !unsafe{ (**symbol).is_variable() } || seen.insert(*symbol),
+
+      TermNode::Application { head, tail } => {
+        head.variables_are_distinct(seen) && tail.iter().all(|arg| arg.variables_are_distinct(seen))
+      }
+
+    }
+  }
+}
+
+/// The sort of `subject`, if it is known.
+fn subject_sort(subject: &RcDagNode) -> Option<SortPtr> {
+  subject.borrow().sort()
+}
+
+/// Whether a variable of sort `sort` can be bound to `subject`. A variable without a sort can be bound to anything.
+pub(crate) fn sort_admits(sort: Option<SortPtr>, subject: &RcDagNode) -> bool {
+  match (sort, subject_sort(subject)) {
+    (Some(sort), Some(subject_sort)) => unsafe{ (*subject_sort).leq_sort(&*sort) },
+    _ => true
+  }
+}
+
+impl Term {
+  /**
+  The representation of the term in which the variables in `names` are shown by the names they are mapped to rather
+  than by their own. See `PreEquation::canonical_variable_names(…)`.
+  */
+  pub fn repr_with_names(&self, style: FormatStyle, names: &HashMap<SymbolPtr, String>) -> String {
+    match &self.term_node {
+
+      TermNode::Symbol(symbol) => {
+        match names.get(symbol) {
+          Some(name) if style == FormatStyle::Latex  => latex_escape(name),
+          Some(name) if style == FormatStyle::SExpr  => format!("(var {})", sexpr_atom(name)),
+          Some(name)                                 => name.clone(),
+          None                                       => unsafe{ (**symbol).repr(style) }
+        }
+      }
+
+      TermNode::Application { head, tail } => {
+        let args: Vec<String> = tail.iter().map(|t| t.repr_with_names(style, names)).collect();
+        match head.term_node {
+          TermNode::Symbol(symbol) => unsafe{ (*symbol).repr_application(&args, style) },
+          _ if style == FormatStyle::SExpr => {
+            format!("({} {})", head.repr_with_names(style, names), args.join(" "))
+          }
+          _ => {
+            let args = join_iter(args.into_iter(), |_| ", ".to_string()).collect::<String>();
+            format!("{}({})", head.repr_with_names(style, names), args)
+          }
+        }
+      }
+
+    }
+  }
+}
+
+impl Formattable for Term {
+  fn repr(&self, style: FormatStyle) -> String {
+    self.repr_with_names(style, &HashMap::new())
+  }
+}
+
+impl Display for Term {
+  fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    write!(f, "{}", self.repr(FormatStyle::Default))
+  }
 }
 
 /// The part of the term that holds the subterms.
@@ -72,3 +694,276 @@ pub enum TermAttribute {
   HonorsGroundOutMatch
 }
 pub type TermAttributes = BitFlags<TermAttribute>;
+
+
+#[cfg(test)]
+pub(crate) mod tests {
+  use crate::{
+    builtin::{
+      integer_symbol::IntegerSymbol,
+      string_symbol::StringSymbol
+    },
+    core::command::Command,
+    heap_construct,
+    parser::parse_to_module_with_commands,
+    theory::{
+      dag_node_cache::CacheStats,
+      symbol_type::{CoreSymbolType, SymbolType}
+    }
+  };
+  use super::*;
+
+  /// Dagifies the term and checks that the term and the DAG have the same structural hash.
+  pub(crate) fn assert_term_dag_hash_consistent(term: &Term) {
+    let dag = term.dagify();
+    assert_eq!(
+      term.structural_hash(),
+      dag.borrow_mut().structural_hash(),
+      "hash of term {} disagrees with the hash of its DAG", term
+    );
+  }
+
+  pub(crate) fn symbol_term(symbol: SymbolPtr) -> BxTerm {
+    Box::new(Term {
+      term_node : TermNode::Symbol(symbol),
+      attributes: TermAttributes::default()
+    })
+  }
+
+  pub(crate) fn application(head: SymbolPtr, tail: Vec<BxTerm>) -> BxTerm {
+    Box::new(Term {
+      term_node : TermNode::Application { head: symbol_term(head), tail },
+      attributes: TermAttributes::default()
+    })
+  }
+
+  pub(crate) fn variable(name: &str) -> SymbolPtr {
+    let mut symbol = Symbol::new(IString::from(name));
+    symbol.symbol_type = SymbolType { core_type: CoreSymbolType::Variable, attributes: Default::default() };
+    heap_construct!(symbol)
+  }
+
+  #[test]
+  fn term_and_dag_hashes_agree() {
+    let f   = heap_construct!(Symbol::new(IString::from("f")));
+    let g   = heap_construct!(Symbol::new(IString::from("g")));
+    let a   = heap_construct!(Symbol::new(IString::from("a")));
+    let x   = variable("X");
+    let one = heap_construct!(IntegerSymbol::new(1));
+    let s   = heap_construct!(StringSymbol::new("s".to_string()));
+
+    assert_term_dag_hash_consistent(&symbol_term(a));
+    assert_term_dag_hash_consistent(&symbol_term(x));
+    assert_term_dag_hash_consistent(&symbol_term(one));
+    assert_term_dag_hash_consistent(&application(f, vec![symbol_term(a), symbol_term(x)]));
+    assert_term_dag_hash_consistent(
+      &application(f, vec![application(g, vec![symbol_term(x)]), symbol_term(x), symbol_term(one), symbol_term(s)])
+    );
+  }
+
+  #[test]
+  fn estimated_dag_size_counts_shared_subterms_once() {
+    let f = heap_construct!(Symbol::new(IString::from("f")));
+    let x = heap_construct!(Symbol::new(IString::from("x")));
+
+    // t₀ = x, tₙ₊₁ = f(tₙ, tₙ)
+    fn doubled(f: SymbolPtr, x: SymbolPtr, depth: usize) -> BxTerm {
+      match depth {
+        0 => symbol_term(x),
+        _ => application(f, vec![doubled(f, x, depth - 1), doubled(f, x, depth - 1)])
+      }
+    }
+    let term = doubled(f, x, 12);
+
+    assert_eq!(term.compute_size(), (1 << 13) - 1);
+    assert_eq!(term.estimated_dag_size(), 13);
+    let dag = term.dagify();
+    assert!(RcCell::ptr_eq(&dag.borrow().args[0], &dag.borrow().args[1]));
+  }
+
+  #[test]
+  fn dagify_shares_equal_constants_only() {
+    let f = heap_construct!(Symbol::new(IString::from("f")));
+    let integer = |value| symbol_term(heap_construct!(IntegerSymbol::new(value)));
+
+    // Each literal is its own symbol, but equal literals are equal constants.
+    let dag = application(f, vec![integer(1), integer(1)]).dagify();
+    assert!(RcCell::ptr_eq(&dag.borrow().args[0], &dag.borrow().args[1]));
+
+    let dag = application(f, vec![integer(1), integer(2)]).dagify();
+    assert!(!RcCell::ptr_eq(&dag.borrow().args[0], &dag.borrow().args[1]));
+    assert!(!dag.borrow().args[0].borrow().equals(&dag.borrow().args[1].borrow()));
+
+    // A string and an integer with the same text are different constants with different hashes.
+    let quoted_one = symbol_term(heap_construct!(StringSymbol::new("1".to_string()))).dagify();
+    assert_ne!(integer(1).structural_hash(), quoted_one.borrow_mut().structural_hash());
+  }
+
+  #[test]
+  fn variables_are_listed_once_in_order_of_occurrence() {
+    let f = heap_construct!(Symbol::new(IString::from("f")));
+    let g = heap_construct!(Symbol::new(IString::from("g")));
+    let a = heap_construct!(Symbol::new(IString::from("a")));
+    let x = variable("x");
+    let y = variable("y");
+
+    let term  = application(f, vec![symbol_term(x), application(g, vec![symbol_term(y), symbol_term(x)])]);
+    let names = | variables: Vec<(IString, Option<SortPtr>)> | {
+      variables.into_iter().map(|(name, _)| name.to_string()).collect::<Vec<_>>()
+    };
+    assert_eq!(names(term.variables()), vec!["x", "y"]);
+    assert_eq!(names(term.dagify().borrow().variables()), vec!["x", "y"]);
+    assert!(term.variables().iter().all(|(_, sort)| sort.is_none()));
+
+    let ground = application(f, vec![symbol_term(a), application(g, vec![symbol_term(a)])]);
+    assert!(ground.variables().is_empty());
+    assert!(ground.dagify().borrow().variables().is_empty());
+  }
+
+  #[test]
+  fn dagify_never_shares_nodes_with_colliding_hashes() {
+    let f = heap_construct!(Symbol::new(IString::from("f")));
+    let a = heap_construct!(Symbol::new(IString::from("a")));
+    let b = heap_construct!(Symbol::new(IString::from("b")));
+
+    // Every node has the same hash, so every lookup after the first finds a bucket of other nodes.
+    let mut cache = DagNodeCache::with_hasher(|_| 0);
+    let dag = application(f, vec![symbol_term(a), symbol_term(b)]).dagify_with_cache(&mut cache);
+    assert!(!RcCell::ptr_eq(&dag.borrow().args[0], &dag.borrow().args[1]));
+    assert_eq!(dag.borrow().to_string(), "f(a, b)");
+    assert_eq!(cache.len(), 3);
+    assert_eq!(cache.cache_stats(), CacheStats { hits: 0, misses: 3, collisions: 2 });
+
+    // Structurally equal nodes are still shared.
+    let dag = application(f, vec![symbol_term(b), symbol_term(b)]).dagify_with_cache(&mut cache);
+    assert!(RcCell::ptr_eq(&dag.borrow().args[0], &dag.borrow().args[1]));
+    assert_eq!(cache.cache_stats(), CacheStats { hits: 2, misses: 4, collisions: 3 });
+  }
+
+  #[test]
+  fn normalize_orders_commutative_arguments() {
+    let mut f = Symbol::new(IString::from("f"));
+    f.symbol_type.attributes.insert(SymbolAttribute::Commutative);
+    let f = heap_construct!(f);
+    let g = heap_construct!(Symbol::new(IString::from("g")));
+    let a = heap_construct!(Symbol::new(IString::from("a")));
+    let b = heap_construct!(Symbol::new(IString::from("b")));
+
+    let mut ab = application(f, vec![symbol_term(a), symbol_term(b)]);
+    let mut ba = application(f, vec![symbol_term(b), symbol_term(a)]);
+    ab.normalize();
+    ba.normalize();
+    assert_eq!(ab.compare(&ba), Ordering::Equal);
+    assert_eq!(ab.structural_hash(), ba.structural_hash());
+    assert_term_dag_hash_consistent(&ba);
+
+    // Commutative arguments are ordered below a free symbol, and free arguments are left alone.
+    let mut nested = application(g, vec![application(f, vec![symbol_term(b), symbol_term(a)]), symbol_term(a)]);
+    let mut free   = application(g, vec![application(f, vec![symbol_term(a), symbol_term(b)]), symbol_term(a)]);
+    nested.normalize();
+    free.normalize();
+    assert_eq!(nested.compare(&free), Ordering::Equal);
+
+    let mut g_ba = application(g, vec![symbol_term(b), symbol_term(a)]);
+    g_ba.normalize();
+    assert_eq!(g_ba.to_string(), "g(b, a)");
+  }
+
+  #[test]
+  fn terms_with_heads_that_are_not_symbols_are_ordered() {
+    let f = heap_construct!(Symbol::new(IString::from("f")));
+    let a = heap_construct!(Symbol::new(IString::from("a")));
+    let b = heap_construct!(Symbol::new(IString::from("b")));
+    // `f(a)(x)` for the argument `x`.
+    let curried = | arg: SymbolPtr | Box::new(Term {
+      term_node : TermNode::Application { head: application(f, vec![symbol_term(a)]), tail: vec![symbol_term(arg)] },
+      attributes: TermAttributes::default()
+    });
+
+    let (fa_a, fa_b) = (curried(a), curried(b));
+    assert_eq!(fa_a.compare(&curried(a)), Ordering::Equal);
+    assert_eq!(fa_a.compare(&fa_b), unsafe{ (*a).cmp(&*b) });
+    assert_eq!(fa_b.compare(&fa_a), fa_a.compare(&fa_b).reverse());
+    // The head `f(a)` has more arguments than `f`, and `f(a)(a)` is ordered after its own head.
+    let fa = application(f, vec![symbol_term(a)]);
+    assert_eq!(fa_a.compare(&application(f, vec![symbol_term(a), symbol_term(a)])), Ordering::Greater);
+    assert_eq!(fa_a.compare(&fa), Ordering::Greater);
+    assert_eq!(fa.compare(&fa_a), Ordering::Less);
+  }
+
+  #[test]
+  fn substitute_replaces_bound_variables_with_copies() {
+    let f = heap_construct!(Symbol::new(IString::from("f")));
+    let g = heap_construct!(Symbol::new(IString::from("g")));
+    let a = heap_construct!(Symbol::new(IString::from("a")));
+    let x = variable("x");
+    let y = variable("y");
+
+    let term     = application(f, vec![symbol_term(x), symbol_term(x), symbol_term(y)]);
+    let bindings = HashMap::from([(IString::from("x"), application(g, vec![symbol_term(a)]))]);
+    let result   = term.substitute(&bindings);
+    assert_eq!(result.to_string(), "f(g(a), g(a), y)");
+    // The hash is that of the term built directly, and the term is unchanged.
+    let expected = application(f, vec![application(g, vec![symbol_term(a)]), application(g, vec![symbol_term(a)]),
+                                       symbol_term(y)]);
+    assert_eq!(result.structural_hash(), expected.structural_hash());
+    assert_ne!(result.structural_hash(), term.structural_hash());
+    assert_eq!(term.to_string(), "f(x, x, y)");
+    assert_term_dag_hash_consistent(&result);
+
+    // Only variables are replaced.
+    let bindings = HashMap::from([(IString::from("a"), symbol_term(y))]);
+    assert_eq!(application(g, vec![symbol_term(a)]).substitute(&bindings).to_string(), "g(a)");
+  }
+
+  #[test]
+  fn identity_elements_collapse() {
+    let (_module, commands) = parse_to_module_with_commands(
+      "
+      symbol f [assoc, id(e)];
+      symbol l [left id(e)];
+      symbol r [right id(e)];
+
+      reduce f(e, x, e, x, e);
+      reduce f(e, f(x, e));
+      reduce f(e, e, e);
+      reduce l(e, x);
+      reduce l(x, e);
+      reduce r(x, e);
+      reduce r(e, x);
+      reduce r(l(e, e), e);
+      "
+    ).unwrap();
+    let normal_forms: Vec<String> = commands.into_iter()
+                                            .map(|command| match command {
+                                              Command::Reduce(mut term) => {
+                                                term.normalize();
+                                                term.to_string()
+                                              }
+                                              _ => unreachable!()
+                                            })
+                                            .collect();
+    assert_eq!(
+      normal_forms,
+      vec!["f(x, x)", "x", "e", "x", "l(x, e)", "x", "r(e, x)", "e"]
+    );
+  }
+
+  #[test]
+  fn terms_render_as_s_expressions() {
+    let f   = heap_construct!(Symbol::new(IString::from("f")));
+    let g   = heap_construct!(Symbol::new(IString::from("g")));
+    let a   = heap_construct!(Symbol::new(IString::from("a")));
+    let b   = heap_construct!(Symbol::new(IString::from("b c")));
+    let x   = variable("X");
+    let one = heap_construct!(IntegerSymbol::new(1));
+    let s   = heap_construct!(StringSymbol::new("hi".to_string()));
+
+    let term = application(
+      f,
+      vec![application(g, vec![symbol_term(a)]), symbol_term(b), symbol_term(one), symbol_term(x), symbol_term(s)]
+    );
+    assert_eq!(term.repr(FormatStyle::SExpr), "(f (g a) |b c| 1 (var X) \"hi\")");
+    assert_eq!(term.dagify().borrow().repr(FormatStyle::SExpr), "(f (g a) |b c| 1 (var X) \"hi\")");
+  }
+}